@@ -0,0 +1,132 @@
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+
+use anyhow::{Context, Result};
+
+use kbtz_workspace::protocol::{self, Message};
+use kbtz_workspace::shepherd;
+
+static RESIZED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn sigwinch_handler(_sig: libc::c_int) {
+    RESIZED.store(true, Ordering::SeqCst);
+}
+
+/// Size of the terminal on stdout, falling back to 24x80.
+fn terminal_size() -> (u16, u16) {
+    let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+    let rc = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) };
+    if rc == 0 && ws.ws_row > 0 && ws.ws_col > 0 {
+        (ws.ws_row, ws.ws_col)
+    } else {
+        (24, 80)
+    }
+}
+
+/// Keeps stdin in raw mode until dropped, so keystrokes reach the agent
+/// unprocessed.
+struct RawMode {
+    original: libc::termios,
+}
+
+impl RawMode {
+    fn enable() -> Result<Self> {
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(std::io::Error::last_os_error()).context("tcgetattr failed");
+        }
+        let mut raw = original;
+        unsafe { libc::cfmakeraw(&mut raw) };
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(std::io::Error::last_os_error()).context("tcsetattr failed");
+        }
+        Ok(Self { original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original) };
+    }
+}
+
+/// Connect this terminal to the shepherd listening on `socket`: paint the
+/// agent's current screen, then forward output, input and resizes until
+/// the shepherd hangs up (the agent exited) or stdin closes.
+pub fn run(socket: &Path) -> Result<()> {
+    let mut stream = UnixStream::connect(socket)
+        .with_context(|| format!("failed to connect to shepherd at {}", socket.display()))?;
+    let mut reader = stream
+        .try_clone()
+        .context("failed to clone shepherd socket")?;
+    let (rows, cols) = terminal_size();
+    let initial = shepherd::handshake(&mut reader, &mut stream, rows, cols)?;
+
+    let _raw = RawMode::enable()?;
+    unsafe {
+        let mut sa: libc::sigaction = std::mem::zeroed();
+        sa.sa_sigaction = sigwinch_handler as *const () as usize;
+        libc::sigemptyset(&mut sa.sa_mask);
+        libc::sigaction(libc::SIGWINCH, &sa, std::ptr::null_mut());
+    }
+
+    {
+        let mut out = std::io::stdout().lock();
+        out.write_all(b"\x1b[H\x1b[2J")?;
+        out.write_all(&initial)?;
+        out.flush()?;
+    }
+
+    // Shepherd -> terminal. The shepherd closes the socket when the
+    // agent exits.
+    let (done_tx, done_rx) = mpsc::channel::<()>();
+    std::thread::spawn(move || {
+        let mut out = std::io::stdout();
+        while let Ok(Some(msg)) = protocol::read_message(&mut reader) {
+            if let Message::PtyOutput(data) = msg {
+                if out.write_all(&data).and_then(|()| out.flush()).is_err() {
+                    break;
+                }
+            }
+        }
+        drop(done_tx);
+    });
+
+    // Terminal -> shepherd. Poll with a timeout so resizes and the
+    // output thread finishing are noticed without input.
+    let mut buf = [0u8; 4096];
+    loop {
+        if !matches!(done_rx.try_recv(), Err(mpsc::TryRecvError::Empty)) {
+            break;
+        }
+        if RESIZED.swap(false, Ordering::SeqCst) {
+            let (rows, cols) = terminal_size();
+            if protocol::write_message(&mut stream, &Message::Resize { rows, cols }).is_err() {
+                break;
+            }
+        }
+        let mut pfd = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut pfd, 1, 100) } <= 0 {
+            continue;
+        }
+        let n = unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr().cast(), buf.len()) };
+        if n < 0 && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+            continue;
+        }
+        if n <= 0 {
+            break;
+        }
+        let input = Message::PtyInput(buf[..n as usize].to_vec());
+        if protocol::write_message(&mut stream, &input).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -1,2 +1,3 @@
 pub mod lifecycle;
+pub mod status;
 pub mod tmux;
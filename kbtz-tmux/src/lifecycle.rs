@@ -1,5 +1,10 @@
+use std::collections::HashSet;
+use std::path::Path;
 use std::time::{Duration, Instant};
 
+use kbtz::paths;
+use kbtz_workspace::shepherd;
+
 pub const GRACEFUL_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Debug, Clone)]
@@ -15,6 +20,8 @@ pub struct WindowSnapshot {
     pub task_name: String,
     pub window_id: String,
     pub phase: WindowPhase,
+    /// The window is attached to an agent running under a shepherd.
+    pub shepherd: bool,
 }
 
 pub struct WorldSnapshot {
@@ -68,6 +75,90 @@ pub fn tick(world: &WorldSnapshot) -> Vec<Action> {
     actions
 }
 
+/// Select `(task, assignee)` claims held by a workspace session ID with no
+/// live window. Assignees outside the `ws/` namespace belong to other
+/// agents and are never reported.
+pub fn orphaned_claims<'a>(
+    claims: &'a [(String, String)],
+    live_sids: &HashSet<&str>,
+) -> Vec<&'a (String, String)> {
+    claims
+        .iter()
+        .filter(|(_, sid)| {
+            sid.starts_with(kbtz::paths::SESSION_ID_PREFIX) && !live_sids.contains(sid.as_str())
+        })
+        .collect()
+}
+
+/// `(task, session ID)` claims to release when the orchestrator exits.
+/// Agents under a shepherd keep running and keep their claims, so the next
+/// orchestrator re-attaches them.
+pub fn shutdown_releases(windows: &[WindowSnapshot]) -> Vec<(&str, &str)> {
+    windows
+        .iter()
+        .filter(|w| !w.shepherd)
+        .map(|w| (w.task_name.as_str(), w.session_id.as_str()))
+        .collect()
+}
+
+/// What to do with a shepherd that has a socket in the workspace dir but
+/// no tracked window.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ShepherdAction {
+    /// The shepherd is running and its session still claims `task`: open
+    /// a window attached to it.
+    Reattach { session_id: String, task: String },
+    /// The shepherd has no claim or is not running: stop it, and release
+    /// `release` if its session still claims that task.
+    Stop {
+        session_id: String,
+        release: Option<String>,
+    },
+}
+
+/// Decide what to do with each shepherd socket in `workspace_dir` whose
+/// session is not in `tracked`, given the `(task, assignee)` claims and a
+/// test for whether a shepherd PID is running. Sorted by session ID.
+pub fn shepherd_actions(
+    workspace_dir: &Path,
+    tracked: &HashSet<&str>,
+    claims: &[(String, String)],
+    pid_alive: impl Fn(i32) -> bool,
+) -> Vec<ShepherdAction> {
+    let Ok(entries) = std::fs::read_dir(workspace_dir) else {
+        return Vec::new();
+    };
+    let mut sids: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("sock"))
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_str()?;
+            Some(paths::filename_to_session_id(stem))
+        })
+        .filter(|sid| !tracked.contains(sid.as_str()))
+        .collect();
+    sids.sort();
+
+    sids.into_iter()
+        .map(|session_id| {
+            let alive = shepherd::read_pid(&shepherd::pid_path(workspace_dir, &session_id))
+                .is_some_and(&pid_alive);
+            let claimed = claims
+                .iter()
+                .find(|(_, assignee)| *assignee == session_id)
+                .map(|(task, _)| task.clone());
+            match claimed {
+                Some(task) if alive => ShepherdAction::Reattach { session_id, task },
+                release => ShepherdAction::Stop {
+                    session_id,
+                    release,
+                },
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -78,6 +169,7 @@ mod tests {
             task_name: task_name.into(),
             window_id: "@0".into(),
             phase,
+            shepherd: false,
         }
     }
 
@@ -178,4 +270,88 @@ mod tests {
         let actions = tick(&w);
         assert!(actions.contains(&Action::SpawnUpTo { count: 1 }));
     }
+
+    #[test]
+    fn orphaned_claims_skips_live_and_foreign_assignees() {
+        let claims = vec![
+            ("live".to_string(), "ws/1".to_string()),
+            ("dead".to_string(), "ws/2".to_string()),
+            ("manual".to_string(), "alice".to_string()),
+        ];
+        let live = HashSet::from(["ws/1"]);
+        let orphaned = orphaned_claims(&claims, &live);
+        assert_eq!(orphaned, [&("dead".to_string(), "ws/2".to_string())]);
+    }
+
+    /// A workspace dir with a shepherd socket per session ID, and a PID
+    /// file for those given a PID. Only the file names matter.
+    fn workspace(shepherds: &[(&str, Option<i32>)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (sid, pid) in shepherds {
+            std::fs::write(shepherd::socket_path(dir.path(), sid), "").unwrap();
+            if let Some(pid) = pid {
+                std::fs::write(shepherd::pid_path(dir.path(), sid), pid.to_string()).unwrap();
+            }
+        }
+        std::fs::write(dir.path().join("ws-0"), "active").unwrap();
+        dir
+    }
+
+    fn claim(task: &str, sid: &str) -> (String, String) {
+        (task.into(), sid.into())
+    }
+
+    #[test]
+    fn shepherds_with_live_claims_are_reattached_and_others_stopped() {
+        let dir = workspace(&[
+            ("ws/0", Some(100)),
+            ("ws/1", Some(101)),
+            ("ws/2", Some(102)),
+            ("ws/3", None),
+            ("ws/4", Some(104)),
+        ]);
+        let claims = [
+            claim("task-a", "ws/0"),
+            claim("task-c", "ws/2"),
+            claim("task-d", "ws/3"),
+            claim("task-e", "ws/4"),
+            claim("manual", "alice"),
+        ];
+        // ws/4 already has a window.
+        let tracked = HashSet::from(["ws/4"]);
+        let actions = shepherd_actions(dir.path(), &tracked, &claims, |pid| pid != 102);
+        assert_eq!(
+            actions,
+            [
+                ShepherdAction::Reattach {
+                    session_id: "ws/0".into(),
+                    task: "task-a".into(),
+                },
+                // Running but unclaimed.
+                ShepherdAction::Stop {
+                    session_id: "ws/1".into(),
+                    release: None,
+                },
+                // Claimed but dead, or with no PID file.
+                ShepherdAction::Stop {
+                    session_id: "ws/2".into(),
+                    release: Some("task-c".into()),
+                },
+                ShepherdAction::Stop {
+                    session_id: "ws/3".into(),
+                    release: Some("task-d".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn shutdown_keeps_shepherd_claims() {
+        let mut windows = vec![
+            snapshot("ws/0", "task-a", WindowPhase::Running),
+            snapshot("ws/1", "task-b", WindowPhase::Running),
+        ];
+        windows[1].shepherd = true;
+        assert_eq!(shutdown_releases(&windows), [("task-a", "ws/0")]);
+    }
 }
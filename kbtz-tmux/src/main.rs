@@ -1,9 +1,11 @@
+mod attach;
 mod orchestrator;
 
 use std::collections::HashMap;
 use std::fs::{self, OpenOptions};
 use std::io;
 use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
@@ -14,9 +16,9 @@ use anyhow::{bail, Context, Result};
 use clap::{Parser, Subcommand};
 use log::{error, info};
 
-use kbtz::config::Config;
-use kbtz::paths;
-use kbtz_tmux::tmux;
+use kbtz::config::{Config, WorkspaceConfig};
+use kbtz::{db, ops, paths};
+use kbtz_tmux::{status, tmux};
 use kbtz_workspace::prompt::TOPLEVEL_PROMPT;
 
 use crate::orchestrator::Orchestrator;
@@ -24,11 +26,13 @@ use crate::orchestrator::Orchestrator;
 #[derive(Parser)]
 #[command(name = "kbtz-tmux", about = "Tmux-based workspace orchestrator")]
 struct Cli {
-    /// Maximum concurrent agent sessions
-    #[arg(long, default_value_t = 4)]
-    max: usize,
+    /// Maximum concurrent agent sessions [default: workspace.concurrency
+    /// from the config file, else 4]
+    #[arg(long)]
+    max: Option<usize>,
 
-    /// FTS preference text for task selection
+    /// FTS preference text for task selection [default: workspace.prefer
+    /// from the config file]
     #[arg(long)]
     prefer: Option<String>,
 
@@ -40,10 +44,20 @@ struct Cli {
     #[arg(long, default_value = "workspace", env = "KBTZ_TMUX_SESSION")]
     session: String,
 
+    /// Run agents under kbtz-shepherd so they survive orchestrator and tmux
+    /// session restarts [default: workspace.persistent_sessions from the
+    /// config file]
+    #[arg(long)]
+    persistent: bool,
+
     /// Run orchestrator directly (no session bootstrap)
     #[arg(long)]
     no_attach: bool,
 
+    /// Print the tmux commands bootstrap would run, without running them
+    #[arg(long, conflicts_with = "no_attach")]
+    dry_run: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -54,11 +68,31 @@ enum Commands {
     JumpNeedsInput {
         #[arg(long, default_value = "workspace", env = "KBTZ_TMUX_SESSION")]
         session: String,
+
+        /// Cycle backwards to the previous session instead
+        #[arg(long)]
+        prev: bool,
+    },
+    /// List agent windows with their claims and status, flagging mismatches
+    Status {
+        #[arg(long, default_value = "workspace", env = "KBTZ_TMUX_SESSION")]
+        session: String,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Connect this terminal to an agent's shepherd (run in --persistent
+    /// agent windows)
+    #[command(hide = true)]
+    Attach {
+        /// Shepherd socket path
+        socket: PathBuf,
     },
 }
 
 fn acquire_lock(workspace_dir: &str) -> Result<fs::File> {
-    let lock_path = format!("{workspace_dir}/orchestrator.lock");
+    let lock_path = format!("{workspace_dir}/{}", paths::ORCHESTRATOR_LOCK);
     let file = OpenOptions::new()
         .write(true)
         .create(true)
@@ -103,7 +137,7 @@ impl io::Write for TeeWriter {
 }
 
 fn setup_logging(workspace_dir: &str) -> Result<()> {
-    let log_path = format!("{workspace_dir}/orchestrator.log");
+    let log_path = format!("{workspace_dir}/{}", paths::ORCHESTRATOR_LOG);
     let file = OpenOptions::new()
         .create(true)
         .append(true)
@@ -130,7 +164,10 @@ fn check_tmux() -> Result<()> {
     Ok(())
 }
 
-fn spawn_manager_window(session: &str, config: &Config) -> Result<()> {
+/// Command, args, and environment for the toplevel task-management window.
+fn manager_window_command(
+    config: &Config,
+) -> Result<(String, Vec<String>, HashMap<String, String>)> {
     let backend_name = config.workspace.backend.as_deref().unwrap_or("claude");
     let agent_cfg = config.agent.get(backend_name);
     let binary = agent_cfg
@@ -170,11 +207,138 @@ fn spawn_manager_window(session: &str, config: &Config) -> Result<()> {
     let mut env = HashMap::new();
     env.insert("KBTZ_DB".into(), db_path);
 
-    let window_id = tmux::spawn_window(session, "💬 manager", &env, &binary, &args)?;
+    Ok((binary, args, env))
+}
+
+fn spawn_manager_window(session: &str, config: &Config) -> Result<()> {
+    let (binary, args, env) = manager_window_command(config)?;
+    let window_id = tmux::spawn_window(session, MANAGER_WINDOW, &env, &binary, &args)?;
     tmux::set_window_option(&window_id, "@kbtz_toplevel", "true")?;
     Ok(())
 }
 
+const TASKS_WINDOW: &str = "📋 tasks";
+const MANAGER_WINDOW: &str = "💬 manager";
+const ORCHESTRATOR_WINDOW: &str = "🔧 orchestrator";
+
+/// Args for the `kbtz watch` process in window 0. Its action jumps to the
+/// window working on the selected task.
+fn watch_args(session: &str, workspace_dir: &str) -> Vec<String> {
+    let action = format!(
+        concat!(
+            "tmux list-windows -t {} -F '#{{window_id}} #{{@kbtz_task}}' ",
+            "| awk -v t=\"$KBTZ_TASK\" '$2==t {{print $1}}' ",
+            "| head -1 | xargs -r tmux select-window -t"
+        ),
+        session
+    );
+    vec![
+        "watch".into(),
+        "--workspace-dir".into(),
+        workspace_dir.into(),
+        "--action".into(),
+        action,
+    ]
+}
+
+/// Default for `--max` when neither the CLI nor the config sets it.
+const DEFAULT_MAX: usize = 4;
+
+/// Session limit and preference for the orchestrator: CLI flags take
+/// precedence over `[workspace]` config, which takes precedence over the
+/// defaults, as in kbtz-workspace.
+fn resolve_limits(cli: &Cli, config: &WorkspaceConfig) -> (usize, Option<String>) {
+    let max = cli.max.or(config.concurrency).unwrap_or(DEFAULT_MAX);
+    let prefer = cli.prefer.clone().or_else(|| config.prefer.clone());
+    (max, prefer)
+}
+
+/// Args for the orchestrator process spawned in its own window. Only flags
+/// given on the command line are forwarded; the orchestrator reads the
+/// config for the rest.
+fn orchestrator_args(cli: &Cli) -> Vec<String> {
+    let mut args = vec![
+        "--no-attach".to_string(),
+        "--session".to_string(),
+        cli.session.clone(),
+        "--poll".to_string(),
+        cli.poll.to_string(),
+    ];
+    if let Some(max) = cli.max {
+        args.push("--max".into());
+        args.push(max.to_string());
+    }
+    if let Some(ref pref) = cli.prefer {
+        args.push("--prefer".into());
+        args.push(pref.clone());
+    }
+    if cli.persistent {
+        args.push("--persistent".into());
+    }
+    args
+}
+
+/// Print every tmux command `bootstrap` would run, without touching tmux.
+fn print_bootstrap_plan(cli: &Cli) -> Result<()> {
+    let print = |args: Vec<String>| println!("{}", tmux::format_command("tmux", &args));
+    println!(
+        "# dry run for session '{}' (attaches instead if it already exists)",
+        cli.session
+    );
+
+    let workspace_dir = paths::workspace_dir();
+    let watch = watch_args(&cli.session, &workspace_dir);
+    let watch: Vec<&str> = watch.iter().map(String::as_str).collect();
+    print(tmux::create_session_args(
+        &cli.session,
+        TASKS_WINDOW,
+        "kbtz",
+        &watch,
+    ));
+    for args in tmux::configure_session_args(&cli.session) {
+        print(args);
+    }
+    print(vec![
+        "set-option".into(),
+        "-t".into(),
+        cli.session.clone(),
+        "@kbtz_workspace_dir".into(),
+        workspace_dir,
+    ]);
+
+    let config = Config::load()?;
+    let (binary, args, env) = manager_window_command(&config)?;
+    print(tmux::spawn_window_args(
+        &cli.session,
+        MANAGER_WINDOW,
+        &env,
+        &binary,
+        &args,
+    ));
+    print(vec![
+        "set-window-option".into(),
+        "-t".into(),
+        "<manager-window>".into(),
+        "@kbtz_toplevel".into(),
+        "true".into(),
+    ]);
+
+    let self_exe = std::env::current_exe().context("failed to determine kbtz-tmux binary path")?;
+    print(tmux::spawn_window_args(
+        &cli.session,
+        ORCHESTRATOR_WINDOW,
+        &HashMap::new(),
+        &self_exe.to_string_lossy(),
+        &orchestrator_args(cli),
+    ));
+    print(vec![
+        "attach-session".into(),
+        "-t".into(),
+        cli.session.clone(),
+    ]);
+    Ok(())
+}
+
 fn bootstrap(cli: &Cli) -> Result<()> {
     check_tmux()?;
 
@@ -187,27 +351,10 @@ fn bootstrap(cli: &Cli) -> Result<()> {
     eprintln!("Creating session '{}'...", cli.session);
 
     // Step 3: Create session with kbtz watch in window 0.
-    let action = format!(
-        concat!(
-            "tmux list-windows -t {} -F '#{{window_id}} #{{@kbtz_task}}' ",
-            "| awk -v t=\"$KBTZ_TASK\" '$2==t {{print $1}}' ",
-            "| head -1 | xargs -r tmux select-window -t"
-        ),
-        cli.session
-    );
     let workspace_dir = paths::workspace_dir();
-    tmux::create_session(
-        &cli.session,
-        "📋 tasks",
-        "kbtz",
-        &[
-            "watch",
-            "--workspace-dir",
-            &workspace_dir,
-            "--action",
-            &action,
-        ],
-    )?;
+    let watch = watch_args(&cli.session, &workspace_dir);
+    let watch: Vec<&str> = watch.iter().map(String::as_str).collect();
+    tmux::create_session(&cli.session, TASKS_WINDOW, "kbtz", &watch)?;
 
     // Step 4: Configure tmux settings.
     tmux::configure_session(&cli.session)?;
@@ -223,27 +370,13 @@ fn bootstrap(cli: &Cli) -> Result<()> {
     let self_exe = std::env::current_exe().context("failed to determine kbtz-tmux binary path")?;
     let self_exe = self_exe.to_string_lossy();
 
-    let mut orch_args = vec![
-        "--no-attach".to_string(),
-        "--session".to_string(),
-        cli.session.clone(),
-        "--max".to_string(),
-        cli.max.to_string(),
-        "--poll".to_string(),
-        cli.poll.to_string(),
-    ];
-    if let Some(ref pref) = cli.prefer {
-        orch_args.push("--prefer".into());
-        orch_args.push(pref.clone());
-    }
-
     let orch_env = HashMap::new();
     tmux::spawn_window(
         &cli.session,
-        "🔧 orchestrator",
+        ORCHESTRATOR_WINDOW,
         &orch_env,
         &self_exe,
-        &orch_args,
+        &orchestrator_args(cli),
     )?;
 
     // Step 7: Attach.
@@ -265,9 +398,12 @@ fn run_orchestrator(cli: Cli) -> Result<()> {
         }
     };
 
+    let config = Config::load()?;
+    let (max, prefer) = resolve_limits(&cli, &config.workspace);
+    let persistent = cli.persistent || config.workspace.persistent_sessions.unwrap_or(false);
     info!(
-        "Starting (max={}, poll={}s, session={})",
-        cli.max, cli.poll, cli.session
+        "Starting (max={max}, poll={}s, session={}, persistent={persistent})",
+        cli.poll, cli.session
     );
 
     let running = Arc::new(AtomicBool::new(true));
@@ -283,9 +419,10 @@ fn run_orchestrator(cli: Cli) -> Result<()> {
 
     let mut orch = Orchestrator::new(
         cli.session,
-        cli.max,
+        max,
         Duration::from_secs(cli.poll),
-        cli.prefer,
+        prefer,
+        persistent,
         running,
     )?;
 
@@ -295,8 +432,22 @@ fn run_orchestrator(cli: Cli) -> Result<()> {
     Ok(())
 }
 
-fn jump_needs_input(session: &str) -> Result<()> {
-    // Get workspace dir from tmux session option or env.
+/// Pick the session after `current` in sorted `sids` (before it when
+/// `reverse`), wrapping around. Falls back to the first (or last) entry
+/// when there is no current session. `sids` must be non-empty.
+fn next_needs_input<'a>(sids: &'a [String], current: Option<&str>, reverse: bool) -> &'a str {
+    let found = match (current, reverse) {
+        (Some(cur), false) => sids.iter().find(|s| s.as_str() > cur),
+        (Some(cur), true) => sids.iter().rev().find(|s| s.as_str() < cur),
+        (None, _) => None,
+    };
+    let fallback = if reverse { sids.last() } else { sids.first() };
+    found.or(fallback).expect("sids must be non-empty")
+}
+
+/// Workspace dir from `KBTZ_WORKSPACE_DIR`, else the session's
+/// `@kbtz_workspace_dir` option.
+fn resolve_workspace_dir(session: &str) -> Result<String> {
     let workspace_dir = std::env::var("KBTZ_WORKSPACE_DIR").ok().or_else(|| {
         let output = Command::new("tmux")
             .args(["show-option", "-t", session, "-v", "@kbtz_workspace_dir"])
@@ -312,10 +463,14 @@ fn jump_needs_input(session: &str) -> Result<()> {
             Some(val)
         }
     });
-    let workspace_dir = match workspace_dir {
-        Some(d) => d,
+    match workspace_dir {
+        Some(d) => Ok(d),
         None => bail!("cannot determine workspace dir: set KBTZ_WORKSPACE_DIR or @kbtz_workspace_dir session option"),
-    };
+    }
+}
+
+fn jump_needs_input(session: &str, reverse: bool) -> Result<()> {
+    let workspace_dir = resolve_workspace_dir(session)?;
 
     // Get current window's session ID to know where we are in the cycle.
     let current_sid = Command::new("tmux")
@@ -336,26 +491,11 @@ fn jump_needs_input(session: &str) -> Result<()> {
         });
 
     // Find session IDs with needs_input status.
-    let mut needs_input_sids: Vec<String> = Vec::new();
-    if let Ok(entries) = fs::read_dir(&workspace_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if !path.is_file() {
-                continue;
-            }
-            if let Ok(content) = fs::read_to_string(&path) {
-                if content.trim() == "needs_input" {
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        // Skip non-status files (locks, sentinels, sockets, pid files).
-                        if name.contains('.') || name == "pane-exited" || name == "orchestrator" {
-                            continue;
-                        }
-                        needs_input_sids.push(paths::filename_to_session_id(name));
-                    }
-                }
-            }
-        }
-    }
+    let mut needs_input_sids: Vec<String> = status::read_states(Path::new(&workspace_dir))
+        .into_iter()
+        .filter(|(_, state)| state == "needs_input")
+        .map(|(sid, _)| sid)
+        .collect();
 
     if needs_input_sids.is_empty() {
         return Ok(());
@@ -363,17 +503,8 @@ fn jump_needs_input(session: &str) -> Result<()> {
 
     needs_input_sids.sort();
 
-    // Pick the next one after current_sid (cycle).
-    let target = match &current_sid {
-        Some(cur) => {
-            let pos = needs_input_sids.iter().position(|s| s > cur);
-            match pos {
-                Some(i) => &needs_input_sids[i],
-                None => &needs_input_sids[0],
-            }
-        }
-        None => &needs_input_sids[0],
-    };
+    // Pick the next (or previous) one relative to current_sid (cycle).
+    let target = next_needs_input(&needs_input_sids, current_sid.as_deref(), reverse);
 
     // Find the window with this session ID.
     let output = Command::new("tmux")
@@ -404,13 +535,43 @@ fn jump_needs_input(session: &str) -> Result<()> {
     Ok(())
 }
 
+/// Print each agent session's window, claim and status-file state,
+/// flagging windows without claims and claims without windows.
+fn print_status(session: &str, json: bool) -> Result<()> {
+    let workspace_dir = resolve_workspace_dir(session)?;
+    let states = status::read_states(Path::new(&workspace_dir));
+    let windows = if tmux::has_session(session) {
+        tmux::list_tagged_windows(session)?
+    } else {
+        Vec::new()
+    };
+
+    let conn = db::open(&paths::db_path())?;
+    db::init(&conn)?;
+    let claims: Vec<(String, String)> = ops::list_tasks(&conn, None, true, None, None, None)?
+        .into_iter()
+        .filter(|t| t.status == "active")
+        .filter_map(|t| Some((t.name, t.assignee?)))
+        .collect();
+
+    let rows = status::sessions(&windows, &claims, &states);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+    } else {
+        print!("{}", status::format_sessions(&rows));
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     // Handle subcommands first.
     if let Some(cmd) = &cli.command {
         match cmd {
-            Commands::JumpNeedsInput { session } => return jump_needs_input(session),
+            Commands::JumpNeedsInput { session, prev } => return jump_needs_input(session, *prev),
+            Commands::Attach { socket } => return attach::run(socket),
+            Commands::Status { session, json } => return print_status(session, *json),
         }
     }
 
@@ -419,6 +580,86 @@ fn main() -> Result<()> {
         return run_orchestrator(cli);
     }
 
+    if cli.dry_run {
+        return print_bootstrap_plan(&cli);
+    }
+
     // Default: bootstrap the tmux session and attach.
     bootstrap(&cli)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sids() -> Vec<String> {
+        vec!["ws/1".into(), "ws/3".into(), "ws/5".into()]
+    }
+
+    #[test]
+    fn next_needs_input_cycles_forward() {
+        let sids = sids();
+        assert_eq!(next_needs_input(&sids, Some("ws/1"), false), "ws/3");
+        assert_eq!(next_needs_input(&sids, Some("ws/4"), false), "ws/5");
+        assert_eq!(next_needs_input(&sids, Some("ws/5"), false), "ws/1");
+        assert_eq!(next_needs_input(&sids, None, false), "ws/1");
+    }
+
+    #[test]
+    fn next_needs_input_cycles_backward() {
+        let sids = sids();
+        assert_eq!(next_needs_input(&sids, Some("ws/3"), true), "ws/1");
+        assert_eq!(next_needs_input(&sids, Some("ws/4"), true), "ws/3");
+        assert_eq!(next_needs_input(&sids, Some("ws/1"), true), "ws/5");
+        assert_eq!(next_needs_input(&sids, None, true), "ws/5");
+    }
+
+    #[test]
+    fn limits_merge_cli_over_config_over_default() {
+        let parse = |args: &[&str]| Cli::parse_from([&["kbtz-tmux"], args].concat());
+        let empty = WorkspaceConfig::default();
+        let config = WorkspaceConfig {
+            concurrency: Some(2),
+            prefer: Some("backend".into()),
+            ..Default::default()
+        };
+
+        assert_eq!(resolve_limits(&parse(&[]), &empty), (DEFAULT_MAX, None));
+        assert_eq!(
+            resolve_limits(&parse(&[]), &config),
+            (2, Some("backend".into()))
+        );
+        assert_eq!(
+            resolve_limits(&parse(&["--max", "6", "--prefer", "ui"]), &config),
+            (6, Some("ui".into()))
+        );
+        assert_eq!(
+            resolve_limits(&parse(&["--max", "1"]), &config),
+            (1, Some("backend".into()))
+        );
+    }
+
+    #[test]
+    fn orchestrator_args_forward_only_cli_flags() {
+        let cli = Cli::parse_from(["kbtz-tmux", "--session", "s"]);
+        let args = orchestrator_args(&cli);
+        assert!(!args.contains(&"--max".to_string()));
+        assert!(!args.contains(&"--prefer".to_string()));
+        assert!(!args.contains(&"--persistent".to_string()));
+
+        let cli = Cli::parse_from(["kbtz-tmux", "--max", "3", "--prefer", "x", "--persistent"]);
+        let args = orchestrator_args(&cli).join(" ");
+        assert!(args.contains("--max 3"), "{args}");
+        assert!(args.contains("--prefer x"), "{args}");
+        assert!(args.contains("--persistent"), "{args}");
+    }
+
+    #[test]
+    fn next_needs_input_single_candidate() {
+        let sids = vec!["ws/2".to_string()];
+        for reverse in [false, true] {
+            assert_eq!(next_needs_input(&sids, Some("ws/2"), reverse), "ws/2");
+            assert_eq!(next_needs_input(&sids, None, reverse), "ws/2");
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -10,8 +11,11 @@ use rusqlite::Connection;
 use kbtz::config::Config;
 use kbtz::{db, ops, paths, watch};
 use kbtz_workspace::prompt::AGENT_PROMPT;
+use kbtz_workspace::shepherd;
 
-use kbtz_tmux::lifecycle::{self, Action, WindowPhase, WindowSnapshot, WorldSnapshot};
+use kbtz_tmux::lifecycle::{
+    self, Action, ShepherdAction, WindowPhase, WindowSnapshot, WorldSnapshot,
+};
 use kbtz_tmux::tmux;
 
 /// Send a signal to a process, logging unexpected errors.
@@ -39,6 +43,42 @@ struct TrackedWindow {
     task_name: String,
     session_id: String,
     phase: WindowPhase,
+    /// Window name last applied by the orchestrator. Empty for adopted
+    /// windows so the next refresh renames them.
+    title: String,
+    /// The agent runs under a shepherd and the window only attaches to
+    /// it, so closing the window must stop the shepherd too.
+    shepherd: bool,
+}
+
+/// PTY size a shepherd starts with; the attach client resizes it to the
+/// window as soon as it connects.
+const SHEPHERD_ROWS: u16 = 24;
+const SHEPHERD_COLS: u16 = 80;
+
+/// Window name for an agent working on `task_name`.
+fn window_title(task_name: &str) -> String {
+    format!("🚀 {task_name}")
+}
+
+/// Window name for an agent whose task has been marked done.
+fn done_window_title(task_name: &str) -> String {
+    format!("done:{task_name}")
+}
+
+/// Tag a window with its task and session so reconcile can adopt it.
+fn tag_window(window_id: &str, task_name: &str, session_id: &str) -> Result<()> {
+    tmux::set_window_option(window_id, "@kbtz_task", task_name)?;
+    tmux::set_window_option(window_id, "@kbtz_sid", session_id)
+}
+
+/// The task `session_id` currently holds, if any.
+fn claimed_task(conn: &Connection, session_id: &str) -> Option<String> {
+    ops::list_tasks(conn, None, true, None, Some(session_id), None)
+        .ok()?
+        .into_iter()
+        .find(|t| t.status == "active")
+        .map(|t| t.name)
 }
 
 pub struct Orchestrator {
@@ -46,6 +86,7 @@ pub struct Orchestrator {
     max_concurrent: usize,
     poll_interval: Duration,
     prefer: Option<String>,
+    persistent: bool,
     db_path: String,
     workspace_dir: String,
     conn: Connection,
@@ -60,6 +101,7 @@ impl Orchestrator {
         max_concurrent: usize,
         poll_interval: Duration,
         prefer: Option<String>,
+        persistent: bool,
         running: Arc<AtomicBool>,
     ) -> Result<Self> {
         let db_path = paths::db_path();
@@ -73,6 +115,7 @@ impl Orchestrator {
             max_concurrent,
             poll_interval,
             prefer,
+            persistent,
             db_path,
             workspace_dir,
             conn,
@@ -103,6 +146,7 @@ impl Orchestrator {
                 task_name: tw.task_name.clone(),
                 window_id: tw.window_id.clone(),
                 phase: tw.phase.clone(),
+                shepherd: tw.shepherd,
             })
             .collect();
 
@@ -118,7 +162,9 @@ impl Orchestrator {
             Action::ForceKill { session_id } => {
                 if let Some(tw) = self.windows.get(session_id) {
                     info!("Force-killing {} (task={})", session_id, tw.task_name);
-                    if let Ok(Some(pid)) = tmux::pane_pid(&tw.window_id) {
+                    if tw.shepherd {
+                        shepherd::kill(Path::new(&self.workspace_dir), session_id);
+                    } else if let Ok(Some(pid)) = tmux::pane_pid(&tw.window_id) {
                         send_signal(pid, libc::SIGKILL);
                     }
                 }
@@ -127,6 +173,9 @@ impl Orchestrator {
                 if let Some(tw) = self.windows.remove(session_id) {
                     info!("Removing {} (task={})", session_id, tw.task_name);
                     let _ = tmux::kill_window(&tw.window_id);
+                    if tw.shepherd {
+                        shepherd::discard(Path::new(&self.workspace_dir), session_id);
+                    }
                     if let Err(e) = ops::release_task(&self.conn, &tw.task_name, &tw.session_id) {
                         warn!("Failed to release {}: {e}", tw.task_name);
                     }
@@ -148,7 +197,7 @@ impl Orchestrator {
         let session_id = format!("{}{slot}", paths::SESSION_ID_PREFIX);
 
         let task_name =
-            ops::claim_next_task(&self.conn, &session_id, self.prefer.as_deref(), None)?
+            ops::claim_next_task(&self.conn, &session_id, self.prefer.as_deref(), None, None)?
                 .context("no tasks available")?;
 
         let task = ops::get_task(&self.conn, &task_name)?;
@@ -183,24 +232,37 @@ impl Orchestrator {
         env.insert("KBTZ_SESSION_ID".into(), session_id.clone());
         env.insert("KBTZ_WORKSPACE_DIR".into(), self.workspace_dir.clone());
 
-        let window_title = format!("🚀 {task_name}");
-        let window_id =
-            match tmux::spawn_window(&self.session, &window_title, &env, &command, &args) {
-                Ok(wid) => wid,
-                Err(e) => {
-                    error!("Failed to spawn window for {task_name}: {e}");
-                    let _ = ops::release_task(&self.conn, &task_name, &session_id);
-                    return Err(e);
+        info!(
+            "Spawn command for {task_name}: {}",
+            tmux::format_command(&command, &args)
+        );
+
+        let window_title = window_title(&task_name);
+        let spawned = if self.persistent {
+            self.spawn_shepherd_window(&session_id, &window_title, &env, &command, &args)
+        } else {
+            tmux::spawn_window(&self.session, &window_title, &env, &command, &args)
+        };
+        let window_id = match spawned {
+            Ok(wid) => wid,
+            Err(e) => {
+                error!("Failed to spawn window for {task_name}: {e}");
+                if self.persistent {
+                    shepherd::kill(Path::new(&self.workspace_dir), &session_id);
                 }
-            };
+                let _ = ops::release_task(&self.conn, &task_name, &session_id);
+                return Err(e);
+            }
+        };
 
         // Tag window for crash recovery. If tagging fails, the window is
         // invisible to reconcile — kill it and release the claim.
-        if let Err(e) = tmux::set_window_option(&window_id, "@kbtz_task", &task_name)
-            .and_then(|()| tmux::set_window_option(&window_id, "@kbtz_sid", &session_id))
-        {
+        if let Err(e) = tag_window(&window_id, &task_name, &session_id) {
             error!("Failed to tag window {window_id} for {task_name}: {e}");
             let _ = tmux::kill_window(&window_id);
+            if self.persistent {
+                shepherd::kill(Path::new(&self.workspace_dir), &session_id);
+            }
             let _ = ops::release_task(&self.conn, &task_name, &session_id);
             return Err(e);
         }
@@ -212,12 +274,59 @@ impl Orchestrator {
                 task_name,
                 session_id,
                 phase: WindowPhase::Running,
+                title: window_title,
+                shepherd: self.persistent,
             },
         );
 
         Ok(())
     }
 
+    /// Start the agent under a shepherd whose socket and PID files live in
+    /// the workspace dir, and open a window attached to it.
+    fn spawn_shepherd_window(
+        &self,
+        session_id: &str,
+        title: &str,
+        env: &HashMap<String, String>,
+        command: &str,
+        args: &[String],
+    ) -> Result<String> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let env: Vec<(&str, &str)> = env.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let cwd = std::env::current_dir().context("failed to get current directory")?;
+        let mut child = shepherd::launch(
+            Path::new(&self.workspace_dir),
+            session_id,
+            command,
+            &args,
+            SHEPHERD_ROWS,
+            SHEPHERD_COLS,
+            &env,
+            &cwd,
+            kbtz_workspace::SCROLLBACK_ROWS,
+        )?;
+        // The shepherd is our child until it exits; reap it off-thread.
+        std::thread::spawn(move || {
+            let _ = child.wait();
+        });
+        self.attach_window(session_id, title)
+    }
+
+    /// Open a window running `kbtz-tmux attach` on a session's shepherd.
+    fn attach_window(&self, session_id: &str, title: &str) -> Result<String> {
+        let self_exe =
+            std::env::current_exe().context("failed to determine kbtz-tmux binary path")?;
+        let socket = shepherd::socket_path(Path::new(&self.workspace_dir), session_id);
+        tmux::spawn_window(
+            &self.session,
+            title,
+            &HashMap::new(),
+            &self_exe.to_string_lossy(),
+            &["attach".into(), socket.to_string_lossy().into_owned()],
+        )
+    }
+
     /// Check which tracked windows are still alive in tmux.
     /// Calls list_window_ids once and does set lookups instead of O(N) tmux calls.
     fn detect_dead_windows(&mut self) {
@@ -234,6 +343,75 @@ impl Orchestrator {
         }
     }
 
+    /// Keep window names in sync with the task each agent holds. Follows
+    /// renames via the session's claim (also updating `@kbtz_task` so
+    /// reconcile still recognizes the window), and renames windows whose
+    /// task was marked done to `done:<task>`.
+    fn refresh_window_names(&mut self) {
+        for tw in self.windows.values_mut() {
+            if !matches!(tw.phase, WindowPhase::Running) {
+                continue;
+            }
+            let title = match claimed_task(&self.conn, &tw.session_id) {
+                Some(name) => {
+                    if name != tw.task_name {
+                        info!(
+                            "Task renamed for {}: {} -> {}",
+                            tw.session_id, tw.task_name, name
+                        );
+                        if let Err(e) = tmux::set_window_option(&tw.window_id, "@kbtz_task", &name)
+                        {
+                            warn!("Failed to retag {}: {e}", tw.window_id);
+                            continue;
+                        }
+                        tw.task_name = name;
+                    }
+                    window_title(&tw.task_name)
+                }
+                None => match ops::get_task(&self.conn, &tw.task_name) {
+                    Ok(task) if task.status == "done" => done_window_title(&tw.task_name),
+                    _ => continue,
+                },
+            };
+            if title != tw.title {
+                match tmux::rename_window(&tw.window_id, &title) {
+                    Ok(()) => tw.title = title,
+                    Err(e) => warn!("Failed to rename {}: {e}", tw.window_id),
+                }
+            }
+        }
+    }
+
+    /// `(task, assignee)` for every active task.
+    fn active_claims(&self) -> Result<Vec<(String, String)>> {
+        Ok(ops::list_tasks(&self.conn, None, true, None, None, None)?
+            .into_iter()
+            .filter(|t| t.status == "active")
+            .filter_map(|t| Some((t.name, t.assignee?)))
+            .collect())
+    }
+
+    /// Release `ws/*` claims whose session has no tmux window and is not
+    /// tracked, e.g. after a window was closed while the orchestrator was
+    /// not watching it. Mirrors the workspace's `release_orphaned_tasks`.
+    fn release_orphaned_tasks(&self) -> Result<()> {
+        let window_sids = tmux::list_window_sids(&self.session)?;
+        let live: HashSet<&str> = window_sids
+            .iter()
+            .map(String::as_str)
+            .chain(self.windows.keys().map(String::as_str))
+            .collect();
+
+        let claims = self.active_claims()?;
+        for (task, sid) in lifecycle::orphaned_claims(&claims, &live) {
+            info!("Releasing claim with no window: {task} ({sid})");
+            if let Err(e) = ops::release_task(&self.conn, task, sid) {
+                warn!("Failed to release {task}: {e}");
+            }
+        }
+        Ok(())
+    }
+
     pub fn reconcile(&mut self) -> Result<()> {
         info!("Reconciling state...");
         let window_ids = tmux::list_window_ids(&self.session)?;
@@ -251,6 +429,8 @@ impl Orchestrator {
             match ops::get_task(&self.conn, &task) {
                 Ok(t) if t.status == "active" && t.assignee.as_deref() == Some(&sid) => {
                     info!("Adopting orphaned window: {task} ({wid}, {sid})");
+                    let shepherd =
+                        shepherd::socket_path(Path::new(&self.workspace_dir), &sid).exists();
                     self.windows.insert(
                         sid.clone(),
                         TrackedWindow {
@@ -258,6 +438,8 @@ impl Orchestrator {
                             task_name: task,
                             session_id: sid,
                             phase: WindowPhase::Running,
+                            title: String::new(),
+                            shepherd,
                         },
                     );
                 }
@@ -269,12 +451,78 @@ impl Orchestrator {
             }
         }
 
+        self.reattach_shepherds();
         self.cleanup_stale_status_files();
 
         info!("Reconciliation done ({} adopted)", self.windows.len());
         Ok(())
     }
 
+    /// Open windows for shepherds that outlived theirs, e.g. after the tmux
+    /// session was killed, as long as their session still holds a claim.
+    /// Shepherds with no claim are stopped, and a dead shepherd's claim is
+    /// released.
+    fn reattach_shepherds(&mut self) {
+        let dir = Path::new(&self.workspace_dir);
+        let claims = match self.active_claims() {
+            Ok(claims) => claims,
+            Err(e) => {
+                warn!("Failed to read claims for shepherds: {e}");
+                return;
+            }
+        };
+        let tracked: HashSet<&str> = self.windows.keys().map(String::as_str).collect();
+        let actions = lifecycle::shepherd_actions(dir, &tracked, &claims, shepherd::pid_alive);
+
+        for action in actions {
+            let (sid, task) = match action {
+                ShepherdAction::Reattach { session_id, task } => (session_id, task),
+                ShepherdAction::Stop {
+                    session_id,
+                    release,
+                } => {
+                    info!("Stopping shepherd with no claim or no process: {session_id}");
+                    shepherd::kill(dir, &session_id);
+                    if let Some(task) = release {
+                        let _ = ops::release_task(&self.conn, &task, &session_id);
+                    }
+                    continue;
+                }
+            };
+            let title = window_title(&task);
+            let attached = self.attach_window(&sid, &title).and_then(|wid| {
+                match tag_window(&wid, &task, &sid) {
+                    Ok(()) => Ok(wid),
+                    Err(e) => {
+                        let _ = tmux::kill_window(&wid);
+                        Err(e)
+                    }
+                }
+            });
+            match attached {
+                Ok(window_id) => {
+                    info!("Re-attached shepherd: {task} ({window_id}, {sid})");
+                    self.windows.insert(
+                        sid.clone(),
+                        TrackedWindow {
+                            window_id,
+                            task_name: task,
+                            session_id: sid,
+                            phase: WindowPhase::Running,
+                            title,
+                            shepherd: true,
+                        },
+                    );
+                }
+                Err(e) => {
+                    warn!("Failed to re-attach {sid} (task={task}): {e}");
+                    shepherd::kill(dir, &sid);
+                    let _ = ops::release_task(&self.conn, &task, &sid);
+                }
+            }
+        }
+    }
+
     /// Delete status files that don't correspond to any tracked window.
     fn cleanup_stale_status_files(&self) {
         let live_sids: HashSet<&str> = self.windows.keys().map(|s| s.as_str()).collect();
@@ -345,7 +593,16 @@ impl Orchestrator {
         });
 
         while self.running.load(Ordering::SeqCst) {
+            // If session vanished, stop the loop. Checked first so its
+            // windows aren't removed as dead (which would also stop their
+            // shepherds).
+            if !tmux::has_session(&self.session) {
+                info!("Tmux session '{}' gone, exiting", self.session);
+                break;
+            }
+
             self.detect_dead_windows();
+            self.refresh_window_names();
 
             let world = self.snapshot_world();
             let actions = lifecycle::tick(&world);
@@ -353,10 +610,8 @@ impl Orchestrator {
                 self.apply_action(action);
             }
 
-            // If session vanished, stop the loop.
-            if !tmux::has_session(&self.session) {
-                info!("Tmux session '{}' gone, exiting", self.session);
-                break;
+            if let Err(e) = self.release_orphaned_tasks() {
+                warn!("Failed to release orphaned claims: {e}");
             }
 
             watch::drain_events(&wake_rx);
@@ -371,8 +626,9 @@ impl Orchestrator {
     pub fn shutdown(&mut self) {
         info!("Shutting down...");
 
-        for tw in self.windows.values() {
-            let _ = ops::release_task(&self.conn, &tw.task_name, &tw.session_id);
+        let world = self.snapshot_world();
+        for (task, sid) in lifecycle::shutdown_releases(&world.windows) {
+            let _ = ops::release_task(&self.conn, task, sid);
         }
         self.windows.clear();
 
@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde::Serialize;
+
+use kbtz::paths;
+
+use crate::tmux::TaggedWindow;
+
+/// What is wrong with a session, from the orchestrator's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mismatch {
+    /// A tagged window exists but its session holds no claim.
+    NoClaim,
+    /// The session holds a claim but has no window.
+    NoWindow,
+}
+
+impl Mismatch {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Mismatch::NoClaim => "window but no claim",
+            Mismatch::NoWindow => "claim but no window",
+        }
+    }
+}
+
+/// One agent session: its window, the task the window was tagged with,
+/// the task its session has claimed, and its status file state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionRow {
+    pub session_id: String,
+    pub window_id: Option<String>,
+    pub window_task: Option<String>,
+    pub claimed_task: Option<String>,
+    pub state: Option<String>,
+    pub mismatch: Option<Mismatch>,
+}
+
+/// Read every session status file in `workspace_dir`, keyed by session ID.
+/// Locks, sentinels, sockets and PID files are skipped.
+pub fn read_states(workspace_dir: &Path) -> HashMap<String, String> {
+    let mut states = HashMap::new();
+    let Ok(entries) = std::fs::read_dir(workspace_dir) else {
+        return states;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if name.contains('.') || name == "pane-exited" || name == "orchestrator" {
+            continue;
+        }
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            states.insert(
+                paths::filename_to_session_id(name),
+                content.trim().to_string(),
+            );
+        }
+    }
+    states
+}
+
+/// Join tagged windows with `ws/*` claims (`(task, assignee)` pairs) and
+/// status file states into one row per session, sorted by session ID.
+pub fn sessions(
+    windows: &[TaggedWindow],
+    claims: &[(String, String)],
+    states: &HashMap<String, String>,
+) -> Vec<SessionRow> {
+    let mut rows: BTreeMap<&str, SessionRow> = BTreeMap::new();
+    let row = |sid: &str| SessionRow {
+        session_id: sid.to_string(),
+        window_id: None,
+        window_task: None,
+        claimed_task: None,
+        state: states.get(sid).cloned(),
+        mismatch: None,
+    };
+    for w in windows {
+        let r = rows
+            .entry(&w.session_id)
+            .or_insert_with(|| row(&w.session_id));
+        r.window_id = Some(w.window_id.clone());
+        r.window_task = Some(w.task.clone());
+    }
+    for (task, sid) in claims {
+        if !sid.starts_with(paths::SESSION_ID_PREFIX) {
+            continue;
+        }
+        rows.entry(sid).or_insert_with(|| row(sid)).claimed_task = Some(task.clone());
+    }
+    rows.into_values()
+        .map(|mut r| {
+            r.mismatch = match (&r.window_id, &r.claimed_task) {
+                (Some(_), None) => Some(Mismatch::NoClaim),
+                (None, Some(_)) => Some(Mismatch::NoWindow),
+                _ => None,
+            };
+            r
+        })
+        .collect()
+}
+
+/// Human-readable table, one line per session, with mismatches flagged.
+pub fn format_sessions(rows: &[SessionRow]) -> String {
+    if rows.is_empty() {
+        return "No agent sessions.\n".to_string();
+    }
+    let dash = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".into());
+    let cells: Vec<[String; 4]> = rows
+        .iter()
+        .map(|r| {
+            [
+                r.session_id.clone(),
+                dash(&r.window_id),
+                dash(&r.claimed_task.clone().or_else(|| r.window_task.clone())),
+                dash(&r.state),
+            ]
+        })
+        .collect();
+    let width = |i: usize| cells.iter().map(|c| c[i].len()).max().unwrap_or(0);
+    let widths = [width(0), width(1), width(2), width(3)];
+
+    let mut out = String::new();
+    for (r, c) in rows.iter().zip(&cells) {
+        let mut line = format!(
+            "{:w0$}  {:w1$}  {:w2$}  {:w3$}",
+            c[0],
+            c[1],
+            c[2],
+            c[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+        );
+        if let Some(m) = r.mismatch {
+            line.push_str("  ! ");
+            line.push_str(m.describe());
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(id: &str, sid: &str, task: &str) -> TaggedWindow {
+        TaggedWindow {
+            window_id: id.into(),
+            session_id: sid.into(),
+            task: task.into(),
+        }
+    }
+
+    fn claim(task: &str, sid: &str) -> (String, String) {
+        (task.into(), sid.into())
+    }
+
+    #[test]
+    fn read_states_skips_non_status_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("ws-0"), "active\n").unwrap();
+        std::fs::write(dir.path().join("ws-1"), "needs_input").unwrap();
+        for other in ["ws-0.pid", "ws-0.sock", "orchestrator.lock", "pane-exited"] {
+            std::fs::write(dir.path().join(other), "1").unwrap();
+        }
+
+        let states = read_states(dir.path());
+        assert_eq!(states.len(), 2);
+        assert_eq!(states["ws/0"], "active");
+        assert_eq!(states["ws/1"], "needs_input");
+    }
+
+    #[test]
+    fn sessions_flag_claim_and_window_mismatches() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("ws-0"), "active").unwrap();
+        std::fs::write(dir.path().join("ws-2"), "idle").unwrap();
+        let states = read_states(dir.path());
+
+        let windows = [window("@1", "ws/0", "a"), window("@3", "ws/1", "b")];
+        let claims = [claim("a", "ws/0"), claim("c", "ws/2"), claim("d", "alice")];
+        let rows = sessions(&windows, &claims, &states);
+
+        let summary: Vec<_> = rows
+            .iter()
+            .map(|r| (r.session_id.as_str(), r.state.as_deref(), r.mismatch))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("ws/0", Some("active"), None),
+                ("ws/1", None, Some(Mismatch::NoClaim)),
+                ("ws/2", Some("idle"), Some(Mismatch::NoWindow)),
+            ]
+        );
+        assert_eq!(rows[2].claimed_task.as_deref(), Some("c"));
+        assert_eq!(rows[2].window_id, None);
+    }
+
+    #[test]
+    fn format_sessions_aligns_and_flags() {
+        let rows = sessions(
+            &[window("@1", "ws/0", "alpha"), window("@12", "ws/1", "b")],
+            &[claim("alpha", "ws/0")],
+            &HashMap::from([("ws/0".to_string(), "active".to_string())]),
+        );
+        assert_eq!(
+            format_sessions(&rows),
+            "ws/0  @1   alpha  active\nws/1  @12  b      -       ! window but no claim\n"
+        );
+        assert_eq!(format_sessions(&[]), "No agent sessions.\n");
+    }
+
+    #[test]
+    fn session_row_json_uses_snake_case_mismatch() {
+        let rows = sessions(&[], &[claim("a", "ws/4")], &HashMap::new());
+        let json = serde_json::to_value(&rows).unwrap();
+        assert_eq!(json[0]["session_id"], "ws/4");
+        assert_eq!(json[0]["mismatch"], "no_window");
+        assert!(json[0]["window_id"].is_null());
+    }
+}
@@ -19,6 +19,64 @@ pub fn list_window_ids(session: &str) -> Result<Vec<String>> {
         .collect())
 }
 
+/// List the `@kbtz_sid` of every window in the session that has one.
+pub fn list_window_sids(session: &str) -> Result<Vec<String>> {
+    let output = Command::new("tmux")
+        .args(["list-windows", "-t", session, "-F", "#{@kbtz_sid}"])
+        .output()
+        .context("failed to run tmux list-windows")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("tmux list-windows failed for session {session}: {stderr}");
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_string())
+        .collect())
+}
+
+/// A window tagged by the orchestrator with `@kbtz_sid` and `@kbtz_task`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedWindow {
+    pub window_id: String,
+    pub session_id: String,
+    pub task: String,
+}
+
+/// List the agent windows in the session, skipping untagged ones.
+pub fn list_tagged_windows(session: &str) -> Result<Vec<TaggedWindow>> {
+    let output = Command::new("tmux")
+        .args([
+            "list-windows",
+            "-t",
+            session,
+            "-F",
+            "#{window_id} #{@kbtz_sid} #{@kbtz_task}",
+        ])
+        .output()
+        .context("failed to run tmux list-windows")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("tmux list-windows failed for session {session}: {stderr}");
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split(' ');
+            let window_id = parts.next()?;
+            let session_id = parts.next().filter(|s| !s.is_empty())?;
+            let task = parts.next().filter(|s| !s.is_empty())?;
+            Some(TaggedWindow {
+                window_id: window_id.to_string(),
+                session_id: session_id.to_string(),
+                task: task.to_string(),
+            })
+        })
+        .collect())
+}
+
 /// Get a tmux window option value (e.g., @kbtz_task).
 pub fn get_window_option(window_id: &str, option: &str) -> Result<Option<String>> {
     let output = Command::new("tmux")
@@ -49,6 +107,19 @@ pub fn set_window_option(window_id: &str, option: &str, value: &str) -> Result<(
     Ok(())
 }
 
+/// Rename a tmux window.
+pub fn rename_window(window_id: &str, name: &str) -> Result<()> {
+    let output = Command::new("tmux")
+        .args(["rename-window", "-t", window_id, name])
+        .output()
+        .context("failed to run tmux rename-window")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("tmux rename-window failed for {window_id}: {stderr}");
+    }
+    Ok(())
+}
+
 /// Check if a tmux session exists.
 pub fn has_session(name: &str) -> bool {
     Command::new("tmux")
@@ -60,14 +131,28 @@ pub fn has_session(name: &str) -> bool {
         .unwrap_or(false)
 }
 
+/// Build the `tmux new-session` args used by `create_session`.
+pub fn create_session_args(
+    name: &str,
+    window_name: &str,
+    command: &str,
+    args: &[&str],
+) -> Vec<String> {
+    let mut argv: Vec<String> = ["new-session", "-d", "-s", name, "-n", window_name, "--"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    argv.push(command.to_string());
+    argv.extend(args.iter().map(|s| s.to_string()));
+    argv
+}
+
 /// Create a new tmux session with the given name and initial window.
 pub fn create_session(name: &str, window_name: &str, command: &str, args: &[&str]) -> Result<()> {
-    let mut cmd = Command::new("tmux");
-    cmd.args(["new-session", "-d", "-s", name, "-n", window_name, "--"]);
-    cmd.arg(command);
-    cmd.args(args);
-
-    let output = cmd.output().context("failed to run tmux new-session")?;
+    let output = Command::new("tmux")
+        .args(create_session_args(name, window_name, command, args))
+        .output()
+        .context("failed to run tmux new-session")?;
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         bail!("tmux new-session failed: {stderr}");
@@ -101,15 +186,40 @@ pub fn set_window_option_default(session: &str, option: &str, value: &str) -> Re
     Ok(())
 }
 
+/// Session options applied by `configure_session`.
+const SESSION_OPTIONS: [(&str, &str); 1] = [("status-interval", "1")];
+
+/// Window option defaults applied by `configure_session` (apply to all new
+/// windows in the session).
+const WINDOW_OPTION_DEFAULTS: [(&str, &str); 3] = [
+    ("automatic-rename", "off"),
+    ("allow-rename", "off"),
+    ("remain-on-exit", "off"),
+];
+
+/// The `tmux set-option` arg lists `configure_session` runs, excluding the
+/// per-window fixups for windows that already exist.
+pub fn configure_session_args(session: &str) -> Vec<Vec<String>> {
+    let session_opts = SESSION_OPTIONS
+        .iter()
+        .map(|(option, value)| vec!["set-option", "-t", session, option, value]);
+    let window_opts = WINDOW_OPTION_DEFAULTS
+        .iter()
+        .map(|(option, value)| vec!["set-option", "-w", "-t", session, option, value]);
+    session_opts
+        .chain(window_opts)
+        .map(|argv| argv.into_iter().map(String::from).collect())
+        .collect()
+}
+
 /// Apply tmux settings for the workspace session.
 pub fn configure_session(session: &str) -> Result<()> {
-    // Session options.
-    set_session_option(session, "status-interval", "1")?;
-
-    // Window option defaults (apply to all new windows in this session).
-    set_window_option_default(session, "automatic-rename", "off")?;
-    set_window_option_default(session, "allow-rename", "off")?;
-    set_window_option_default(session, "remain-on-exit", "off")?;
+    for (option, value) in SESSION_OPTIONS {
+        set_session_option(session, option, value)?;
+    }
+    for (option, value) in WINDOW_OPTION_DEFAULTS {
+        set_window_option_default(session, option, value)?;
+    }
 
     // Also set on the existing window 0 (kbtz watch) since it was
     // created before these defaults were applied.
@@ -130,17 +240,16 @@ pub fn configure_session(session: &str) -> Result<()> {
     Ok(())
 }
 
-/// Spawn a new tmux window running the given command with environment variables.
-/// Returns the new window ID.
-pub fn spawn_window(
+/// Build the `tmux new-window` args used by `spawn_window`. Environment
+/// variables are emitted in sorted order so the arg list is deterministic.
+pub fn spawn_window_args(
     session: &str,
     name: &str,
     env: &HashMap<String, String>,
     command: &str,
     args: &[String],
-) -> Result<String> {
-    let mut cmd = Command::new("tmux");
-    cmd.args([
+) -> Vec<String> {
+    let mut argv: Vec<String> = [
         "new-window",
         "-d",
         "-P",
@@ -150,15 +259,35 @@ pub fn spawn_window(
         session,
         "-n",
         name,
-    ]);
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    let mut env: Vec<_> = env.iter().collect();
+    env.sort();
     for (key, val) in env {
-        cmd.args(["-e", &format!("{key}={val}")]);
+        argv.push("-e".into());
+        argv.push(format!("{key}={val}"));
     }
-    cmd.arg("--");
-    cmd.arg(command);
-    cmd.args(args);
+    argv.push("--".into());
+    argv.push(command.to_string());
+    argv.extend(args.iter().cloned());
+    argv
+}
 
-    let output = cmd.output().context("failed to run tmux new-window")?;
+/// Spawn a new tmux window running the given command with environment variables.
+/// Returns the new window ID.
+pub fn spawn_window(
+    session: &str,
+    name: &str,
+    env: &HashMap<String, String>,
+    command: &str,
+    args: &[String],
+) -> Result<String> {
+    let output = Command::new("tmux")
+        .args(spawn_window_args(session, name, env, command, args))
+        .output()
+        .context("failed to run tmux new-window")?;
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         bail!("tmux new-window failed: {stderr}");
@@ -212,3 +341,66 @@ pub fn remove_pane_hook(session: &str) -> Result<()> {
         .output(); // capture stderr to avoid leaking to terminal
     Ok(())
 }
+
+/// Render a command line for display, single-quoting args that contain
+/// anything beyond shell-safe characters.
+pub fn format_command(program: &str, args: &[String]) -> String {
+    let mut line = program.to_string();
+    for arg in args {
+        line.push(' ');
+        let safe = !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c));
+        if safe {
+            line.push_str(arg);
+        } else {
+            line.push('\'');
+            line.push_str(&arg.replace('\'', "'\\''"));
+            line.push('\'');
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_window_args_sorts_env_and_separates_command() {
+        let env = HashMap::from([
+            ("KBTZ_TASK".to_string(), "a".to_string()),
+            ("KBTZ_DB".to_string(), "/tmp/db".to_string()),
+        ]);
+        let argv = spawn_window_args("ws", "win", &env, "claude", &["--flag".into()]);
+        let tail: Vec<&str> = argv[9..].iter().map(String::as_str).collect();
+        assert_eq!(
+            tail,
+            [
+                "-e",
+                "KBTZ_DB=/tmp/db",
+                "-e",
+                "KBTZ_TASK=a",
+                "--",
+                "claude",
+                "--flag"
+            ]
+        );
+    }
+
+    #[test]
+    fn format_command_quotes_unsafe_args() {
+        let args = vec![
+            "new-session".to_string(),
+            "-n".to_string(),
+            "my window".to_string(),
+            "it's".to_string(),
+            String::new(),
+        ];
+        assert_eq!(
+            format_command("tmux", &args),
+            "tmux new-session -n 'my window' 'it'\\''s' ''"
+        );
+    }
+}
@@ -25,12 +25,14 @@ fn full_lifecycle_scenario() {
                 task_name: "task-a".into(),
                 window_id: "@1".into(),
                 phase: WindowPhase::Running,
+                shepherd: false,
             },
             WindowSnapshot {
                 session_id: "ws/1".into(),
                 task_name: "task-b".into(),
                 window_id: "@2".into(),
                 phase: WindowPhase::Running,
+                shepherd: false,
             },
         ],
         max_concurrency: 2,
@@ -52,12 +54,14 @@ fn full_lifecycle_scenario() {
                 task_name: "task-a".into(),
                 window_id: "@1".into(),
                 phase: WindowPhase::Gone,
+                shepherd: false,
             },
             WindowSnapshot {
                 session_id: "ws/1".into(),
                 task_name: "task-b".into(),
                 window_id: "@2".into(),
                 phase: WindowPhase::Running,
+                shepherd: false,
             },
         ],
         max_concurrency: 2,
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{bail, Context, Result};
 use rusqlite::ffi::ErrorCode;
@@ -9,13 +9,15 @@ use rusqlite::Connection;
 use kbtz::model::Task;
 use kbtz::ops;
 use kbtz::ui::{ActiveTaskPolicy, NotesPanel, TreeView};
+use kbtz_workspace::shepherd;
 
 use crate::backend::Backend;
-use crate::lifecycle::{
-    self, SessionAction, SessionPhase, SessionSnapshot, WorldSnapshot, GRACEFUL_TIMEOUT,
+use crate::lifecycle::{self, SessionAction, SessionPhase, SessionSnapshot, WorldSnapshot};
+use crate::session::{
+    PtySpawner, SessionHandle, SessionSpawner, SessionStatus, ShepherdSpawner, TerminalOptions,
 };
-use crate::session::{PtySpawner, SessionHandle, SessionSpawner, SessionStatus, ShepherdSpawner};
 use crate::shepherd_session::ShepherdSession;
+use crate::status_report::{SessionReport, WorkspaceReport, STATUS_REPORT_FILE};
 
 pub struct TermSize {
     pub rows: u16,
@@ -29,6 +31,12 @@ pub struct TrackedSession {
     pub unread: bool,
 }
 
+/// Database snapshot backing the tree view.
+struct TreeCache {
+    tasks: Vec<Task>,
+    deps: HashMap<String, ops::TaskDeps>,
+}
+
 pub struct App {
     // kbtz state
     pub db_path: String,
@@ -48,10 +56,41 @@ pub struct App {
     pub max_concurrency: usize,
     pub manual: bool,
     pub prefer: Option<String>,
+    /// Restricts auto-spawn claims to this task and its descendants.
+    pub root: Option<String>,
     pub backends: HashMap<String, Box<dyn Backend>>,
     pub default_backend: String,
+    /// `workspace.exit_sequence`, applied to backends created on demand
+    /// for agent types without their own config.
+    pub default_exit_sequence: Option<Vec<u8>>,
     pub spawner: Box<dyn SessionSpawner>,
     pub persistent_sessions: bool,
+    /// Control byte that starts a passthrough-mode command (Ctrl-B by default).
+    pub prefix_key: u8,
+    /// Also copy scroll-mode dumps to the clipboard via OSC 52.
+    pub osc52_clipboard: bool,
+    /// Clipboard and scrollback settings for session terminals.
+    pub terminal: TerminalOptions,
+    /// Whether zoomed mode shows the session list sidebar.
+    pub sidebar: bool,
+    /// Sidebar width in columns (including its border) when shown.
+    pub sidebar_width: u16,
+    /// How long stopping sessions get to exit before being force-killed.
+    pub graceful_timeout: Duration,
+    /// Respawn workers that exit on their own while their task is still
+    /// claimed, keeping the session ID instead of releasing the task.
+    pub restart_in_place: bool,
+    /// Runtime budget for worker sessions (`workspace.max_session_minutes`).
+    pub max_session_runtime: Option<Duration>,
+    /// Idle time after which a worker's task is paused
+    /// (`workspace.idle_pause_minutes`).
+    pub idle_pause: Option<Duration>,
+    /// When each worker session started reporting `idle`.
+    idle_since: HashMap<String, Instant>,
+    /// Last report written to `workspace-status.json`.
+    last_status_report: Option<WorkspaceReport>,
+    /// Recent in-place restart times per task, for the crash-loop cap.
+    restart_history: HashMap<String, Vec<Instant>>,
     /// Default working directory for agent sessions.
     /// Resolved at startup: config directory > workspace cwd.
     pub default_directory: PathBuf,
@@ -61,7 +100,14 @@ pub struct App {
 
     pub term: TermSize,
     pub tree: TreeView,
+    /// Set when the database changed; the next `refresh_tree` re-reads it.
     pub tree_dirty: bool,
+    /// Tasks and dependencies from the last database read. `None` until
+    /// the first `refresh_tree`.
+    tree_cache: Option<TreeCache>,
+    /// Modification time and length of each session's status file when it
+    /// was last parsed, so unchanged files are skipped.
+    status_stamps: HashMap<String, (SystemTime, u64)>,
     pub notes_panel: Option<NotesPanel>,
 
     /// The session_id currently being viewed in passthrough mode (None in tree view).
@@ -70,6 +116,15 @@ pub struct App {
 
 pub const TOPLEVEL_SESSION_ID: &str = "ws/toplevel";
 
+/// Narrowest session width the sidebar may leave; below this the sidebar
+/// is hidden automatically.
+pub const MIN_SESSION_COLS: u16 = 40;
+
+/// In-place restarts allowed per task within `RESTART_WINDOW` before the
+/// task is released instead, to stop crash loops.
+const MAX_RESTARTS: usize = 3;
+const RESTART_WINDOW: Duration = Duration::from_secs(300);
+
 /// What the top-level loop should do next.
 pub enum Action {
     Continue,
@@ -87,21 +142,8 @@ fn session_id_to_filename(session_id: &str) -> String {
 
 /// Remove the status, socket, PID, and child-PID files for a session.
 fn cleanup_session_files(status_dir: &Path, session_id: &str) {
-    let filename = session_id_to_filename(session_id);
-    let _ = std::fs::remove_file(status_dir.join(&filename));
-    let _ = std::fs::remove_file(status_dir.join(format!("{filename}.sock")));
-    let _ = std::fs::remove_file(status_dir.join(format!("{filename}.pid")));
-    let _ = std::fs::remove_file(status_dir.join(format!("{filename}.child-pid")));
-}
-
-/// Kill the agent child process group from the `.child-pid` file next to a shepherd `.pid` file.
-fn kill_child_from_pid_file(pid_path: &Path) {
-    let child_pid_path = pid_path.with_extension("child-pid");
-    if let Ok(pid_str) = std::fs::read_to_string(&child_pid_path) {
-        if let Ok(pid) = pid_str.trim().parse::<i32>() {
-            unsafe { libc::kill(-pid, libc::SIGKILL) };
-        }
-    }
+    let _ = std::fs::remove_file(status_dir.join(session_id_to_filename(session_id)));
+    shepherd::remove_files(status_dir, session_id);
 }
 
 /// Returns true if the error is an SQLite SQLITE_BUSY (database locked)
@@ -119,9 +161,21 @@ impl App {
         status_dir: PathBuf,
         max_concurrency: usize,
         manual: bool,
+        auto_confirm: bool,
+        prefix_key: u8,
+        osc52_clipboard: bool,
+        terminal: TerminalOptions,
+        sidebar: bool,
+        sidebar_width: u16,
+        graceful_timeout: Duration,
+        restart_in_place: bool,
+        max_session_runtime: Option<Duration>,
+        idle_pause: Option<Duration>,
         prefer: Option<String>,
+        root: Option<String>,
         backends: HashMap<String, Box<dyn Backend>>,
         default_backend: String,
+        default_exit_sequence: Option<Vec<u8>>,
         term: TermSize,
         persistent_sessions: bool,
         default_directory: PathBuf,
@@ -138,9 +192,10 @@ impl App {
         let spawner: Box<dyn SessionSpawner> = if persistent_sessions {
             Box::new(ShepherdSpawner {
                 status_dir: status_dir.clone(),
+                terminal,
             })
         } else {
-            Box::new(PtySpawner)
+            Box::new(PtySpawner { terminal })
         };
         let claude_sessions_dir = status_dir.join("claude-sessions");
         std::fs::create_dir_all(&claude_sessions_dir)
@@ -162,18 +217,49 @@ impl App {
             max_concurrency,
             manual,
             prefer,
+            root,
             backends,
             default_backend,
+            default_exit_sequence,
             spawner,
             persistent_sessions,
+            prefix_key,
+            osc52_clipboard,
+            terminal,
+            sidebar,
+            sidebar_width,
+            graceful_timeout,
+            restart_in_place,
+            max_session_runtime,
+            idle_pause,
+            idle_since: HashMap::new(),
+            last_status_report: None,
+            restart_history: HashMap::new(),
             default_directory,
             toplevel: None,
             term,
-            tree: TreeView::new(ActiveTaskPolicy::Confirm),
+            tree: TreeView::new(if auto_confirm {
+                ActiveTaskPolicy::Allow
+            } else {
+                ActiveTaskPolicy::Confirm
+            }),
             tree_dirty: false,
+            tree_cache: None,
+            status_stamps: HashMap::new(),
             notes_panel: None,
             zoomed_session: None,
         };
+        let issues = kbtz::ops::validate_integrity(&app.conn)?;
+        if !issues.is_empty() {
+            kbtz::debug_log::log(&format!("startup: {} integrity issue(s)", issues.len()));
+            app.tree.error = Some(format!(
+                "{} task DB integrity issue(s); run `kbtz check --fix`",
+                issues.len()
+            ));
+        }
+        app.refresh_tree()?;
+        app.tree
+            .load_collapsed(status_dir.join("tree-state.json"), &app.conn)?;
         app.refresh_tree()?;
         if persistent_sessions {
             app.reconnect_sessions()?;
@@ -183,6 +269,19 @@ impl App {
         Ok(app)
     }
 
+    /// Runtime left before a worker session hits `max_session_runtime`.
+    /// `None` when there is no budget or `session_id` is not a worker.
+    pub fn runtime_left(&self, session_id: &str) -> Option<Duration> {
+        let budget = self.max_session_runtime?;
+        let ts = self.sessions.get(session_id)?;
+        Some(budget.saturating_sub(ts.handle.started_at().elapsed()))
+    }
+
+    /// Display label for the prefix key, e.g. `^B`.
+    pub fn prefix_label(&self) -> String {
+        format!("^{}", (self.prefix_key | 0x40) as char)
+    }
+
     /// Look up any session by its session_id, checking both worker sessions
     /// and the toplevel session.
     pub fn get_session(&self, session_id: &str) -> Option<&dyn SessionHandle> {
@@ -208,13 +307,23 @@ impl App {
         }
     }
 
-    /// Rebuild the tree view from the database.
+    /// Rebuild the tree view. Re-reads the database only when `tree_dirty`
+    /// is set (or nothing is cached yet); otherwise collapse, filter, and
+    /// visibility changes are applied to the cached tasks.
     pub fn refresh_tree(&mut self) -> Result<()> {
-        let mut tasks = ops::list_tasks(&self.conn, None, true, None, None, None)?;
+        if self.tree_dirty || self.tree_cache.is_none() {
+            self.tree_cache = Some(TreeCache {
+                tasks: ops::list_tasks(&self.conn, None, true, None, None, None)?,
+                deps: ops::get_all_deps(&self.conn)?,
+            });
+            self.tree_dirty = false;
+        }
+        let cache = self.tree_cache.as_ref().expect("tree cache loaded above");
+        let mut tasks = cache.tasks.clone();
         let session_tasks: std::collections::HashSet<String> =
             self.task_to_session.keys().cloned().collect();
         self.tree.filter_tasks(&mut tasks, &session_tasks);
-        let rows = kbtz::ui::flatten_tree(&tasks, &self.tree.collapsed, &self.conn)?;
+        let rows = kbtz::ui::flatten_tree_with_deps(&tasks, &self.tree.collapsed, &cache.deps);
         self.tree.rows = match &self.tree.filter {
             Some(query) => kbtz::ui::filter_rows(&rows, query),
             None => rows,
@@ -228,6 +337,13 @@ impl App {
         Ok(())
     }
 
+    /// Re-read the database and rebuild the tree, for use after the app
+    /// itself writes to the database.
+    pub fn reload_tree(&mut self) -> Result<()> {
+        self.tree_dirty = true;
+        self.refresh_tree()
+    }
+
     /// Toggle the notes panel for the currently selected task.
     pub fn toggle_notes(&mut self) -> Result<()> {
         if self.notes_panel.is_some() {
@@ -255,8 +371,12 @@ impl App {
     /// type name as the command.
     fn ensure_backend(&mut self, agent_type: &str) {
         if !self.backends.contains_key(agent_type) {
-            self.backends
-                .insert(agent_type.to_string(), crate::backend::generic(agent_type));
+            let backend = crate::backend::generic(agent_type);
+            let backend = match &self.default_exit_sequence {
+                Some(sequence) => crate::backend::with_exit_sequence(backend, sequence.clone()),
+                None => backend,
+            };
+            self.backends.insert(agent_type.to_string(), backend);
         }
     }
 
@@ -267,6 +387,9 @@ impl App {
 
     /// Build a snapshot of the current world for the pure tick function.
     fn snapshot(&mut self) -> WorldSnapshot {
+        let now = std::time::Instant::now();
+        let idle_since = &mut self.idle_since;
+        idle_since.retain(|sid, _| self.sessions.contains_key(sid));
         let sessions = self
             .sessions
             .iter_mut()
@@ -290,9 +413,18 @@ impl App {
                     SessionPhase::Running
                 };
 
+                let idle_since = if *ts.handle.status() == SessionStatus::Idle {
+                    Some(*idle_since.entry(session_id.clone()).or_insert(now))
+                } else {
+                    idle_since.remove(session_id);
+                    None
+                };
+
                 SessionSnapshot {
                     session_id: session_id.clone(),
                     phase,
+                    started_at: ts.handle.started_at(),
+                    idle_since,
                 }
             })
             .collect();
@@ -303,16 +435,81 @@ impl App {
         WorldSnapshot {
             sessions,
             max_concurrency: effective_concurrency,
-            now: std::time::Instant::now(),
+            graceful_timeout: self.graceful_timeout,
+            max_runtime: self.max_session_runtime,
+            idle_pause: self.idle_pause,
+            now,
         }
     }
 
     /// Execute lifecycle actions. Returns a debug description if anything notable happened.
     fn execute_actions(&mut self, actions: Vec<SessionAction>) -> Result<Option<String>> {
         let mut descriptions: Vec<String> = Vec::new();
+        // Sessions restarted in place take back the slot the lifecycle
+        // counted as free.
+        let mut restarted = 0;
 
         for action in actions {
             match action {
+                SessionAction::RequestExit { session_id } => {
+                    let Some(ts) = self.sessions.get(&session_id) else {
+                        continue;
+                    };
+                    let task = ts.handle.task_name().to_string();
+                    let agent_type = ts.agent_type.clone();
+                    kbtz::debug_log::log(&format!(
+                        "action: request_exit {session_id} (task={task}, over runtime budget)"
+                    ));
+                    self.ensure_backend(&agent_type);
+                    if let Some(ts) = self.sessions.get_mut(&session_id) {
+                        self.backends[&agent_type].request_exit(ts.handle.as_mut());
+                        let minutes = self.max_session_runtime.unwrap_or_default().as_secs() / 60;
+                        let note = format!(
+                            "Session {session_id} was stopped after exceeding the \
+                             {minutes}-minute runtime budget (workspace.max_session_minutes)."
+                        );
+                        if let Err(e) = ops::add_note(&self.conn, &task, &note) {
+                            kbtz::debug_log::log(&format!(
+                                "action: failed to note timeout on {task}: {e}"
+                            ));
+                        }
+                        descriptions.push(format!("{session_id} over budget"));
+                    }
+                }
+                SessionAction::PauseIdle { session_id } => {
+                    let Some(ts) = self.sessions.get(&session_id) else {
+                        continue;
+                    };
+                    let task = ts.handle.task_name().to_string();
+                    let agent_type = ts.agent_type.clone();
+                    let minutes = self.idle_pause.unwrap_or_default().as_secs() / 60;
+                    kbtz::debug_log::log(&format!(
+                        "action: pause_idle {session_id} (task={task}, idle {minutes}m)"
+                    ));
+                    // Pause before stopping so the exit neither releases the
+                    // task for re-claiming nor restarts it in place.
+                    if let Err(e) = ops::pause_task(&self.conn, &task) {
+                        kbtz::debug_log::log(&format!("action: failed to pause {task}: {e}"));
+                    }
+                    let note = format!(
+                        "Paused after session {session_id} was idle for {minutes} minutes \
+                         (workspace.idle_pause_minutes); unpause to resume."
+                    );
+                    if let Err(e) = ops::add_note(&self.conn, &task, &note) {
+                        kbtz::debug_log::log(&format!(
+                            "action: failed to note idle pause on {task}: {e}"
+                        ));
+                    }
+                    self.ensure_backend(&agent_type);
+                    if let Some(ts) = self.sessions.get_mut(&session_id) {
+                        self.backends[&agent_type].request_exit(ts.handle.as_mut());
+                    }
+                    self.idle_since.remove(&session_id);
+                    self.tree.error =
+                        Some(format!("{task} paused: {session_id} idle for {minutes}m"));
+                    self.tree_dirty = true;
+                    descriptions.push(format!("{session_id} idle, paused {task}"));
+                }
                 SessionAction::ForceKill { session_id } => {
                     if let Some(ts) = self.sessions.get_mut(&session_id) {
                         kbtz::debug_log::log(&format!(
@@ -334,12 +531,19 @@ impl App {
                         // If it wasn't force-killed, it exited on its own.
                         if !descriptions.iter().any(|d| d.starts_with(&session_id)) {
                             descriptions.push(format!("{session_id} exited"));
+                            if self.try_restart_in_place(&session_id) {
+                                if self.sessions.contains_key(&session_id) {
+                                    descriptions.push(format!("{session_id} restarted"));
+                                    restarted += 1;
+                                }
+                                continue;
+                            }
                         }
                         self.remove_session(&session_id);
                     }
                 }
                 SessionAction::SpawnUpTo { count } => {
-                    self.spawn_up_to(count)?;
+                    self.spawn_up_to(count.saturating_sub(restarted))?;
                 }
             }
         }
@@ -360,6 +564,7 @@ impl App {
     /// time — the existing `is_db_busy` error handling in `spawn_up_to`
     /// and `remove_session` already handles this gracefully.
     pub fn tick(&mut self) -> Result<Option<String>> {
+        self.write_status_report();
         let world = self.snapshot();
         let actions = lifecycle::tick(&world);
         if actions.is_empty() {
@@ -373,60 +578,94 @@ impl App {
         result
     }
 
+    /// Write `workspace-status.json` into the status directory when the
+    /// sessions, their statuses, or the concurrency settings have changed
+    /// since the last write. Failures are logged and retried next tick.
+    fn write_status_report(&mut self) {
+        let sessions = self
+            .sessions
+            .iter()
+            .map(|(session_id, ts)| SessionReport {
+                session_id: session_id.clone(),
+                task: ts.handle.task_name().to_string(),
+                status: ts.handle.status().clone(),
+                pid: ts.handle.process_id(),
+                uptime_secs: ts.handle.started_at().elapsed().as_secs(),
+            })
+            .collect();
+        let report = WorkspaceReport::new(self.max_concurrency, self.manual, sessions);
+        if self
+            .last_status_report
+            .as_ref()
+            .is_some_and(|last| last.same_state(&report))
+        {
+            return;
+        }
+        match report.write_to(&self.status_dir) {
+            Ok(()) => self.last_status_report = Some(report),
+            Err(e) => kbtz::debug_log::log(&format!("status report: write failed: {e}")),
+        }
+    }
+
     /// Spawn sessions for claimable tasks, up to `count` new sessions.
+    ///
+    /// All tasks are claimed in one transaction, with session ids following
+    /// `counter`; sessions are then spawned in claim order.
     fn spawn_up_to(&mut self, count: usize) -> Result<()> {
-        for _ in 0..count {
-            self.counter += 1;
-            let session_id = format!("{}{}", kbtz::paths::SESSION_ID_PREFIX, self.counter);
-
-            let claim =
-                match ops::claim_next_task(&self.conn, &session_id, self.prefer.as_deref(), None) {
-                    Ok(v) => v,
-                    Err(e) if is_db_busy(&e) => {
-                        // Transient lock contention — skip this tick, try again next time.
-                        self.counter -= 1;
-                        break;
-                    }
-                    Err(e) => return Err(e),
-                };
-            match claim {
-                Some(task_name) => {
-                    kbtz::debug_log::log(&format!("spawn: claimed {task_name} as {session_id}"));
-                    let task = ops::get_task(&self.conn, &task_name)?;
-                    let agent_type = self.resolve_agent_type(&task).to_string();
-                    self.ensure_backend(&agent_type);
-                    let backend = self.backends[&agent_type].as_ref();
+        if count == 0 {
+            return Ok(());
+        }
+        let claims = match ops::claim_next_batch(
+            &self.conn,
+            kbtz::paths::SESSION_ID_PREFIX,
+            self.counter + 1,
+            count,
+            self.prefer.as_deref(),
+            self.root.as_deref(),
+            None,
+        ) {
+            Ok(v) => v,
+            // Transient lock contention — skip this tick, try again next time.
+            Err(e) if is_db_busy(&e) => return Ok(()),
+            Err(e) => return Err(e),
+        };
 
-                    match self.spawn_session_with(backend, &agent_type, &task, &session_id) {
-                        Ok(handle) => {
-                            kbtz::debug_log::log(&format!(
-                                "spawn: session started for {task_name} ({session_id}, agent={agent_type})"
-                            ));
-                            self.task_to_session
-                                .insert(task_name.clone(), session_id.clone());
-                            self.sessions.insert(
-                                session_id,
-                                TrackedSession {
-                                    handle,
-                                    agent_type,
-                                    unread: false,
-                                },
-                            );
-                        }
-                        Err(e) => {
-                            kbtz::debug_log::log(&format!(
-                                "spawn: FAILED for {task_name} ({session_id}): {e}"
-                            ));
-                            // Failed to spawn — release the claim
-                            let _ = ops::release_task(&self.conn, &task_name, &session_id);
-                            self.counter -= 1;
-                            self.tree.error = Some(format!("failed to spawn session: {e}"));
-                            break;
-                        }
-                    }
+        let mut claims = claims.into_iter();
+        while let Some((session_id, task_name)) = claims.next() {
+            kbtz::debug_log::log(&format!("spawn: claimed {task_name} as {session_id}"));
+            let task = ops::get_task(&self.conn, &task_name)?;
+            let agent_type = self.resolve_agent_type(&task).to_string();
+            self.ensure_backend(&agent_type);
+            let backend = self.backends[&agent_type].as_ref();
+
+            match self.spawn_session_with(backend, &agent_type, &task, &session_id) {
+                Ok(handle) => {
+                    kbtz::debug_log::log(&format!(
+                        "spawn: session started for {task_name} ({session_id}, agent={agent_type})"
+                    ));
+                    self.counter += 1;
+                    self.task_to_session
+                        .insert(task_name.clone(), session_id.clone());
+                    self.sessions.insert(
+                        session_id,
+                        TrackedSession {
+                            handle,
+                            agent_type,
+                            unread: false,
+                        },
+                    );
                 }
-                None => {
-                    self.counter -= 1;
+                Err(e) => {
+                    kbtz::debug_log::log(&format!(
+                        "spawn: FAILED for {task_name} ({session_id}): {e}"
+                    ));
+                    // Failed to spawn — release this claim and the rest of
+                    // the batch so their session ids can be reused.
+                    let _ = ops::release_task(&self.conn, &task_name, &session_id);
+                    for (session_id, task_name) in claims {
+                        let _ = ops::release_task(&self.conn, &task_name, &session_id);
+                    }
+                    self.tree.error = Some(format!("failed to spawn session: {e}"));
                     break;
                 }
             }
@@ -498,13 +737,16 @@ impl App {
         let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
         let session_id = TOPLEVEL_SESSION_ID;
         let env_vars: Vec<(&str, &str)> = vec![("KBTZ_DB", &self.db_path)];
-        let session = PtySpawner.spawn(
+        let spawner = PtySpawner {
+            terminal: self.terminal,
+        };
+        let session = spawner.spawn(
             &command,
             &arg_refs,
             "toplevel",
             session_id,
             self.term.rows,
-            self.term.cols,
+            self.session_cols(),
             &env_vars,
             &self.default_directory,
         )?;
@@ -607,7 +849,7 @@ impl App {
             &task.name,
             session_id,
             self.term.rows,
-            self.term.cols,
+            self.session_cols(),
             &env_vars,
             &session_dir,
         );
@@ -653,26 +895,41 @@ impl App {
     }
 
     /// Read status files from the status directory and update session statuses.
+    ///
+    /// Files whose modification time and length match the last read are
+    /// skipped, and `set_status` is only called when the status changes.
     pub fn read_status_files(&mut self) -> Result<()> {
+        self.status_stamps
+            .retain(|sid, _| self.sessions.contains_key(sid));
         for (session_id, ts) in &mut self.sessions {
             let path = self.status_dir.join(session_id_to_filename(session_id));
-            if let Ok(content) = std::fs::read_to_string(&path) {
-                let new_status = SessionStatus::from_str(&content);
-                if *ts.handle.status() != new_status {
-                    kbtz::debug_log::log(&format!(
-                        "status: {} {} -> {} (task={})",
-                        session_id,
-                        ts.handle.status().label(),
-                        new_status.label(),
-                        ts.handle.task_name()
-                    ));
-                    // Mark unread if the user is not currently viewing this session.
-                    if self.zoomed_session.as_deref() != Some(session_id.as_str()) {
-                        ts.unread = true;
-                    }
-                }
-                ts.handle.set_status(new_status);
+            let Ok(meta) = std::fs::metadata(&path) else {
+                continue;
+            };
+            let stamp = (meta.modified()?, meta.len());
+            if self.status_stamps.get(session_id) == Some(&stamp) {
+                continue;
+            }
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            self.status_stamps.insert(session_id.clone(), stamp);
+            let new_status = SessionStatus::from_str(&content);
+            if *ts.handle.status() == new_status {
+                continue;
             }
+            kbtz::debug_log::log(&format!(
+                "status: {} {} -> {} (task={})",
+                session_id,
+                ts.handle.status().label(),
+                new_status.label(),
+                ts.handle.task_name()
+            ));
+            // Mark unread if the user is not currently viewing this session.
+            if self.zoomed_session.as_deref() != Some(session_id.as_str()) {
+                ts.unread = true;
+            }
+            ts.handle.set_status(new_status);
         }
         Ok(())
     }
@@ -724,8 +981,24 @@ impl App {
             // valid and resumable. Only unsolicited non-zero exits indicate
             // a crash during init that would cause a resume-crash loop.
             let was_requested = ts.handle.stopping_since().is_some();
-            let child_failed =
-                !was_requested && ts.handle.exit_code().is_some_and(|code| code != 0);
+            // `exit_code` reaps the process if `is_alive` has not already
+            // recorded its status.
+            let failed_code = ts
+                .handle
+                .exit_code()
+                .or(ts.handle.exit_status())
+                .filter(|&code| !was_requested && code != 0);
+            let child_failed = failed_code.is_some();
+
+            if let Some(code) = failed_code.filter(|_| !task_deleted) {
+                let note = format!("Session {sid} exited with code {code}.");
+                if let Err(e) = ops::add_note(&self.conn, &task_name, &note) {
+                    kbtz::debug_log::log(&format!(
+                        "remove_session: failed to note exit code on {task_name}: {e}"
+                    ));
+                }
+                self.tree.error = Some(format!("{task_name}: agent exited with code {code}"));
+            }
 
             if task_deleted || child_failed {
                 if child_failed {
@@ -739,6 +1012,89 @@ impl App {
         }
     }
 
+    /// With `restart_in_place`, respawn a worker that exited on its own
+    /// while its task is still active and claimed by it, keeping the same
+    /// session ID so the claim never lapses. Returns false (leaving the
+    /// session for `remove_session`) when the option is off, the exit was
+    /// requested, the claim moved, or the restart cap was hit. A failed
+    /// respawn releases the task itself and still returns true.
+    fn try_restart_in_place(&mut self, session_id: &str) -> bool {
+        if !self.restart_in_place {
+            return false;
+        }
+        let Some(ts) = self.sessions.get_mut(session_id) else {
+            return false;
+        };
+        if ts.handle.stopping_since().is_some() {
+            return false;
+        }
+        let task_name = ts.handle.task_name().to_string();
+        let agent_type = ts.agent_type.clone();
+        let child_failed = ts.handle.exit_code().is_some_and(|code| code != 0);
+        let task = match ops::get_task(&self.conn, &task_name) {
+            Ok(task) if task.status == "active" && task.assignee.as_deref() == Some(session_id) => {
+                task
+            }
+            _ => return false,
+        };
+
+        let now = Instant::now();
+        let history = self.restart_history.entry(task_name.clone()).or_default();
+        history.retain(|t| now.duration_since(*t) < RESTART_WINDOW);
+        if history.len() >= MAX_RESTARTS {
+            kbtz::debug_log::log(&format!(
+                "restart_in_place: {task_name} hit restart cap, releasing"
+            ));
+            self.restart_history.remove(&task_name);
+            self.tree.error = Some(format!(
+                "{task_name} exited {MAX_RESTARTS} times in {}s; released",
+                RESTART_WINDOW.as_secs()
+            ));
+            return false;
+        }
+        history.push(now);
+
+        // Same reasoning as remove_session: an unsolicited crash may be a
+        // resume loop, so start the conversation fresh.
+        if child_failed {
+            let _ = std::fs::remove_file(self.claude_sessions_dir.join(&task_name));
+        }
+        self.sessions.remove(session_id);
+        cleanup_session_files(&self.status_dir, session_id);
+
+        self.ensure_backend(&agent_type);
+        let backend = self.backends[&agent_type].as_ref();
+        match self.spawn_session_with(backend, &agent_type, &task, session_id) {
+            Ok(handle) => {
+                kbtz::debug_log::log(&format!(
+                    "restart_in_place: respawned {task_name} as {session_id}"
+                ));
+                self.sessions.insert(
+                    session_id.to_string(),
+                    TrackedSession {
+                        handle,
+                        agent_type,
+                        unread: false,
+                    },
+                );
+                true
+            }
+            Err(e) => {
+                kbtz::debug_log::log(&format!(
+                    "restart_in_place: FAILED for {task_name} ({session_id}): {e}"
+                ));
+                let _ = ops::release_task(&self.conn, &task_name, session_id);
+                if self.task_to_session.get(&task_name).map(String::as_str) == Some(session_id) {
+                    self.task_to_session.remove(&task_name);
+                }
+                self.tree.error = Some(format!("failed to restart session: {e}"));
+                // Already cleaned up; report as handled so the caller does
+                // not try to remove it again.
+                true
+            }
+        }
+    }
+
     /// Reconnect to shepherd sessions from a previous workspace instance.
     ///
     /// This deliberately ignores `max_concurrency` — ALL surviving sessions
@@ -755,11 +1111,10 @@ impl App {
             }
             let stem = path.file_stem().unwrap().to_string_lossy();
             let session_id = kbtz::paths::filename_to_session_id(&stem);
-            let pid_path = path.with_extension("pid");
+            let pid_path = shepherd::pid_path(&self.status_dir, &session_id);
 
             // Verify the shepherd process is still alive before attempting to connect.
-            let pid_result = std::fs::read_to_string(&pid_path);
-            if pid_result.is_err() {
+            if !pid_path.exists() {
                 kbtz::debug_log::log(&format!(
                     "reconnect: no PID file for {session_id} at {}, skipping",
                     pid_path.display()
@@ -767,25 +1122,19 @@ impl App {
                 cleanup_session_files(&self.status_dir, &session_id);
                 continue;
             }
-            if let Ok(pid_str) = pid_result {
-                if let Ok(pid) = pid_str.trim().parse::<i32>() {
-                    let ret = unsafe { libc::kill(pid, 0) };
-                    let errno = std::io::Error::last_os_error();
-                    let alive =
-                        ret == 0 || (ret == -1 && errno.raw_os_error() == Some(libc::EPERM));
-                    kbtz::debug_log::log(&format!(
-                        "reconnect: checking {session_id} shepherd pid={pid} \
-                         kill(0)={ret} errno={errno} alive={alive}"
-                    ));
-                    if !alive {
-                        // Shepherd died — clean up stale files and kill orphaned child
-                        kill_child_from_pid_file(&pid_path);
-                        cleanup_session_files(&self.status_dir, &session_id);
-                        if let Some(task_name) = self.find_task_for_session(&session_id) {
-                            let _ = ops::release_task(&self.conn, &task_name, &session_id);
-                        }
-                        continue;
+            if let Some(pid) = shepherd::read_pid(&pid_path) {
+                let alive = shepherd::pid_alive(pid);
+                kbtz::debug_log::log(&format!(
+                    "reconnect: checking {session_id} shepherd pid={pid} alive={alive}"
+                ));
+                if !alive {
+                    // Shepherd died — clean up stale files and kill orphaned child
+                    shepherd::kill_child(&pid_path);
+                    cleanup_session_files(&self.status_dir, &session_id);
+                    if let Some(task_name) = self.find_task_for_session(&session_id) {
+                        let _ = ops::release_task(&self.conn, &task_name, &session_id);
                     }
+                    continue;
                 }
             }
 
@@ -793,13 +1142,13 @@ impl App {
             match self.find_task_for_session(&session_id) {
                 Some(task_name) => {
                     match ShepherdSession::connect(
-                        &path,
-                        &pid_path,
+                        &self.status_dir,
                         &task_name,
                         &session_id,
                         self.term.rows,
-                        self.term.cols,
+                        self.session_cols(),
                         None, // no Child handle for reconnected sessions
+                        self.terminal,
                     ) {
                         Ok(session) => {
                             // Resolve agent type from the task's agent field.
@@ -835,12 +1184,7 @@ impl App {
                             ));
                             // Kill child and shepherd before deleting PID files,
                             // otherwise they become permanently orphaned.
-                            kill_child_from_pid_file(&pid_path);
-                            if let Ok(pid_str) = std::fs::read_to_string(&pid_path) {
-                                if let Ok(pid) = pid_str.trim().parse::<i32>() {
-                                    unsafe { libc::kill(pid, libc::SIGKILL) };
-                                }
-                            }
+                            shepherd::kill(&self.status_dir, &session_id);
                             cleanup_session_files(&self.status_dir, &session_id);
                             let _ = ops::release_task(&self.conn, &task_name, &session_id);
                         }
@@ -851,12 +1195,7 @@ impl App {
                         "reconnect: orphaned shepherd {session_id}, killing"
                     ));
                     // No task claim -- orphaned shepherd. Kill child and shepherd, clean up.
-                    kill_child_from_pid_file(&pid_path);
-                    if let Ok(pid_str) = std::fs::read_to_string(&pid_path) {
-                        if let Ok(pid) = pid_str.trim().parse::<i32>() {
-                            unsafe { libc::kill(pid, libc::SIGKILL) };
-                        }
-                    }
+                    shepherd::kill(&self.status_dir, &session_id);
                     cleanup_session_files(&self.status_dir, &session_id);
                 }
             }
@@ -912,14 +1251,40 @@ impl App {
     /// Propagate terminal resize to all PTYs.
     pub fn handle_resize(&mut self, cols: u16, rows: u16) {
         self.term = TermSize { rows, cols };
+        let offset = self.sidebar_cols();
+        let cols = self.session_cols();
         for ts in self.sessions.values() {
             let _ = ts.handle.resize(rows, cols);
+            let _ = ts.handle.set_col_offset(offset);
         }
         if let Some(ref toplevel) = self.toplevel {
             let _ = toplevel.resize(rows, cols);
+            let _ = toplevel.set_col_offset(offset);
+        }
+    }
+
+    /// Columns taken by the zoomed-mode sidebar: 0 when it is hidden or
+    /// the terminal is too narrow to leave `MIN_SESSION_COLS` for the
+    /// session.
+    pub fn sidebar_cols(&self) -> u16 {
+        if self.sidebar && self.term.cols >= self.sidebar_width + MIN_SESSION_COLS {
+            self.sidebar_width
+        } else {
+            0
         }
     }
 
+    /// Width of session PTYs: the terminal width minus the sidebar.
+    pub fn session_cols(&self) -> u16 {
+        self.term.cols - self.sidebar_cols()
+    }
+
+    /// Show or hide the sidebar and resize every session to match.
+    pub fn toggle_sidebar(&mut self) {
+        self.sidebar = !self.sidebar;
+        self.handle_resize(self.term.cols, self.term.rows);
+    }
+
     /// Get an ordered list of session IDs for cycling.
     pub fn session_ids_ordered(&self) -> Vec<String> {
         let mut ids: Vec<String> = self.sessions.keys().cloned().collect();
@@ -1048,7 +1413,7 @@ impl App {
             // default_backend is validated at construction, so this is safe.
             self.backends[&self.default_backend].request_exit(toplevel.as_mut());
         }
-        let deadline = std::time::Instant::now() + GRACEFUL_TIMEOUT;
+        let deadline = std::time::Instant::now() + self.graceful_timeout;
         loop {
             let toplevel_dead = self.toplevel.as_mut().is_none_or(|s| !s.is_alive());
             if toplevel_dead || std::time::Instant::now() >= deadline {
@@ -1083,6 +1448,8 @@ impl App {
                 let _ = std::fs::remove_file(path);
             }
         }
+        // No workspace, no live picture: don't leave a stale report behind.
+        let _ = std::fs::remove_file(self.status_dir.join(STATUS_REPORT_FILE));
     }
 }
 
@@ -1098,7 +1465,9 @@ fn cycle_after<T: Ord>(sorted: &[T], current: Option<&T>) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::Instant;
+    use crate::lifecycle::GRACEFUL_TIMEOUT;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
     use tempfile::TempDir;
 
     struct StubSession {
@@ -1107,7 +1476,10 @@ mod tests {
         status: SessionStatus,
         alive: bool,
         stopping_since: Option<Instant>,
+        started_at: Instant,
         exit_code: Option<i32>,
+        set_status_calls: Arc<AtomicUsize>,
+        written: Arc<Mutex<Vec<u8>>>,
     }
 
     impl StubSession {
@@ -1118,7 +1490,10 @@ mod tests {
                 status: SessionStatus::Starting,
                 alive,
                 stopping_since: None,
+                started_at: Instant::now(),
                 exit_code: None,
+                set_status_calls: Arc::new(AtomicUsize::new(0)),
+                written: Arc::new(Mutex::new(Vec::new())),
             }
         }
     }
@@ -1134,11 +1509,15 @@ mod tests {
             &self.status
         }
         fn set_status(&mut self, status: SessionStatus) {
+            self.set_status_calls.fetch_add(1, Ordering::SeqCst);
             self.status = status;
         }
         fn stopping_since(&self) -> Option<Instant> {
             self.stopping_since
         }
+        fn started_at(&self) -> Instant {
+            self.started_at
+        }
         fn is_alive(&mut self) -> bool {
             self.alive
         }
@@ -1168,10 +1547,20 @@ mod tests {
         fn scrollback_available(&self) -> Result<usize> {
             Ok(0)
         }
+        fn search_scrollback(&self, _query: &str) -> Result<Vec<usize>> {
+            Ok(Vec::new())
+        }
+        fn dump_scrollback(&self) -> Result<String> {
+            Ok(String::new())
+        }
+        fn set_col_offset(&self, _cols: u16) -> Result<()> {
+            Ok(())
+        }
         fn has_mouse_tracking(&self) -> bool {
             false
         }
-        fn write_input(&mut self, _buf: &[u8]) -> Result<()> {
+        fn write_input(&mut self, buf: &[u8]) -> Result<()> {
+            self.written.lock().unwrap().extend_from_slice(buf);
             Ok(())
         }
         fn resize(&self, _rows: u16, _cols: u16) -> Result<()> {
@@ -1189,6 +1578,9 @@ mod tests {
         fn exit_code(&mut self) -> Option<i32> {
             self.exit_code
         }
+        fn exit_status(&self) -> Option<i32> {
+            self.exit_code
+        }
     }
 
     struct StubBackend;
@@ -1290,21 +1682,361 @@ mod tests {
             max_concurrency: 2,
             manual: false,
             prefer: None,
+            root: None,
             backends,
             default_backend: "claude".to_string(),
+            default_exit_sequence: None,
             spawner: Box::new(StubSpawner),
             persistent_sessions: false,
+            prefix_key: kbtz::config::DEFAULT_PREFIX_KEY,
+            osc52_clipboard: false,
+            terminal: TerminalOptions::default(),
+            sidebar: false,
+            sidebar_width: 24,
+            graceful_timeout: GRACEFUL_TIMEOUT,
+            restart_in_place: false,
+            max_session_runtime: None,
+            idle_pause: None,
+            idle_since: HashMap::new(),
+            last_status_report: None,
+            restart_history: HashMap::new(),
             default_directory: std::env::current_dir().unwrap(),
             toplevel: None,
             term: TermSize { rows: 24, cols: 80 },
             tree: TreeView::new(ActiveTaskPolicy::Confirm),
             tree_dirty: false,
+            tree_cache: None,
+            status_stamps: HashMap::new(),
             notes_panel: None,
             zoomed_session: None,
         };
         (app, status_dir)
     }
 
+    fn tree_row(name: &str, status: &str) -> kbtz::ui::TreeRow {
+        kbtz::ui::TreeRow {
+            name: name.to_string(),
+            status: status.to_string(),
+            description: String::new(),
+            assignee: None,
+            depth: 0,
+            has_children: false,
+            is_last_at_depth: vec![true],
+            blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
+        }
+    }
+
+    #[test]
+    fn read_status_files_skips_unchanged_status() {
+        let (mut app, _dir) = test_app();
+        let stub = StubSession::new("task-a", "ws/1", true);
+        let calls = stub.set_status_calls.clone();
+        app.sessions.insert(
+            "ws/1".to_string(),
+            TrackedSession {
+                handle: Box::new(stub),
+                agent_type: "claude".to_string(),
+                unread: false,
+            },
+        );
+        let path = app.status_dir.join(session_id_to_filename("ws/1"));
+
+        std::fs::write(&path, "active").unwrap();
+        app.read_status_files().unwrap();
+        // Unchanged file, then a rewrite with the same status.
+        app.read_status_files().unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        std::fs::write(&path, "active").unwrap();
+        app.read_status_files().unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(*app.sessions["ws/1"].handle.status(), SessionStatus::Active);
+
+        std::fs::write(&path, "idle").unwrap();
+        app.read_status_files().unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(*app.sessions["ws/1"].handle.status(), SessionStatus::Idle);
+    }
+
+    #[test]
+    fn session_decorator_shows_needs_input_indicator() {
+        use kbtz::ui::TreeDecorator;
+
+        let (mut app, _dir) = test_app();
+        let mut stub = StubSession::new("task-a", "ws/1", true);
+        stub.status = SessionStatus::NeedsInput;
+        app.sessions.insert(
+            "ws/1".to_string(),
+            TrackedSession {
+                handle: Box::new(stub),
+                agent_type: "claude".to_string(),
+                unread: false,
+            },
+        );
+        app.task_to_session
+            .insert("task-a".to_string(), "ws/1".to_string());
+
+        let decorator = crate::tree::SessionDecorator::new(&app);
+        let deco = decorator.decorate(&tree_row("task-a", "active"));
+        let (icon, _) = deco.icon_override.expect("session row should be decorated");
+        assert!(icon.contains('\u{1f514}'), "expected bell in {icon:?}");
+    }
+
+    #[test]
+    fn session_decorator_degrades_without_session() {
+        use kbtz::ui::TreeDecorator;
+
+        let (mut app, _dir) = test_app();
+        // Mapping without a tracked session (e.g. mid-removal).
+        app.task_to_session
+            .insert("task-a".to_string(), "ws/9".to_string());
+
+        let decorator = crate::tree::SessionDecorator::new(&app);
+        assert!(decorator
+            .decorate(&tree_row("task-a", "open"))
+            .icon_override
+            .is_none());
+        assert!(decorator
+            .decorate(&tree_row("task-b", "open"))
+            .icon_override
+            .is_none());
+    }
+
+    #[test]
+    fn sidebar_cols_respects_toggle_and_min_width() {
+        let (mut app, _dir) = test_app();
+        assert_eq!(app.sidebar_cols(), 0);
+        assert_eq!(app.session_cols(), 80);
+
+        app.toggle_sidebar();
+        assert_eq!(app.sidebar_cols(), 24);
+        assert_eq!(app.session_cols(), 56);
+
+        // Too narrow to fit the sidebar and a usable session.
+        app.handle_resize(60, 24);
+        assert_eq!(app.sidebar_cols(), 0);
+        assert_eq!(app.session_cols(), 60);
+    }
+
+    /// App with `restart_in_place` on and `task-a` claimed by a dead `ws/1`.
+    fn restart_app() -> (App, TempDir) {
+        let (mut app, dir) = test_app();
+        app.restart_in_place = true;
+        app.counter = 1;
+        ops::add_task(
+            &app.conn,
+            ops::AddTaskParams {
+                name: "task-a",
+                description: "desc",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        ops::claim_task(&app.conn, "task-a", "ws/1").unwrap();
+        app.sessions.insert(
+            "ws/1".to_string(),
+            TrackedSession {
+                handle: Box::new(StubSession::new("task-a", "ws/1", false)),
+                agent_type: "claude".to_string(),
+                unread: false,
+            },
+        );
+        app.task_to_session
+            .insert("task-a".to_string(), "ws/1".to_string());
+        (app, dir)
+    }
+
+    #[test]
+    fn restart_in_place_keeps_claim_and_session_id() {
+        let (mut app, _dir) = restart_app();
+
+        app.tick().unwrap();
+
+        let task = ops::get_task(&app.conn, "task-a").unwrap();
+        assert_eq!(task.status, "active");
+        assert_eq!(task.assignee.as_deref(), Some("ws/1"));
+        assert_eq!(app.sessions.len(), 1);
+        assert!(app.sessions.get_mut("ws/1").unwrap().handle.is_alive());
+        assert_eq!(app.task_to_session["task-a"], "ws/1");
+    }
+
+    #[test]
+    fn restart_in_place_releases_after_cap() {
+        let (mut app, _dir) = restart_app();
+
+        for _ in 0..MAX_RESTARTS {
+            app.tick().unwrap();
+            assert!(app.tree.error.is_none());
+            app.sessions.get_mut("ws/1").unwrap().handle.force_kill();
+        }
+        app.tick().unwrap();
+
+        assert!(app.tree.error.as_deref().unwrap().contains("task-a"));
+        assert!(!app.sessions.contains_key("ws/1"));
+        // Released, then picked up again by the regular spawn path.
+        let task = ops::get_task(&app.conn, "task-a").unwrap();
+        assert_ne!(task.assignee.as_deref(), Some("ws/1"));
+    }
+
+    #[test]
+    fn restart_in_place_skips_requested_stops() {
+        let (mut app, _dir) = restart_app();
+        app.sessions.get_mut("ws/1").unwrap().handle.mark_stopping();
+
+        app.tick().unwrap();
+
+        assert!(!app.sessions.contains_key("ws/1"));
+    }
+
+    #[test]
+    fn status_report_reflects_sessions_and_skips_unchanged_ticks() {
+        let (mut app, dir) = test_app();
+        app.max_concurrency = 0;
+        for (sid, task, status) in [
+            ("ws/1", "task-a", SessionStatus::Active),
+            ("ws/2", "task-b", SessionStatus::NeedsInput),
+            ("ws/3", "task-c", SessionStatus::Idle),
+        ] {
+            let mut stub = StubSession::new(task, sid, true);
+            stub.status = status;
+            app.sessions.insert(
+                sid.to_string(),
+                TrackedSession {
+                    handle: Box::new(stub),
+                    agent_type: "claude".to_string(),
+                    unread: false,
+                },
+            );
+        }
+        let path = dir.path().join(STATUS_REPORT_FILE);
+
+        app.tick().unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(report["counts"]["active"], 1);
+        assert_eq!(report["counts"]["needs_input"], 1);
+        assert_eq!(report["counts"]["idle"], 1);
+        let sessions = report["sessions"].as_array().unwrap();
+        assert_eq!(sessions.len(), 3);
+        assert_eq!(sessions[1]["session_id"], "ws/2");
+        assert_eq!(sessions[1]["task"], "task-b");
+        assert_eq!(sessions[1]["status"], "needs_input");
+        assert!(sessions[1]["pid"].is_null());
+
+        // Nothing changed: the file is not rewritten.
+        std::fs::remove_file(&path).unwrap();
+        app.tick().unwrap();
+        assert!(!path.exists());
+
+        app.sessions
+            .get_mut("ws/3")
+            .unwrap()
+            .handle
+            .set_status(SessionStatus::Active);
+        app.tick().unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(report["counts"]["active"], 2);
+        assert_eq!(report["counts"]["idle"], 0);
+
+        app.shutdown();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn idle_session_pauses_task_and_stops() {
+        let (mut app, _dir) = test_app();
+        app.max_concurrency = 0;
+        app.restart_in_place = true;
+        app.idle_pause = Some(Duration::from_secs(5 * 60));
+        ops::add_task(
+            &app.conn,
+            ops::AddTaskParams {
+                name: "task-a",
+                description: "desc",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        ops::claim_task(&app.conn, "task-a", "ws/1").unwrap();
+        let mut stub = StubSession::new("task-a", "ws/1", true);
+        stub.status = SessionStatus::Idle;
+        app.sessions.insert(
+            "ws/1".to_string(),
+            TrackedSession {
+                handle: Box::new(stub),
+                agent_type: "claude".to_string(),
+                unread: false,
+            },
+        );
+        app.task_to_session
+            .insert("task-a".to_string(), "ws/1".to_string());
+
+        // Idle only just now: the clock starts, nothing happens yet.
+        app.tick().unwrap();
+        assert!(app.sessions["ws/1"].handle.stopping_since().is_none());
+        assert!(app.idle_since.contains_key("ws/1"));
+
+        app.idle_since.insert(
+            "ws/1".to_string(),
+            Instant::now() - Duration::from_secs(6 * 60),
+        );
+        app.tick().unwrap();
+
+        assert!(app.sessions["ws/1"].handle.stopping_since().is_some());
+        let task = ops::get_task(&app.conn, "task-a").unwrap();
+        assert_eq!(task.status, "paused");
+        assert_eq!(task.assignee, None);
+        let notes = ops::list_notes(&app.conn, "task-a").unwrap();
+        assert!(notes[0].content.contains("idle for 5 minutes"));
+        assert!(app.tree.error.as_deref().unwrap().contains("task-a paused"));
+
+        // Once the session exits it is removed, not restarted or re-claimed.
+        app.sessions.get_mut("ws/1").unwrap().handle.force_kill();
+        app.tick().unwrap();
+        assert!(app.sessions.is_empty());
+        assert_eq!(ops::get_task(&app.conn, "task-a").unwrap().status, "paused");
+    }
+
+    #[test]
+    fn over_budget_session_is_asked_to_exit_with_note() {
+        let (mut app, _dir) = test_app();
+        app.max_concurrency = 0;
+        app.max_session_runtime = Some(Duration::from_secs(60));
+        ops::add_task(
+            &app.conn,
+            ops::AddTaskParams {
+                name: "task-a",
+                description: "desc",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        ops::claim_task(&app.conn, "task-a", "ws/1").unwrap();
+        let mut stub = StubSession::new("task-a", "ws/1", true);
+        stub.started_at = Instant::now() - Duration::from_secs(120);
+        app.sessions.insert(
+            "ws/1".to_string(),
+            TrackedSession {
+                handle: Box::new(stub),
+                agent_type: "claude".to_string(),
+                unread: false,
+            },
+        );
+        app.task_to_session
+            .insert("task-a".to_string(), "ws/1".to_string());
+        assert_eq!(app.runtime_left("ws/1"), Some(Duration::ZERO));
+
+        app.tick().unwrap();
+
+        let ts = &app.sessions["ws/1"];
+        assert!(ts.handle.stopping_since().is_some());
+        let notes = ops::list_notes(&app.conn, "task-a").unwrap();
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].content.contains("1-minute runtime budget"));
+    }
+
     #[test]
     fn remove_session_cleans_up_mapping() {
         let (mut app, _dir) = test_app();
@@ -1516,6 +2248,69 @@ mod tests {
         assert_eq!(cycle_after(&ids, None), 0);
     }
 
+    static TRACED_QUERIES: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_query(_sql: &str) {
+        TRACED_QUERIES.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn refresh_tree_reads_db_only_when_dirty() {
+        let (mut app, _dir) = test_app();
+        app.conn.execute_batch("BEGIN").unwrap();
+        for p in 0..300 {
+            let parent = format!("parent-{p}");
+            ops::add_task(
+                &app.conn,
+                ops::AddTaskParams {
+                    name: &parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            for c in 0..9 {
+                let child = format!("child-{p}-{c}");
+                ops::add_task(
+                    &app.conn,
+                    ops::AddTaskParams {
+                        name: &child,
+                        parent: Some(&parent),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+                if c > 0 {
+                    ops::add_block(&app.conn, &format!("child-{p}-{}", c - 1), &child).unwrap();
+                }
+            }
+        }
+        app.conn.execute_batch("COMMIT").unwrap();
+
+        app.refresh_tree().unwrap();
+        assert_eq!(app.tree.rows.len(), 3000);
+        assert_eq!(app.tree.rows[2].blocked_by, ["child-0-0"]);
+
+        app.conn.trace(Some(count_query));
+        TRACED_QUERIES.store(0, Ordering::SeqCst);
+
+        // Navigation and collapsing rebuild from the cache.
+        app.tree.move_down();
+        app.tree.move_up();
+        app.tree.toggle_collapse();
+        app.refresh_tree().unwrap();
+        assert_eq!(app.tree.rows.len(), 2991);
+        assert_eq!(TRACED_QUERIES.load(Ordering::SeqCst), 0);
+
+        // A DB change re-reads with a fixed number of queries, not one
+        // per task.
+        app.tree_dirty = true;
+        app.refresh_tree().unwrap();
+        let queries = TRACED_QUERIES.load(Ordering::SeqCst);
+        assert!((1..10).contains(&queries), "{queries} queries");
+        assert!(!app.tree_dirty);
+        app.conn.trace(None);
+    }
+
     #[test]
     fn release_orphaned_tasks_releases_stale_ws_claims() {
         let (app, _dir) = test_app();
@@ -1618,15 +2413,31 @@ mod tests {
             max_concurrency: 2,
             manual: false,
             prefer: None,
+            root: None,
             backends,
             default_backend: "claude".to_string(),
+            default_exit_sequence: None,
             spawner: Box::new(StubSpawner),
             persistent_sessions: false,
+            prefix_key: kbtz::config::DEFAULT_PREFIX_KEY,
+            osc52_clipboard: false,
+            terminal: TerminalOptions::default(),
+            sidebar: false,
+            sidebar_width: 24,
+            graceful_timeout: GRACEFUL_TIMEOUT,
+            restart_in_place: false,
+            max_session_runtime: None,
+            idle_pause: None,
+            idle_since: HashMap::new(),
+            last_status_report: None,
+            restart_history: HashMap::new(),
             default_directory: std::env::current_dir().unwrap(),
             toplevel: None,
             term: TermSize { rows: 24, cols: 80 },
             tree: TreeView::new(ActiveTaskPolicy::Confirm),
             tree_dirty: false,
+            tree_cache: None,
+            status_stamps: HashMap::new(),
             notes_panel: None,
             zoomed_session: None,
         };
@@ -1886,6 +2697,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn remove_session_notes_nonzero_exit() {
+        let (mut app, _dir) = test_app();
+        for name in ["task-a", "task-b"] {
+            ops::add_task(
+                &app.conn,
+                ops::AddTaskParams {
+                    name,
+                    description: "desc",
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        ops::claim_task(&app.conn, "task-a", "ws/1").unwrap();
+        ops::claim_task(&app.conn, "task-b", "ws/2").unwrap();
+
+        let mut failed = StubSession::new("task-a", "ws/1", false);
+        failed.exit_code = Some(3);
+        let mut stopped = StubSession::new("task-b", "ws/2", false);
+        stopped.exit_code = Some(1);
+        stopped.mark_stopping();
+        for stub in [failed, stopped] {
+            app.sessions.insert(
+                stub.session_id.clone(),
+                TrackedSession {
+                    handle: Box::new(stub),
+                    agent_type: "claude".to_string(),
+                    unread: false,
+                },
+            );
+        }
+
+        app.remove_session("ws/1");
+        app.remove_session("ws/2");
+
+        let notes = ops::list_notes(&app.conn, "task-a").unwrap();
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].content.contains("ws/1 exited with code 3"));
+        assert_eq!(
+            app.tree.error.as_deref(),
+            Some("task-a: agent exited with code 3")
+        );
+        // A requested stop that ends non-zero is not a failure.
+        assert!(ops::list_notes(&app.conn, "task-b").unwrap().is_empty());
+    }
+
     #[test]
     fn remove_session_preserves_file_on_zero_exit() {
         let (mut app, _dir) = test_app_resumable();
@@ -2043,15 +2901,31 @@ mod tests {
             max_concurrency: 2,
             manual: false,
             prefer: None,
+            root: None,
             backends,
             default_backend: "claude".to_string(),
+            default_exit_sequence: None,
             spawner: Box::new(StubSpawner),
             persistent_sessions: false,
+            prefix_key: kbtz::config::DEFAULT_PREFIX_KEY,
+            osc52_clipboard: false,
+            terminal: TerminalOptions::default(),
+            sidebar: false,
+            sidebar_width: 24,
+            graceful_timeout: GRACEFUL_TIMEOUT,
+            restart_in_place: false,
+            max_session_runtime: None,
+            idle_pause: None,
+            idle_since: HashMap::new(),
+            last_status_report: None,
+            restart_history: HashMap::new(),
             default_directory: std::env::current_dir().unwrap(),
             toplevel: None,
             term: TermSize { rows: 24, cols: 80 },
             tree: TreeView::new(ActiveTaskPolicy::Confirm),
             tree_dirty: false,
+            tree_cache: None,
+            status_stamps: HashMap::new(),
             notes_panel: None,
             zoomed_session: None,
         };
@@ -2084,6 +2958,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lazily_created_backend_uses_workspace_exit_sequence() {
+        let (mut app, _dir) = test_app();
+        app.default_exit_sequence = Some(b"/quit\n".to_vec());
+        app.ensure_backend("aider");
+
+        let mut session = StubSession::new("task-a", "ws/1", true);
+        let written = Arc::clone(&session.written);
+        app.backends["aider"].request_exit(&mut session);
+
+        assert_eq!(*written.lock().unwrap(), b"/quit\n");
+        assert!(session.stopping_since.is_some());
+    }
+
     #[test]
     fn spawn_uses_default_backend_for_task_without_agent() {
         let (mut app, _dir) = test_app_multi_backend();
@@ -2203,15 +3091,31 @@ mod tests {
             max_concurrency: 2,
             manual: false,
             prefer: None,
+            root: None,
             backends,
             default_backend: "claude".to_string(),
+            default_exit_sequence: None,
             spawner: Box::new(spawner),
             persistent_sessions: false,
+            prefix_key: kbtz::config::DEFAULT_PREFIX_KEY,
+            osc52_clipboard: false,
+            terminal: TerminalOptions::default(),
+            sidebar: false,
+            sidebar_width: 24,
+            graceful_timeout: GRACEFUL_TIMEOUT,
+            restart_in_place: false,
+            max_session_runtime: None,
+            idle_pause: None,
+            idle_since: HashMap::new(),
+            last_status_report: None,
+            restart_history: HashMap::new(),
             default_directory: std::env::current_dir().unwrap(),
             toplevel: None,
             term: TermSize { rows: 24, cols: 80 },
             tree: TreeView::new(ActiveTaskPolicy::Confirm),
             tree_dirty: false,
+            tree_cache: None,
+            status_stamps: HashMap::new(),
             notes_panel: None,
             zoomed_session: None,
         };
@@ -2323,43 +3227,6 @@ mod tests {
         );
     }
 
-    #[test]
-    fn kill_child_from_pid_file_missing_file_is_noop() {
-        let dir = tempfile::tempdir().unwrap();
-        let pid_path = dir.path().join("nonexistent.pid");
-        // Should not panic when the file doesn't exist.
-        kill_child_from_pid_file(&pid_path);
-    }
-
-    #[test]
-    fn kill_child_from_pid_file_kills_process_group() {
-        use std::os::unix::process::CommandExt;
-
-        let dir = tempfile::tempdir().unwrap();
-        let pid_path = dir.path().join("test.pid");
-
-        // Spawn a sleep in its own process group.
-        let mut child = unsafe {
-            std::process::Command::new("sleep")
-                .arg("999")
-                .pre_exec(|| {
-                    libc::setpgid(0, 0);
-                    Ok(())
-                })
-                .spawn()
-                .unwrap()
-        };
-        let child_pid = child.id();
-
-        std::fs::write(pid_path.with_extension("child-pid"), format!("{child_pid}")).unwrap();
-
-        kill_child_from_pid_file(&pid_path);
-
-        // wait() reaps the zombie and confirms the process exited.
-        let exited = child.wait().unwrap().code().is_none(); // killed by signal → no code
-        assert!(exited, "child should have been killed by signal");
-    }
-
     #[test]
     fn release_orphaned_tasks_ignores_non_ws_assignees() {
         let (app, _dir) = test_app();
@@ -2538,15 +3405,31 @@ mod tests {
             max_concurrency: 2,
             manual: false,
             prefer: None,
+            root: None,
             backends,
             default_backend: "claude".to_string(),
+            default_exit_sequence: None,
             spawner: Box::new(StubSpawner),
             persistent_sessions: false,
+            prefix_key: kbtz::config::DEFAULT_PREFIX_KEY,
+            osc52_clipboard: false,
+            terminal: TerminalOptions::default(),
+            sidebar: false,
+            sidebar_width: 24,
+            graceful_timeout: GRACEFUL_TIMEOUT,
+            restart_in_place: false,
+            max_session_runtime: None,
+            idle_pause: None,
+            idle_since: HashMap::new(),
+            last_status_report: None,
+            restart_history: HashMap::new(),
             default_directory: std::env::current_dir().unwrap(),
             toplevel: None,
             term: TermSize { rows: 24, cols: 80 },
             tree: TreeView::new(ActiveTaskPolicy::Confirm),
             tree_dirty: false,
+            tree_cache: None,
+            status_stamps: HashMap::new(),
             notes_panel: None,
             zoomed_session: None,
         };
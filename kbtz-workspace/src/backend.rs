@@ -177,12 +177,193 @@ impl Backend for Generic {
     }
 }
 
+/// Interactive shell backend for running arbitrary long-lived commands in
+/// workspace sessions.
+///
+/// Ignores the system instructions and prompt entirely and starts the shell
+/// with `-i`; the session still gets `KBTZ_TASK`, `KBTZ_SESSION_ID`, and
+/// `KBTZ_WORKSPACE_DIR`, so scripts run inside it can report status. Uses
+/// SIGHUP for graceful exit because interactive shells ignore SIGTERM.
+pub struct Shell {
+    command: String,
+    prefix_args: Vec<String>,
+    extra_args: Vec<String>,
+}
+
+impl Backend for Shell {
+    fn command(&self) -> &str {
+        &self.command
+    }
+
+    fn worker_args(&self, _system_instructions: &str, _initial_prompt: &str) -> Vec<String> {
+        let mut args = Vec::with_capacity(self.prefix_args.len() + 1 + self.extra_args.len());
+        args.extend(self.prefix_args.iter().cloned());
+        args.push("-i".into());
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+
+    fn request_exit(&self, session: &mut dyn SessionHandle) {
+        if session.stopping_since().is_some() {
+            return;
+        }
+        if let Some(pid) = session.process_id() {
+            unsafe { libc::kill(pid as libc::pid_t, libc::SIGHUP) };
+        }
+        session.mark_stopping();
+    }
+}
+
+/// Backend defined entirely in config via an `[agent.<name>]`
+/// `prompt_args` template.
+///
+/// `{protocol}` and `{task}` in each template arg are replaced with the
+/// system instructions and initial prompt. Uses SIGTERM for graceful exit
+/// and does not support session resume.
+pub struct Template {
+    command: String,
+    prefix_args: Vec<String>,
+    prompt_args: Vec<String>,
+    extra_args: Vec<String>,
+}
+
+impl Backend for Template {
+    fn command(&self) -> &str {
+        &self.command
+    }
+
+    fn worker_args(&self, system_instructions: &str, initial_prompt: &str) -> Vec<String> {
+        let mut args = Vec::with_capacity(
+            self.prefix_args.len() + self.prompt_args.len() + self.extra_args.len(),
+        );
+        args.extend(self.prefix_args.iter().cloned());
+        args.extend(
+            self.prompt_args
+                .iter()
+                .map(|arg| substitute(arg, system_instructions, initial_prompt)),
+        );
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+
+    fn request_exit(&self, session: &mut dyn SessionHandle) {
+        if session.stopping_since().is_some() {
+            return;
+        }
+        if let Some(pid) = session.process_id() {
+            unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) };
+        }
+        session.mark_stopping();
+    }
+}
+
+/// Replace `{protocol}` and `{task}` in one left-to-right pass, so
+/// placeholders inside the inserted text are left as they are.
+fn substitute(arg: &str, protocol: &str, task: &str) -> String {
+    let mut out = String::with_capacity(arg.len());
+    let mut rest = arg;
+    while let Some(i) = rest.find('{') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if let Some(after) = rest.strip_prefix("{protocol}") {
+            out.push_str(protocol);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{task}") {
+            out.push_str(task);
+            rest = after;
+        } else {
+            out.push('{');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Create a `Template` backend from an `[agent.<name>]` `prompt_args` list.
+/// Placeholders are validated when the config is loaded.
+pub fn template(
+    command: &str,
+    prefix_args: &[String],
+    prompt_args: &[String],
+    extra_args: &[String],
+) -> Box<dyn Backend> {
+    Box::new(Template {
+        command: command.to_string(),
+        prefix_args: prefix_args.to_vec(),
+        prompt_args: prompt_args.to_vec(),
+        extra_args: extra_args.to_vec(),
+    })
+}
+
+/// Wraps another backend so `request_exit` types a configured exit
+/// sequence (e.g. `/exit\n` or Ctrl-C) into the session instead of using
+/// the inner backend's signal. Falls back to the inner backend if the
+/// write fails.
+pub struct ExitSequence {
+    inner: Box<dyn Backend>,
+    sequence: Vec<u8>,
+}
+
+impl Backend for ExitSequence {
+    fn command(&self) -> &str {
+        self.inner.command()
+    }
+
+    fn worker_args(&self, system_instructions: &str, initial_prompt: &str) -> Vec<String> {
+        self.inner.worker_args(system_instructions, initial_prompt)
+    }
+
+    fn toplevel_args(&self, system_instructions: &str, initial_prompt: &str) -> Vec<String> {
+        self.inner
+            .toplevel_args(system_instructions, initial_prompt)
+    }
+
+    fn fresh_args(
+        &self,
+        system_instructions: &str,
+        initial_prompt: &str,
+        session_id: &str,
+    ) -> Option<Vec<String>> {
+        self.inner
+            .fresh_args(system_instructions, initial_prompt, session_id)
+    }
+
+    fn resume_args(
+        &self,
+        system_instructions: &str,
+        session_id: &str,
+        initial_prompt: &str,
+    ) -> Option<Vec<String>> {
+        self.inner
+            .resume_args(system_instructions, session_id, initial_prompt)
+    }
+
+    fn request_exit(&self, session: &mut dyn SessionHandle) {
+        if session.stopping_since().is_some() {
+            return;
+        }
+        if session.write_input(&self.sequence).is_err() {
+            self.inner.request_exit(session);
+            return;
+        }
+        session.mark_stopping();
+    }
+}
+
+/// Wrap `inner` so graceful exit sends `sequence` as keystrokes.
+pub fn with_exit_sequence(inner: Box<dyn Backend>, sequence: Vec<u8>) -> Box<dyn Backend> {
+    Box::new(ExitSequence { inner, sequence })
+}
+
 /// Create a backend by name, with an optional command override, prefix args,
 /// and extra args.
 ///
-/// Named backends (e.g., "claude") get type-specific behavior like separate
-/// system prompt injection. All other names produce a generic backend that
-/// concatenates system instructions and initial prompt into a single arg.
+/// Named backends get type-specific behavior: "claude" injects the system
+/// prompt separately and supports resume, and "shell" runs `$SHELL -i`
+/// (falling back to `/bin/sh`) without any prompt. All other names produce
+/// a generic backend that concatenates system instructions and initial
+/// prompt into a single arg.
 ///
 /// The command override replaces the backend's default binary path.
 /// Prefix args (from array-valued `command` config) are inserted before
@@ -199,6 +380,14 @@ pub fn from_name(
             prefix_args: prefix_args.to_vec(),
             extra_args: extra_args.to_vec(),
         }),
+        "shell" => Box::new(Shell {
+            command: command_override
+                .map(str::to_string)
+                .or_else(|| std::env::var("SHELL").ok().filter(|s| !s.is_empty()))
+                .unwrap_or_else(|| "/bin/sh".to_string()),
+            prefix_args: prefix_args.to_vec(),
+            extra_args: extra_args.to_vec(),
+        }),
         _ => Box::new(Generic {
             command: command_override.unwrap_or(name).to_string(),
             prefix_args: prefix_args.to_vec(),
@@ -244,6 +433,75 @@ mod tests {
         assert_eq!(backend.command(), "/usr/local/bin/gemini-cli");
     }
 
+    #[test]
+    fn from_name_shell_command_override() {
+        let backend = from_name("shell", Some("/bin/zsh"), &[], &[]);
+        assert_eq!(backend.command(), "/bin/zsh");
+    }
+
+    #[test]
+    fn shell_args_ignore_prompts() {
+        let backend = from_name("shell", Some("/bin/bash"), &["--norc".into()], &[]);
+        assert_eq!(
+            backend.worker_args("system text", "task text"),
+            ["--norc", "-i"]
+        );
+        assert_eq!(
+            backend.toplevel_args("system text", "task text"),
+            ["--norc", "-i"]
+        );
+        assert!(backend.fresh_args("sys", "task", "sess-1").is_none());
+    }
+
+    #[test]
+    fn template_substitutes_placeholders() {
+        let backend = template(
+            "goose",
+            &["run".into()],
+            &[
+                "--system".into(),
+                "{protocol}".into(),
+                "--text=Task: {task}".into(),
+            ],
+            &["--quiet".into()],
+        );
+        assert_eq!(backend.command(), "goose");
+        assert_eq!(
+            backend.worker_args("system text", "task text"),
+            [
+                "run",
+                "--system",
+                "system text",
+                "--text=Task: task text",
+                "--quiet"
+            ]
+        );
+        assert!(backend.resume_args("sys", "sess-1", "continue").is_none());
+    }
+
+    #[test]
+    fn template_does_not_rescan_substituted_text() {
+        let backend = template("goose", &[], &["{task} / {protocol} / {{task}".into()], &[]);
+        assert_eq!(
+            backend.worker_args("claim {task} first", "do {protocol}"),
+            ["do {protocol} / claim {task} first / {do {protocol}"]
+        );
+    }
+
+    #[test]
+    fn exit_sequence_delegates_args_to_inner() {
+        let backend = with_exit_sequence(
+            from_name("claude", None, &[], &["--verbose".into()]),
+            b"/exit\n".to_vec(),
+        );
+        assert_eq!(backend.command(), "claude");
+        assert_eq!(
+            backend.worker_args("sys", "task"),
+            from_name("claude", None, &[], &["--verbose".into()]).worker_args("sys", "task")
+        );
+        assert!(backend.resume_args("sys", "sess-1", "continue").is_some());
+    }
+
     #[test]
     fn generic_worker_args_concatenates_instructions_and_prompt() {
         let backend = Generic {
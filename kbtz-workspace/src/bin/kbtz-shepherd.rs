@@ -7,7 +7,9 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use portable_pty::{native_pty_system, CommandBuilder, PtySize};
 
 use kbtz_workspace::protocol::{self, Message};
-use kbtz_workspace::{build_restore_sequence, resize_both_screens, SCROLLBACK_ROWS};
+use kbtz_workspace::{
+    build_restore_sequence, resize_both_screens, SCROLLBACK_ROWS, SCROLLBACK_ROWS_ENV,
+};
 
 /// Non-blocking client connection with message buffering.
 ///
@@ -132,13 +134,25 @@ fn main() {
     });
     let command = &args[5];
     let command_args: Vec<&str> = args[6..].iter().map(|s| s.as_str()).collect();
+    let scrollback_rows = std::env::var(SCROLLBACK_ROWS_ENV)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(SCROLLBACK_ROWS);
 
     kbtz::debug_log::log(&format!(
-        "shepherd: starting pid={} socket={} command={command} args={command_args:?} rows={rows} cols={cols}",
+        "shepherd: starting pid={} socket={} command={command} args={command_args:?} rows={rows} cols={cols} scrollback={scrollback_rows}",
         std::process::id(),
         socket_path.display(),
     ));
-    if let Err(e) = run(&socket_path, &pid_file, rows, cols, command, &command_args) {
+    if let Err(e) = run(
+        &socket_path,
+        &pid_file,
+        rows,
+        cols,
+        scrollback_rows,
+        command,
+        &command_args,
+    ) {
         kbtz::debug_log::log(&format!(
             "shepherd: run() failed pid={}: {e:#}",
             std::process::id()
@@ -163,6 +177,7 @@ fn run(
     pid_file: &Path,
     rows: u16,
     cols: u16,
+    scrollback_rows: usize,
     command: &str,
     command_args: &[&str],
 ) -> anyhow::Result<()> {
@@ -221,6 +236,7 @@ fn run(
 
     let mut cmd = CommandBuilder::new(command);
     cmd.args(command_args);
+    cmd.env_remove(SCROLLBACK_ROWS_ENV);
     if let Ok(cwd) = std::env::current_dir() {
         cmd.cwd(cwd);
     }
@@ -277,7 +293,7 @@ fn run(
 
     // 6. VTE parser with scrollback — this is the authoritative scrollback
     // store, like tmux's server-side pane history.  No raw byte buffer.
-    let mut vte = vt100::Parser::new(rows, cols, SCROLLBACK_ROWS);
+    let mut vte = vt100::Parser::new(rows, cols, scrollback_rows);
 
     let mut client: Option<ClientConn> = None;
     let mut shutdown_requested = false;
@@ -378,7 +394,7 @@ fn run(
                     // doesn't implement this, so clear scrollback
                     // manually to stay consistent with the workspace.
                     if data.windows(4).any(|w| w == b"\x1b[3J") {
-                        clear_scrollback(&mut vte);
+                        clear_scrollback(&mut vte, scrollback_rows);
                     }
 
                     if let Some(ref mut cc) = client {
@@ -474,10 +490,17 @@ fn run(
                                 restore.len(),
                                 std::process::id()
                             ));
-                            if let Err(e) = protocol::write_message(
-                                &mut handshake_stream,
-                                &Message::InitialState(restore),
-                            ) {
+                            let hello = Message::Hello {
+                                version: protocol::PROTOCOL_VERSION,
+                            };
+                            if let Err(e) = protocol::write_message(&mut handshake_stream, &hello)
+                                .and_then(|()| {
+                                    protocol::write_message(
+                                        &mut handshake_stream,
+                                        &Message::InitialState(restore),
+                                    )
+                                })
+                            {
                                 kbtz::debug_log::log(&format!(
                                     "shepherd: failed to send InitialState pid={}: {e:#}",
                                     std::process::id()
@@ -581,7 +604,7 @@ fn run(
 /// the visible screen state.  Mirrors `Passthrough::clear_scrollback()`
 /// in the workspace — both must handle CSI 3 J identically so the
 /// shepherd's authoritative scrollback matches the workspace's view.
-fn clear_scrollback(vte: &mut vt100::Parser) {
+fn clear_scrollback(vte: &mut vt100::Parser, scrollback_rows: usize) {
     let (rows, cols) = vte.screen().size();
     let was_alt = vte.screen().alternate_screen();
 
@@ -595,7 +618,7 @@ fn clear_scrollback(vte: &mut vt100::Parser) {
         vte.process(b"\x1b[?47h");
     }
 
-    let mut fresh = vt100::Parser::new(rows, cols, SCROLLBACK_ROWS);
+    let mut fresh = vt100::Parser::new(rows, cols, scrollback_rows);
     fresh.process(&main_state);
     if let Some(alt) = alt_state {
         fresh.process(b"\x1b[?47h");
@@ -613,6 +636,34 @@ fn forward_sigterm(child_pid: Option<u32>) {
     }
 }
 
+fn drain_pty(
+    reader: &mut Box<dyn Read + Send>,
+    vte: &mut vt100::Parser,
+    client: &mut Option<ClientConn>,
+) {
+    let mut buf = [0u8; 8192];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                let data = &buf[..n];
+                vte.process(data);
+
+                if let Some(ref mut cc) = client {
+                    if cc
+                        .write_message(&Message::PtyOutput(data.to_vec()))
+                        .is_err()
+                    {
+                        *client = None;
+                    }
+                }
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(_) => break,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -796,31 +847,3 @@ mod tests {
         assert_eq!(parsed, msg);
     }
 }
-
-fn drain_pty(
-    reader: &mut Box<dyn Read + Send>,
-    vte: &mut vt100::Parser,
-    client: &mut Option<ClientConn>,
-) {
-    let mut buf = [0u8; 8192];
-    loop {
-        match reader.read(&mut buf) {
-            Ok(0) => break,
-            Ok(n) => {
-                let data = &buf[..n];
-                vte.process(data);
-
-                if let Some(ref mut cc) = client {
-                    if cc
-                        .write_message(&Message::PtyOutput(data.to_vec()))
-                        .is_err()
-                    {
-                        *client = None;
-                    }
-                }
-            }
-            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
-            Err(_) => break,
-        }
-    }
-}
@@ -1,12 +1,18 @@
 pub mod prompt;
 pub mod protocol;
+pub mod shepherd;
 
 use std::io::{BufWriter, StdoutLock, Write};
 
-/// Max scrollback rows retained per session for the scroll-back viewer.
-/// Shared between the workspace (session.rs) and the shepherd.
+/// Default scrollback rows retained per session for the scroll-back
+/// viewer, when `workspace.scrollback_rows` is not set.  Shared between
+/// the workspace (session.rs) and the shepherd.
 pub const SCROLLBACK_ROWS: usize = 10_000;
 
+/// Environment variable through which the workspace passes its
+/// scrollback size to the shepherds it spawns.
+pub const SCROLLBACK_ROWS_ENV: &str = "KBTZ_SCROLLBACK_ROWS";
+
 /// Run `f` inside a buffered, synchronized stdout update.
 ///
 /// Wraps stdout in a `BufWriter` so all writes coalesce into one flush,
@@ -26,7 +32,11 @@ pub fn with_sync_stdout<T>(f: impl FnOnce(&mut BufWriter<StdoutLock<'_>>) -> T)
 /// Resize both the main and alternate screen grids.  The vt100 crate's
 /// `set_size()` only resizes the active screen; a terminal has one
 /// physical size so both grids must match.
-pub fn resize_both_screens(vte: &mut vt100::Parser, rows: u16, cols: u16) {
+pub fn resize_both_screens<CB: vt100::Callbacks>(
+    vte: &mut vt100::Parser<CB>,
+    rows: u16,
+    cols: u16,
+) {
     let was_alt = vte.screen().alternate_screen();
     if was_alt {
         vte.process(b"\x1b[?47l"); // expose main grid
@@ -37,8 +47,9 @@ pub fn resize_both_screens(vte: &mut vt100::Parser, rows: u16, cols: u16) {
 }
 
 /// Build a synthetic byte stream from a VTE that, when processed by a
-/// fresh `vt100::Parser` with `SCROLLBACK_ROWS`, reproduces the screen
-/// state including scrollback.
+/// fresh `vt100::Parser`, reproduces the screen state including
+/// scrollback.  The scrollback depth is probed from `vte`; a receiver
+/// with a smaller scrollback keeps only the newest rows.
 ///
 /// The sequence is:
 /// 1. Scrollback rows (oldest first), each followed by `\r\n` — these
@@ -73,11 +84,20 @@ pub fn build_restore_sequence(vte: &mut vt100::Parser) -> Vec<u8> {
 
     // Phase 1: Write scrollback rows (oldest first = highest offset)
     // At offset N, the top row of the viewport is the Nth-oldest scrollback line.
+    //
+    // vt100 keeps scrollback rows at the width they were written and
+    // records whether each one soft-wrapped into the next.  A wrapped row
+    // is emitted in full (trailing blanks included) without a line break,
+    // so a receiver wider than the original re-wraps the logical line
+    // instead of showing each fragment on its own row.  The last
+    // scrollback row always ends the line so phase 2's row count holds.
     for offset in (1..=total_scrollback).rev() {
         screen.set_scrollback(offset);
         if let Some(row_bytes) = screen.rows_formatted(0, cols).next() {
             restore.extend_from_slice(&row_bytes);
-            restore.extend_from_slice(b"\r\n");
+            if offset == 1 || !screen.row_wrapped(0) {
+                restore.extend_from_slice(b"\r\n");
+            }
         }
     }
     screen.set_scrollback(0);
@@ -106,8 +126,16 @@ pub fn build_restore_sequence(vte: &mut vt100::Parser) -> Vec<u8> {
     if was_alt {
         vte.process(b"\x1b[?47h"); // restore shepherd's VTE to alt screen
 
+        // The child entered the alt screen with 1049h, which saved the main
+        // cursor; its 1049l will restore it.  The main cursor has not moved
+        // since, so DECSC here recreates that saved position (the saved
+        // SGR attributes are not observable and become the current ones).
+        restore.extend_from_slice(b"\x1b7");
+
         // Use DECSET 47 (not 1049) in the restore stream — 47 does NOT
-        // clear the alt grid on the receiving side.
+        // clear the alt grid on the receiving side.  Each phase's
+        // `state_formatted()` also carries cursor visibility and input
+        // modes, so the alt phase leaves them as the child last set them.
         restore.extend_from_slice(b"\x1b[?47h");
         restore.extend_from_slice(&vte.screen().state_formatted());
     }
@@ -198,6 +226,78 @@ mod tests {
         );
     }
 
+    /// Replay `src` into a fresh VTE and check cursor visibility,
+    /// position, pending attributes, and input modes survive.
+    fn assert_cursor_state_restored(src: &mut vt100::Parser) {
+        let restore = build_restore_sequence(src);
+        let mut dst = vt100::Parser::new(5, 40, SCROLLBACK_ROWS);
+        dst.process(&restore);
+
+        let (s, d) = (src.screen(), dst.screen());
+        assert_eq!(s.alternate_screen(), d.alternate_screen());
+        assert_eq!(s.hide_cursor(), d.hide_cursor(), "cursor visibility");
+        assert_eq!(s.cursor_position(), d.cursor_position());
+        assert_eq!(s.input_mode_formatted(), d.input_mode_formatted());
+        // Text the child writes next must pick up its pending SGR state.
+        src.process(b"X");
+        dst.process(b"X");
+        let (row, col) = src.screen().cursor_position();
+        let (sc, dc) = (
+            src.screen().cell(row, col - 1).unwrap(),
+            dst.screen().cell(row, col - 1).unwrap(),
+        );
+        assert_eq!(
+            (sc.bold(), sc.fgcolor()),
+            (dc.bold(), dc.fgcolor()),
+            "pending attributes"
+        );
+    }
+
+    #[test]
+    fn restore_preserves_hidden_cursor_and_attributes() {
+        let mut src = vt100::Parser::new(5, 40, SCROLLBACK_ROWS);
+        for i in 0..20 {
+            src.process(format!("line {i}\r\n").as_bytes());
+        }
+        src.process(b"\x1b[?25l\x1b[?1h\x1b[3;7Hprompt\x1b[1;31m");
+        assert_cursor_state_restored(&mut src);
+    }
+
+    #[test]
+    fn restore_preserves_cursor_state_on_alt_screen() {
+        let mut src = vt100::Parser::new(5, 40, SCROLLBACK_ROWS);
+        for i in 0..20 {
+            src.process(format!("main line {i}\r\n").as_bytes());
+        }
+        src.process(b"\x1b[?1049h\x1b[2;5Halt\x1b[?25l\x1b[?2004h\x1b[1;32m");
+        assert_cursor_state_restored(&mut src);
+
+        // Visible cursor on the alt screen while the main screen had hidden it.
+        let mut src = vt100::Parser::new(5, 40, SCROLLBACK_ROWS);
+        src.process(b"main\x1b[?25l\x1b[?1049h\x1b[?25h\x1b[4;2Halt");
+        assert_cursor_state_restored(&mut src);
+    }
+
+    #[test]
+    fn restore_preserves_main_cursor_for_alt_screen_exit() {
+        let mut src = vt100::Parser::new(5, 40, SCROLLBACK_ROWS);
+        src.process(b"shell prompt $ ");
+        src.process(b"\x1b[?1049h\x1b[5;10Heditor");
+
+        let restore = build_restore_sequence(&mut src);
+        let mut dst = vt100::Parser::new(5, 40, SCROLLBACK_ROWS);
+        dst.process(&restore);
+
+        // The child quits its full-screen UI and keeps typing.
+        src.process(b"\x1b[?1049lls");
+        dst.process(b"\x1b[?1049lls");
+        assert_eq!(
+            src.screen().cursor_position(),
+            dst.screen().cursor_position()
+        );
+        assert_eq!(src.screen().contents(), dst.screen().contents());
+    }
+
     #[test]
     fn restore_sequence_no_scrollback() {
         // Source VTE with no scrollback (fewer lines than screen height).
@@ -238,6 +338,54 @@ mod tests {
         assert!(dst_scrollback > 0, "should have scrollback after reflow");
     }
 
+    #[test]
+    fn restore_into_smaller_scrollback_keeps_newest_rows() {
+        let mut src = vt100::Parser::new(5, 40, SCROLLBACK_ROWS);
+        for i in 0..60 {
+            src.process(format!("line {i}\r\n").as_bytes());
+        }
+        src.process(b"visible");
+
+        let restore = build_restore_sequence(&mut src);
+        let mut dst = vt100::Parser::new(5, 40, 20);
+        dst.process(&restore);
+
+        assert_eq!(src.screen().contents(), dst.screen().contents());
+        let viewports = scrollback_viewports(&mut dst);
+        assert_eq!(viewports.len(), 20);
+        assert!(viewports[0].starts_with("line 36"), "{}", viewports[0]);
+    }
+
+    #[test]
+    fn restore_at_wider_size_unwraps_scrollback() {
+        // Source at 20 cols: each 30-char line wraps onto a second row.
+        let mut src = vt100::Parser::new(5, 20, SCROLLBACK_ROWS);
+        for i in 0..10 {
+            src.process(format!("wrapped line {i:02} abcdefghijklmn\r\n").as_bytes());
+        }
+
+        // The workspace reconnects at 40 cols.
+        src.screen_mut().set_size(5, 40);
+
+        let restore = build_restore_sequence(&mut src);
+        let mut dst = vt100::Parser::new(5, 40, SCROLLBACK_ROWS);
+        dst.process(&restore);
+
+        assert_eq!(src.screen().contents(), dst.screen().contents());
+        let lines: Vec<String> = scrollback_viewports(&mut dst)
+            .iter()
+            .map(|vp| vp.lines().next().unwrap_or("").to_string())
+            .collect();
+        assert!(
+            lines.contains(&"wrapped line 03 abcdefghijklmn".to_string()),
+            "logical lines should be rejoined: {lines:?}"
+        );
+        assert!(
+            lines.iter().all(|l| !l.is_empty()),
+            "no spurious blank lines: {lines:?}"
+        );
+    }
+
     /// Helper: capture the visible viewport at each scrollback offset.
     /// Returns one `contents()` snapshot per offset, oldest first.
     fn scrollback_viewports(vte: &mut vt100::Parser) -> Vec<String> {
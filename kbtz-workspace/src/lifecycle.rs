@@ -1,5 +1,7 @@
 use std::time::{Duration, Instant};
 
+/// Default time a stopping session gets before it is force-killed;
+/// overridden by `workspace.graceful_timeout_secs`.
 pub const GRACEFUL_TIMEOUT: Duration = Duration::from_secs(5);
 
 // ── Snapshot types (pure data, no IO) ──────────────────────────────────
@@ -15,6 +17,9 @@ pub enum SessionPhase {
 pub struct SessionSnapshot {
     pub session_id: String,
     pub phase: SessionPhase,
+    pub started_at: Instant,
+    /// When the session started reporting `idle`, if it still is.
+    pub idle_since: Option<Instant>,
 }
 
 pub struct WorldSnapshot {
@@ -22,6 +27,14 @@ pub struct WorldSnapshot {
     /// Max sessions that tick() will auto-spawn. Set to 0 in manual mode
     /// to disable auto-spawning while preserving all reaping/cleanup logic.
     pub max_concurrency: usize,
+    /// How long a `Stopping` session may linger before it is force-killed.
+    pub graceful_timeout: Duration,
+    /// Runtime budget per session; running sessions past it are asked to
+    /// exit. `None` means unlimited.
+    pub max_runtime: Option<Duration>,
+    /// How long a running session may stay idle before its task is paused.
+    /// `None` disables the check.
+    pub idle_pause: Option<Duration>,
     pub now: Instant,
 }
 
@@ -29,9 +42,22 @@ pub struct WorldSnapshot {
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum SessionAction {
-    ForceKill { session_id: String },
-    Remove { session_id: String },
-    SpawnUpTo { count: usize },
+    RequestExit {
+        session_id: String,
+    },
+    ForceKill {
+        session_id: String,
+    },
+    Remove {
+        session_id: String,
+    },
+    SpawnUpTo {
+        count: usize,
+    },
+    /// Pause the session's task and ask the session to exit.
+    PauseIdle {
+        session_id: String,
+    },
 }
 
 // ── Pure decision function ─────────────────────────────────────────────
@@ -48,7 +74,7 @@ pub fn tick(world: &WorldSnapshot) -> Vec<SessionAction> {
                 });
             }
             SessionPhase::Stopping { since } => {
-                if world.now.duration_since(*since) >= GRACEFUL_TIMEOUT {
+                if world.now.duration_since(*since) >= world.graceful_timeout {
                     actions.push(SessionAction::ForceKill {
                         session_id: session.session_id.clone(),
                     });
@@ -59,8 +85,26 @@ pub fn tick(world: &WorldSnapshot) -> Vec<SessionAction> {
                 // Stopping sessions do NOT count toward concurrency.
             }
             SessionPhase::Running => {
-                // Never auto-kill running sessions. The user decides
-                // when to close them.
+                // Running sessions are only stopped when they sit idle or
+                // exceed the runtime budget; otherwise the user decides when
+                // to close them. A session asked to exit still holds its
+                // slot until it reaches Stopping on a later tick.
+                let idle_too_long = match (world.idle_pause, session.idle_since) {
+                    (Some(limit), Some(since)) => world.now.duration_since(since) >= limit,
+                    _ => false,
+                };
+                let over_budget = world
+                    .max_runtime
+                    .is_some_and(|budget| world.now.duration_since(session.started_at) >= budget);
+                if idle_too_long {
+                    actions.push(SessionAction::PauseIdle {
+                        session_id: session.session_id.clone(),
+                    });
+                } else if over_budget {
+                    actions.push(SessionAction::RequestExit {
+                        session_id: session.session_id.clone(),
+                    });
+                }
                 running_count += 1;
             }
         }
@@ -82,6 +126,8 @@ mod tests {
         SessionSnapshot {
             session_id: session_id.into(),
             phase,
+            started_at: Instant::now(),
+            idle_since: None,
         }
     }
 
@@ -89,6 +135,9 @@ mod tests {
         WorldSnapshot {
             sessions,
             max_concurrency,
+            graceful_timeout: GRACEFUL_TIMEOUT,
+            max_runtime: None,
+            idle_pause: None,
             now: Instant::now(),
         }
     }
@@ -243,4 +292,72 @@ mod tests {
         let actions = tick(&w);
         assert!(actions.is_empty());
     }
+
+    // 12. Configured graceful timeout replaces the default
+    #[test]
+    fn stopping_honors_configured_graceful_timeout() {
+        let since = Instant::now() - Duration::from_secs(10);
+        let mut w = world(vec![snapshot("ws/1", SessionPhase::Stopping { since })], 0);
+        w.graceful_timeout = Duration::from_secs(30);
+        assert!(tick(&w).is_empty());
+
+        w.graceful_timeout = Duration::from_secs(8);
+        assert_eq!(
+            tick(&w),
+            vec![
+                SessionAction::ForceKill {
+                    session_id: "ws/1".into()
+                },
+                SessionAction::Remove {
+                    session_id: "ws/1".into()
+                },
+            ]
+        );
+    }
+
+    // 13. Running past the runtime budget -> RequestExit, slot still held
+    #[test]
+    fn running_past_budget_requests_exit() {
+        let mut old = snapshot("ws/1", SessionPhase::Running);
+        old.started_at = Instant::now() - Duration::from_secs(31 * 60);
+        let fresh = snapshot("ws/2", SessionPhase::Running);
+        let mut w = world(vec![old, fresh], 3);
+
+        assert_eq!(tick(&w), vec![SessionAction::SpawnUpTo { count: 1 }]);
+
+        w.max_runtime = Some(Duration::from_secs(30 * 60));
+        assert_eq!(
+            tick(&w),
+            vec![
+                SessionAction::RequestExit {
+                    session_id: "ws/1".into()
+                },
+                SessionAction::SpawnUpTo { count: 1 },
+            ]
+        );
+    }
+
+    // 14. Idle past the threshold -> PauseIdle instead of RequestExit
+    #[test]
+    fn idle_past_threshold_pauses() {
+        let now = Instant::now();
+        let mut idle = snapshot("ws/1", SessionPhase::Running);
+        idle.idle_since = Some(now - Duration::from_secs(11 * 60));
+        idle.started_at = now - Duration::from_secs(60 * 60);
+        let mut recent = snapshot("ws/2", SessionPhase::Running);
+        recent.idle_since = Some(now - Duration::from_secs(60));
+        let mut w = world(vec![idle, recent], 2);
+        w.now = now;
+
+        assert!(tick(&w).is_empty());
+
+        w.idle_pause = Some(Duration::from_secs(10 * 60));
+        w.max_runtime = Some(Duration::from_secs(30 * 60));
+        assert_eq!(
+            tick(&w),
+            vec![SessionAction::PauseIdle {
+                session_id: "ws/1".into()
+            }]
+        );
+    }
 }
@@ -3,14 +3,16 @@ mod backend;
 mod lifecycle;
 mod session;
 mod shepherd_session;
+mod status_report;
 mod tree;
 
 use kbtz::config;
+use kbtz::debug_log::Level;
 use kbtz_workspace::prompt;
 
 use std::io::{self, Read, Write};
 use std::os::unix::fs::OpenOptionsExt;
-use std::os::unix::io::AsRawFd;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -18,7 +20,10 @@ use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use crossterm::event::{self as ct_event, Event, KeyCode, KeyEventKind};
+use crossterm::event::{
+    self as ct_event, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind,
+    MouseButton, MouseEvent, MouseEventKind,
+};
 use crossterm::execute;
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::prelude::*;
@@ -71,8 +76,8 @@ TREE MODE KEYS:
     s               Spawn session for task
     c               Switch to task manager session
     Space           Collapse/expand
-    p               Pause/unpause task
-    d               Mark task done
+    p               Pause/unpause task (asks first if a session is active)
+    d               Mark task done (asks first if a session is active)
     U               Force-unassign task
     /               Search/filter tasks
     ?               Help
@@ -84,9 +89,16 @@ ZOOMED MODE / TASK MANAGER:
     ^B n/p          Next/prev session
     ^B Tab          Jump to next needs-input/unread session
     ^B [            Scroll mode (also: Shift+Up, PgUp, left-click)
+    ^B b            Toggle the session list sidebar
     ^B ^B           Send literal Ctrl-B
     ^B ?            Help
-    ^B q            Quit"
+    ^B q            Quit
+
+    The prefix defaults to Ctrl-B; set prefix_key = \"C-a\" under [workspace]
+    to change it.
+
+    With --yes (or auto_confirm = true), p and d act on tasks with active
+    sessions immediately instead of asking for confirmation."
 )]
 struct Cli {
     /// Path to kbtz database [default: $KBTZ_DB or ~/.kbtz/kbtz.db]
@@ -101,6 +113,10 @@ struct Cli {
     #[arg(long)]
     prefer: Option<String>,
 
+    /// Only claim tasks within this task's subtree
+    #[arg(long)]
+    root: Option<String>,
+
     /// Agent backend to use for sessions [default: claude]
     #[arg(long)]
     backend: Option<String>,
@@ -113,6 +129,10 @@ struct Cli {
     #[arg(long)]
     manual: bool,
 
+    /// Pause or complete tasks with active sessions without confirmation
+    #[arg(short = 'y', long)]
+    yes: bool,
+
     /// Enable persistent sessions (shepherd-based); sessions survive workspace restart
     #[arg(long)]
     persistent_sessions: bool,
@@ -120,63 +140,244 @@ struct Cli {
     /// Workspace status directory [default: ~/.kbtz/workspace]
     #[arg(long, env = "KBTZ_WORKSPACE_DIR")]
     workspace_dir: Option<String>,
-}
-
-const PREFIX_KEY: u8 = 0x02; // Ctrl-B
 
-/// Length of the CSI u encoding of Ctrl-B: ESC [ 98 ; 5 u
-const CSIU_CTRL_B: [u8; 7] = *b"\x1b[98;5u";
+    /// Run without the TUI: work through claimable tasks, print session
+    /// status changes, and exit once no sessions remain
+    #[arg(long, conflicts_with = "manual")]
+    once: bool,
+}
 
 /// Check if bytes at position `i` are the CSI u (kitty keyboard protocol)
-/// encoding of Ctrl-B.  Returns the sequence length (7) on match, 0 otherwise.
+/// encoding of the prefix key.  Returns the sequence length on match, 0
+/// otherwise.
 ///
 /// Terminals that support the kitty keyboard protocol (e.g. Ghostty on macOS)
 /// may send Ctrl-B as `ESC [ 98 ; 5 u` instead of the legacy byte 0x02.
-fn csiu_prefix_len(buf: &[u8], i: usize, n: usize) -> usize {
-    if i + CSIU_CTRL_B.len() <= n && buf[i..i + CSIU_CTRL_B.len()] == CSIU_CTRL_B {
-        CSIU_CTRL_B.len()
+fn csiu_prefix_len(buf: &[u8], i: usize, n: usize, prefix: u8) -> usize {
+    if buf[i] != 0x1b {
+        return 0;
+    }
+    // The prefix is Ctrl + a lowercase letter; its codepoint is the letter.
+    let seq = format!("\x1b[{};5u", prefix | 0x60);
+    let seq = seq.as_bytes();
+    if i + seq.len() <= n && buf[i..i + seq.len()] == *seq {
+        seq.len()
     } else {
         0
     }
 }
 
+/// Bracketed paste markers sent by the terminal around pasted text.
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Tracks whether stdin is inside a bracketed paste.  Pasted text may
+/// contain the prefix key or sequences like PgUp, so while a paste is
+/// open every byte goes to the child verbatim until `PASTE_END` arrives,
+/// even when the end marker is split across reads.
+#[derive(Default)]
+struct PasteState {
+    active: bool,
+    /// Bytes of `PASTE_END` matched at the end of the previous chunk.
+    end_matched: usize,
+}
+
+impl PasteState {
+    /// Consume the leading part of `data` that belongs to the open paste:
+    /// everything up to and including `PASTE_END`, or all of `data` if
+    /// the end marker has not arrived yet.  Returns the consumed length.
+    fn consume(&mut self, data: &[u8]) -> usize {
+        for (idx, &b) in data.iter().enumerate() {
+            self.end_matched = if b == PASTE_END[self.end_matched] {
+                self.end_matched + 1
+            } else if b == PASTE_END[0] {
+                1
+            } else {
+                0
+            };
+            if self.end_matched == PASTE_END.len() {
+                self.active = false;
+                self.end_matched = 0;
+                return idx + 1;
+            }
+        }
+        data.len()
+    }
+}
+
+/// How long to wait for the rest of a paste start marker cut off by the
+/// end of a read.  A lone ESC keypress looks the same, so this must stay
+/// short enough not to be noticed.
+const PASTE_SPLIT_WAIT: Duration = Duration::from_millis(20);
+
+/// Check whether `buf[i..n]` is a bracketed paste start marker cut off
+/// by the end of the read.
+fn partial_paste_start(buf: &[u8], i: usize, n: usize) -> bool {
+    n - i < PASTE_START.len() && PASTE_START.starts_with(&buf[i..n])
+}
+
 /// Watches the kbtz database and status directory for changes.
 /// Polling with `poll()` checks both channels and refreshes app state.
+///
+/// Every event also writes a byte to a non-blocking pipe, so the main
+/// loops can block on `wake_fd()` alongside stdin and react to changes
+/// immediately even while backed off to a long poll timeout.
 struct Watchers {
     _db_watcher: notify::RecommendedWatcher,
     db_rx: std::sync::mpsc::Receiver<()>,
     _status_watcher: notify::RecommendedWatcher,
     status_rx: std::sync::mpsc::Receiver<()>,
+    wake: OwnedFd,
 }
 
 impl Watchers {
     fn new(app: &App) -> Result<Self> {
+        let mut fds = [0; 2];
+        // pipe2 isn't available on macOS, so set the flags separately.
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error()).context("failed to create wake pipe");
+        }
+        let (wake, wake_tx) =
+            unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        for fd in fds {
+            let ok = unsafe {
+                let flags = libc::fcntl(fd, libc::F_GETFL);
+                flags >= 0
+                    && libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) == 0
+                    && libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) == 0
+            };
+            if !ok {
+                return Err(io::Error::last_os_error()).context("failed to configure wake pipe");
+            }
+        }
+        let wake_tx = Arc::new(wake_tx);
+
         let (_db_watcher, db_rx) = kbtz::watch::watch_db(&app.db_path)?;
         let (_status_watcher, status_rx) = kbtz::watch::watch_dir(&app.status_dir)?;
         Ok(Watchers {
             _db_watcher,
-            db_rx,
+            db_rx: forward_with_wake(db_rx, wake_tx.clone()),
             _status_watcher,
-            status_rx,
+            status_rx: forward_with_wake(status_rx, wake_tx),
+            wake,
         })
     }
 
-    fn poll(&self, app: &mut App) -> Result<()> {
+    /// Read end of the wake pipe; readable whenever a watcher has fired
+    /// since the last `poll`.
+    fn wake_fd(&self) -> RawFd {
+        self.wake.as_raw_fd()
+    }
+
+    /// Apply pending watcher events to the app. Returns whether any fired.
+    fn poll(&self, app: &mut App) -> Result<bool> {
+        let mut drain = [0u8; 64];
+        while unsafe { libc::read(self.wake_fd(), drain.as_mut_ptr().cast(), drain.len()) } > 0 {}
+
         let db_event = kbtz::watch::wait_for_change(&self.db_rx, Duration::ZERO);
         if db_event {
             kbtz::watch::drain_events(&self.db_rx);
-            kbtz::debug_log::log("watchers.poll: db event -> tree_dirty");
+            kbtz::debug_log::log_at(Level::Debug, "watchers.poll: db event -> tree_dirty");
             app.tree_dirty = true;
         }
-        if kbtz::watch::wait_for_change(&self.status_rx, Duration::ZERO) {
+        let status_event = kbtz::watch::wait_for_change(&self.status_rx, Duration::ZERO);
+        if status_event {
             kbtz::watch::drain_events(&self.status_rx);
-            kbtz::debug_log::log("watchers.poll: status event -> read_status_files");
+            kbtz::debug_log::log_at(
+                Level::Debug,
+                "watchers.poll: status event -> read_status_files",
+            );
             app.read_status_files()?;
         }
-        Ok(())
+        Ok(db_event || status_event)
     }
 }
 
+/// Re-send events from `rx` on a new channel, poking the wake pipe after
+/// each one. The thread exits once the watcher (and so `rx`'s sender) or
+/// the returned receiver is dropped.
+fn forward_with_wake(
+    rx: std::sync::mpsc::Receiver<()>,
+    wake_tx: Arc<OwnedFd>,
+) -> std::sync::mpsc::Receiver<()> {
+    let (tx, out) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for () in rx {
+            if tx.send(()).is_err() {
+                return;
+            }
+            // A full pipe already guarantees a wakeup, so EAGAIN is fine.
+            unsafe { libc::write(wake_tx.as_raw_fd(), [1u8].as_ptr().cast(), 1) };
+        }
+    });
+    out
+}
+
+/// Number of consecutive idle loop iterations before the poll timeout
+/// starts growing.
+const IDLE_ITERATIONS_BEFORE_BACKOFF: u32 = 10;
+
+/// Longest poll timeout the main loops back off to while idle.
+const MAX_IDLE_POLL: Duration = Duration::from_millis(500);
+
+/// Adaptive poll timeout for the main loops. Starts at the loop's base
+/// interval, doubles per idle iteration once the loop has been idle for a
+/// while (up to `MAX_IDLE_POLL`), and snaps back on any activity. Input and
+/// watcher events wake the loops directly, so this only delays periodic
+/// work like liveness checks and lifecycle ticks.
+struct IdleBackoff {
+    base: Duration,
+    idle: u32,
+}
+
+impl IdleBackoff {
+    fn new(base: Duration) -> Self {
+        IdleBackoff { base, idle: 0 }
+    }
+
+    /// Record whether the last iteration did anything.
+    fn record(&mut self, active: bool) {
+        self.idle = if active {
+            0
+        } else {
+            self.idle.saturating_add(1)
+        };
+    }
+
+    fn timeout(&self) -> Duration {
+        let doublings = self
+            .idle
+            .saturating_sub(IDLE_ITERATIONS_BEFORE_BACKOFF)
+            .min(16);
+        self.base
+            .saturating_mul(1 << doublings)
+            .min(MAX_IDLE_POLL.max(self.base))
+    }
+}
+
+/// Block until stdin or `wake_fd` is readable, or `timeout` elapses.
+/// Returns whether stdin is readable. EINTR (e.g. SIGWINCH) counts as a
+/// timeout so callers loop and re-check state.
+fn poll_input(stdin_fd: RawFd, wake_fd: RawFd, timeout: Duration) -> bool {
+    let mut pfds = [
+        libc::pollfd {
+            fd: stdin_fd,
+            events: libc::POLLIN,
+            revents: 0,
+        },
+        libc::pollfd {
+            fd: wake_fd,
+            events: libc::POLLIN,
+            revents: 0,
+        },
+    ];
+    let ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+    if unsafe { libc::poll(pfds.as_mut_ptr(), 2, ms) } <= 0 {
+        return false;
+    }
+    pfds[0].revents != 0
+}
+
 fn main() {
     if let Err(e) = run() {
         eprintln!("kbtz-workspace: {e:#}");
@@ -210,7 +411,7 @@ fn run() -> Result<()> {
     std::fs::create_dir_all(&status_dir).context("failed to create status directory")?;
 
     // Acquire exclusive lock on the status directory to prevent concurrent instances.
-    let lock_path = status_dir.join("workspace.lock");
+    let lock_path = status_dir.join(kbtz::paths::WORKSPACE_LOCK);
     let _lock_file = std::fs::OpenOptions::new()
         .write(true)
         .create(true)
@@ -228,7 +429,13 @@ fn run() -> Result<()> {
     }
     // _lock_file must stay alive for the duration of run() — the lock is released when the fd is closed.
 
-    let (cols, rows) = terminal::size().context("failed to get terminal size")?;
+    // Batch mode may run without a terminal (CI, cron); sessions still
+    // need a PTY size.
+    let (cols, rows) = if cli.once {
+        terminal::size().unwrap_or((80, 24))
+    } else {
+        terminal::size().context("failed to get terminal size")?
+    };
 
     // Disable focus event reporting that may be left over from a previous
     // session.  Some terminals (notably on macOS) keep DECSET 1004 enabled
@@ -239,12 +446,39 @@ fn run() -> Result<()> {
     // Also pop the kitty keyboard protocol stack in case a prior child
     // pushed it.  Without this, the terminal may encode Ctrl-B as a CSI u
     // sequence that we wouldn't detect as our prefix key.
-    let _ = write!(io::stdout(), "\x1b[?1004l\x1b[<u");
-    let _ = io::stdout().flush();
+    if !cli.once {
+        let _ = write!(io::stdout(), "\x1b[?1004l\x1b[<u");
+        let _ = io::stdout().flush();
+    }
 
     let concurrency = cli.concurrency.or(ws.concurrency).unwrap_or(8);
-    let manual = cli.manual || ws.manual.unwrap_or(false);
+    let manual = !cli.once && (cli.manual || ws.manual.unwrap_or(false));
+    let auto_confirm = cli.yes || ws.auto_confirm.unwrap_or(false);
+    let prefix_key = match ws.prefix_key.as_deref() {
+        Some(spec) => config::parse_prefix_key(spec)?,
+        None => config::DEFAULT_PREFIX_KEY,
+    };
+    let osc52_clipboard = ws.osc52_clipboard.unwrap_or(false);
+    let terminal = session::TerminalOptions {
+        clipboard: ws.allow_clipboard.unwrap_or_default(),
+        scrollback_rows: ws
+            .scrollback_rows
+            .unwrap_or(kbtz_workspace::SCROLLBACK_ROWS),
+    };
+    let sidebar = ws.sidebar.unwrap_or(false);
+    let sidebar_width = ws.sidebar_width.unwrap_or(24);
+    if sidebar_width < 10 {
+        anyhow::bail!("sidebar_width must be at least 10 columns");
+    }
+    let graceful_timeout = ws
+        .graceful_timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or(lifecycle::GRACEFUL_TIMEOUT);
+    let restart_in_place = ws.restart_in_place.unwrap_or(false);
+    let max_session_runtime = ws.max_session_minutes.map(|m| Duration::from_secs(m * 60));
+    let idle_pause = ws.idle_pause_minutes.map(|m| Duration::from_secs(m * 60));
     let prefer = cli.prefer.or(ws.prefer);
+    let root = cli.root.or(ws.root);
     let default_backend = cli
         .backend
         .or(ws.backend)
@@ -274,14 +508,23 @@ fn run() -> Result<()> {
     let default_extra_args: Vec<String> = default_agent_config
         .map(|a| a.args.clone())
         .unwrap_or_default();
+    let default_template = default_agent_config.and_then(|a| a.prompt_args.as_deref());
     backends.insert(
         default_backend.clone(),
-        backend::from_name(
-            default_backend_impl,
-            default_command_override,
-            &default_prefix_args,
-            &default_extra_args,
-        ),
+        match default_template {
+            Some(prompt_args) => backend::template(
+                default_command_override.unwrap_or(&default_backend),
+                &default_prefix_args,
+                prompt_args,
+                &default_extra_args,
+            ),
+            None => backend::from_name(
+                default_backend_impl,
+                default_command_override,
+                &default_prefix_args,
+                &default_extra_args,
+            ),
+        },
     );
 
     // Build backends for all other configured agent types.
@@ -290,17 +533,48 @@ fn run() -> Result<()> {
             continue; // already built (e.g. the default backend)
         }
         let backend_impl = agent_cfg.backend.as_deref().unwrap_or(name);
-        backends.insert(
-            name.clone(),
-            backend::from_name(
+        let backend = match &agent_cfg.prompt_args {
+            Some(prompt_args) => backend::template(
+                agent_cfg.binary().unwrap_or(name),
+                agent_cfg.prefix_args(),
+                prompt_args,
+                &agent_cfg.args,
+            ),
+            None => backend::from_name(
                 backend_impl,
                 agent_cfg.binary(),
                 agent_cfg.prefix_args(),
                 &agent_cfg.args,
             ),
-        );
+        };
+        backends.insert(name.clone(), backend);
     }
 
+    // Wrap backends whose agents (or the workspace) configure an exit sequence.
+    // The workspace default is kept for backends created on demand later.
+    let default_exit_sequence = ws
+        .exit_sequence
+        .as_deref()
+        .map(config::parse_exit_sequence)
+        .transpose()?;
+    let backends = backends
+        .into_iter()
+        .map(|(name, backend)| {
+            let spec = config
+                .agent
+                .get(&name)
+                .and_then(|a| a.exit_sequence.as_deref())
+                .or(ws.exit_sequence.as_deref());
+            let backend = match spec {
+                Some(spec) => {
+                    backend::with_exit_sequence(backend, config::parse_exit_sequence(spec)?)
+                }
+                None => backend,
+            };
+            Ok((name, backend))
+        })
+        .collect::<Result<std::collections::HashMap<_, _>>>()?;
+
     // Default working directory for agent sessions: config > workspace cwd
     let default_directory = ws
         .directory
@@ -312,9 +586,21 @@ fn run() -> Result<()> {
         status_dir,
         concurrency,
         manual,
+        auto_confirm,
+        prefix_key,
+        osc52_clipboard,
+        terminal,
+        sidebar,
+        sidebar_width,
+        graceful_timeout,
+        restart_in_place,
+        max_session_runtime,
+        idle_pause,
         prefer,
+        root,
         backends,
         default_backend,
+        default_exit_sequence,
         app::TermSize { rows, cols },
         persistent_sessions,
         default_directory,
@@ -331,6 +617,12 @@ fn run() -> Result<()> {
     })
     .context("failed to set Ctrl+C handler")?;
 
+    if cli.once {
+        let result = once_loop(&mut app, &running);
+        app.shutdown();
+        return result;
+    }
+
     // Enter alternate screen once for the entire session.
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
@@ -350,7 +642,12 @@ fn run() -> Result<()> {
             "\x1b[?1004l", // disable focus event reporting
         )
     );
-    let _ = execute!(stdout, crossterm::cursor::Show, LeaveAlternateScreen,);
+    let _ = execute!(
+        stdout,
+        DisableMouseCapture,
+        crossterm::cursor::Show,
+        LeaveAlternateScreen,
+    );
 
     result
 }
@@ -401,20 +698,117 @@ fn main_loop(app: &mut App, running: &Arc<AtomicBool>) -> Result<()> {
     }
 }
 
+/// Session id → (task, status), ordered for stable output.
+type SessionStatuses = std::collections::BTreeMap<String, (String, SessionStatus)>;
+
+fn session_snapshot(app: &App) -> SessionStatuses {
+    app.sessions
+        .iter()
+        .map(|(sid, ts)| {
+            (
+                sid.clone(),
+                (
+                    ts.handle.task_name().to_string(),
+                    ts.handle.status().clone(),
+                ),
+            )
+        })
+        .collect()
+}
+
+/// One line per session that appeared, changed status, or exited
+/// between two snapshots.
+fn status_transitions(prev: &SessionStatuses, next: &SessionStatuses) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (sid, (task, status)) in next {
+        if prev.get(sid).map(|(_, s)| s) != Some(status) {
+            lines.push(format!("{task} ({sid}): {}", status.label()));
+        }
+    }
+    for (sid, (task, _)) in prev {
+        if !next.contains_key(sid) {
+            lines.push(format!("{task} ({sid}): exited"));
+        }
+    }
+    lines
+}
+
+/// `--once`: drive the lifecycle without a UI until no sessions remain.
+/// Every tick claims tasks up to the concurrency limit, so an empty
+/// session table after a tick means nothing is left to claim.
+///
+/// A session that exits while its task is still open would have the task
+/// reclaimed on the next tick, forever.  Instead, the first such exit
+/// stops further claims; the running sessions finish and the batch fails.
+fn once_loop(app: &mut App, running: &Arc<AtomicBool>) -> Result<()> {
+    let mut prev = SessionStatuses::new();
+    let mut unfinished = Vec::new();
+    loop {
+        if !running.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        app.read_status_files()?;
+        // Reap without claiming when a session has died: a tick that does
+        // both would hand an unfinished task to a new session before we
+        // can tell it was left open.
+        let reaping = app.sessions.values_mut().any(|ts| !ts.handle.is_alive());
+        let manual = app.manual;
+        app.manual |= reaping;
+        app.tick()?;
+        app.manual = manual;
+        if let Some(err) = app.tree.error.take() {
+            eprintln!("kbtz-workspace: {err}");
+        }
+        let next = session_snapshot(app);
+        for line in status_transitions(&prev, &next) {
+            println!("{line}");
+        }
+        for (sid, (task, _)) in &prev {
+            if next.contains_key(sid) {
+                continue;
+            }
+            let Ok(t) = kbtz::ops::get_task(&app.conn, task) else {
+                continue;
+            };
+            let still_held = t.assignee.as_deref() == Some(sid.as_str());
+            if still_held {
+                // The reap's release lost a lock race; retry with the
+                // normal busy timeout so the claim doesn't outlive us.
+                kbtz::ops::release_task(&app.conn, task, sid)?;
+            }
+            if t.status == "open" || still_held {
+                if unfinished.is_empty() {
+                    eprintln!("kbtz-workspace: {task} exited unfinished; not claiming more tasks");
+                }
+                app.manual = true;
+                unfinished.push(task.clone());
+            }
+        }
+        if next.is_empty() && (!reaping || !unfinished.is_empty()) {
+            if !unfinished.is_empty() {
+                anyhow::bail!("tasks left unfinished: {}", unfinished.join(", "));
+            }
+            return Ok(());
+        }
+        prev = next;
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
 // ── Stdin helpers ─────────────────────────────────────────────────────
 
-/// Poll stdin with a 16ms (~60 fps) timeout. Returns `Some(n)` if `n`
-/// bytes were read (`0` means EOF/error), or `None` on timeout/EINTR.
-fn poll_stdin(stdin: &mut io::StdinLock, buf: &mut [u8]) -> Option<usize> {
-    let stdin_fd = stdin.as_raw_fd();
-    let mut pfd = libc::pollfd {
-        fd: stdin_fd,
-        events: libc::POLLIN,
-        revents: 0,
-    };
+/// Poll stdin, waking early if `wake_fd` becomes readable. Returns
+/// `Some(n)` if `n` bytes were read (`0` means EOF/error), or `None` on
+/// timeout/wake/EINTR.
+fn poll_stdin(
+    stdin: &mut io::StdinLock,
+    buf: &mut [u8],
+    wake_fd: RawFd,
+    timeout: Duration,
+) -> Option<usize> {
     // EINTR on poll() is benign — the caller loops, so we'll retry
     // on the next iteration.
-    if unsafe { libc::poll(&mut pfd, 1, 16) } <= 0 {
+    if !poll_input(stdin.as_raw_fd(), wake_fd, timeout) {
         return None;
     }
     match stdin.read(buf) {
@@ -427,6 +821,30 @@ fn poll_stdin(stdin: &mut io::StdinLock, buf: &mut [u8]) -> Option<usize> {
     }
 }
 
+/// Move the unread tail `buf[*i..*n]` to the front of `buf` and append
+/// whatever stdin delivers within `timeout`, so a sequence split across
+/// reads can be matched whole.  Returns `false` on EOF.
+fn read_more(
+    buf: &mut [u8],
+    i: &mut usize,
+    n: &mut usize,
+    stdin: &mut io::StdinLock,
+    timeout: Duration,
+) -> bool {
+    buf.copy_within(*i..*n, 0);
+    *n -= *i;
+    *i = 0;
+    // poll() ignores negative fds, so only stdin can end the wait.
+    match poll_stdin(stdin, &mut buf[*n..], -1, timeout) {
+        Some(0) => false,
+        Some(read) => {
+            *n += read;
+            true
+        }
+        None => true,
+    }
+}
+
 /// Read one byte from the buffer (advancing `*i`) or from `stdin`.
 /// Returns `None` on EOF only.  Retries on EINTR.
 fn read_one_byte(buf: &[u8], i: &mut usize, n: usize, stdin: &mut io::StdinLock) -> Option<u8> {
@@ -520,7 +938,7 @@ fn csiu_to_legacy(codepoint: u32, modifier: u32) -> Option<u8> {
     }
 }
 
-/// Read the command byte after a PREFIX_KEY, either from the remaining
+/// Read the command byte after the prefix key, either from the remaining
 /// buffer or by reading one more byte from stdin.  Returns `None` on
 /// EOF only.
 ///
@@ -575,11 +993,15 @@ fn tree_mode(app: &mut App, running: &Arc<AtomicBool>) -> Result<Action> {
     // this behaviour.
     let mut stdout = io::stdout();
     write!(stdout, "\x1b[r\x1b[H\x1b[J")?;
+    // Mouse capture is only enabled in tree mode; passthrough sessions
+    // must receive the terminal's native mouse handling.
+    execute!(stdout, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     let result = tree_loop(&mut terminal, app, running);
 
+    execute!(io::stdout(), DisableMouseCapture)?;
     terminal::disable_raw_mode()?;
 
     result
@@ -603,10 +1025,11 @@ fn tree_loop(
     use kbtz::ui::TreeKeyAction;
 
     // Catch any DB changes that happened during zoomed mode.
-    app.refresh_tree()?;
-    app.tree_dirty = false;
+    app.reload_tree()?;
 
     let watchers = Watchers::new(app)?;
+    let mut last_click: Option<(usize, Instant)> = None;
+    let mut backoff = IdleBackoff::new(Duration::from_millis(100));
 
     loop {
         if !running.load(Ordering::SeqCst) {
@@ -616,7 +1039,11 @@ fn tree_loop(
         terminal.draw(|frame| {
             tree::render(frame, app);
             match &app.tree.mode {
-                kbtz::ui::TreeMode::Help => tree::render_help(frame),
+                kbtz::ui::TreeMode::Help => tree::render_help(
+                    frame,
+                    app.tree.active_policy == kbtz::ui::ActiveTaskPolicy::Allow,
+                    &app.prefix_label(),
+                ),
                 kbtz::ui::TreeMode::ConfirmDone(name) => {
                     let msg = active_task_message(app, name);
                     kbtz::ui::render_confirm(frame, "Done", name, &msg);
@@ -629,12 +1056,28 @@ fn tree_loop(
             }
         })?;
 
-        if ct_event::poll(Duration::from_millis(100))? {
+        // crossterm may already hold buffered events; otherwise block on
+        // the terminal and the watchers together.
+        let has_event = ct_event::poll(Duration::ZERO)?
+            || (poll_input(
+                io::stdin().as_raw_fd(),
+                watchers.wake_fd(),
+                backoff.timeout(),
+            ) && ct_event::poll(Duration::ZERO)?);
+        if has_event {
+            backoff.record(true);
             let event = ct_event::read()?;
             if let Event::Resize(cols, rows) = event {
                 app.handle_resize(cols, rows);
                 continue;
             }
+            if let Event::Mouse(mouse) = event {
+                let area = tree::tree_area(terminal.size()?.into());
+                if let Some(action) = handle_tree_mouse(app, mouse, area, &mut last_click) {
+                    return Ok(action);
+                }
+                continue;
+            }
             if let Event::Key(key) = event {
                 if key.kind != KeyEventKind::Press {
                     continue;
@@ -644,6 +1087,12 @@ fn tree_loop(
                 if let Some(panel) = &mut app.notes_panel {
                     match panel.handle_key(key) {
                         kbtz::ui::NotesKeyAction::Close => app.notes_panel = None,
+                        kbtz::ui::NotesKeyAction::Delete(id) => {
+                            if let Err(e) = kbtz::ops::delete_note(&app.conn, id) {
+                                app.tree.error = Some(e.to_string());
+                            }
+                            app.reload_tree()?;
+                        }
                         kbtz::ui::NotesKeyAction::Continue => {}
                     }
                     continue;
@@ -653,12 +1102,12 @@ fn tree_loop(
                     TreeKeyAction::Quit => return Ok(Action::Quit),
                     TreeKeyAction::Refresh | TreeKeyAction::ToggleShowAll => app.refresh_tree()?,
                     TreeKeyAction::Pause(name) => match kbtz::ops::pause_task(&app.conn, &name) {
-                        Ok(()) => app.refresh_tree()?,
+                        Ok(()) => app.reload_tree()?,
                         Err(e) => app.tree.error = Some(e.to_string()),
                     },
                     TreeKeyAction::Unpause(name) => {
                         match kbtz::ops::unpause_task(&app.conn, &name) {
-                            Ok(()) => app.refresh_tree()?,
+                            Ok(()) => app.reload_tree()?,
                             Err(e) => app.tree.error = Some(e.to_string()),
                         }
                     }
@@ -666,15 +1115,15 @@ fn tree_loop(
                         kbtz::debug_log::log(&format!("mark done: {name}"));
                         match kbtz::ops::mark_done(&app.conn, &name) {
                             Ok(()) => {
-                                app.refresh_tree()?;
-                                kbtz::debug_log::log("mark done: refresh_tree complete");
+                                app.reload_tree()?;
+                                kbtz::debug_log::log("mark done: reload_tree complete");
                             }
                             Err(e) => app.tree.error = Some(e.to_string()),
                         }
                     }
                     TreeKeyAction::ForceUnassign(name) => {
                         match kbtz::ops::force_unassign_task(&app.conn, &name) {
-                            Ok(()) => app.refresh_tree()?,
+                            Ok(()) => app.reload_tree()?,
                             Err(e) => app.tree.error = Some(e.to_string()),
                         }
                     }
@@ -724,22 +1173,70 @@ fn tree_loop(
             }
         }
 
-        watchers.poll(app)?;
+        let mut active = watchers.poll(app)?;
         if app.tree_dirty {
             app.refresh_tree()?;
-            app.tree_dirty = false;
         }
         if let Some(desc) = app.tick()? {
-            kbtz::debug_log::log(&format!("tick: {desc}"));
+            active = true;
+            kbtz::debug_log::log_at(Level::Debug, format_args!("tick: {desc}"));
             // Session removals for done tasks don't write to the DB
             // (release_task fails because the task is already done), so the
             // DB watcher never fires and the tree would stay stale.  Refresh
             // immediately so done tasks disappear when their session ends.
-            app.refresh_tree()?;
+            app.reload_tree()?;
+        }
+        if !has_event {
+            backoff.record(active);
         }
     }
 }
 
+/// Two clicks on the same row within this window count as a double-click.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Handle a mouse event in tree mode: left click selects a row, a
+/// double-click zooms into the row's session, and the scroll wheel moves
+/// the cursor. Ignored while the notes panel or a popup is shown.
+fn handle_tree_mouse(
+    app: &mut App,
+    mouse: MouseEvent,
+    area: Rect,
+    last_click: &mut Option<(usize, Instant)>,
+) -> Option<Action> {
+    use kbtz::ui::TreeMode;
+
+    if app.notes_panel.is_some() || !matches!(app.tree.mode, TreeMode::Normal | TreeMode::Search(_))
+    {
+        return None;
+    }
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            let idx = app.tree.row_at(area, mouse.row)?;
+            app.tree.select(idx);
+            let now = Instant::now();
+            let double = matches!(
+                *last_click,
+                Some((prev, at)) if prev == idx && now.duration_since(at) < DOUBLE_CLICK_INTERVAL
+            );
+            if !double {
+                *last_click = Some((idx, now));
+                return None;
+            }
+            *last_click = None;
+            let name = app.tree.selected_name()?;
+            if app.task_to_session.contains_key(name) {
+                return Some(Action::ZoomIn(name.to_string()));
+            }
+            app.tree.error = Some("no active session for this task".into());
+        }
+        MouseEventKind::ScrollUp => app.tree.move_up(),
+        MouseEventKind::ScrollDown => app.tree.move_down(),
+        _ => {}
+    }
+    None
+}
+
 // ── Passthrough mode (unified for both worker and toplevel sessions) ──
 
 fn passthrough_mode(
@@ -762,6 +1259,7 @@ fn passthrough_mode(
     // Start raw byte forwarding: render VTE state to screen and enable
     // the reader thread to write child output directly to stdout.
     if let Some(session) = app.get_session(kind.session_id()) {
+        session.set_col_offset(app.sidebar_cols())?;
         session.start_passthrough()?;
     }
 
@@ -873,9 +1371,15 @@ fn handle_prefix_command(
             }
             Ok(None)
         }
-        PREFIX_KEY => {
+        b'b' => {
+            app.toggle_sidebar();
+            refresh_passthrough_screen(app, kind, last_status, scroll)?;
+            Ok(None)
+        }
+        cmd if cmd == app.prefix_key => {
+            let prefix = app.prefix_key;
             if let Some(session) = app.get_session_mut(sid) {
-                session.write_input(&[PREFIX_KEY])?;
+                session.write_input(&[prefix])?;
             }
             Ok(None)
         }
@@ -906,6 +1410,13 @@ struct ScrollState {
     active: bool,
     offset: usize,
     total: usize,
+    /// Query being typed after `/`, or `None` when not prompting.
+    prompt: Option<Vec<u8>>,
+    /// Last submitted search, stepped through with `n`/`N`.
+    search: Option<ScrollSearch>,
+    /// One-shot status bar message (e.g. the dump path), cleared on the
+    /// next key.
+    message: Option<String>,
 }
 
 impl ScrollState {
@@ -914,10 +1425,36 @@ impl ScrollState {
             active: false,
             offset: 0,
             total: 0,
+            prompt: None,
+            search: None,
+            message: None,
         }
     }
 }
 
+/// A submitted scroll-mode search.  `matches` holds absolute line numbers
+/// (see `SessionHandle::search_scrollback`) in ascending order.
+struct ScrollSearch {
+    query: String,
+    matches: Vec<usize>,
+    current: usize,
+}
+
+/// Index of the newest match at or above `line`, falling back to the
+/// newest match overall (search wraps around like `less`).
+fn match_at_or_above(matches: &[usize], line: usize) -> Option<usize> {
+    matches
+        .iter()
+        .rposition(|&m| m <= line)
+        .or_else(|| matches.len().checked_sub(1))
+}
+
+/// Scrollback offset that puts absolute `line` at the top of the viewport,
+/// or 0 when the line is already on the live screen.
+fn offset_for_line(line: usize, total: usize) -> usize {
+    total.saturating_sub(line)
+}
+
 /// Enter scroll mode.  Freezes the screen and disables mouse tracking
 /// so the terminal can handle native text selection.  Works even when
 /// there is no scrollback (the user can still select visible text).
@@ -926,6 +1463,8 @@ fn enter_scroll_mode(app: &App, session_id: &str, scroll: &mut ScrollState) -> R
         scroll.total = session.enter_scroll_mode()?;
         scroll.offset = 0;
         scroll.active = true;
+        scroll.prompt = None;
+        scroll.search = None;
         // Disable mouse tracking so the terminal handles native text selection.
         // Scoped so the stdout lock is released before render_scrollback,
         // which acquires passthrough → stdout in the correct order.
@@ -945,12 +1484,140 @@ fn enter_scroll_mode(app: &App, session_id: &str, scroll: &mut ScrollState) -> R
 fn exit_scroll_mode(app: &App, session_id: &str, scroll: &mut ScrollState) -> Result<()> {
     scroll.active = false;
     scroll.offset = 0;
+    scroll.prompt = None;
+    scroll.search = None;
+    scroll.message = None;
     if let Some(session) = app.get_session(session_id) {
         session.exit_scroll_mode()?;
     }
     Ok(())
 }
 
+/// Write the scroll snapshot as plain text to `<status_dir>/<session>.dump.txt`
+/// and, when `osc52_clipboard` is enabled, copy it to the clipboard.  The
+/// outcome is reported in the scroll status bar.
+fn dump_scrollback(app: &App, session_id: &str, scroll: &mut ScrollState) -> Result<()> {
+    let Some(session) = app.get_session(session_id) else {
+        return Ok(());
+    };
+    let text = session.dump_scrollback()?;
+    let path = app.status_dir.join(format!(
+        "{}.dump.txt",
+        kbtz::paths::session_id_to_filename(session_id)
+    ));
+    if let Err(e) = std::fs::write(&path, &text) {
+        scroll.message = Some(format!("dump failed: {e}"));
+        return Ok(());
+    }
+    let mut message = format!("saved {}", path.display());
+    if app.osc52_clipboard {
+        use base64::Engine;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&text);
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        let _ = write!(out, "\x1b]52;c;{encoded}\x07");
+        let _ = out.flush();
+        message.push_str(" (copied)");
+    }
+    scroll.message = Some(message);
+    Ok(())
+}
+
+/// Run the search typed at the `/` prompt and jump to the newest match at
+/// or above the bottom of the current viewport.
+fn submit_scroll_search(
+    app: &App,
+    session_id: &str,
+    scroll: &mut ScrollState,
+    query: String,
+    page: usize,
+) -> Result<()> {
+    let Some(session) = app.get_session(session_id) else {
+        return Ok(());
+    };
+    let matches = session.search_scrollback(&query)?;
+    scroll.total = session.scrollback_available()?;
+    if query.is_empty() {
+        scroll.search = None;
+        return scroll_to(app, session_id, scroll, scroll.offset);
+    }
+    let bottom = scroll.total - scroll.offset.min(scroll.total) + page;
+    let current = match_at_or_above(&matches, bottom).unwrap_or(0);
+    scroll.search = Some(ScrollSearch {
+        query,
+        matches,
+        current,
+    });
+    jump_to_current_match(app, session_id, scroll)
+}
+
+/// Step to an older (`older = true`, `n`) or newer (`N`) match, wrapping
+/// around at either end.
+fn step_scroll_search(
+    app: &App,
+    session_id: &str,
+    scroll: &mut ScrollState,
+    older: bool,
+) -> Result<()> {
+    let Some(search) = scroll.search.as_mut() else {
+        return Ok(());
+    };
+    let len = search.matches.len();
+    if len == 0 {
+        return Ok(());
+    }
+    search.current = if older {
+        (search.current + len - 1) % len
+    } else {
+        (search.current + 1) % len
+    };
+    jump_to_current_match(app, session_id, scroll)
+}
+
+fn jump_to_current_match(app: &App, session_id: &str, scroll: &mut ScrollState) -> Result<()> {
+    let line = match &scroll.search {
+        Some(search) => match search.matches.get(search.current) {
+            Some(&line) => line,
+            None => return scroll_to(app, session_id, scroll, scroll.offset),
+        },
+        None => return Ok(()),
+    };
+    scroll_to(app, session_id, scroll, offset_for_line(line, scroll.total))
+}
+
+/// Handle a byte typed at the `/` search prompt.
+fn handle_search_prompt(
+    app: &App,
+    session_id: &str,
+    scroll: &mut ScrollState,
+    byte: u8,
+    page: usize,
+) -> Result<()> {
+    let Some(prompt) = scroll.prompt.as_mut() else {
+        return Ok(());
+    };
+    match byte {
+        b'\r' | b'\n' => {
+            let query = String::from_utf8_lossy(prompt).into_owned();
+            scroll.prompt = None;
+            submit_scroll_search(app, session_id, scroll, query, page)?;
+        }
+        0x1b => scroll.prompt = None,
+        0x7f | 0x08 => {
+            // Pop one UTF-8 character (continuation bytes are 10xxxxxx).
+            while let Some(b) = prompt.pop() {
+                if b & 0xc0 != 0x80 {
+                    break;
+                }
+            }
+        }
+        0x15 => prompt.clear(), // Ctrl-U
+        b if b < 0x20 => {}
+        b => prompt.push(b),
+    }
+    Ok(())
+}
+
 fn scroll_to(
     app: &App,
     session_id: &str,
@@ -978,6 +1645,14 @@ fn handle_scroll_input(
     rows: u16,
 ) -> Result<bool> {
     let page = (rows.saturating_sub(2)) as usize; // leave room for status bar
+    scroll.message = None;
+
+    if scroll.prompt.is_some() {
+        let byte = buf[*i];
+        *i += 1;
+        handle_search_prompt(app, session_id, scroll, byte, page)?;
+        return Ok(true);
+    }
 
     // Check for CSI sequences (arrow keys, PgUp/PgDn, mouse, etc.)
     if buf[*i] == 0x1b && *i + 2 < n && buf[*i + 1] == b'[' {
@@ -1064,6 +1739,26 @@ fn handle_scroll_input(
             exit_scroll_mode(app, session_id, scroll)?;
             Ok(false)
         }
+        b'/' => {
+            *i += 1;
+            scroll.prompt = Some(Vec::new());
+            Ok(true)
+        }
+        b'w' => {
+            *i += 1;
+            dump_scrollback(app, session_id, scroll)?;
+            Ok(true)
+        }
+        b'n' => {
+            *i += 1;
+            step_scroll_search(app, session_id, scroll, true)?;
+            Ok(true)
+        }
+        b'N' => {
+            *i += 1;
+            step_scroll_search(app, session_id, scroll, false)?;
+            Ok(true)
+        }
         _ => {
             *i += 1;
             Ok(true) // consume unknown keys in scroll mode
@@ -1076,6 +1771,24 @@ struct SgrMouseEvent {
     len: usize,
 }
 
+/// Shift the column of a complete SGR mouse sequence (`\x1b[<b;x;yM`)
+/// left by `offset`.  Returns `None` if the event lands within the
+/// first `offset` columns (the sidebar) or the sequence is malformed.
+fn shift_sgr_mouse(seq: &[u8], offset: u16) -> Option<Vec<u8>> {
+    if offset == 0 {
+        return Some(seq.to_vec());
+    }
+    let body = std::str::from_utf8(seq.get(3..seq.len() - 1)?).ok()?;
+    let mut parts = body.split(';');
+    let (button, x, y) = (parts.next()?, parts.next()?, parts.next()?);
+    let x: u16 = x.parse().ok()?;
+    if x <= offset {
+        return None;
+    }
+    let last = *seq.last()? as char;
+    Some(format!("\x1b[<{button};{};{y}{last}", x - offset).into_bytes())
+}
+
 /// Try to parse an SGR mouse sequence starting at `buf[start]`.
 /// Expected format: \x1b[<button;x;yM (or m for release).
 /// Returns None if the sequence is incomplete.
@@ -1141,6 +1854,7 @@ fn refresh_passthrough_screen(
     if let Some(session) = app.get_session(sid) {
         session.start_passthrough()?;
     }
+    draw_sidebar(app, sid, &sidebar_lines(app, sid));
     draw_normal_status_bar(
         app,
         sid,
@@ -1170,11 +1884,17 @@ fn passthrough_loop(
     let mut buf = [0u8; 4096];
     let mut last_status = SessionStatus::Starting;
     let mut scroll = ScrollState::new();
+    let mut paste = PasteState::default();
 
     let sid = kind.session_id();
     let watchers = Watchers::new(app)?;
     let mut debug_msg: Option<String> = None;
     let mut last_iter = Instant::now();
+    let mut last_sidebar = sidebar_lines(app, sid);
+    draw_sidebar(app, sid, &last_sidebar);
+    let mut last_budget = runtime_left_label(app, sid);
+    // ~60 fps while active.
+    let mut backoff = IdleBackoff::new(Duration::from_millis(16));
 
     draw_normal_status_bar(
         app,
@@ -1214,11 +1934,12 @@ fn passthrough_loop(
             }
         }
 
-        watchers.poll(app)?;
+        let mut active = watchers.poll(app)?;
 
         // Run lifecycle tick (reaps exited, enforces timeouts, spawns)
         if let Some(msg) = app.tick()? {
             debug_msg = Some(msg);
+            active = true;
         }
 
         // Re-check after tick (session may have been removed).
@@ -1230,15 +1951,19 @@ fn passthrough_loop(
         // bypasses crossterm's event system (using raw libc::poll), so
         // SIGWINCH is not delivered as a Resize event.  We detect resize
         // by polling terminal::size() each iteration, and detect sleep
-        // via a time-jump (the 100ms poll timeout took >2s).
+        // via a time-jump (a poll timeout of at most 500ms took >2s).
         let elapsed = last_iter.elapsed();
         last_iter = Instant::now();
         let (cur_cols, cur_rows) = terminal::size()?;
         let size_changed = cur_cols != app.term.cols || cur_rows != app.term.rows;
         if size_changed || elapsed > SLEEP_THRESHOLD {
-            kbtz::debug_log::log(&format!(
-                "passthrough refresh: size_changed={size_changed} elapsed={elapsed:?}"
-            ));
+            active = true;
+            kbtz::debug_log::log_at(
+                Level::Debug,
+                format_args!(
+                    "passthrough refresh: size_changed={size_changed} elapsed={elapsed:?}"
+                ),
+            );
             refresh_passthrough_screen(app, kind, &last_status, &mut scroll)?;
             if app.get_session(sid).is_none() {
                 return Ok(Action::ReturnToTree);
@@ -1259,6 +1984,21 @@ fn passthrough_loop(
         if debug_msg.is_some() {
             redraw = true;
         }
+        let budget = runtime_left_label(app, sid);
+        if budget != last_budget {
+            last_budget = budget;
+            redraw = true;
+        }
+        // Redraw the sidebar when any session's status or unread flag changes.
+        let sidebar = sidebar_lines(app, sid);
+        if sidebar != last_sidebar {
+            draw_sidebar(app, sid, &sidebar);
+            last_sidebar = sidebar;
+            active = true;
+        }
+        if redraw {
+            active = true;
+        }
         if redraw && !scroll.active {
             draw_normal_status_bar(
                 app,
@@ -1271,23 +2011,39 @@ fn passthrough_loop(
             );
         }
 
-        let n = match poll_stdin(&mut stdin, &mut buf) {
-            None => continue,
+        let mut n = match poll_stdin(&mut stdin, &mut buf, watchers.wake_fd(), backoff.timeout()) {
+            None => {
+                backoff.record(active);
+                continue;
+            }
             Some(0) => return Ok(Action::Quit),
             Some(n) => n,
         };
+        backoff.record(true);
 
         let rows = app.term.rows;
         let cols = app.term.cols;
 
         let mut i = 0;
         while i < n {
+            // ── Bracketed paste ────────────────────────────────────
+            // Pasted bytes go to the child untouched; no prefix or
+            // scroll interception until the paste ends.
+            if paste.active {
+                let len = paste.consume(&buf[i..n]);
+                if let Some(session) = app.get_session_mut(sid) {
+                    session.write_input(&buf[i..i + len])?;
+                }
+                i += len;
+                continue;
+            }
+
             // ── Scroll mode input ──────────────────────────────────
             if scroll.active {
-                // PREFIX_KEY commands still work in scroll mode.
-                // Detect both legacy 0x02 and CSI u encoding (kitty keyboard protocol).
-                let csiu = csiu_prefix_len(&buf, i, n);
-                if buf[i] == PREFIX_KEY || csiu > 0 {
+                // Prefix commands still work in scroll mode.
+                // Detect both the legacy byte and CSI u encoding (kitty keyboard protocol).
+                let csiu = csiu_prefix_len(&buf, i, n, app.prefix_key);
+                if buf[i] == app.prefix_key || csiu > 0 {
                     i += if csiu > 0 { csiu } else { 1 };
                     let cmd = match read_prefix_cmd(&buf, &mut i, n, &mut stdin) {
                         Some(b) => b,
@@ -1322,9 +2078,29 @@ fn passthrough_loop(
 
             // ── Normal mode input ──────────────────────────────────
 
+            if partial_paste_start(&buf, i, n)
+                && !read_more(&mut buf, &mut i, &mut n, &mut stdin, PASTE_SPLIT_WAIT)
+            {
+                return Ok(Action::Quit);
+            }
+            if buf[i..n].starts_with(PASTE_START) {
+                paste.active = true;
+                if let Some(session) = app.get_session_mut(sid) {
+                    session.write_input(PASTE_START)?;
+                }
+                i += PASTE_START.len();
+                continue;
+            }
+
             // Check for SGR mouse events
             if buf[i] == 0x1b && i + 2 < n && buf[i + 1] == b'[' && buf[i + 2] == b'<' {
                 if let Some(evt) = parse_sgr_mouse_scroll(&buf, i, n) {
+                    let seq = &buf[i..i + evt.len];
+                    // Translate to child coordinates; drop events on the sidebar.
+                    let Some(seq) = shift_sgr_mouse(seq, app.sidebar_cols()) else {
+                        i += evt.len;
+                        continue;
+                    };
                     if evt.button == 0 {
                         // Left click → enter scroll mode for text selection.
                         enter_scroll_mode(app, sid, &mut scroll)?;
@@ -1338,7 +2114,7 @@ fn passthrough_loop(
                     // mouse tracking, otherwise discard.
                     if let Some(session) = app.get_session_mut(sid) {
                         if session.has_mouse_tracking() {
-                            session.write_input(&buf[i..i + evt.len])?;
+                            session.write_input(&seq)?;
                         }
                     }
                     i += evt.len;
@@ -1380,9 +2156,9 @@ fn passthrough_loop(
                 }
             }
 
-            // Detect both legacy 0x02 and CSI u encoding (kitty keyboard protocol).
-            let csiu = csiu_prefix_len(&buf, i, n);
-            if buf[i] == PREFIX_KEY || csiu > 0 {
+            // Detect both the legacy byte and CSI u encoding (kitty keyboard protocol).
+            let csiu = csiu_prefix_len(&buf, i, n, app.prefix_key);
+            if buf[i] == app.prefix_key || csiu > 0 {
                 i += if csiu > 0 { csiu } else { 1 };
                 let cmd = match read_prefix_cmd(&buf, &mut i, n, &mut stdin) {
                     Some(b) => b,
@@ -1397,14 +2173,20 @@ fn passthrough_loop(
                     draw_scroll_status_bar(app, sid, rows, cols, &scroll);
                 }
             } else {
-                // Find the next PREFIX_KEY, CSI u prefix, or ESC sequence
+                // Find the next prefix key, CSI u prefix, or ESC sequence
                 // we intercept, and write the entire chunk to the PTY in
                 // one call.
                 let start = i;
                 while i < n
-                    && buf[i] != PREFIX_KEY
-                    && (buf[i] != 0x1b || csiu_prefix_len(&buf, i, n) == 0)
+                    && buf[i] != app.prefix_key
+                    && (buf[i] != 0x1b || csiu_prefix_len(&buf, i, n, app.prefix_key) == 0)
                 {
+                    // Stop before a (possibly split) paste start
+                    if i > start
+                        && (buf[i..n].starts_with(PASTE_START) || partial_paste_start(&buf, i, n))
+                    {
+                        break;
+                    }
                     if buf[i] == 0x1b && i + 2 < n && buf[i + 1] == b'[' {
                         // Stop before SGR mouse sequence
                         if buf[i + 2] == b'<' {
@@ -1502,12 +2284,111 @@ fn write_bar(
     );
 }
 
+/// Sidebar content for zoomed mode: one `(text, is_current)` entry per
+/// session, manager first.  Empty when the sidebar is hidden.
+fn sidebar_lines(app: &App, current_sid: &str) -> Vec<(String, bool)> {
+    if app.sidebar_cols() == 0 {
+        return Vec::new();
+    }
+    let mut lines = Vec::new();
+    if let Some(toplevel) = &app.toplevel {
+        lines.push((
+            format!(" {} manager", toplevel.status().indicator()),
+            current_sid == TOPLEVEL_SESSION_ID,
+        ));
+    }
+    for id in app.session_ids_ordered() {
+        let Some(ts) = app.sessions.get(&id) else {
+            continue;
+        };
+        let unread = if ts.unread { "\u{1f440}" } else { "" };
+        lines.push((
+            format!(
+                " {} {}{unread}",
+                ts.handle.status().indicator(),
+                ts.handle.task_name()
+            ),
+            id == current_sid,
+        ));
+    }
+    lines
+}
+
+/// Draw the sidebar in the left `sidebar_cols()` columns of every row
+/// above the status bar, ending each row with a vertical border.
+fn draw_sidebar(app: &App, sid: &str, lines: &[(String, bool)]) {
+    let width = app.sidebar_cols() as usize;
+    if width == 0 {
+        return;
+    }
+    let rows = app.term.rows.saturating_sub(1) as usize;
+    write_and_sync(app, sid, |out| {
+        for row in 0..rows {
+            let (text, current) = lines
+                .get(row)
+                .map(|(t, c)| (t.as_str(), *c))
+                .unwrap_or(("", false));
+            let style = if current { "7" } else { "0" };
+            let _ = write!(
+                out,
+                "\x1b[{};1H\x1b[{style}m{}\x1b[0;2m\u{2502}\x1b[0m",
+                row + 1,
+                fit_width(text, width - 1),
+            );
+        }
+    });
+}
+
+/// Truncate `s` to at most `width` display columns and pad with spaces
+/// to exactly `width`.
+fn fit_width(s: &str, width: usize) -> String {
+    let mut out = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let w = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        if used + w > width {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    out.push_str(&" ".repeat(width - used));
+    out
+}
+
 fn draw_scroll_status_bar(app: &App, sid: &str, rows: u16, cols: u16, scroll: &ScrollState) {
+    if let Some(prompt) = &scroll.prompt {
+        let content = format!(" [SEARCH] /{}", String::from_utf8_lossy(prompt));
+        draw_bar(
+            app,
+            sid,
+            rows,
+            cols,
+            "7;33",
+            &content,
+            Some("Enter:search  Esc:cancel"),
+        );
+        return;
+    }
     let content = format!(
-        " [SCROLL] line {}/{}  q:exit  k/\u{2191}/S-\u{2191}:up  j/\u{2193}:down  PgUp/PgDn  g/G:top/bot  click+drag:select",
+        " [SCROLL] line {}/{}  q:exit  k/\u{2191}/S-\u{2191}:up  j/\u{2193}:down  PgUp/PgDn  g/G:top/bot  /:search  w:dump  click+drag:select",
         scroll.offset, scroll.total,
     );
-    draw_bar(app, sid, rows, cols, "7;33", &content, None);
+    let right = scroll.message.clone().or_else(|| {
+        scroll.search.as_ref().map(|s| {
+            if s.matches.is_empty() {
+                format!("/{}: no matches", s.query)
+            } else {
+                format!(
+                    "/{} {}/{}  n/N:older/newer",
+                    s.query,
+                    s.matches.len() - s.current,
+                    s.matches.len()
+                )
+            }
+        })
+    });
+    draw_bar(app, sid, rows, cols, "7;33", &content, right.as_deref());
 }
 
 fn draw_normal_status_bar(
@@ -1519,37 +2400,125 @@ fn draw_normal_status_bar(
     status: &SessionStatus,
     debug: Option<&str>,
 ) {
+    let pk = app.prefix_label();
     let left = match kind {
-        SessionKind::TopLevel => " ^B ? help \u{2502} task manager".to_string(),
+        SessionKind::TopLevel => format!(" {pk} ? help \u{2502} task manager"),
         SessionKind::Worker { task, session_id } => {
-            format!(
-                " ^B ? help │ {} ({}) │ {} {}",
+            let mut left = format!(
+                " {pk} ? help │ {} ({}) │ {} {}",
                 task,
                 session_id,
                 status.indicator(),
                 status.label(),
-            )
+            );
+            if let Some(budget) = runtime_left_label(app, session_id) {
+                left.push_str(&format!(" │ {budget}"));
+            }
+            left
         }
     };
     draw_bar(app, sid, rows, cols, "7", &left, debug);
 }
 
+/// Remaining runtime budget for a worker, e.g. `⏱ 1h05m left`, rounded up
+/// to the minute.  `None` when no budget is configured.
+fn runtime_left_label(app: &App, session_id: &str) -> Option<String> {
+    let left = app.runtime_left(session_id)?;
+    let minutes = left.as_secs().div_ceil(60);
+    let text = if minutes >= 60 {
+        format!("{}h{:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{minutes}m")
+    };
+    Some(format!("\u{23f1} {text} left"))
+}
+
 fn draw_help_bar(app: &App, sid: &str, rows: u16, cols: u16, kind: &SessionKind) {
+    let pk = app.prefix_label();
     let content = match kind {
-        SessionKind::TopLevel => {
-            " ^B t:tree  ^B n:next worker  ^B p:prev worker  ^B Tab:input  ^B [:scroll  ^B ^B:send ^B  ^B q:quit  ^B ?:help"
-        }
-        SessionKind::Worker { .. } => {
-            " ^B t:tree  ^B c:manager  ^B n:next  ^B p:prev  ^B Tab:input  ^B [:scroll  ^B ^B:send ^B  ^B q:quit  ^B ?:help"
-        }
+        SessionKind::TopLevel => format!(
+            " {pk} t:tree  {pk} n:next worker  {pk} p:prev worker  {pk} Tab:input  {pk} [:scroll  {pk} b:sidebar  {pk} {pk}:send {pk}  {pk} q:quit  {pk} ?:help"
+        ),
+        SessionKind::Worker { .. } => format!(
+            " {pk} t:tree  {pk} c:manager  {pk} n:next  {pk} p:prev  {pk} Tab:input  {pk} [:scroll  {pk} b:sidebar  {pk} {pk}:send {pk}  {pk} q:quit  {pk} ?:help"
+        ),
     };
-    draw_bar(app, sid, rows, cols, "7;33", content, None);
+    draw_bar(app, sid, rows, cols, "7;33", &content, None);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn status_transitions_reports_changes_and_exits() {
+        let entry = |task: &str, status| (task.to_string(), status);
+        let prev = SessionStatuses::from([
+            ("ws/1".into(), entry("a", SessionStatus::Starting)),
+            ("ws/2".into(), entry("b", SessionStatus::Active)),
+            ("ws/3".into(), entry("c", SessionStatus::Idle)),
+        ]);
+        let next = SessionStatuses::from([
+            ("ws/1".into(), entry("a", SessionStatus::Active)),
+            ("ws/2".into(), entry("b", SessionStatus::Active)),
+            ("ws/4".into(), entry("d", SessionStatus::Starting)),
+        ]);
+        let starting = SessionStatus::Starting.label();
+        let active = SessionStatus::Active.label();
+        assert_eq!(
+            status_transitions(&prev, &next),
+            vec![
+                format!("a (ws/1): {active}"),
+                format!("d (ws/4): {starting}"),
+                "c (ws/3): exited".to_string(),
+            ]
+        );
+        assert!(status_transitions(&next, &next).is_empty());
+    }
+
+    #[test]
+    fn idle_backoff_grows_after_idle_iterations_and_resets() {
+        let base = Duration::from_millis(16);
+        let mut backoff = IdleBackoff::new(base);
+        for _ in 0..IDLE_ITERATIONS_BEFORE_BACKOFF {
+            backoff.record(false);
+            assert_eq!(backoff.timeout(), base);
+        }
+        backoff.record(false);
+        assert_eq!(backoff.timeout(), base * 2);
+        for _ in 0..100 {
+            backoff.record(false);
+        }
+        assert_eq!(backoff.timeout(), MAX_IDLE_POLL);
+
+        backoff.record(true);
+        assert_eq!(backoff.timeout(), base);
+    }
+
+    #[test]
+    fn poll_input_wakes_on_wake_pipe() {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        let mut stdin_fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(stdin_fds.as_mut_ptr()) }, 0);
+        let (fake_stdin, _fake_stdin_tx) = unsafe {
+            (
+                OwnedFd::from_raw_fd(stdin_fds[0]),
+                OwnedFd::from_raw_fd(stdin_fds[1]),
+            )
+        };
+
+        unsafe { libc::write(write.as_raw_fd(), [1u8].as_ptr().cast(), 1) };
+        let start = Instant::now();
+        assert!(!poll_input(
+            fake_stdin.as_raw_fd(),
+            read.as_raw_fd(),
+            Duration::from_secs(5)
+        ));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
     #[test]
     fn parse_sgr_scroll_up() {
         let buf = b"\x1b[<64;10;5M";
@@ -1617,26 +2586,129 @@ mod tests {
         assert_eq!(evt.len, buf.len());
     }
 
+    // ── Sidebar tests ────────────────────────────────────────────────
+
+    #[test]
+    fn shift_sgr_mouse_translates_column() {
+        assert_eq!(
+            shift_sgr_mouse(b"\x1b[<0;30;5M", 24).unwrap(),
+            b"\x1b[<0;6;5M".to_vec()
+        );
+        assert_eq!(
+            shift_sgr_mouse(b"\x1b[<64;30;5m", 0).unwrap(),
+            b"\x1b[<64;30;5m".to_vec()
+        );
+    }
+
+    #[test]
+    fn shift_sgr_mouse_drops_sidebar_events() {
+        assert!(shift_sgr_mouse(b"\x1b[<0;24;5M", 24).is_none());
+        assert!(shift_sgr_mouse(b"\x1b[<0;1;1M", 24).is_none());
+    }
+
+    #[test]
+    fn fit_width_truncates_and_pads() {
+        assert_eq!(fit_width("abc", 5), "abc  ");
+        assert_eq!(fit_width("abcdef", 4), "abcd");
+        // Wide characters never split across the boundary.
+        assert_eq!(fit_width("a\u{1f916}b", 2), "a ");
+    }
+
+    // ── Scroll search tests ──────────────────────────────────────────
+
+    #[test]
+    fn match_at_or_above_picks_newest_not_below() {
+        let matches = [2, 10, 40];
+        assert_eq!(match_at_or_above(&matches, 39), Some(1));
+        assert_eq!(match_at_or_above(&matches, 40), Some(2));
+        assert_eq!(match_at_or_above(&matches, 100), Some(2));
+    }
+
+    #[test]
+    fn match_at_or_above_wraps_to_newest() {
+        assert_eq!(match_at_or_above(&[5, 8], 1), Some(1));
+        assert_eq!(match_at_or_above(&[], 1), None);
+    }
+
+    #[test]
+    fn offset_for_line_clamps_to_live_screen() {
+        assert_eq!(offset_for_line(0, 50), 50);
+        assert_eq!(offset_for_line(30, 50), 20);
+        assert_eq!(offset_for_line(55, 50), 0);
+    }
+
+    // ── Bracketed paste tests ────────────────────────────────────────
+
+    #[test]
+    fn paste_forwards_prefix_key_and_pgup_verbatim() {
+        let mut paste = PasteState {
+            active: true,
+            ..Default::default()
+        };
+        let data = b"a\x02d\x1b[5~b\x1b[201~\x02x";
+        let len = paste.consume(data);
+        assert_eq!(&data[..len], b"a\x02d\x1b[5~b\x1b[201~");
+        assert!(!paste.active);
+    }
+
+    #[test]
+    fn paste_end_marker_split_across_reads() {
+        let mut paste = PasteState {
+            active: true,
+            ..Default::default()
+        };
+        assert_eq!(paste.consume(b"text\x02\x1b[20"), 9);
+        assert!(paste.active);
+        assert_eq!(paste.consume(b"1~\x1b[5~"), 2);
+        assert!(!paste.active);
+    }
+
+    #[test]
+    fn paste_end_marker_restarts_after_mismatch() {
+        let mut paste = PasteState {
+            active: true,
+            ..Default::default()
+        };
+        let data = b"\x1b[20\x1b[201~";
+        assert_eq!(paste.consume(data), data.len());
+        assert!(!paste.active);
+    }
+
+    #[test]
+    fn partial_paste_start_detects_cut_markers() {
+        assert!(partial_paste_start(b"ab\x1b[20", 2, 6));
+        assert!(partial_paste_start(b"\x1b", 0, 1));
+        assert!(!partial_paste_start(b"\x1b[200~", 0, 6));
+        assert!(!partial_paste_start(b"\x1b[5~", 0, 4));
+    }
+
     // ── CSI u (kitty keyboard protocol) tests ────────────────────────
 
     #[test]
     fn csiu_prefix_len_matches_ctrl_b() {
         let buf = b"\x1b[98;5u";
-        assert_eq!(csiu_prefix_len(buf, 0, buf.len()), 7);
+        assert_eq!(csiu_prefix_len(buf, 0, buf.len(), 0x02), 7);
     }
 
     #[test]
     fn csiu_prefix_len_at_offset() {
         let buf = b"xx\x1b[98;5urest";
-        assert_eq!(csiu_prefix_len(buf, 2, buf.len()), 7);
+        assert_eq!(csiu_prefix_len(buf, 2, buf.len(), 0x02), 7);
     }
 
     #[test]
     fn csiu_prefix_len_no_match() {
-        assert_eq!(csiu_prefix_len(b"\x1b[65;5u", 0, 7), 0); // wrong codepoint
-        assert_eq!(csiu_prefix_len(b"\x1b[98;3u", 0, 7), 0); // wrong modifier
-        assert_eq!(csiu_prefix_len(b"\x1b[98;5", 0, 6), 0); // truncated
-        assert_eq!(csiu_prefix_len(b"hello", 0, 5), 0);
+        assert_eq!(csiu_prefix_len(b"\x1b[65;5u", 0, 7, 0x02), 0); // wrong codepoint
+        assert_eq!(csiu_prefix_len(b"\x1b[98;3u", 0, 7, 0x02), 0); // wrong modifier
+        assert_eq!(csiu_prefix_len(b"\x1b[98;5", 0, 6, 0x02), 0); // truncated
+        assert_eq!(csiu_prefix_len(b"hello", 0, 5, 0x02), 0);
+    }
+
+    #[test]
+    fn csiu_prefix_len_matches_configured_prefix() {
+        let buf = b"\x1b[97;5u";
+        assert_eq!(csiu_prefix_len(buf, 0, buf.len(), 0x01), 7); // Ctrl-A
+        assert_eq!(csiu_prefix_len(buf, 0, buf.len(), 0x02), 0);
     }
 
     #[test]
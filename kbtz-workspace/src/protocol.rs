@@ -14,13 +14,22 @@ pub enum Message {
     InitialState(Vec<u8>),
     /// Request graceful shutdown (workspace -> shepherd). Type 0x05.
     Shutdown,
+    /// Protocol version, sent just before `InitialState` (shepherd -> workspace). Type 0x06.
+    Hello { version: u32 },
 }
 
+/// Bumped whenever the wire format or handshake changes incompatibly.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Leads the `Hello` payload so a stray frame is not mistaken for a version.
+const HELLO_MAGIC: &[u8; 4] = b"KBTZ";
+
 const TYPE_PTY_OUTPUT: u8 = 0x01;
 const TYPE_PTY_INPUT: u8 = 0x02;
 const TYPE_RESIZE: u8 = 0x03;
 const TYPE_INITIAL_STATE: u8 = 0x04;
 const TYPE_SHUTDOWN: u8 = 0x05;
+const TYPE_HELLO: u8 = 0x06;
 
 /// Serialize a message to bytes using the wire format:
 /// `[4 bytes big-endian length] [1 byte type] [payload]`
@@ -42,6 +51,15 @@ pub fn encode(msg: &Message) -> Vec<u8> {
         }
         Message::InitialState(data) => (TYPE_INITIAL_STATE, data.as_slice()),
         Message::Shutdown => (TYPE_SHUTDOWN, [].as_slice()),
+        Message::Hello { version } => {
+            let mut buf = Vec::with_capacity(4 + 1 + 8);
+            let length: u32 = 1 + 8; // type byte + magic + version
+            buf.extend_from_slice(&length.to_be_bytes());
+            buf.push(TYPE_HELLO);
+            buf.extend_from_slice(HELLO_MAGIC);
+            buf.extend_from_slice(&version.to_be_bytes());
+            return buf;
+        }
     };
 
     let length: u32 = 1 + payload.len() as u32; // type byte + payload
@@ -77,10 +95,31 @@ pub fn decode(buf: &[u8]) -> Result<Message> {
         }
         TYPE_INITIAL_STATE => Ok(Message::InitialState(payload.to_vec())),
         TYPE_SHUTDOWN => Ok(Message::Shutdown),
+        TYPE_HELLO => {
+            if payload.len() < 8 || &payload[..4] != HELLO_MAGIC {
+                bail!("malformed hello payload");
+            }
+            let version = u32::from_be_bytes([payload[4], payload[5], payload[6], payload[7]]);
+            Ok(Message::Hello { version })
+        }
         _ => bail!("unknown message type: 0x{:02x}", type_byte),
     }
 }
 
+/// Protocol version announced by the shepherd's first handshake reply.
+/// Shepherds that predate versioning answer `Resize` with `InitialState`
+/// directly; they are reported as version 0.
+pub fn handshake_version(first: &Message) -> Result<u32> {
+    match first {
+        Message::Hello { version } => Ok(*version),
+        Message::InitialState(_) => Ok(0),
+        other => bail!(
+            "expected Hello from shepherd, got {:?}",
+            std::mem::discriminant(other)
+        ),
+    }
+}
+
 /// Read one framed message from a reader. Returns `None` on clean EOF
 /// (zero bytes read when expecting the length prefix).
 pub fn read_message(reader: &mut impl Read) -> Result<Option<Message>> {
@@ -161,6 +200,43 @@ mod tests {
         assert_eq!(msg, decoded);
     }
 
+    #[test]
+    fn roundtrip_hello() {
+        let msg = Message::Hello {
+            version: PROTOCOL_VERSION,
+        };
+        let encoded = encode(&msg);
+        let decoded = decode(&encoded[4..]).unwrap();
+        assert_eq!(msg, decoded);
+    }
+
+    #[test]
+    fn decode_hello_without_magic_fails() {
+        let buf = [TYPE_HELLO, b'N', b'O', b'P', b'E', 0, 0, 0, 1];
+        assert!(decode(&buf).is_err());
+    }
+
+    #[test]
+    fn handshake_version_reports_mismatch() {
+        assert_eq!(
+            handshake_version(&Message::Hello {
+                version: PROTOCOL_VERSION
+            })
+            .unwrap(),
+            PROTOCOL_VERSION
+        );
+        // A pre-versioning shepherd skips Hello entirely.
+        assert_eq!(
+            handshake_version(&Message::InitialState(b"screen".to_vec())).unwrap(),
+            0
+        );
+        assert_ne!(
+            handshake_version(&Message::Hello { version: 99 }).unwrap(),
+            PROTOCOL_VERSION
+        );
+        assert!(handshake_version(&Message::Shutdown).is_err());
+    }
+
     #[test]
     fn decode_empty_fails() {
         let result = decode(&[]);
@@ -2,10 +2,13 @@ use std::io::{Read, Write};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result};
+use kbtz::config::ClipboardPolicy;
 use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use unicode_width::UnicodeWidthStr;
 
 use crate::shepherd_session::ShepherdSession;
 
@@ -15,6 +18,8 @@ pub trait SessionHandle: Send {
     fn status(&self) -> &SessionStatus;
     fn set_status(&mut self, status: SessionStatus);
     fn stopping_since(&self) -> Option<Instant>;
+    /// When the session was spawned (or adopted, for reconnected sessions).
+    fn started_at(&self) -> Instant;
     fn is_alive(&mut self) -> bool;
     fn mark_stopping(&mut self);
     fn force_kill(&mut self);
@@ -29,6 +34,14 @@ pub trait SessionHandle: Send {
     fn exit_scroll_mode(&self) -> Result<()>;
     fn render_scrollback(&self, offset: usize, cols: u16) -> Result<usize>;
     fn scrollback_available(&self) -> Result<usize>;
+    /// Search the scroll-mode snapshot for `query` and highlight matches
+    /// in later renders.  See [`Passthrough::search_scrollback`].
+    fn search_scrollback(&self, query: &str) -> Result<Vec<usize>>;
+    /// Shift rendering right by `cols` columns, leaving room for the
+    /// zoomed-mode sidebar.  Forces a full re-render on the next diff.
+    fn set_col_offset(&self, cols: u16) -> Result<()>;
+    /// Plain-text copy of the scroll-mode snapshot, oldest line first.
+    fn dump_scrollback(&self) -> Result<String>;
     fn has_mouse_tracking(&self) -> bool;
     fn write_input(&mut self, buf: &[u8]) -> Result<()>;
     fn resize(&self, rows: u16, cols: u16) -> Result<()>;
@@ -54,6 +67,10 @@ pub trait SessionHandle: Send {
     /// Reap the underlying process and return its exit code.
     /// Returns `None` if the process is still running or was already reaped.
     fn exit_code(&mut self) -> Option<i32>;
+    /// The exit code recorded when the process was reaped, by either
+    /// `is_alive` or `exit_code`.  `None` while running, or when the exit
+    /// was never observed (e.g. a shepherd adopted after a restart).
+    fn exit_status(&self) -> Option<i32>;
 }
 
 pub trait SessionSpawner: Send {
@@ -71,7 +88,9 @@ pub trait SessionSpawner: Send {
     ) -> Result<Box<dyn SessionHandle>>;
 }
 
-pub struct PtySpawner;
+pub struct PtySpawner {
+    pub terminal: TerminalOptions,
+}
 
 impl SessionSpawner for PtySpawner {
     fn spawn(
@@ -86,7 +105,15 @@ impl SessionSpawner for PtySpawner {
         cwd: &std::path::Path,
     ) -> Result<Box<dyn SessionHandle>> {
         Session::spawn(
-            command, args, task_name, session_id, rows, cols, env_vars, cwd,
+            command,
+            args,
+            task_name,
+            session_id,
+            rows,
+            cols,
+            env_vars,
+            cwd,
+            self.terminal,
         )
         .map(|s| Box::new(s) as Box<dyn SessionHandle>)
     }
@@ -94,6 +121,7 @@ impl SessionSpawner for PtySpawner {
 
 pub struct ShepherdSpawner {
     pub status_dir: PathBuf,
+    pub terminal: TerminalOptions,
 }
 
 impl SessionSpawner for ShepherdSpawner {
@@ -108,84 +136,29 @@ impl SessionSpawner for ShepherdSpawner {
         env_vars: &[(&str, &str)],
         cwd: &std::path::Path,
     ) -> Result<Box<dyn SessionHandle>> {
-        let filename = session_id.replace('/', "-");
-        let socket_path = self.status_dir.join(format!("{filename}.sock"));
-        let pid_path = self.status_dir.join(format!("{filename}.pid"));
-
-        // Find kbtz-shepherd binary next to the current executable
-        let self_exe = std::env::current_exe().context("failed to get current executable path")?;
-        let shepherd_bin = self_exe.with_file_name("kbtz-shepherd");
-        if !shepherd_bin.exists() {
-            bail!(
-                "kbtz-shepherd binary not found at {}",
-                shepherd_bin.display()
-            );
-        }
-
-        // Build shepherd command: kbtz-shepherd <socket> <pid> <rows> <cols> <command> [args...]
-        let mut cmd = std::process::Command::new(&shepherd_bin);
-        cmd.arg(&socket_path)
-            .arg(&pid_path)
-            .arg(rows.to_string())
-            .arg(cols.to_string())
-            .arg(command)
-            .args(args);
-        cmd.current_dir(cwd);
-        for (k, v) in env_vars {
-            cmd.env(k, v);
-        }
-        // Detach stdio.  All other FDs (SQLite, sockets, inotify) are
-        // already opened with O_CLOEXEC / SOCK_CLOEXEC / IN_CLOEXEC by
-        // their respective libraries, so no extra cleanup is needed.
-        cmd.stdin(std::process::Stdio::null())
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null());
-
-        let child = cmd.spawn().with_context(|| {
-            format!(
-                "failed to spawn kbtz-shepherd at {}",
-                shepherd_bin.display()
-            )
-        })?;
+        let child = kbtz_workspace::shepherd::launch(
+            &self.status_dir,
+            session_id,
+            command,
+            args,
+            rows,
+            cols,
+            env_vars,
+            cwd,
+            self.terminal.scrollback_rows,
+        )?;
         let shepherd_os_pid = child.id();
-        kbtz::debug_log::log(&format!(
-            "spawn({session_id}): shepherd process started, os_pid={shepherd_os_pid}"
-        ));
-
-        // Wait for socket to appear (shepherd needs a moment to start)
-        let socket_preexisted = socket_path.exists();
-        if socket_preexisted {
-            kbtz::debug_log::log(&format!(
-                "spawn({session_id}): WARNING socket already exists at {} before shepherd started",
-                socket_path.display()
-            ));
-        }
-        let wait_start = Instant::now();
-        let deadline = wait_start + Duration::from_secs(5);
-        while !socket_path.exists() {
-            if Instant::now() >= deadline {
-                bail!(
-                    "shepherd did not create socket at {} within 5 seconds",
-                    socket_path.display()
-                );
-            }
-            std::thread::sleep(Duration::from_millis(50));
-        }
-        let wait_ms = wait_start.elapsed().as_millis();
-        kbtz::debug_log::log(&format!(
-            "spawn({session_id}): socket appeared after {wait_ms}ms (preexisted={socket_preexisted})"
-        ));
 
         // Connect to the shepherd, passing the Child handle so the
         // workspace can reap it and read its exit code later.
         ShepherdSession::connect(
-            &socket_path,
-            &pid_path,
+            &self.status_dir,
             task_name,
             session_id,
             rows,
             cols,
             Some(child),
+            self.terminal,
         )
         .map_err(|e| {
             kbtz::debug_log::log(&format!(
@@ -207,14 +180,51 @@ pub struct Session {
     pub session_id: String,
     /// Set when exit has been requested and we are waiting for the process to stop.
     pub stopping_since: Option<Instant>,
+    pub started_at: Instant,
     /// Set to false by the reader thread when it exits.  Allows the main
     /// thread to detect a dead reader (e.g. due to a premature EOF on the
     /// PTY) while the child process is still running.
     pub reader_alive: Arc<AtomicBool>,
+    /// Recorded the first time `try_wait` reports the child has exited.
+    exit_status: Option<i32>,
 }
 
 use kbtz_workspace::SCROLLBACK_ROWS;
 
+/// Terminal emulation settings from `[workspace]`, applied to every
+/// session's `Passthrough`.
+#[derive(Debug, Clone, Copy)]
+pub struct TerminalOptions {
+    /// What to do with the child's OSC 52 clipboard writes.
+    pub clipboard: ClipboardPolicy,
+    /// Rows of scrollback the VTE retains for scroll mode.
+    pub scrollback_rows: usize,
+}
+
+impl Default for TerminalOptions {
+    fn default() -> Self {
+        Self {
+            clipboard: ClipboardPolicy::default(),
+            scrollback_rows: SCROLLBACK_ROWS,
+        }
+    }
+}
+
+/// OSC 52 clipboard writes (`\x1b]52;<ty>;<base64>\x07`) seen by the VTE
+/// since the reader thread last drained them.  The vt100 parser keeps
+/// partial sequences across `process` calls, so a request split over
+/// several reads is reported once, complete.
+#[derive(Default)]
+struct ClipboardRequests {
+    pending: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl vt100::Callbacks for ClipboardRequests {
+    fn copy_to_clipboard(&mut self, _: &mut vt100::Screen, ty: &[u8], data: &[u8]) {
+        self.pending.push((ty.to_vec(), data.to_vec()));
+    }
+}
+
 /// Shared state between the reader thread and the main thread.
 ///
 /// Holds a virtual terminal emulator (`vt100::Parser`) that receives
@@ -241,9 +251,17 @@ use kbtz_workspace::SCROLLBACK_ROWS;
 /// inverts the order and creates a deadlock with the reader thread.
 pub struct Passthrough {
     pub(crate) active: bool,
-    vte: vt100::Parser,
+    vte: vt100::Parser<ClipboardRequests>,
+    options: TerminalOptions,
     /// Cloned snapshot of the main screen, captured on scroll mode entry.
     scroll_screen: Option<vt100::Screen>,
+    /// Active scroll-mode search query; matches are highlighted when
+    /// rendering scrollback.
+    scroll_query: Option<String>,
+    /// Number of terminal columns to the left of the rendered screen
+    /// (occupied by the sidebar).  The VTE itself is sized to the
+    /// remaining width, so only cursor positioning is affected.
+    col_offset: u16,
     /// Previously rendered row content for diff-based VTE rendering.
     /// Each entry is the `rows_formatted` output for that row index.
     prev_rows: Vec<Vec<u8>>,
@@ -256,13 +274,37 @@ impl Passthrough {
     pub(crate) fn new(rows: u16, cols: u16) -> Self {
         Self {
             active: false,
-            vte: vt100::Parser::new(rows, cols, SCROLLBACK_ROWS),
+            vte: vt100::Parser::new_with_callbacks(
+                rows,
+                cols,
+                SCROLLBACK_ROWS,
+                ClipboardRequests::default(),
+            ),
+            options: TerminalOptions::default(),
             scroll_screen: None,
+            scroll_query: None,
+            col_offset: 0,
             prev_rows: Vec::new(),
             prev_sync: Vec::new(),
         }
     }
 
+    /// Apply `options`.  Call before processing any output: a different
+    /// scrollback size replaces the (still empty) VTE.
+    pub(crate) fn with_options(mut self, options: TerminalOptions) -> Self {
+        if options.scrollback_rows != self.options.scrollback_rows {
+            let (rows, cols) = self.vte.screen().size();
+            self.vte = vt100::Parser::new_with_callbacks(
+                rows,
+                cols,
+                options.scrollback_rows,
+                ClipboardRequests::default(),
+            );
+        }
+        self.options = options;
+        self
+    }
+
     /// Switch to passthrough mode.  Render the VTE's current screen
     /// state using explicit cursor positioning, restore input modes,
     /// and set `active` for VTE-based rendering in the reader thread.
@@ -275,9 +317,7 @@ impl Passthrough {
         let screen = self.vte.screen();
         let (_, cols) = screen.size();
         self.prev_rows = screen.rows_formatted(0, cols).collect();
-        self.prev_sync = screen.attributes_formatted();
-        self.prev_sync
-            .extend_from_slice(&screen.cursor_state_formatted());
+        self.prev_sync = self.terminal_sync_bytes();
 
         self.active = true;
     }
@@ -317,16 +357,16 @@ impl Passthrough {
     fn render_screen_positioned(&self, out: &mut impl Write) {
         let screen = self.vte.screen();
         let (_rows, cols) = screen.size();
+        let col = self.col_offset + 1;
         for (i, row_bytes) in screen.rows_formatted(0, cols).enumerate() {
             // Reset SGR before erasing so \x1b[K doesn't inherit stale
             // attributes (e.g. reverse video) from the previous row.
-            let _ = write!(out, "\x1b[0m\x1b[{};1H\x1b[K", i + 1);
+            let _ = write!(out, "\x1b[0m\x1b[{};{col}H\x1b[K", i + 1);
             let _ = out.write_all(&row_bytes);
         }
         // Sync terminal state (SGR attributes, cursor position, cursor
         // visibility) to match the VTE — the VTE is the source of truth.
-        let _ = out.write_all(&screen.attributes_formatted());
-        let _ = out.write_all(&screen.cursor_state_formatted());
+        let _ = out.write_all(&self.terminal_sync_bytes());
         let _ = out.write_all(&screen.input_mode_formatted());
         let _ = out.write_all(b"\x1b[?1000h\x1b[?1006h");
     }
@@ -343,10 +383,11 @@ impl Passthrough {
         let (_, cols) = screen.size();
         let current_rows: Vec<Vec<u8>> = screen.rows_formatted(0, cols).collect();
         let mut out = Vec::new();
+        let col = self.col_offset + 1;
 
         for (i, row_bytes) in current_rows.iter().enumerate() {
             if i >= self.prev_rows.len() || self.prev_rows[i] != *row_bytes {
-                let _ = write!(out, "\x1b[0m\x1b[{};1H\x1b[K", i + 1);
+                let _ = write!(out, "\x1b[0m\x1b[{};{col}H\x1b[K", i + 1);
                 out.extend_from_slice(row_bytes);
             }
         }
@@ -356,8 +397,7 @@ impl Passthrough {
         // Sync cursor and attributes, but only if something changed
         // (rows or cursor/attributes) to avoid redundant writes that
         // can cause cursor flicker.
-        let mut sync = screen.attributes_formatted();
-        sync.extend_from_slice(&screen.cursor_state_formatted());
+        let sync = self.terminal_sync_bytes();
         if !out.is_empty() || sync != self.prev_sync {
             out.extend_from_slice(&sync);
             self.prev_sync = sync;
@@ -377,6 +417,33 @@ impl Passthrough {
         }
     }
 
+    /// Drain pending OSC 52 clipboard writes and apply the clipboard
+    /// policy.  Returns the bytes to write to the real terminal: the
+    /// re-encoded sequences under `Forward` while zoomed, otherwise
+    /// nothing.  Payloads that are not valid base64 are discarded.
+    pub(crate) fn take_clipboard_output(&mut self) -> Vec<u8> {
+        use base64::Engine;
+
+        let mut out = Vec::new();
+        for (ty, data) in std::mem::take(&mut self.vte.callbacks_mut().pending) {
+            let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(&data) else {
+                continue;
+            };
+            match self.options.clipboard {
+                ClipboardPolicy::Forward if self.active => {
+                    out.extend_from_slice(b"\x1b]52;");
+                    out.extend_from_slice(&ty);
+                    out.push(b';');
+                    out.extend_from_slice(&data);
+                    out.push(0x07);
+                }
+                ClipboardPolicy::Local => copy_to_local_clipboard(decoded),
+                ClipboardPolicy::Forward | ClipboardPolicy::Drop => {}
+            }
+        }
+        out
+    }
+
     /// Check if a byte slice contains the CSI 3 J sequence (\x1b[3J).
     fn contains_csi_3j(data: &[u8]) -> bool {
         data.windows(4).any(|w| w == b"\x1b[3J")
@@ -402,7 +469,9 @@ impl Passthrough {
         }
 
         // Create a fresh VTE with the same dimensions and scrollback capacity.
-        let mut fresh = vt100::Parser::new(rows, cols, SCROLLBACK_ROWS);
+        let callbacks = std::mem::take(self.vte.callbacks_mut());
+        let mut fresh =
+            vt100::Parser::new_with_callbacks(rows, cols, self.options.scrollback_rows, callbacks);
         fresh.process(&main_state);
         if let Some(alt) = alt_state {
             fresh.process(b"\x1b[?47h");
@@ -412,6 +481,14 @@ impl Passthrough {
         self.vte = fresh;
     }
 
+    pub(crate) fn set_col_offset(&mut self, cols: u16) {
+        if cols != self.col_offset {
+            self.col_offset = cols;
+            self.prev_rows.clear();
+            self.prev_sync.clear();
+        }
+    }
+
     pub(crate) fn set_size(&mut self, rows: u16, cols: u16) {
         kbtz_workspace::resize_both_screens(&mut self.vte, rows, cols);
         // Dimensions changed — force a full re-render on next diff.
@@ -452,6 +529,7 @@ impl Passthrough {
     /// screen, and resume VTE-based rendering.
     pub(crate) fn exit_scroll_mode(&mut self) {
         self.scroll_screen = None;
+        self.scroll_query = None;
 
         kbtz_workspace::with_sync_stdout(|out| self.render_screen_positioned(out));
 
@@ -459,9 +537,7 @@ impl Passthrough {
         let screen = self.vte.screen();
         let (_, cols) = screen.size();
         self.prev_rows = screen.rows_formatted(0, cols).collect();
-        self.prev_sync = screen.attributes_formatted();
-        self.prev_sync
-            .extend_from_slice(&screen.cursor_state_formatted());
+        self.prev_sync = self.terminal_sync_bytes();
 
         self.active = true;
     }
@@ -481,13 +557,25 @@ impl Passthrough {
     /// 1. `attributes_formatted()` — SGR reset (`\x1b[0m`) followed by the
     ///    VTE's current text attributes (colors, bold, reverse, etc.)
     /// 2. `cursor_state_formatted()` — cursor position (`\x1b[row;colH`)
-    ///    and visibility (`\x1b[?25h` or `\x1b[?25l`)
+    ///    and visibility (`\x1b[?25h` or `\x1b[?25l`).  With a column
+    ///    offset the position is shifted right to match the rendering.
     ///
     /// See [`SessionHandle::terminal_sync_bytes`] for the rationale.
     pub(crate) fn terminal_sync_bytes(&self) -> Vec<u8> {
         let screen = self.vte.screen();
         let mut bytes = screen.attributes_formatted();
-        bytes.extend_from_slice(&screen.cursor_state_formatted());
+        if self.col_offset == 0 {
+            bytes.extend_from_slice(&screen.cursor_state_formatted());
+        } else {
+            let (row, col) = screen.cursor_position();
+            let _ = write!(bytes, "\x1b[{};{}H", row + 1, col + self.col_offset + 1);
+            let visibility: &[u8] = if screen.hide_cursor() {
+                b"\x1b[?25l"
+            } else {
+                b"\x1b[?25h"
+            };
+            bytes.extend_from_slice(visibility);
+        }
         bytes
     }
 
@@ -507,15 +595,99 @@ impl Passthrough {
         let clamped = offset.min(max);
         screen.set_scrollback(clamped);
 
+        let col_offset = self.col_offset as usize;
         for (i, row_bytes) in screen.rows_formatted(0, cols).enumerate() {
-            let _ = write!(out, "\x1b[0m\x1b[{};1H\x1b[K", i + 1);
+            let _ = write!(out, "\x1b[0m\x1b[{};{}H\x1b[K", i + 1, col_offset + 1);
             let _ = out.write_all(&row_bytes);
         }
+
+        // Overlay search matches in reverse video.
+        if let Some(query) = self.scroll_query.as_deref() {
+            for (i, text) in screen.rows(0, cols).enumerate() {
+                for start in find_matches(&text, query) {
+                    let col = col_offset + text[..start].width() + 1;
+                    let matched = &text[start..start + query.len()];
+                    let _ = write!(out, "\x1b[{};{col}H\x1b[0;7m{matched}", i + 1);
+                }
+            }
+        }
         let _ = write!(out, "\x1b[0m");
 
         clamped
     }
 
+    /// Render the scroll snapshot (scrollback plus visible screen) as
+    /// plain text, oldest line first, with trailing blank lines dropped.
+    /// Returns an empty string outside scroll mode.
+    pub(crate) fn dump_scrollback(&mut self) -> String {
+        let screen = match self.scroll_screen.as_mut() {
+            Some(s) => s,
+            None => return String::new(),
+        };
+        let (_, cols) = screen.size();
+        let saved = screen.scrollback();
+        let total = Self::scrollback_depth(screen);
+
+        let mut lines = Vec::new();
+        for offset in (1..=total).rev() {
+            screen.set_scrollback(offset);
+            lines.extend(screen.rows(0, cols).next());
+        }
+        screen.set_scrollback(0);
+        lines.extend(screen.rows(0, cols));
+        screen.set_scrollback(saved);
+
+        while lines.last().is_some_and(|l| l.trim().is_empty()) {
+            lines.pop();
+        }
+        let mut text = String::new();
+        for line in lines {
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Search the scroll snapshot (scrollback plus visible screen) for
+    /// `query`, case-insensitively.  Returns the absolute line numbers
+    /// containing a match in ascending order, where line 0 is the oldest
+    /// scrollback row and the screen's top row at offset 0 is
+    /// `scrollback_available()`.  The query is remembered so subsequent
+    /// `render_scrollback` calls highlight it; an empty query clears it.
+    pub(crate) fn search_scrollback(&mut self, query: &str) -> Vec<usize> {
+        self.scroll_query = (!query.is_empty()).then(|| query.to_string());
+        let screen = match self.scroll_screen.as_mut() {
+            Some(s) => s,
+            None => return Vec::new(),
+        };
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let (_, cols) = screen.size();
+        let saved = screen.scrollback();
+        let total = Self::scrollback_depth(screen);
+
+        let mut matches = Vec::new();
+        // Scrollback rows, oldest first: at offset N the top viewport row
+        // is absolute line `total - N`.
+        for offset in (1..=total).rev() {
+            screen.set_scrollback(offset);
+            if let Some(text) = screen.rows(0, cols).next() {
+                if find_matches(&text, query).next().is_some() {
+                    matches.push(total - offset);
+                }
+            }
+        }
+        screen.set_scrollback(0);
+        for (i, text) in screen.rows(0, cols).enumerate() {
+            if find_matches(&text, query).next().is_some() {
+                matches.push(total + i);
+            }
+        }
+        screen.set_scrollback(saved);
+        matches
+    }
+
     /// Total scrollback rows available (not counting the visible screen).
     pub(crate) fn scrollback_available(&mut self) -> usize {
         match self.scroll_screen.as_mut() {
@@ -534,7 +706,33 @@ impl Passthrough {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Byte offsets of non-overlapping ASCII-case-insensitive matches of
+/// `query` in `text`.
+fn find_matches<'a>(text: &'a str, query: &'a str) -> impl Iterator<Item = usize> + 'a {
+    let needle = query.as_bytes();
+    let hay = text.as_bytes();
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        if needle.is_empty() {
+            return None;
+        }
+        while pos + needle.len() <= hay.len() {
+            let start = pos;
+            if hay[start..start + needle.len()].eq_ignore_ascii_case(needle)
+                && text.is_char_boundary(start)
+                && text.is_char_boundary(start + needle.len())
+            {
+                pos = start + needle.len();
+                return Some(start);
+            }
+            pos += 1;
+        }
+        None
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SessionStatus {
     Starting,
     Active,
@@ -596,8 +794,19 @@ impl SessionHandle for Session {
         self.stopping_since
     }
 
+    fn started_at(&self) -> Instant {
+        self.started_at
+    }
+
     fn is_alive(&mut self) -> bool {
-        matches!(self.child.try_wait(), Ok(None))
+        match self.child.try_wait() {
+            Ok(None) => true,
+            Ok(Some(status)) => {
+                self.exit_status = Some(status.exit_code() as i32);
+                false
+            }
+            Err(_) => false,
+        }
     }
 
     fn mark_stopping(&mut self) {
@@ -660,6 +869,30 @@ impl SessionHandle for Session {
             .scrollback_available())
     }
 
+    fn search_scrollback(&self, query: &str) -> Result<Vec<usize>> {
+        Ok(self
+            .passthrough
+            .lock()
+            .map_err(|_| anyhow::anyhow!("passthrough mutex poisoned"))?
+            .search_scrollback(query))
+    }
+
+    fn dump_scrollback(&self) -> Result<String> {
+        Ok(self
+            .passthrough
+            .lock()
+            .map_err(|_| anyhow::anyhow!("passthrough mutex poisoned"))?
+            .dump_scrollback())
+    }
+
+    fn set_col_offset(&self, cols: u16) -> Result<()> {
+        self.passthrough
+            .lock()
+            .map_err(|_| anyhow::anyhow!("passthrough mutex poisoned"))?
+            .set_col_offset(cols);
+        Ok(())
+    }
+
     fn has_mouse_tracking(&self) -> bool {
         self.passthrough
             .lock()
@@ -719,7 +952,12 @@ impl SessionHandle for Session {
 
     fn exit_code(&mut self) -> Option<i32> {
         let status = self.child.try_wait().ok()??;
-        Some(status.exit_code() as i32)
+        self.exit_status = Some(status.exit_code() as i32);
+        self.exit_status
+    }
+
+    fn exit_status(&self) -> Option<i32> {
+        self.exit_status
     }
 }
 
@@ -734,6 +972,7 @@ impl Session {
         cols: u16,
         env_vars: &[(&str, &str)],
         cwd: &std::path::Path,
+        terminal: TerminalOptions,
     ) -> Result<Self> {
         let pty_system = native_pty_system();
         let pty_rows = rows.saturating_sub(1); // leave room for status bar
@@ -760,7 +999,9 @@ impl Session {
             .map_err(|e| anyhow::anyhow!("{e}"))?;
         drop(pair.slave);
 
-        let passthrough = Arc::new(Mutex::new(Passthrough::new(pty_rows, cols)));
+        let passthrough = Arc::new(Mutex::new(
+            Passthrough::new(pty_rows, cols).with_options(terminal),
+        ));
         let reader_alive = Arc::new(AtomicBool::new(true));
         let reader = pair
             .master
@@ -785,11 +1026,43 @@ impl Session {
             task_name: task_name.to_string(),
             session_id: session_id.to_string(),
             stopping_since: None,
+            started_at: Instant::now(),
             reader_alive,
+            exit_status: None,
         })
     }
 }
 
+/// Hand `text` to the platform clipboard command (`pbcopy` on macOS,
+/// `wl-copy` under Wayland, `xclip` otherwise).  Runs on its own thread
+/// so a slow or missing command never stalls the PTY reader.
+fn copy_to_local_clipboard(text: Vec<u8>) {
+    let (program, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+        ("pbcopy", &[])
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        ("wl-copy", &[])
+    } else {
+        ("xclip", &["-selection", "clipboard"])
+    };
+    std::thread::spawn(move || {
+        let result = std::process::Command::new(program)
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .and_then(|mut child| {
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(&text)?;
+                }
+                child.wait()
+            });
+        if let Err(e) = result {
+            kbtz::debug_log::log(&format!("clipboard: {program} failed: {e}"));
+        }
+    });
+}
+
 fn reader_thread(
     mut reader: Box<dyn Read + Send>,
     passthrough: Arc<Mutex<Passthrough>>,
@@ -833,13 +1106,15 @@ fn reader_thread(
                     break;
                 };
                 pt.process(&buf[..n]);
+                let clipboard = pt.take_clipboard_output();
 
                 if pt.active {
                     // Render from VTE state instead of forwarding raw
                     // bytes.  This prevents child escape sequences
                     // (e.g. \x1b[r, \x1b[2J) from corrupting the real
                     // terminal's scroll region or erasing the status bar.
-                    let output = pt.render_diff();
+                    let mut output = pt.render_diff();
+                    output.extend_from_slice(&clipboard);
                     if !output.is_empty() {
                         let mut out = stdout.lock();
                         let _ = out.write_all(&output);
@@ -860,6 +1135,30 @@ fn reader_thread(
 mod tests {
     use super::*;
 
+    #[test]
+    fn exit_status_records_nonzero_exit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut session = Session::spawn(
+            "sh",
+            &["-c", "exit 3"],
+            "task",
+            "ws/1",
+            24,
+            80,
+            &[],
+            dir.path(),
+            TerminalOptions::default(),
+        )
+        .unwrap();
+        let deadline = Instant::now() + std::time::Duration::from_secs(5);
+        while session.is_alive() {
+            assert!(Instant::now() < deadline, "child did not exit");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(session.exit_status(), Some(3));
+        assert_eq!(session.exit_code(), Some(3));
+    }
+
     #[test]
     fn session_status_from_str_known_values() {
         assert_eq!(SessionStatus::from_str("active"), SessionStatus::Active);
@@ -883,6 +1182,46 @@ mod tests {
         assert_eq!(SessionStatus::from_str("unknown"), SessionStatus::Starting);
     }
 
+    const FORWARD: TerminalOptions = TerminalOptions {
+        clipboard: ClipboardPolicy::Forward,
+        scrollback_rows: SCROLLBACK_ROWS,
+    };
+
+    #[test]
+    fn clipboard_forward_reassembles_split_sequence() {
+        let mut pt = Passthrough::new(4, 80).with_options(FORWARD);
+        pt.active = true;
+        pt.process(b"hi\x1b]52;c;aGVs");
+        assert!(pt.take_clipboard_output().is_empty());
+        pt.process(b"bG8=\x1b\\there");
+        assert_eq!(pt.take_clipboard_output(), b"\x1b]52;c;aGVsbG8=\x07");
+        assert!(pt.take_clipboard_output().is_empty());
+        assert!(pt.vte.screen().contents().contains("hithere"));
+    }
+
+    #[test]
+    fn clipboard_forward_only_while_zoomed() {
+        let mut pt = Passthrough::new(4, 80).with_options(FORWARD);
+        pt.process(b"\x1b]52;c;aGVsbG8=\x07");
+        assert!(pt.take_clipboard_output().is_empty());
+        // The request is consumed, not replayed on the next zoom.
+        pt.active = true;
+        assert!(pt.take_clipboard_output().is_empty());
+    }
+
+    #[test]
+    fn clipboard_drop_and_invalid_payloads_emit_nothing() {
+        let mut pt = Passthrough::new(4, 80);
+        pt.active = true;
+        pt.process(b"\x1b]52;c;aGVsbG8=\x07");
+        assert!(pt.take_clipboard_output().is_empty());
+
+        let mut pt = Passthrough::new(4, 80).with_options(FORWARD);
+        pt.active = true;
+        pt.process(b"\x1b]52;c;not base64!\x07");
+        assert!(pt.take_clipboard_output().is_empty());
+    }
+
     #[test]
     fn session_status_label_roundtrips() {
         // from_str(label()) should return the same variant (except Starting)
@@ -904,6 +1243,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn session_status_parses_every_agent_state() {
+        // `kbtz agent-status` validates against this list without access
+        // to SessionStatus; each entry must parse to the matching variant.
+        for state in kbtz::paths::SESSION_STATES {
+            let status = SessionStatus::from_str(state);
+            assert_eq!(serde_json::to_value(&status).unwrap(), *state);
+        }
+    }
+
     #[test]
     fn session_status_indicators_not_empty() {
         let variants = [
@@ -1077,6 +1426,70 @@ mod tests {
         }
     }
 
+    #[test]
+    fn search_scrollback_finds_lines_across_scrollback_and_screen() {
+        let mut pt = Passthrough::new(4, 80);
+        for i in 0..20 {
+            let word = if i % 7 == 0 { "Needle" } else { "hay" };
+            pt.process(format!("line {i} {word}\r\n").as_bytes());
+        }
+        pt.enter_scroll_mode();
+        let total = pt.scrollback_available();
+
+        // Absolute line numbers count from the oldest scrollback row.
+        let matches = pt.search_scrollback("needle");
+        assert_eq!(matches, vec![0, 7, 14]);
+        assert!(total > 7, "line 7 should be in scrollback");
+
+        assert!(pt.search_scrollback("missing").is_empty());
+    }
+
+    #[test]
+    fn search_scrollback_highlights_matches_when_rendering() {
+        let mut pt = Passthrough::new(4, 80);
+        pt.process(b"alpha beta\r\n");
+        pt.enter_scroll_mode();
+
+        let mut plain = Vec::new();
+        pt.render_scrollback(&mut plain, 0, 80);
+        assert!(!String::from_utf8_lossy(&plain).contains("\x1b[0;7m"));
+
+        pt.search_scrollback("BETA");
+        let mut buf = Vec::new();
+        pt.render_scrollback(&mut buf, 0, 80);
+        assert!(String::from_utf8_lossy(&buf).contains("\x1b[1;7H\x1b[0;7mbeta"));
+
+        // An empty query clears the highlight.
+        pt.search_scrollback("");
+        let mut cleared = Vec::new();
+        pt.render_scrollback(&mut cleared, 0, 80);
+        assert!(!String::from_utf8_lossy(&cleared).contains("\x1b[0;7m"));
+    }
+
+    #[test]
+    fn dump_scrollback_includes_all_lines_oldest_first() {
+        let mut pt = Passthrough::new(4, 80);
+        for i in 0..10 {
+            pt.process(format!("line {i}\r\n").as_bytes());
+        }
+        assert_eq!(pt.dump_scrollback(), "", "empty outside scroll mode");
+
+        pt.enter_scroll_mode();
+        let expected: String = (0..10).map(|i| format!("line {i}\n")).collect();
+        assert_eq!(pt.dump_scrollback(), expected);
+    }
+
+    #[test]
+    fn col_offset_shifts_rows_and_cursor() {
+        let mut pt = Passthrough::new(4, 40);
+        pt.process(b"hello");
+        pt.set_col_offset(24);
+        let out = String::from_utf8_lossy(&pt.render_diff()).into_owned();
+        assert!(out.contains("\x1b[1;25H\x1b[Khello"), "{out:?}");
+        // Cursor sits after "hello" (column 6), shifted by the offset.
+        assert!(out.contains("\x1b[1;30H"), "{out:?}");
+    }
+
     #[test]
     fn scrollback_available_zero_without_scroll_mode() {
         let mut pt = Passthrough::new(4, 80);
@@ -1404,6 +1817,26 @@ mod tests {
         assert!(!buf.is_empty());
     }
 
+    /// A smaller `scrollback_rows` caps retained rows, including after
+    /// CSI 3 J replaces the VTE.
+    #[test]
+    fn scrollback_rows_caps_retained_rows() {
+        let mut pt = Passthrough::new(5, 80).with_options(TerminalOptions {
+            scrollback_rows: 20,
+            ..Default::default()
+        });
+        for i in 0..100 {
+            pt.process(format!("line {i}\r\n").as_bytes());
+        }
+        assert_eq!(scrollback_depth(&mut pt), 20);
+
+        pt.process(b"\x1b[3J");
+        for i in 0..100 {
+            pt.process(format!("line {i}\r\n").as_bytes());
+        }
+        assert_eq!(scrollback_depth(&mut pt), 20);
+    }
+
     // === CSI 3 J (Erase Saved Lines) tests ===
 
     /// CSI 3 J should clear the scrollback buffer.
@@ -0,0 +1,310 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+
+use crate::protocol::{self, Message};
+
+/// Socket a session's shepherd listens on, inside `dir`.
+pub fn socket_path(dir: &Path, session_id: &str) -> PathBuf {
+    let filename = kbtz::paths::session_id_to_filename(session_id);
+    dir.join(format!("{filename}.sock"))
+}
+
+/// File the shepherd writes its own PID to, inside `dir`. The agent's PID
+/// goes next to it with a `.child-pid` extension.
+pub fn pid_path(dir: &Path, session_id: &str) -> PathBuf {
+    let filename = kbtz::paths::session_id_to_filename(session_id);
+    dir.join(format!("{filename}.pid"))
+}
+
+/// Read a PID file written by the shepherd.
+pub fn read_pid(path: &Path) -> Option<i32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether `pid` names a running process. EPERM means it exists but
+/// belongs to someone else, which still counts as alive.
+pub fn pid_alive(pid: i32) -> bool {
+    let ret = unsafe { libc::kill(pid, 0) };
+    ret == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Remove a session's socket, PID and child-PID files.
+pub fn remove_files(dir: &Path, session_id: &str) {
+    let pid_path = pid_path(dir, session_id);
+    let _ = std::fs::remove_file(socket_path(dir, session_id));
+    let _ = std::fs::remove_file(pid_path.with_extension("child-pid"));
+    let _ = std::fs::remove_file(pid_path);
+}
+
+/// Ask a session's shepherd to shut down (it forwards SIGTERM to the
+/// agent) and remove its socket and PID files so nothing reconnects.
+pub fn discard(dir: &Path, session_id: &str) {
+    if let Some(pid) = read_pid(&pid_path(dir, session_id)) {
+        unsafe { libc::kill(pid, libc::SIGTERM) };
+    }
+    remove_files(dir, session_id);
+}
+
+/// SIGKILL the agent process group named by the `.child-pid` file next to
+/// a shepherd's `pid_path`, e.g. when the shepherd itself is already gone.
+pub fn kill_child(pid_path: &Path) {
+    if let Some(pid) = read_pid(&pid_path.with_extension("child-pid")) {
+        unsafe { libc::kill(-pid, libc::SIGKILL) };
+    }
+}
+
+/// SIGKILL a session's agent process group and its shepherd, then remove
+/// their socket and PID files.
+pub fn kill(dir: &Path, session_id: &str) {
+    let pid_path = pid_path(dir, session_id);
+    kill_child(&pid_path);
+    if let Some(pid) = read_pid(&pid_path) {
+        unsafe { libc::kill(pid, libc::SIGKILL) };
+    }
+    remove_files(dir, session_id);
+}
+
+/// Start `kbtz-shepherd` (found next to the current executable) running
+/// `command` in `cwd`, and wait for it to create its socket in `dir`.
+///
+/// The shepherd detaches into its own session, so it and the agent keep
+/// running after the caller exits. The returned `Child` lets the caller
+/// reap it; dropping it leaves the shepherd running.
+#[allow(clippy::too_many_arguments)]
+pub fn launch(
+    dir: &Path,
+    session_id: &str,
+    command: &str,
+    args: &[&str],
+    rows: u16,
+    cols: u16,
+    env_vars: &[(&str, &str)],
+    cwd: &Path,
+    scrollback_rows: usize,
+) -> Result<Child> {
+    let socket_path = socket_path(dir, session_id);
+    let pid_path = pid_path(dir, session_id);
+
+    // Find kbtz-shepherd binary next to the current executable
+    let self_exe = std::env::current_exe().context("failed to get current executable path")?;
+    let shepherd_bin = self_exe.with_file_name("kbtz-shepherd");
+    if !shepherd_bin.exists() {
+        bail!(
+            "kbtz-shepherd binary not found at {}",
+            shepherd_bin.display()
+        );
+    }
+
+    // Build shepherd command: kbtz-shepherd <socket> <pid> <rows> <cols> <command> [args...]
+    let mut cmd = Command::new(&shepherd_bin);
+    cmd.arg(&socket_path)
+        .arg(&pid_path)
+        .arg(rows.to_string())
+        .arg(cols.to_string())
+        .arg(command)
+        .args(args);
+    cmd.current_dir(cwd);
+    for (k, v) in env_vars {
+        cmd.env(k, v);
+    }
+    cmd.env(crate::SCROLLBACK_ROWS_ENV, scrollback_rows.to_string());
+    // Detach stdio.  All other FDs (SQLite, sockets, inotify) are
+    // already opened with O_CLOEXEC / SOCK_CLOEXEC / IN_CLOEXEC by
+    // their respective libraries, so no extra cleanup is needed.
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    let child = cmd.spawn().with_context(|| {
+        format!(
+            "failed to spawn kbtz-shepherd at {}",
+            shepherd_bin.display()
+        )
+    })?;
+    let shepherd_os_pid = child.id();
+    kbtz::debug_log::log(&format!(
+        "spawn({session_id}): shepherd process started, os_pid={shepherd_os_pid}"
+    ));
+
+    // Wait for socket to appear (shepherd needs a moment to start)
+    let socket_preexisted = socket_path.exists();
+    if socket_preexisted {
+        kbtz::debug_log::log(&format!(
+            "spawn({session_id}): WARNING socket already exists at {} before shepherd started",
+            socket_path.display()
+        ));
+    }
+    let wait_start = Instant::now();
+    let deadline = wait_start + Duration::from_secs(5);
+    while !socket_path.exists() {
+        if Instant::now() >= deadline {
+            bail!(
+                "shepherd did not create socket at {} within 5 seconds",
+                socket_path.display()
+            );
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let wait_ms = wait_start.elapsed().as_millis();
+    kbtz::debug_log::log(&format!(
+        "spawn({session_id}): socket appeared after {wait_ms}ms (preexisted={socket_preexisted})"
+    ));
+
+    Ok(child)
+}
+
+/// Returned by `handshake` when the shepherd was built with a different
+/// protocol. Such a shepherd can't be driven safely, so callers that own
+/// it should `discard` it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionMismatch {
+    pub version: u32,
+}
+
+impl std::fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "shepherd speaks protocol v{}, expected v{}",
+            self.version,
+            protocol::PROTOCOL_VERSION
+        )
+    }
+}
+
+impl std::error::Error for VersionMismatch {}
+
+/// Client side of the connect handshake: send our size, check the
+/// shepherd's protocol version, and return the `InitialState` restore
+/// sequence to paint before forwarding live output. A version mismatch
+/// fails with a `VersionMismatch` error.
+pub fn handshake(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    rows: u16,
+    cols: u16,
+) -> Result<Vec<u8>> {
+    // Size-first: the shepherd builds the restore sequence at our size.
+    protocol::write_message(writer, &Message::Resize { rows, cols })
+        .context("failed to send initial resize to shepherd")?;
+    writer.flush()?;
+
+    let Some(first) =
+        protocol::read_message(reader).context("failed to read handshake from shepherd")?
+    else {
+        bail!("shepherd closed connection before sending InitialState");
+    };
+    let version = protocol::handshake_version(&first)?;
+    if version != protocol::PROTOCOL_VERSION {
+        return Err(VersionMismatch { version }.into());
+    }
+    match protocol::read_message(reader).context("failed to read handshake from shepherd")? {
+        Some(Message::InitialState(data)) => Ok(data),
+        Some(other) => bail!(
+            "expected InitialState from shepherd, got {:?}",
+            std::mem::discriminant(&other)
+        ),
+        None => bail!("shepherd closed connection before sending InitialState"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+
+    #[test]
+    fn paths_flatten_session_id() {
+        let dir = Path::new("/tmp/ws");
+        assert_eq!(socket_path(dir, "ws/3"), dir.join("ws-3.sock"));
+        assert_eq!(pid_path(dir, "ws/3"), dir.join("ws-3.pid"));
+    }
+
+    #[test]
+    fn kill_child_missing_file_is_noop() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_path = dir.path().join("nonexistent.pid");
+        // Should not panic when the file doesn't exist.
+        kill_child(&pid_path);
+    }
+
+    #[test]
+    fn kill_child_kills_process_group() {
+        use std::os::unix::process::CommandExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let pid_path = dir.path().join("test.pid");
+
+        // Spawn a sleep in its own process group.
+        let mut child = unsafe {
+            std::process::Command::new("sleep")
+                .arg("999")
+                .pre_exec(|| {
+                    libc::setpgid(0, 0);
+                    Ok(())
+                })
+                .spawn()
+                .unwrap()
+        };
+        let child_pid = child.id();
+
+        std::fs::write(pid_path.with_extension("child-pid"), format!("{child_pid}")).unwrap();
+
+        kill_child(&pid_path);
+
+        // wait() reaps the zombie and confirms the process exited.
+        let exited = child.wait().unwrap().code().is_none(); // killed by signal → no code
+        assert!(exited, "child should have been killed by signal");
+    }
+
+    /// Run `handshake` against a fake shepherd that expects our resize
+    /// and answers with `reply`.
+    fn handshake_with(reply: Vec<Message>) -> Result<Vec<u8>> {
+        let (mut client, mut server) = UnixStream::pair().unwrap();
+        let shepherd = std::thread::spawn(move || {
+            let resize = protocol::read_message(&mut server).unwrap();
+            assert_eq!(resize, Some(Message::Resize { rows: 24, cols: 80 }));
+            for msg in reply {
+                protocol::write_message(&mut server, &msg).unwrap();
+            }
+        });
+        let mut reader = client.try_clone().unwrap();
+        let result = handshake(&mut reader, &mut client, 24, 80);
+        shepherd.join().unwrap();
+        result
+    }
+
+    #[test]
+    fn handshake_returns_initial_state() {
+        let state = handshake_with(vec![
+            Message::Hello {
+                version: protocol::PROTOCOL_VERSION,
+            },
+            Message::InitialState(b"screen".to_vec()),
+        ])
+        .unwrap();
+        assert_eq!(state, b"screen");
+    }
+
+    #[test]
+    fn handshake_rejects_other_protocol_versions() {
+        let err = handshake_with(vec![
+            Message::Hello {
+                version: protocol::PROTOCOL_VERSION + 1,
+            },
+            Message::InitialState(Vec::new()),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<VersionMismatch>(),
+            Some(&VersionMismatch {
+                version: protocol::PROTOCOL_VERSION + 1
+            })
+        );
+        assert!(err.to_string().contains("speaks protocol"), "{err}");
+    }
+}
@@ -5,10 +5,11 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result};
 
-use crate::session::{Passthrough, SessionHandle, SessionStatus};
+use crate::session::{Passthrough, SessionHandle, SessionStatus, TerminalOptions};
 use kbtz_workspace::protocol::{self, Message};
+use kbtz_workspace::shepherd;
 
 pub struct ShepherdSession {
     socket_path: PathBuf,
@@ -20,6 +21,8 @@ pub struct ShepherdSession {
     shepherd_pid: u32,
     child_pid: Option<u32>,
     stopping_since: Option<Instant>,
+    /// Spawn time, or connect time for sessions adopted on reconnect.
+    started_at: Instant,
     /// Set to false by the reader thread when it exits.  Mirrors the
     /// `reader_alive` flag on `Session` — a dead reader with a live
     /// shepherd means the socket connection broke and the session is
@@ -29,19 +32,27 @@ pub struct ShepherdSession {
     /// code. `None` for sessions adopted via `reconnect_sessions()` (the
     /// original parent process is gone).
     process: Option<std::process::Child>,
+    /// Recorded when `exit_code` reaps the shepherd, which exits with its
+    /// child's code.
+    exit_status: Option<i32>,
 }
 
 impl ShepherdSession {
+    /// Connect to the shepherd for `session_id` in `dir` and paint its
+    /// restore sequence. A shepherd speaking another protocol version is
+    /// discarded rather than connected.
     pub fn connect(
-        socket_path: &Path,
-        pid_path: &Path,
+        dir: &Path,
         task_name: &str,
         session_id: &str,
         rows: u16,
         cols: u16,
         process: Option<std::process::Child>,
+        terminal: TerminalOptions,
     ) -> Result<Self> {
-        let pid_str = std::fs::read_to_string(pid_path)
+        let socket_path = shepherd::socket_path(dir, session_id);
+        let pid_path = shepherd::pid_path(dir, session_id);
+        let pid_str = std::fs::read_to_string(&pid_path)
             .with_context(|| format!("failed to read shepherd PID from {}", pid_path.display()))?;
         let shepherd_pid: u32 = pid_str
             .trim()
@@ -57,7 +68,7 @@ impl ShepherdSession {
             socket_path.display()
         ));
 
-        let stream = UnixStream::connect(socket_path).with_context(|| {
+        let stream = UnixStream::connect(&socket_path).with_context(|| {
             format!("failed to connect to shepherd at {}", socket_path.display())
         })?;
         kbtz::debug_log::log(&format!("connect({session_id}): socket connected"));
@@ -68,50 +79,39 @@ impl ShepherdSession {
 
         let pty_rows = rows.saturating_sub(1);
 
-        // Size-first handshake: send Resize before reading InitialState
-        // so the shepherd builds the restore sequence at our terminal size.
+        // Size-first handshake: the shepherd builds the restore sequence
+        // at our terminal size.
         let writer = Mutex::new(BufWriter::new(write_stream));
-        {
+        let mut reader = BufReader::new(read_stream);
+        let handshake = {
             let mut w = writer
                 .lock()
                 .expect("writer lock poisoned during construction");
-            protocol::write_message(
-                &mut *w,
-                &Message::Resize {
-                    rows: pty_rows,
-                    cols,
-                },
-            )
-            .context("failed to send initial resize to shepherd")?;
-        }
-        kbtz::debug_log::log(&format!(
-            "connect({session_id}): sent Resize({pty_rows}x{cols})"
-        ));
-
-        let mut reader = BufReader::new(read_stream);
-
-        // Read InitialState — shepherd builds this from structured VTE
-        // data (scrollback rows + state_formatted), not raw byte replay.
-        let first_msg = protocol::read_message(&mut reader)
-            .context("failed to read initial message from shepherd")?;
-        let initial_data = match first_msg {
-            Some(Message::InitialState(data)) => {
-                kbtz::debug_log::log(&format!(
-                    "connect({session_id}): received InitialState ({} bytes)",
-                    data.len()
-                ));
-                data
+            shepherd::handshake(&mut reader, &mut *w, pty_rows, cols)
+        };
+        let initial_data = match handshake {
+            Ok(data) => data,
+            Err(e) => {
+                // A shepherd left over from an incompatible build can't be
+                // driven safely, so stop it rather than connect.
+                if e.downcast_ref::<shepherd::VersionMismatch>().is_some() {
+                    kbtz::debug_log::log(&format!("connect({session_id}): {e}; stopping it"));
+                    shepherd::discard(dir, session_id);
+                }
+                return Err(e.context(format!(
+                    "handshake with shepherd at {} failed",
+                    socket_path.display()
+                )));
             }
-            Some(other) => bail!(
-                "expected InitialState from shepherd, got {:?}",
-                std::mem::discriminant(&other)
-            ),
-            None => bail!("shepherd closed connection before sending InitialState"),
         };
+        kbtz::debug_log::log(&format!(
+            "connect({session_id}): received InitialState ({} bytes) at {pty_rows}x{cols}",
+            initial_data.len()
+        ));
 
         // Process directly — the restore sequence is structured data at
         // our terminal size, so no temp VTE or filtering needed.
-        let mut pt = Passthrough::new(pty_rows, cols);
+        let mut pt = Passthrough::new(pty_rows, cols).with_options(terminal);
         pt.process(&initial_data);
         let passthrough = Arc::new(Mutex::new(pt));
 
@@ -123,7 +123,7 @@ impl ShepherdSession {
         std::thread::spawn(move || shepherd_reader_thread(reader, pt_clone, ra, reader_sid));
 
         Ok(ShepherdSession {
-            socket_path: socket_path.to_path_buf(),
+            socket_path,
             writer,
             passthrough,
             status: SessionStatus::Starting,
@@ -132,8 +132,10 @@ impl ShepherdSession {
             shepherd_pid,
             child_pid,
             stopping_since: None,
+            started_at: Instant::now(),
             reader_alive,
             process,
+            exit_status: None,
         })
     }
 }
@@ -157,13 +159,15 @@ fn shepherd_reader_thread(
                     break;
                 };
                 pt.process(&data);
+                let clipboard = pt.take_clipboard_output();
 
                 if pt.active {
                     // Render from VTE state instead of forwarding raw
                     // bytes.  This prevents child escape sequences
                     // (e.g. \x1b[r, \x1b[2J) from corrupting the real
                     // terminal's scroll region or erasing the status bar.
-                    let output = pt.render_diff();
+                    let mut output = pt.render_diff();
+                    output.extend_from_slice(&clipboard);
                     if !output.is_empty() {
                         let mut out = stdout.lock();
                         let _ = out.write_all(&output);
@@ -215,6 +219,10 @@ impl SessionHandle for ShepherdSession {
         self.stopping_since
     }
 
+    fn started_at(&self) -> Instant {
+        self.started_at
+    }
+
     fn is_alive(&mut self) -> bool {
         // Check process liveness first: if the shepherd was SIGKILLed its
         // cleanup code never ran and the socket file is left behind.
@@ -294,6 +302,30 @@ impl SessionHandle for ShepherdSession {
             .scrollback_available())
     }
 
+    fn search_scrollback(&self, query: &str) -> Result<Vec<usize>> {
+        Ok(self
+            .passthrough
+            .lock()
+            .map_err(|_| anyhow::anyhow!("passthrough mutex poisoned"))?
+            .search_scrollback(query))
+    }
+
+    fn dump_scrollback(&self) -> Result<String> {
+        Ok(self
+            .passthrough
+            .lock()
+            .map_err(|_| anyhow::anyhow!("passthrough mutex poisoned"))?
+            .dump_scrollback())
+    }
+
+    fn set_col_offset(&self, cols: u16) -> Result<()> {
+        self.passthrough
+            .lock()
+            .map_err(|_| anyhow::anyhow!("passthrough mutex poisoned"))?
+            .set_col_offset(cols);
+        Ok(())
+    }
+
     fn has_mouse_tracking(&self) -> bool {
         self.passthrough
             .lock()
@@ -360,7 +392,12 @@ impl SessionHandle for ShepherdSession {
     fn exit_code(&mut self) -> Option<i32> {
         let child = self.process.as_mut()?;
         let status = child.try_wait().ok()??;
-        status.code()
+        self.exit_status = status.code();
+        self.exit_status
+    }
+
+    fn exit_status(&self) -> Option<i32> {
+        self.exit_status
     }
 }
 
@@ -419,8 +456,10 @@ mod tests {
             shepherd_pid: std::process::id(),
             child_pid: None,
             stopping_since: None,
+            started_at: Instant::now(),
             reader_alive,
             process: None,
+            exit_status: None,
         };
 
         let server_reader = BufReader::new(server_stream);
@@ -445,8 +484,10 @@ mod tests {
             shepherd_pid: std::process::id(),
             child_pid: None,
             stopping_since: None,
+            started_at: Instant::now(),
             reader_alive: Arc::new(AtomicBool::new(true)),
             process: None,
+            exit_status: None,
         };
 
         // is_alive takes &mut self
@@ -578,8 +619,10 @@ mod tests {
             shepherd_pid,
             child_pid: Some(child_pid),
             stopping_since: None,
+            started_at: Instant::now(),
             reader_alive: Arc::new(AtomicBool::new(true)),
             process: None,
+            exit_status: None,
         };
 
         session.force_kill();
@@ -613,8 +656,10 @@ mod tests {
             shepherd_pid,
             child_pid: None,
             stopping_since: None,
+            started_at: Instant::now(),
             reader_alive: Arc::new(AtomicBool::new(true)),
             process: None,
+            exit_status: None,
         };
 
         session.force_kill();
@@ -625,4 +670,49 @@ mod tests {
             "shepherd should have been killed by signal"
         );
     }
+
+    #[test]
+    fn connect_discards_shepherd_with_other_protocol_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("ws-1.sock");
+        let pid_path = dir.path().join("ws-1.pid");
+
+        // A fake shepherd from another build: it answers our Resize with a
+        // newer protocol version.
+        let mut shepherd = spawn_isolated_sleep();
+        std::fs::write(&pid_path, shepherd.id().to_string()).unwrap();
+        std::fs::write(pid_path.with_extension("child-pid"), "0").unwrap();
+        let listener = std::os::unix::net::UnixListener::bind(&socket_path).unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let resize = protocol::read_message(&mut stream).unwrap();
+            assert_eq!(resize, Some(Message::Resize { rows: 23, cols: 80 }));
+            protocol::write_message(
+                &mut stream,
+                &Message::Hello {
+                    version: protocol::PROTOCOL_VERSION + 1,
+                },
+            )
+            .unwrap();
+        });
+
+        let result = ShepherdSession::connect(
+            dir.path(),
+            "test-task",
+            "ws/1",
+            24,
+            80,
+            None,
+            TerminalOptions::default(),
+        );
+        server.join().unwrap();
+
+        let err = result.err().expect("connect should reject the shepherd");
+        assert!(format!("{err:#}").contains("speaks protocol"), "{err:#}");
+        assert!(!socket_path.exists(), "socket should be removed");
+        assert!(!pid_path.exists(), "pid file should be removed");
+        assert!(!pid_path.with_extension("child-pid").exists());
+        // The shepherd was sent SIGTERM.
+        assert!(shepherd.wait().unwrap().code().is_none());
+    }
 }
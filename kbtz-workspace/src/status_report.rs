@@ -0,0 +1,96 @@
+//! `workspace-status.json`: a snapshot of the live sessions, written into
+//! the status directory for external dashboards and tools like `kbtz-tmux`.
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::session::SessionStatus;
+
+/// File name of the report inside the status directory.
+pub const STATUS_REPORT_FILE: &str = "workspace-status.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionReport {
+    pub session_id: String,
+    pub task: String,
+    pub status: SessionStatus,
+    pub pid: Option<u32>,
+    /// Seconds since the session was spawned or adopted, as of `written_at`.
+    pub uptime_secs: u64,
+}
+
+/// Number of sessions in each status.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct StatusCounts {
+    pub starting: usize,
+    pub active: usize,
+    pub idle: usize,
+    pub needs_input: usize,
+    pub error: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WorkspaceReport {
+    /// Unix seconds when the report was built.
+    pub written_at: u64,
+    pub max_concurrency: usize,
+    pub manual: bool,
+    pub counts: StatusCounts,
+    /// Sorted by session ID.
+    pub sessions: Vec<SessionReport>,
+}
+
+impl WorkspaceReport {
+    pub fn new(max_concurrency: usize, manual: bool, mut sessions: Vec<SessionReport>) -> Self {
+        sessions.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        let mut counts = StatusCounts::default();
+        for s in &sessions {
+            *match s.status {
+                SessionStatus::Starting => &mut counts.starting,
+                SessionStatus::Active => &mut counts.active,
+                SessionStatus::Idle => &mut counts.idle,
+                SessionStatus::NeedsInput => &mut counts.needs_input,
+                SessionStatus::Error => &mut counts.error,
+            } += 1;
+        }
+        let written_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        WorkspaceReport {
+            written_at,
+            max_concurrency,
+            manual,
+            counts,
+            sessions,
+        }
+    }
+
+    /// Whether both reports describe the same sessions and settings. Uptimes
+    /// and the write time are ignored so that ticks which only advance the
+    /// clock don't rewrite the file.
+    pub fn same_state(&self, other: &WorkspaceReport) -> bool {
+        self.max_concurrency == other.max_concurrency
+            && self.manual == other.manual
+            && self.sessions.len() == other.sessions.len()
+            && self.sessions.iter().zip(&other.sessions).all(|(a, b)| {
+                a.session_id == b.session_id
+                    && a.task == b.task
+                    && a.status == b.status
+                    && a.pid == b.pid
+            })
+    }
+
+    /// Write the report into `dir`, replacing the previous one atomically so
+    /// readers never see a partial file.
+    pub fn write_to(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(STATUS_REPORT_FILE);
+        let tmp = dir.join(format!("{STATUS_REPORT_FILE}.tmp"));
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
@@ -12,20 +12,43 @@ pub fn render(frame: &mut Frame, app: &mut App) {
         return;
     }
 
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Min(0), Constraint::Length(1)])
-        .split(frame.area());
+    let chunks = layout(frame.area());
 
     render_tree(frame, app, chunks[0]);
     render_footer(frame, app, chunks[1]);
 }
 
-struct SessionDecorator<'a> {
+/// Split the screen into the tree list area and the one-line footer.
+fn layout(area: Rect) -> std::rc::Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(area)
+}
+
+/// The area the tree list is rendered into for a screen of `area`, used
+/// to map mouse clicks back to rows.
+pub fn tree_area(area: Rect) -> Rect {
+    layout(area)[0]
+}
+
+/// Decorates tree rows with live session state: workspace sessions get
+/// their status indicator (e.g. 🔔 for needs-input) before the name;
+/// tasks without a session fall back to the default decoration.
+pub(crate) struct SessionDecorator<'a> {
     task_to_session: &'a HashMap<String, String>,
     sessions: &'a HashMap<String, TrackedSession>,
 }
 
+impl<'a> SessionDecorator<'a> {
+    pub(crate) fn new(app: &'a App) -> Self {
+        Self {
+            task_to_session: &app.task_to_session,
+            sessions: &app.sessions,
+        }
+    }
+}
+
 impl ui::TreeDecorator for SessionDecorator<'_> {
     fn decorate(&self, row: &ui::TreeRow) -> ui::RowDecoration {
         // Workspace session: task-state + 🤖+indicator(+unread) before name, session ID after
@@ -77,10 +100,7 @@ fn render_tree(frame: &mut Frame, app: &mut App, area: Rect) {
         return;
     }
 
-    let decorator = SessionDecorator {
-        task_to_session: &app.task_to_session,
-        sessions: &app.sessions,
-    };
+    let decorator = SessionDecorator::new(app);
     let items = ui::build_tree_items(&app.tree.rows, &app.tree.collapsed, &decorator);
 
     let active = app.sessions.len();
@@ -145,10 +165,18 @@ fn render_footer(frame: &mut Frame, app: &App, area: Rect) {
     frame.render_widget(Paragraph::new(text), area);
 }
 
-pub fn render_help(frame: &mut Frame) {
+/// `auto_confirm` reflects `--yes`: when set, pause/done on tasks with
+/// active sessions happen without a confirmation prompt. `prefix` is the
+/// configured prefix key label (e.g. `^B`).
+pub fn render_help(frame: &mut Frame, auto_confirm: bool, prefix: &str) {
+    let confirm_note = if auto_confirm {
+        " (no confirmation)"
+    } else {
+        ""
+    };
     let term = frame.area();
     let width = 55.min(term.width.saturating_sub(4));
-    let height = 35.min(term.height.saturating_sub(2));
+    let height = 37.min(term.height.saturating_sub(2));
     let area = ui::centered_rect(width, height, term);
     frame.render_widget(Clear, area);
 
@@ -169,6 +197,10 @@ pub fn render_help(frame: &mut Frame) {
             Span::styled("  Enter      ", Style::default().fg(Color::Cyan)),
             Span::raw("Zoom into session"),
         ]),
+        Line::from(vec![
+            Span::styled("  Click      ", Style::default().fg(Color::Cyan)),
+            Span::raw("Select task (double-click to zoom)"),
+        ]),
         Line::from(vec![
             Span::styled("  Tab        ", Style::default().fg(Color::Cyan)),
             Span::raw("Jump to needs-input/unread session"),
@@ -191,11 +223,11 @@ pub fn render_help(frame: &mut Frame) {
         ]),
         Line::from(vec![
             Span::styled("  p          ", Style::default().fg(Color::Cyan)),
-            Span::raw("Pause/unpause task"),
+            Span::raw(format!("Pause/unpause task{confirm_note}")),
         ]),
         Line::from(vec![
             Span::styled("  d          ", Style::default().fg(Color::Cyan)),
-            Span::raw("Mark task done"),
+            Span::raw(format!("Mark task done{confirm_note}")),
         ]),
         Line::from(vec![
             Span::styled("  U          ", Style::default().fg(Color::Cyan)),
@@ -231,27 +263,52 @@ pub fn render_help(frame: &mut Frame) {
             Style::default().bold(),
         )]),
         Line::from(vec![
-            Span::styled("  ^B t       ", Style::default().fg(Color::Cyan)),
+            Span::styled(
+                format!("  {prefix} t       "),
+                Style::default().fg(Color::Cyan),
+            ),
             Span::raw("Return to tree"),
         ]),
         Line::from(vec![
-            Span::styled("  ^B c       ", Style::default().fg(Color::Cyan)),
+            Span::styled(
+                format!("  {prefix} c       "),
+                Style::default().fg(Color::Cyan),
+            ),
             Span::raw("Task manager session"),
         ]),
         Line::from(vec![
-            Span::styled("  ^B n/p     ", Style::default().fg(Color::Cyan)),
+            Span::styled(
+                format!("  {prefix} n/p     "),
+                Style::default().fg(Color::Cyan),
+            ),
             Span::raw("Next/prev session"),
         ]),
         Line::from(vec![
-            Span::styled("  ^B Tab     ", Style::default().fg(Color::Cyan)),
+            Span::styled(
+                format!("  {prefix} Tab     "),
+                Style::default().fg(Color::Cyan),
+            ),
             Span::raw("Jump to needs-input/unread session"),
         ]),
         Line::from(vec![
-            Span::styled("  ^B ^B      ", Style::default().fg(Color::Cyan)),
-            Span::raw("Send literal Ctrl-B"),
+            Span::styled(
+                format!("  {prefix} b       "),
+                Style::default().fg(Color::Cyan),
+            ),
+            Span::raw("Toggle session sidebar"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                format!("  {prefix} {prefix}      "),
+                Style::default().fg(Color::Cyan),
+            ),
+            Span::raw("Send literal prefix key"),
         ]),
         Line::from(vec![
-            Span::styled("  ^B ?       ", Style::default().fg(Color::Cyan)),
+            Span::styled(
+                format!("  {prefix} ?       "),
+                Style::default().fg(Color::Cyan),
+            ),
             Span::raw("Show help"),
         ]),
         Line::raw(""),
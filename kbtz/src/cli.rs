@@ -24,35 +24,60 @@ Task Lifecycle:
   reopen          Reopen a completed task
   pause           Pause a task (remove from active work and default listing)
   unpause         Unpause a paused task (return to open)
+  bulk            Pause, unpause, finish or reopen several tasks at once
   reparent        Change a task's parent
+  move-children   Move every child of a task under another parent
+  dup             Copy a task and its subtasks under prefixed names
   describe        Update a task's description
+  rename          Rename a task
+  priority        Set a task's priority
+  estimate        Set (or clear) a task's effort estimate
+  touch           Bump a task's updated time without changing it
+  flag            Mark a task as needing a human (claim-next skips it)
+  unflag          Clear a task's needs-human flag
   rm              Remove a task
 
 Claiming:
   claim           Claim a task (set assignee)
   claim-next      Claim the best available task
+  next            Show the task claim-next would pick, without claiming it
   steal           Atomically transfer task ownership
   release         Release a task (clear assignee if it matches)
   force-unassign  Forcibly clear a task's assignee (regardless of who holds it)
+  reclaim         Release (or reassign) every claim held by an assignee prefix
 
 Dependencies:
   block           Mark a task as blocking another
   unblock         Remove a blocking relationship
+  deps            Show what blocks a task
+  deps-list       List every dependency edge
 
 Notes:
   note            Add a note to a task
   notes           List notes for a task
+  note-edit       Replace the content of a note
+  note-rm         Delete a note
 
 Viewing:
   show            Show task details
   list            List tasks
-  watch           Launch interactive TUI
+  watch           Launch interactive TUI (--json: stream status changes)
   search          Full-text search across tasks and notes
+  assignees       List active tasks grouped by assignee
+  blocked         List blocked tasks and what blocks each
+  rollup          Sum estimates over a task and its subtasks
+  stale           List active tasks idle for longer than a threshold
   agents          List configured agent types
+  paths           Print the resolved database, workspace, log and lock paths
+  check           Report (and optionally fix) references to missing tasks
+  export          Dump all tasks, notes and dependencies as JSON
+  import          Load an exported JSON snapshot from stdin
+  log             Print (or --follow) the orchestrator, workspace or shepherd log
 
 Coordination:
   wait            Wait for database changes (blocks until a change occurs)
   exec            Execute commands from stdin atomically (all-or-nothing transaction)
+  agent-status    Report this session's state (active, idle, needs_input) to the workspace
   help            Print this message or the help of a given subcommand
 "
 )]
@@ -61,6 +86,10 @@ pub struct Cli {
     #[arg(long, env = "KBTZ_DB", global = true)]
     pub db: Option<String>,
 
+    /// Color plain-text output: auto (a terminal without NO_COLOR), always or never
+    #[arg(long, default_value = "auto", global = true)]
+    pub color: String,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -69,7 +98,7 @@ pub struct Cli {
 pub enum Command {
     /// Add a task
     Add {
-        /// Task name (alphanumeric, hyphens, underscores; immutable after creation)
+        /// Task name (alphanumeric, hyphens, underscores)
         name: String,
         /// Parent task name
         #[arg(short, long)]
@@ -91,9 +120,18 @@ pub enum Command {
         /// Working directory for the agent session
         #[arg(short, long)]
         directory: Option<String>,
+        /// Priority for claim-next ordering (higher is claimed first)
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        priority: i64,
+        /// Effort estimate (minutes or points), summed by `kbtz rollup`
+        #[arg(long)]
+        estimate: Option<u32>,
         /// Output as JSON
         #[arg(long)]
         json: bool,
+        /// Timestamps in JSON output: iso (default) or epoch seconds
+        #[arg(long, default_value = "iso")]
+        time_format: String,
     },
 
     /// Claim a task (set assignee)
@@ -112,9 +150,31 @@ pub enum Command {
         /// Soft preference text for ranking (matched against name, description, and notes)
         #[arg(long)]
         prefer: Option<String>,
+        /// Only consider this task and its descendants
+        #[arg(long)]
+        root: Option<String>,
         /// Output as JSON
         #[arg(long)]
         json: bool,
+        /// Timestamps in JSON output: iso (default) or epoch seconds
+        #[arg(long, default_value = "iso")]
+        time_format: String,
+    },
+
+    /// Show the task claim-next would pick, without claiming it
+    Next {
+        /// Soft preference text for ranking (matched against name, description, and notes)
+        #[arg(long)]
+        prefer: Option<String>,
+        /// Only consider this task and its descendants
+        #[arg(long)]
+        root: Option<String>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Timestamps in JSON output: iso (default) or epoch seconds
+        #[arg(long, default_value = "iso")]
+        time_format: String,
     },
 
     /// Atomically transfer task ownership
@@ -140,16 +200,40 @@ pub enum Command {
         name: String,
     },
 
+    /// Release every active task whose assignee starts with a prefix, or
+    /// reassign them all with --to. Recovers claims left by a crashed agent
+    /// or workspace (e.g. --prefix ws/).
+    Reclaim {
+        /// Assignee prefix to match (e.g. ws/)
+        #[arg(long)]
+        prefix: String,
+        /// Release the matching tasks back to open (the default)
+        #[arg(long, conflicts_with = "to")]
+        release: bool,
+        /// Reassign the matching tasks to this assignee instead of releasing
+        #[arg(long)]
+        to: Option<String>,
+    },
+
     /// Mark a task as done
     Done {
         /// Task name
         name: String,
+        /// Also mark all non-done descendants done
+        #[arg(long)]
+        recursive: bool,
+        /// With --recursive, also mark active descendants done
+        #[arg(long, requires = "recursive")]
+        force: bool,
     },
 
     /// Reopen a completed task
     Reopen {
         /// Task name
         name: String,
+        /// Also reopen done descendants
+        #[arg(long)]
+        recursive: bool,
     },
 
     /// Pause a task (remove from active work and default listing)
@@ -164,6 +248,19 @@ pub enum Command {
         name: String,
     },
 
+    /// Apply pause, unpause, done or reopen to several tasks in one transaction
+    Bulk {
+        /// One of: pause, unpause, done, reopen
+        op: String,
+        /// Task names
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// Apply to every task that can be changed and report the rest,
+        /// instead of rolling back on the first failure
+        #[arg(long)]
+        keep_going: bool,
+    },
+
     /// Change a task's parent
     Reparent {
         /// Task name
@@ -173,12 +270,85 @@ pub enum Command {
         parent: Option<String>,
     },
 
+    /// Move every direct child of a task under another parent (all or nothing)
+    #[command(name = "move-children")]
+    MoveChildren {
+        /// Current parent task name
+        from: String,
+        /// New parent task name (omit to make the children root-level)
+        to: Option<String>,
+    },
+
+    /// Copy a task and all its descendants as fresh open tasks named
+    /// <prefix><original name>. The copy keeps the source's parent;
+    /// dependencies inside the subtree are copied, others are dropped.
+    Dup {
+        /// Root of the subtree to copy
+        src: String,
+        /// Prefix prepended to every copied task's name
+        prefix: String,
+        /// Also copy each task's notes
+        #[arg(long)]
+        notes: bool,
+    },
+
     /// Update a task's description
     Describe {
         /// Task name
         name: String,
         /// New description
-        desc: String,
+        #[arg(required_unless_present = "edit")]
+        desc: Option<String>,
+        /// Edit the current description in $EDITOR
+        #[arg(long, conflicts_with = "desc")]
+        edit: bool,
+    },
+
+    /// Rename a task (updates parent, note, and dependency references)
+    Rename {
+        /// Current task name
+        old: String,
+        /// New task name
+        new: String,
+    },
+
+    /// Set a task's priority (higher is claimed first by claim-next)
+    Priority {
+        /// Task name
+        name: String,
+        /// New priority (default for new tasks is 0)
+        #[arg(allow_negative_numbers = true)]
+        priority: i64,
+    },
+
+    /// Set a task's effort estimate (minutes or points), or clear it
+    Estimate {
+        /// Task name
+        name: String,
+        /// New estimate
+        #[arg(required_unless_present = "clear")]
+        estimate: Option<u32>,
+        /// Remove the estimate
+        #[arg(long, conflicts_with = "estimate")]
+        clear: bool,
+    },
+
+    /// Mark a task as still relevant: bump its updated time and nothing else
+    Touch {
+        /// Task name
+        name: String,
+    },
+
+    /// Mark a task as needing a human decision (claim-next skips it)
+    Flag {
+        /// Task name
+        name: String,
+    },
+
+    /// Clear a task's needs-human flag
+    Unflag {
+        /// Task name
+        name: String,
     },
 
     /// Remove a task
@@ -188,6 +358,9 @@ pub enum Command {
         /// Remove children recursively
         #[arg(long)]
         recursive: bool,
+        /// List what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Show task details
@@ -197,6 +370,9 @@ pub enum Command {
         /// Output as JSON
         #[arg(long)]
         json: bool,
+        /// Timestamps in JSON output: iso (default) or epoch seconds
+        #[arg(long, default_value = "iso")]
+        time_format: String,
     },
 
     /// List tasks
@@ -226,9 +402,46 @@ pub enum Command {
         /// Show only unblocked tasks
         #[arg(long)]
         unblocked: bool,
+        /// Show only top-level tasks (no parent)
+        #[arg(long, conflicts_with = "children")]
+        roots: bool,
+        /// Show only tasks with no children
+        #[arg(long)]
+        leaves: bool,
+        /// Show only tasks updated after this UTC time (YYYY-MM-DDTHH:MM:SSZ)
+        #[arg(long)]
+        since: Option<String>,
+        /// With --tree, show at most N levels below the roots (0 = roots only);
+        /// truncated parents are marked (+N more)
+        #[arg(long, requires = "tree")]
+        depth: Option<usize>,
+        /// Show how long ago each task was last updated
+        #[arg(short, long, conflicts_with = "tree")]
+        long: bool,
+        /// Sort by id, name, status, updated, or created (with --tree, orders siblings)
+        #[arg(long, default_value = "id")]
+        sort: String,
+        /// Reverse the sort order
+        #[arg(long)]
+        reverse: bool,
+        /// Show at most N tasks (the total is reported on stderr)
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Skip the first M tasks
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+        /// Print only the number of matching tasks
+        #[arg(long, conflicts_with_all = ["tree", "long", "limit", "json", "ndjson"])]
+        count: bool,
         /// Output as JSON
         #[arg(long)]
         json: bool,
+        /// Output one compact JSON object per line
+        #[arg(long, conflicts_with = "json")]
+        ndjson: bool,
+        /// Timestamps in JSON output: iso (default) or epoch seconds
+        #[arg(long, default_value = "iso")]
+        time_format: String,
     },
 
     /// Add a note to a task
@@ -237,15 +450,46 @@ pub enum Command {
         name: String,
         /// Note content (omit to read from stdin)
         content: Option<String>,
+        /// Tag the note with a label (e.g. status)
+        #[arg(long)]
+        label: Option<String>,
+        /// Replace the task's note with this label instead of adding another
+        #[arg(long, requires = "label")]
+        replace: bool,
     },
 
     /// List notes for a task
     Notes {
         /// Task name
         name: String,
+        /// Show only notes with this label
+        #[arg(long)]
+        label: Option<String>,
         /// Output as JSON
         #[arg(long)]
         json: bool,
+        /// Output one compact JSON object per line
+        #[arg(long, conflicts_with = "json")]
+        ndjson: bool,
+        /// Timestamps in JSON output: iso (default) or epoch seconds
+        #[arg(long, default_value = "iso")]
+        time_format: String,
+    },
+
+    /// Replace the content of a note
+    #[command(name = "note-edit")]
+    NoteEdit {
+        /// Note ID (shown by `kbtz notes`)
+        id: i64,
+        /// New note content (omit to read from stdin)
+        content: Option<String>,
+    },
+
+    /// Delete a note
+    #[command(name = "note-rm")]
+    NoteRm {
+        /// Note ID (shown by `kbtz notes`)
+        id: i64,
     },
 
     /// Mark a task as blocking another
@@ -257,11 +501,41 @@ pub enum Command {
     },
 
     /// Remove a blocking relationship
+    ///
+    /// With --all, removes every edge where <blocker> is the blocker, or
+    /// every edge blocking the task given to --blocked.
     Unblock {
         /// The blocking task
-        blocker: String,
+        blocker: Option<String>,
         /// The blocked task
-        blocked: String,
+        blocked: Option<String>,
+        /// Remove all matching edges instead of a single one
+        #[arg(long)]
+        all: bool,
+        /// With --all, clear everything blocking this task
+        #[arg(long = "blocked", requires = "all", conflicts_with_all = ["blocker", "blocked"])]
+        blocked_task: Option<String>,
+    },
+
+    /// List every dependency edge
+    #[command(name = "deps-list")]
+    DepsList {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show what blocks a task
+    #[command(after_long_help = STATUS_ICONS_HELP)]
+    Deps {
+        /// Task name
+        name: String,
+        /// Follow blockers transitively instead of showing direct blockers only
+        #[arg(long)]
+        tree: bool,
+        /// Output as JSON (nested by `blocked_by`)
+        #[arg(long)]
+        json: bool,
     },
 
     /// Launch interactive TUI
@@ -270,7 +544,8 @@ pub enum Command {
         /// Root task for subtree
         #[arg(long)]
         root: Option<String>,
-        /// Poll interval in milliseconds
+        /// Longest wait in milliseconds between redraws; keys and DB
+        /// changes wake the TUI sooner
         #[arg(long, default_value = "1000")]
         poll_interval: u64,
         /// Shell command to run on Enter (receives KBTZ_TASK, KBTZ_TASK_STATUS, KBTZ_TASK_ASSIGNEE env vars)
@@ -279,6 +554,13 @@ pub enum Command {
         /// Workspace status directory for session indicators
         #[arg(long, env = "KBTZ_WORKSPACE_DIR")]
         workspace_dir: Option<String>,
+        /// Instead of the TUI, print task status changes as JSON lines
+        #[arg(long, conflicts_with = "action")]
+        json: bool,
+        /// Start with the task detail pane open (Enter toggles it when
+        /// there is no --action)
+        #[arg(long, conflicts_with = "json")]
+        preview: bool,
     },
 
     /// Wait for database changes (blocks until a change occurs)
@@ -289,14 +571,164 @@ pub enum Command {
     Search {
         /// Search query
         query: String,
+        /// Require every word to match (default: any word)
+        #[arg(long)]
+        all: bool,
+        /// Match words as prefixes (e.g. "auth" matches "authentication")
+        #[arg(long)]
+        prefix: bool,
+        /// Filter by status (open, active, paused, done)
+        #[arg(long)]
+        status: Option<String>,
+        /// Filter by assignee
+        #[arg(long)]
+        assignee: Option<String>,
+        /// Show at most N results (the total is reported on stderr)
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Skip the first M results
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Output one compact JSON object per line
+        #[arg(long, conflicts_with = "json")]
+        ndjson: bool,
+        /// Timestamps in JSON output: iso (default) or epoch seconds
+        #[arg(long, default_value = "iso")]
+        time_format: String,
+    },
+
+    /// List active tasks grouped by assignee
+    Assignees {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Timestamps in JSON output: iso (default) or epoch seconds
+        #[arg(long, default_value = "iso")]
+        time_format: String,
+    },
+
+    /// List tasks with unfinished blockers, and what blocks each
+    Blocked {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Timestamps in JSON output: iso (default) or epoch seconds
+        #[arg(long, default_value = "iso")]
+        time_format: String,
+    },
+
+    /// Sum estimates over a task and its descendants: total vs. done
+    Rollup {
+        /// Root task of the subtree
+        name: String,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List active tasks whose status has not changed for a while
+    Stale {
+        /// Minimum idle time in minutes
+        #[arg(long, default_value_t = 60)]
+        minutes: u64,
         /// Output as JSON
         #[arg(long)]
         json: bool,
+        /// Timestamps in JSON output: iso (default) or epoch seconds
+        #[arg(long, default_value = "iso")]
+        time_format: String,
     },
 
     /// List configured agent types from workspace config
     Agents,
 
+    /// Print where the database, workspace directory, logs and locks live
+    ///
+    /// Resolves KBTZ_DB and KBTZ_WORKSPACE_DIR the same way every kbtz
+    /// binary does, and notes which paths are missing.
+    Paths {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Report this agent session's state to the workspace
+    ///
+    /// Writes the status file for $KBTZ_SESSION_ID in $KBTZ_WORKSPACE_DIR,
+    /// both of which the workspace sets for every session.
+    AgentStatus {
+        /// One of: starting, active, idle, needs_input, error
+        state: String,
+    },
+
+    /// Check for references to missing tasks
+    ///
+    /// Reports tasks whose parent is missing, dependency edges with a missing
+    /// endpoint, and notes attached to missing tasks. Exits non-zero if any
+    /// issues remain.
+    Check {
+        /// Move dangling-parent tasks to the root and delete dangling
+        /// dependencies and notes
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Dump every task, note and dependency for backup or migration
+    ///
+    /// Includes done and paused tasks. Without --json, prints a summary of
+    /// what the snapshot contains.
+    Export {
+        /// Output the full snapshot as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Load a snapshot produced by `export --json` from stdin
+    ///
+    /// Runs in a single transaction. With --mode merge (the default), tasks
+    /// whose names already exist are skipped along with their notes. With
+    /// --mode replace, any collision is an error unless --force is given,
+    /// which wipes the database before loading.
+    Import {
+        /// Collision handling: merge or replace
+        #[arg(long, default_value = "merge")]
+        mode: String,
+        /// With --mode replace, delete all existing data first
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Print the commands that turn the database into a snapshot
+    ///
+    /// Compares tasks, parents, statuses and dependencies against a file in
+    /// the `export --json` format and prints them as an exec script. Tasks
+    /// missing from the file are removed; notes and other fields are not
+    /// compared. With --apply, runs the commands in one transaction instead.
+    Diff {
+        /// Target snapshot file (- for stdin)
+        file: String,
+        /// Apply the changes instead of printing them
+        #[arg(long)]
+        apply: bool,
+    },
+
+    /// Print a kbtz log file, optionally following appends
+    ///
+    /// The orchestrator log is `orchestrator.log` in the workspace directory.
+    /// kbtz-workspace and its shepherds log to the file named by KBTZ_DEBUG;
+    /// --which shepherd shows only the shepherd lines from it.
+    Log {
+        /// Which log: orchestrator, workspace or shepherd
+        #[arg(long, default_value = "workspace")]
+        which: String,
+        /// Keep printing lines as they are appended
+        #[arg(short, long)]
+        follow: bool,
+    },
+
     /// Execute commands from stdin atomically (all-or-nothing transaction)
     ///
     /// Reads commands from stdin, one per line, and runs them in a single
@@ -304,11 +736,12 @@ pub enum Command {
     ///
     /// Lines are parsed with double-quote-only quoting (single quotes/apostrophes
     /// are ordinary characters). Use \" for literal double quotes inside a quoted
-    /// string. Blank lines and lines starting with # are ignored. Do not prefix
+    /// string. Blank lines and lines starting with # are ignored, as is the rest
+    /// of a line after a # that starts a word outside quotes. Do not prefix
     /// commands with "kbtz":
     ///
     ///     add my-task "A new task"
-    ///     block my-task other-task
+    ///     block my-task other-task   # other-task waits for my-task
     ///     note my-task "It's a note with apostrophes"
     ///
     /// Heredoc syntax is supported for multiline arguments (one per command):
@@ -322,7 +755,30 @@ pub enum Command {
     /// this does not work inside exec. Always pass note content as an argument or
     /// use heredoc syntax.
     ///
+    /// "set KEY value" defines a variable for the rest of the batch; ${KEY} in
+    /// later command lines (inside or outside quotes, but not in heredoc
+    /// bodies) is replaced with its value before the line is parsed. Using an
+    /// undefined variable is an error:
+    ///
+    ///     set EPIC auth-rewrite
+    ///     add ${EPIC}-tokens "Token storage for ${EPIC}" -p ${EPIC}
+    ///
     /// The exec, watch, and wait commands cannot be used inside exec.
+    ///
+    /// With --dry-run, the batch is parsed and checked the same way but not
+    /// run, and the number of commands that would run is printed.
+    ///
+    /// With --keep-going, each command commits on its own: failed commands
+    /// are reported at the end (and exec exits non-zero) while the rest take
+    /// effect. Batches whose commands depend on each other (add a parent,
+    /// then its child) may partially fail in this mode.
     #[command(verbatim_doc_comment)]
-    Exec,
+    Exec {
+        /// Parse and validate the batch without touching the database
+        #[arg(long)]
+        dry_run: bool,
+        /// Commit each successful command and report failures at the end
+        #[arg(long, conflicts_with = "dry_run")]
+        keep_going: bool,
+    },
 }
@@ -17,11 +17,112 @@ pub struct Config {
 pub struct WorkspaceConfig {
     pub concurrency: Option<usize>,
     pub manual: Option<bool>,
+    pub auto_confirm: Option<bool>,
     pub prefer: Option<String>,
+    pub root: Option<String>,
     pub backend: Option<String>,
     pub persistent_sessions: Option<bool>,
     pub workspace_dir: Option<String>,
     pub directory: Option<String>,
+    /// Passthrough-mode prefix key, e.g. `"C-a"`. Defaults to Ctrl-B.
+    pub prefix_key: Option<String>,
+    /// Copy scroll-mode dumps (`w`) to the clipboard via OSC 52.
+    pub osc52_clipboard: Option<bool>,
+    /// Show the session list sidebar in zoomed mode at startup.
+    pub sidebar: Option<bool>,
+    /// Sidebar width in columns.
+    pub sidebar_width: Option<u16>,
+    /// Seconds a stopping session gets to exit before it is force-killed.
+    pub graceful_timeout_secs: Option<u64>,
+    /// Respawn a worker that exits mid-task under the same claim instead
+    /// of releasing the task.
+    pub restart_in_place: Option<bool>,
+    /// Ask worker sessions to exit once they have run this many minutes.
+    pub max_session_minutes: Option<u64>,
+    /// Pause the task (and stop its session) once the session has reported
+    /// `idle` for this many minutes.
+    pub idle_pause_minutes: Option<u64>,
+    /// Keystrokes sent to ask a session to exit, e.g. `'/exit\n'`.
+    /// Applies to agents without their own `exit_sequence`.
+    pub exit_sequence: Option<String>,
+    /// What to do with OSC 52 clipboard writes from agent sessions.
+    pub allow_clipboard: Option<ClipboardPolicy>,
+    /// Scrollback rows retained per session. Defaults to 10000.
+    pub scrollback_rows: Option<usize>,
+}
+
+/// How the workspace handles an agent's OSC 52 "set clipboard" request.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardPolicy {
+    /// Re-emit the sequence to the real terminal while the session is zoomed.
+    Forward,
+    /// Discard the request.
+    #[default]
+    Drop,
+    /// Decode the payload and pipe it to a local clipboard command.
+    Local,
+}
+
+/// The prefix key used when `workspace.prefix_key` is not set (Ctrl-B).
+pub const DEFAULT_PREFIX_KEY: u8 = 0x02;
+
+/// Parse a prefix key spec (`"C-a"` or `"^a"`, case-insensitive) into its
+/// control byte.
+///
+/// Only Ctrl + letter is accepted. Printable characters would break normal
+/// typing, and Ctrl-H/I/J/M are rejected because terminals send the same
+/// bytes for Backspace, Tab, and Enter.
+pub fn parse_prefix_key(spec: &str) -> Result<u8> {
+    let letter = spec
+        .strip_prefix("C-")
+        .or_else(|| spec.strip_prefix("c-"))
+        .or_else(|| spec.strip_prefix('^'));
+    let c = match letter.map(|l| l.chars().collect::<Vec<_>>()).as_deref() {
+        Some([c]) if c.is_ascii_alphabetic() => c.to_ascii_lowercase(),
+        _ => bail!("invalid prefix key '{spec}': expected a control key like \"C-a\""),
+    };
+    if matches!(c, 'h' | 'i' | 'j' | 'm') {
+        bail!("invalid prefix key '{spec}': indistinguishable from Backspace, Tab, or Enter");
+    }
+    Ok(c as u8 & 0x1f)
+}
+
+/// Parse an exit sequence spec into raw bytes.
+///
+/// Supports `\n`, `\r`, `\t`, `\e`, `\0`, `\\`, and `\xHH` escapes;
+/// every other character is taken literally as UTF-8.
+pub fn parse_exit_sequence(spec: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(spec.len());
+    let mut chars = spec.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push(b'\n'),
+            Some('r') => out.push(b'\r'),
+            Some('t') => out.push(b'\t'),
+            Some('e') => out.push(0x1b),
+            Some('0') => out.push(0),
+            Some('\\') => out.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(b) if hex.len() == 2 => out.push(b),
+                    _ => bail!("invalid exit sequence '{spec}': \\x needs two hex digits"),
+                }
+            }
+            Some(other) => bail!("invalid exit sequence '{spec}': unknown escape \\{other}"),
+            None => bail!("invalid exit sequence '{spec}': trailing backslash"),
+        }
+    }
+    if out.is_empty() {
+        bail!("invalid exit sequence: must not be empty");
+    }
+    Ok(out)
 }
 
 /// The `command` field in agent config: either a plain string or an array
@@ -36,13 +137,20 @@ pub enum AgentCommand {
 #[derive(Debug, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct AgentConfig {
-    /// Which Rust backend implementation to use (e.g., "claude").
+    /// Which Rust backend implementation to use (e.g., "claude" or "shell").
     /// Defaults to the agent name. Allows multiple agent types to share
     /// the same backend with different command/args.
     pub backend: Option<String>,
     pub command: Option<AgentCommand>,
     #[serde(default)]
     pub args: Vec<String>,
+    /// Prompt-injection template for agents without a built-in backend,
+    /// e.g. `["--system", "{protocol}", "{task}"]`. `{protocol}` expands to
+    /// the workspace system instructions and `{task}` to the initial prompt.
+    pub prompt_args: Option<Vec<String>>,
+    /// Keystrokes sent to ask this agent to exit, with escapes such as
+    /// `\x03` or `\n`. Overrides `workspace.exit_sequence`.
+    pub exit_sequence: Option<String>,
 }
 
 impl AgentConfig {
@@ -64,6 +172,9 @@ impl AgentConfig {
     }
 }
 
+/// Placeholders every `prompt_args` template must use.
+pub const PROMPT_PLACEHOLDERS: [&str; 2] = ["{protocol}", "{task}"];
+
 impl Config {
     /// Load config from `~/.kbtz/workspace.toml`.
     /// Returns default config if the file doesn't exist.
@@ -85,7 +196,19 @@ impl Config {
     }
 
     fn validate(&self, path: &Path) -> Result<()> {
+        if let Some(spec) = &self.workspace.prefix_key {
+            parse_prefix_key(spec)
+                .with_context(|| format!("failed to parse {}", path.display()))?;
+        }
+        if let Some(spec) = &self.workspace.exit_sequence {
+            parse_exit_sequence(spec)
+                .with_context(|| format!("failed to parse {}", path.display()))?;
+        }
         for (name, agent) in &self.agent {
+            if let Some(spec) = &agent.exit_sequence {
+                parse_exit_sequence(spec)
+                    .with_context(|| format!("failed to parse {}: agent.{name}", path.display()))?;
+            }
             if let Some(AgentCommand::WithPrefix(v)) = &agent.command {
                 if v.is_empty() {
                     bail!(
@@ -94,6 +217,22 @@ impl Config {
                     );
                 }
             }
+            if let Some(template) = &agent.prompt_args {
+                if agent.backend.is_some() {
+                    bail!(
+                        "failed to parse {}: agent.{name}.prompt_args cannot be combined with backend",
+                        path.display()
+                    );
+                }
+                for placeholder in PROMPT_PLACEHOLDERS {
+                    if !template.iter().any(|arg| arg.contains(placeholder)) {
+                        bail!(
+                            "failed to parse {}: agent.{name}.prompt_args must contain {placeholder}",
+                            path.display()
+                        );
+                    }
+                }
+            }
         }
         Ok(())
     }
@@ -314,4 +453,102 @@ command = "gemini-cli"
         let agent = config.agent.get("gemini").unwrap();
         assert!(agent.backend.is_none());
     }
+
+    #[test]
+    fn parse_prompt_args_template() {
+        let toml = r#"
+[agent.aider]
+command = "aider"
+prompt_args = ["--read-system", "{protocol}", "--message", "task: {task}"]
+"#;
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        f.write_all(toml.as_bytes()).unwrap();
+
+        let config = Config::load_from(f.path()).unwrap();
+        let agent = config.agent.get("aider").unwrap();
+        assert_eq!(agent.prompt_args.as_ref().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn prompt_args_require_placeholders() {
+        for (body, needle) in [
+            (r#"prompt_args = ["{task}"]"#, "{protocol}"),
+            (r#"prompt_args = ["--system", "{protocol}"]"#, "{task}"),
+            (
+                "backend = \"claude\"\nprompt_args = [\"{protocol}\", \"{task}\"]",
+                "backend",
+            ),
+        ] {
+            let toml = format!("[agent.custom]\n{body}\n");
+            let mut f = tempfile::NamedTempFile::new().unwrap();
+            f.write_all(toml.as_bytes()).unwrap();
+            let err = Config::load_from(f.path()).unwrap_err().to_string();
+            assert!(err.contains(needle), "expected {needle} in: {err}");
+        }
+    }
+
+    #[test]
+    fn parse_exit_sequence_handles_escapes() {
+        assert_eq!(parse_exit_sequence("/exit\\n").unwrap(), b"/exit\n");
+        assert_eq!(parse_exit_sequence("\\x03").unwrap(), [0x03]);
+        assert_eq!(parse_exit_sequence("\\e:q\\r").unwrap(), b"\x1b:q\r");
+        assert_eq!(parse_exit_sequence("a\\\\b").unwrap(), b"a\\b");
+    }
+
+    #[test]
+    fn parse_exit_sequence_rejects_bad_escapes() {
+        for spec in ["", "\\", "\\q", "\\x3", "\\xzz"] {
+            assert!(
+                parse_exit_sequence(spec).is_err(),
+                "{spec:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_exit_sequence_rejected_on_load() {
+        let toml = "[agent.codex]\nexit_sequence = \"\\\\q\"\n";
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        f.write_all(toml.as_bytes()).unwrap();
+        let err = format!("{:#}", Config::load_from(f.path()).unwrap_err());
+        assert!(err.contains("agent.codex"), "{err}");
+    }
+
+    #[test]
+    fn parse_prefix_key_accepts_control_letters() {
+        assert_eq!(parse_prefix_key("C-a").unwrap(), 0x01);
+        assert_eq!(parse_prefix_key("C-B").unwrap(), 0x02);
+        assert_eq!(parse_prefix_key("^z").unwrap(), 0x1a);
+    }
+
+    #[test]
+    fn parse_prefix_key_rejects_ambiguous_keys() {
+        for spec in ["a", "C-", "C-ab", "C-1", "M-a", "C-m", "C-i", "^h", "C-j"] {
+            assert!(parse_prefix_key(spec).is_err(), "{spec} should be rejected");
+        }
+    }
+
+    #[test]
+    fn invalid_prefix_key_rejected_on_load() {
+        let toml = "[workspace]\nprefix_key = \"x\"\n";
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        f.write_all(toml.as_bytes()).unwrap();
+
+        let err = Config::load_from(f.path()).unwrap_err();
+        assert!(format!("{err:#}").contains("invalid prefix key"), "{err:#}");
+    }
+
+    #[test]
+    fn allow_clipboard_parses_policies() {
+        for (value, policy) in [
+            ("forward", ClipboardPolicy::Forward),
+            ("drop", ClipboardPolicy::Drop),
+            ("local", ClipboardPolicy::Local),
+        ] {
+            let toml = format!("[workspace]\nallow_clipboard = \"{value}\"\n");
+            let config: Config = toml::from_str(&toml).unwrap();
+            assert_eq!(config.workspace.allow_clipboard, Some(policy));
+        }
+        assert!(toml::from_str::<Config>("[workspace]\nallow_clipboard = \"yes\"\n").is_err());
+    }
 }
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use rusqlite::Connection;
 
 const SCHEMA: &str = "
@@ -11,6 +11,9 @@ CREATE TABLE IF NOT EXISTS tasks (
     assignee           TEXT,
     agent              TEXT,
     directory          TEXT,
+    priority           INTEGER NOT NULL DEFAULT 0,
+    needs_human        INTEGER NOT NULL DEFAULT 0,
+    estimate           INTEGER CHECK(estimate >= 0),
     status_changed_at  TEXT,
     created_at         TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
     updated_at         TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
@@ -21,7 +24,8 @@ CREATE TABLE IF NOT EXISTS notes (
     id         INTEGER PRIMARY KEY,
     task       TEXT NOT NULL REFERENCES tasks(name) ON UPDATE CASCADE ON DELETE CASCADE,
     content    TEXT NOT NULL,
-    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    label      TEXT
 );
 
 CREATE TABLE IF NOT EXISTS task_deps (
@@ -32,7 +36,7 @@ CREATE TABLE IF NOT EXISTS task_deps (
 );
 
 CREATE VIRTUAL TABLE IF NOT EXISTS tasks_fts USING fts5(
-    name, description,
+    name, description, assignee,
     content='tasks', content_rowid='id'
 );
 
@@ -45,16 +49,18 @@ CREATE VIRTUAL TABLE IF NOT EXISTS notes_fts USING fts5(
 const TRIGGERS: &str = "
 -- Keep tasks_fts in sync with tasks
 CREATE TRIGGER IF NOT EXISTS tasks_fts_ai AFTER INSERT ON tasks BEGIN
-    INSERT INTO tasks_fts(rowid, name, description) VALUES(new.id, new.name, new.description);
+    INSERT INTO tasks_fts(rowid, name, description, assignee)
+    VALUES(new.id, new.name, new.description, new.assignee);
 END;
 CREATE TRIGGER IF NOT EXISTS tasks_fts_ad AFTER DELETE ON tasks BEGIN
-    INSERT INTO tasks_fts(tasks_fts, rowid, name, description)
-    VALUES('delete', old.id, old.name, old.description);
+    INSERT INTO tasks_fts(tasks_fts, rowid, name, description, assignee)
+    VALUES('delete', old.id, old.name, old.description, old.assignee);
 END;
 CREATE TRIGGER IF NOT EXISTS tasks_fts_au AFTER UPDATE ON tasks BEGIN
-    INSERT INTO tasks_fts(tasks_fts, rowid, name, description)
-    VALUES('delete', old.id, old.name, old.description);
-    INSERT INTO tasks_fts(rowid, name, description) VALUES(new.id, new.name, new.description);
+    INSERT INTO tasks_fts(tasks_fts, rowid, name, description, assignee)
+    VALUES('delete', old.id, old.name, old.description, old.assignee);
+    INSERT INTO tasks_fts(rowid, name, description, assignee)
+    VALUES(new.id, new.name, new.description, new.assignee);
 END;
 
 -- Keep notes_fts in sync with notes
@@ -70,86 +76,151 @@ CREATE TRIGGER IF NOT EXISTS notes_fts_au AFTER UPDATE ON notes BEGIN
 END;
 ";
 
+/// Per-connection settings shared by file and in-memory databases.
 fn set_pragmas(conn: &Connection) -> Result<()> {
     conn.execute_batch(
-        "PRAGMA journal_mode = WAL;
-         PRAGMA foreign_keys = ON;
+        "PRAGMA foreign_keys = ON;
          PRAGMA busy_timeout = 5000;",
     )?;
     Ok(())
 }
 
+/// Parse a `KBTZ_SYNCHRONOUS` value into its `PRAGMA synchronous` level.
+fn parse_synchronous(value: &str) -> Result<&'static str> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "off" => Ok("OFF"),
+        "normal" => Ok("NORMAL"),
+        "full" => Ok("FULL"),
+        "extra" => Ok("EXTRA"),
+        _ => bail!("invalid KBTZ_SYNCHRONOUS '{value}': expected off, normal, full or extra"),
+    }
+}
+
+/// The `synchronous` level for file databases: `NORMAL` unless
+/// `KBTZ_SYNCHRONOUS` overrides it.
+fn synchronous_level() -> Result<&'static str> {
+    match std::env::var("KBTZ_SYNCHRONOUS") {
+        Ok(value) => parse_synchronous(&value),
+        Err(_) => Ok("NORMAL"),
+    }
+}
+
+/// Open a database file in WAL mode. Readers no longer block the writer
+/// (and vice versa), so concurrent agents polling and claiming tasks hit
+/// `SQLITE_BUSY` far less often than with a rollback journal. WAL stays
+/// durable against corruption with `synchronous = NORMAL`; only the last
+/// commits before a power loss can be lost. The journal mode is persistent,
+/// so databases created before WAL are switched on their next open.
 pub fn open(path: &str) -> Result<Connection> {
+    let synchronous = synchronous_level()?;
     let conn = Connection::open(path)?;
+    let mode: String = conn.query_row("PRAGMA journal_mode = WAL", [], |r| r.get(0))?;
+    if !mode.eq_ignore_ascii_case("wal") {
+        bail!("could not enable WAL mode for {path} (journal_mode is {mode})");
+    }
+    conn.execute_batch(&format!("PRAGMA synchronous = {synchronous};"))?;
     set_pragmas(&conn)?;
     Ok(conn)
 }
 
+/// Newest schema version: the shape `SCHEMA` creates.
+pub const SCHEMA_VERSION: i64 = 9;
+
+/// One step in the schema history, taking a database from `version - 1` to
+/// `version`.
+struct Migration {
+    version: i64,
+    up: fn(&Connection) -> Result<()>,
+}
+
+/// Every migration, oldest first. Version 1 is the original released
+/// schema; add a step here (and bump `SCHEMA_VERSION` and `SCHEMA`) for
+/// each schema change.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 2,
+        up: migrate_v1_to_v2,
+    },
+    Migration {
+        version: 3,
+        up: migrate_v2_to_v3,
+    },
+    Migration {
+        version: 4,
+        up: migrate_v3_to_v4,
+    },
+    Migration {
+        version: 5,
+        up: migrate_v4_to_v5,
+    },
+    Migration {
+        version: 6,
+        up: migrate_v5_to_v6,
+    },
+    Migration {
+        version: 7,
+        up: migrate_v6_to_v7,
+    },
+    Migration {
+        version: 8,
+        up: migrate_v7_to_v8,
+    },
+    Migration {
+        version: 9,
+        up: migrate_v8_to_v9,
+    },
+];
+
 pub fn init(conn: &Connection) -> Result<()> {
     conn.execute_batch(SCHEMA)?;
     conn.execute_batch(TRIGGERS)?;
 
-    let version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+    let version = user_version(conn)?;
     if version < 1 {
-        conn.execute_batch(
+        // A new database: SCHEMA already created the current shape.
+        conn.execute_batch(&format!(
             "INSERT INTO tasks_fts(tasks_fts) VALUES('rebuild');
              INSERT INTO notes_fts(notes_fts) VALUES('rebuild');
-             PRAGMA user_version = 4;",
-        )?;
-    } else if version < 2 {
-        migrate_v1_to_v2(conn)?;
-        migrate_v2_to_v3(conn)?;
-        migrate_v3_to_v4(conn)?;
-    } else if version < 3 {
-        migrate_v2_to_v3(conn)?;
-        migrate_v3_to_v4(conn)?;
-    } else if version < 4 {
-        migrate_v3_to_v4(conn)?;
+             PRAGMA user_version = {SCHEMA_VERSION};"
+        ))?;
+    } else {
+        run_migrations(conn, MIGRATIONS, SCHEMA_VERSION)?;
     }
 
     Ok(())
 }
 
-fn migrate_v1_to_v2(conn: &Connection) -> Result<()> {
+fn user_version(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("PRAGMA user_version", [], |r| r.get(0))?)
+}
+
+/// Apply each migration newer than the database and no newer than
+/// `target`, in order.
+fn run_migrations(conn: &Connection, migrations: &[Migration], target: i64) -> Result<()> {
+    let current = user_version(conn)?;
+    for migration in migrations
+        .iter()
+        .filter(|m| m.version > current && m.version <= target)
+    {
+        apply_migration(conn, migration).with_context(|| {
+            format!("migrating database to schema version {}", migration.version)
+        })?;
+    }
+    Ok(())
+}
+
+/// Run one migration in its own transaction, bumping `user_version` with
+/// it, so a failure leaves the database at the previous version.
+fn apply_migration(conn: &Connection, migration: &Migration) -> Result<()> {
     // SQLite requires foreign_keys OFF for table-rebuild migrations.
     // The pragma cannot be changed inside a transaction.
     // See: https://www.sqlite.org/lang_altertable.html#otheralter
     conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
 
     let result = (|| -> Result<()> {
-        conn.execute_batch(
-            "BEGIN;
-
-            CREATE TABLE tasks_new (
-                id                 INTEGER PRIMARY KEY,
-                name               TEXT NOT NULL UNIQUE CHECK(name GLOB '[a-zA-Z0-9_-]*' AND length(name) > 0),
-                parent             TEXT REFERENCES tasks_new(name) ON UPDATE CASCADE ON DELETE RESTRICT,
-                description        TEXT NOT NULL DEFAULT '',
-                status             TEXT NOT NULL DEFAULT 'open' CHECK(status IN ('open', 'paused', 'active', 'done')),
-                assignee           TEXT,
-                status_changed_at  TEXT,
-                created_at         TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
-                updated_at         TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
-                CHECK((status = 'active') = (assignee IS NOT NULL))
-            );
-
-            INSERT INTO tasks_new (id, name, parent, description, status, assignee, status_changed_at, created_at, updated_at)
-                SELECT id, name, parent, description,
-                       CASE WHEN done = 1 THEN 'done'
-                            WHEN assignee IS NOT NULL THEN 'active'
-                            ELSE 'open'
-                       END,
-                       CASE WHEN done = 1 THEN NULL ELSE assignee END,
-                       assigned_at,
-                       created_at,
-                       updated_at
-                FROM tasks;
-
-            DROP TABLE tasks;
-            ALTER TABLE tasks_new RENAME TO tasks;
-
-            PRAGMA user_version = 2;",
-        )?;
+        conn.execute_batch("BEGIN;")?;
+        (migration.up)(conn)?;
+        conn.execute_batch(&format!("PRAGMA user_version = {};", migration.version))?;
 
         // Verify FK integrity before committing
         let fk_violations: Vec<String> = conn
@@ -172,7 +243,39 @@ fn migrate_v1_to_v2(conn: &Connection) -> Result<()> {
     }
 
     conn.execute_batch("PRAGMA foreign_keys = ON;")?;
-    result?;
+    result
+}
+
+fn migrate_v1_to_v2(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE tasks_new (
+            id                 INTEGER PRIMARY KEY,
+            name               TEXT NOT NULL UNIQUE CHECK(name GLOB '[a-zA-Z0-9_-]*' AND length(name) > 0),
+            parent             TEXT REFERENCES tasks_new(name) ON UPDATE CASCADE ON DELETE RESTRICT,
+            description        TEXT NOT NULL DEFAULT '',
+            status             TEXT NOT NULL DEFAULT 'open' CHECK(status IN ('open', 'paused', 'active', 'done')),
+            assignee           TEXT,
+            status_changed_at  TEXT,
+            created_at         TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            updated_at         TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            CHECK((status = 'active') = (assignee IS NOT NULL))
+        );
+
+        INSERT INTO tasks_new (id, name, parent, description, status, assignee, status_changed_at, created_at, updated_at)
+            SELECT id, name, parent, description,
+                   CASE WHEN done = 1 THEN 'done'
+                        WHEN assignee IS NOT NULL THEN 'active'
+                        ELSE 'open'
+                   END,
+                   CASE WHEN done = 1 THEN NULL ELSE assignee END,
+                   assigned_at,
+                   created_at,
+                   updated_at
+            FROM tasks;
+
+        DROP TABLE tasks;
+        ALTER TABLE tasks_new RENAME TO tasks;",
+    )?;
 
     // Recreate triggers (DROP TABLE killed them)
     conn.execute_batch(TRIGGERS)?;
@@ -184,22 +287,67 @@ fn migrate_v1_to_v2(conn: &Connection) -> Result<()> {
 }
 
 fn migrate_v2_to_v3(conn: &Connection) -> Result<()> {
-    conn.execute_batch(
-        "ALTER TABLE tasks ADD COLUMN agent TEXT;
-         PRAGMA user_version = 3;",
-    )?;
+    conn.execute_batch("ALTER TABLE tasks ADD COLUMN agent TEXT;")?;
     Ok(())
 }
 
 fn migrate_v3_to_v4(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE tasks ADD COLUMN directory TEXT;")?;
+    Ok(())
+}
+
+fn migrate_v4_to_v5(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE tasks ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;")?;
+    Ok(())
+}
+
+fn migrate_v5_to_v6(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE tasks ADD COLUMN needs_human INTEGER NOT NULL DEFAULT 0;")?;
+    Ok(())
+}
+
+fn migrate_v6_to_v7(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE notes ADD COLUMN label TEXT;")?;
+    Ok(())
+}
+
+/// Columns `tasks_fts` is declared with in `SCHEMA`.
+const TASKS_FTS_COLUMNS: [&str; 3] = ["name", "description", "assignee"];
+
+fn migrate_v7_to_v8(conn: &Connection) -> Result<()> {
+    rebuild_tasks_fts_if_stale(conn)
+}
+
+fn migrate_v8_to_v9(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE tasks ADD COLUMN estimate INTEGER CHECK(estimate >= 0);")?;
+    Ok(())
+}
+
+/// FTS5 tables can't gain columns, so when `tasks_fts` was created with a
+/// different column set than `SCHEMA` declares, drop it along with its
+/// triggers, recreate both, and reindex every task.
+fn rebuild_tasks_fts_if_stale(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('tasks_fts')")?;
+    let columns = stmt
+        .query_map([], |r| r.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if columns == TASKS_FTS_COLUMNS {
+        return Ok(());
+    }
     conn.execute_batch(
-        "ALTER TABLE tasks ADD COLUMN directory TEXT;
-         PRAGMA user_version = 4;",
+        "DROP TRIGGER IF EXISTS tasks_fts_ai;
+         DROP TRIGGER IF EXISTS tasks_fts_ad;
+         DROP TRIGGER IF EXISTS tasks_fts_au;
+         DROP TABLE IF EXISTS tasks_fts;",
     )?;
+    conn.execute_batch(SCHEMA)?;
+    conn.execute_batch(TRIGGERS)?;
+    conn.execute_batch("INSERT INTO tasks_fts(tasks_fts) VALUES('rebuild');")?;
     Ok(())
 }
 
 /// Open an in-memory database for tests. Available to all crate targets.
+/// In-memory databases have no journal file, so WAL is not requested.
 pub fn open_memory() -> Result<Connection> {
     let conn = Connection::open_in_memory()?;
     set_pragmas(&conn)?;
@@ -266,7 +414,7 @@ mod tests {
         let version: i64 = conn
             .query_row("PRAGMA user_version", [], |r| r.get(0))
             .unwrap();
-        assert_eq!(version, 4);
+        assert_eq!(version, SCHEMA_VERSION);
     }
 
     #[test]
@@ -388,14 +536,70 @@ mod tests {
     }
 
     #[test]
-    fn fresh_db_gets_version_4() {
+    fn fresh_db_gets_version_5() {
         let conn = Connection::open_in_memory().unwrap();
         set_pragmas(&conn).unwrap();
         init(&conn).unwrap();
         let version: i64 = conn
             .query_row("PRAGMA user_version", [], |r| r.get(0))
             .unwrap();
-        assert_eq!(version, 4);
+        assert_eq!(version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn parse_synchronous_accepts_known_levels() {
+        assert_eq!(parse_synchronous("normal").unwrap(), "NORMAL");
+        assert_eq!(parse_synchronous(" FULL ").unwrap(), "FULL");
+        assert_eq!(parse_synchronous("off").unwrap(), "OFF");
+        assert_eq!(parse_synchronous("extra").unwrap(), "EXTRA");
+        let err = parse_synchronous("fast").unwrap_err().to_string();
+        assert!(err.contains("KBTZ_SYNCHRONOUS"), "{err}");
+    }
+
+    #[test]
+    fn open_switches_existing_file_to_wal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kbtz.db");
+        let path = path.to_str().unwrap();
+        {
+            // A database left in rollback-journal mode by an older build.
+            let conn = Connection::open(path).unwrap();
+            conn.execute_batch("PRAGMA journal_mode = DELETE;").unwrap();
+            set_pragmas(&conn).unwrap();
+            init(&conn).unwrap();
+            conn.execute("INSERT INTO tasks (name) VALUES ('kept')", [])
+                .unwrap();
+        }
+
+        for _ in 0..2 {
+            let conn = open(path).unwrap();
+            init(&conn).unwrap();
+            let mode: String = conn
+                .query_row("PRAGMA journal_mode", [], |r| r.get(0))
+                .unwrap();
+            assert_eq!(mode, "wal");
+            if std::env::var_os("KBTZ_SYNCHRONOUS").is_none() {
+                let sync: i64 = conn
+                    .query_row("PRAGMA synchronous", [], |r| r.get(0))
+                    .unwrap();
+                assert_eq!(sync, 1, "NORMAL");
+            }
+            let count: i64 = conn
+                .query_row("SELECT COUNT(*) FROM tasks WHERE name = 'kept'", [], |r| {
+                    r.get(0)
+                })
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+    }
+
+    #[test]
+    fn open_memory_skips_wal() {
+        let conn = open_memory().unwrap();
+        let mode: String = conn
+            .query_row("PRAGMA journal_mode", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(mode, "memory");
     }
 
     #[test]
@@ -408,7 +612,7 @@ mod tests {
         let version: i64 = conn
             .query_row("PRAGMA user_version", [], |r| r.get(0))
             .unwrap();
-        assert_eq!(version, 4);
+        assert_eq!(version, SCHEMA_VERSION);
     }
 
     /// Create an in-memory v2 database (no agent or directory columns).
@@ -462,7 +666,7 @@ mod tests {
     /// Create an in-memory v3 database (has agent column, no directory column).
     fn open_v3_memory() -> Connection {
         let conn = open_v2_memory();
-        migrate_v2_to_v3(&conn).unwrap();
+        run_migrations(&conn, MIGRATIONS, 3).unwrap();
         conn
     }
 
@@ -476,7 +680,7 @@ mod tests {
         let version: i64 = conn
             .query_row("PRAGMA user_version", [], |r| r.get(0))
             .unwrap();
-        assert_eq!(version, 4);
+        assert_eq!(version, SCHEMA_VERSION);
 
         // agent column exists and is NULL for existing rows
         let agent: Option<String> = conn
@@ -494,7 +698,7 @@ mod tests {
         let version: i64 = conn
             .query_row("PRAGMA user_version", [], |r| r.get(0))
             .unwrap();
-        assert_eq!(version, 4);
+        assert_eq!(version, SCHEMA_VERSION);
     }
 
     #[test]
@@ -507,7 +711,7 @@ mod tests {
         let version: i64 = conn
             .query_row("PRAGMA user_version", [], |r| r.get(0))
             .unwrap();
-        assert_eq!(version, 4);
+        assert_eq!(version, SCHEMA_VERSION);
 
         // directory column exists and is nullable
         conn.execute(
@@ -522,4 +726,248 @@ mod tests {
             .unwrap();
         assert_eq!(dir.as_deref(), Some("/tmp"));
     }
+
+    #[test]
+    fn migrate_v4_to_v5_adds_priority_column() {
+        let conn = open_v3_memory();
+        run_migrations(&conn, MIGRATIONS, 4).unwrap();
+        conn.execute_batch("INSERT INTO tasks (name, description) VALUES ('test', 'a task');")
+            .unwrap();
+
+        init(&conn).unwrap();
+        let version: i64 = conn
+            .query_row("PRAGMA user_version", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+
+        // Existing rows get the default priority
+        let priority: i64 = conn
+            .query_row("SELECT priority FROM tasks WHERE name = 'test'", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(priority, 0);
+    }
+
+    #[test]
+    fn migrate_v5_to_v6_adds_needs_human_column() {
+        let conn = open_v3_memory();
+        run_migrations(&conn, MIGRATIONS, 5).unwrap();
+        conn.execute_batch("INSERT INTO tasks (name, description) VALUES ('test', 'a task');")
+            .unwrap();
+
+        init(&conn).unwrap();
+        let version: i64 = conn
+            .query_row("PRAGMA user_version", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+
+        let needs_human: bool = conn
+            .query_row(
+                "SELECT needs_human FROM tasks WHERE name = 'test'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert!(!needs_human);
+    }
+
+    #[test]
+    fn migrate_v6_to_v7_adds_note_label_column() {
+        let conn = open_v3_memory();
+        run_migrations(&conn, MIGRATIONS, 6).unwrap();
+        conn.execute_batch(
+            "INSERT INTO tasks (name, description) VALUES ('test', 'a task');
+             INSERT INTO notes (task, content) VALUES ('test', 'old note');",
+        )
+        .unwrap();
+
+        init(&conn).unwrap();
+        let version: i64 = conn
+            .query_row("PRAGMA user_version", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+
+        let label: Option<String> = conn
+            .query_row("SELECT label FROM notes WHERE task = 'test'", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(label, None);
+    }
+
+    /// The `tasks` column added by the v9 migration, as `SCHEMA` declares it.
+    const V9_ESTIMATE_COLUMN: &str = "    estimate           INTEGER CHECK(estimate >= 0),\n";
+
+    #[test]
+    fn migrate_v8_to_v9_adds_estimate() {
+        let conn = Connection::open_in_memory().unwrap();
+        set_pragmas(&conn).unwrap();
+        conn.execute_batch(&SCHEMA.replace(V9_ESTIMATE_COLUMN, ""))
+            .unwrap();
+        conn.execute_batch(TRIGGERS).unwrap();
+        conn.execute_batch(
+            "INSERT INTO tasks (name) VALUES ('old');
+             PRAGMA user_version = 8;",
+        )
+        .unwrap();
+
+        init(&conn).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), SCHEMA_VERSION);
+        let estimate: Option<i64> = conn
+            .query_row("SELECT estimate FROM tasks WHERE name = 'old'", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(estimate, None);
+        conn.execute("UPDATE tasks SET estimate = 5 WHERE name = 'old'", [])
+            .unwrap();
+        assert!(conn
+            .execute("UPDATE tasks SET estimate = -1 WHERE name = 'old'", [])
+            .is_err());
+    }
+
+    #[test]
+    fn migrate_v7_to_v8_reindexes_assignee() {
+        let conn = Connection::open_in_memory().unwrap();
+        // A v7 database: tasks_fts without assignee and no estimate column.
+        conn.execute_batch(
+            &SCHEMA
+                .replace("name, description, assignee,", "name, description,")
+                .replace(V9_ESTIMATE_COLUMN, ""),
+        )
+        .unwrap();
+        conn.execute_batch(
+            "CREATE TRIGGER tasks_fts_ai AFTER INSERT ON tasks BEGIN
+                 INSERT INTO tasks_fts(rowid, name, description)
+                 VALUES(new.id, new.name, new.description);
+             END;
+             INSERT INTO tasks (name, description, status, assignee)
+             VALUES ('held', 'a task', 'active', 'alice');
+             PRAGMA user_version = 7;",
+        )
+        .unwrap();
+
+        init(&conn).unwrap();
+        let version: i64 = conn
+            .query_row("PRAGMA user_version", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        let found: String = conn
+            .query_row(
+                "SELECT name FROM tasks_fts WHERE tasks_fts MATCH 'alice'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(found, "held");
+
+        // Triggers were recreated for the new column set.
+        conn.execute_batch("UPDATE tasks SET status = 'open', assignee = NULL")
+            .unwrap();
+        let count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM tasks_fts WHERE tasks_fts MATCH 'alice'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn migrations_are_contiguous_and_end_at_schema_version() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as i64 + 2);
+        }
+        assert_eq!(MIGRATIONS.last().unwrap().version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn failed_migration_rolls_back_to_previous_version() {
+        let conn = open_v2_memory();
+        let steps = [
+            Migration {
+                version: 3,
+                up: |conn| Ok(conn.execute_batch("ALTER TABLE tasks ADD COLUMN a TEXT")?),
+            },
+            Migration {
+                version: 4,
+                up: |conn| {
+                    conn.execute_batch("ALTER TABLE tasks ADD COLUMN b TEXT")?;
+                    anyhow::bail!("boom")
+                },
+            },
+        ];
+        let err = run_migrations(&conn, &steps, 4).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "migrating database to schema version 4: boom"
+        );
+        assert_eq!(user_version(&conn).unwrap(), 3);
+        let columns: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_table_info('tasks')")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert!(columns.contains(&"a".to_string()));
+        assert!(!columns.contains(&"b".to_string()));
+        // Foreign keys are back on after the failed step.
+        let fk: bool = conn
+            .query_row("PRAGMA foreign_keys", [], |r| r.get(0))
+            .unwrap();
+        assert!(fk);
+    }
+
+    #[test]
+    fn migrate_v1_to_current_keeps_all_data() {
+        let conn = open_v1_memory();
+        conn.execute_batch(
+            "INSERT INTO tasks (name, description, created_at) VALUES ('a', 'task a', '2024-01-01T00:00:00Z');
+             INSERT INTO tasks (name, parent, description, assignee, assigned_at)
+                 VALUES ('b', 'a', 'task b', 'agent', '2024-02-01T00:00:00Z');
+             INSERT INTO notes (task, content) VALUES ('b', 'a note on b');
+             INSERT INTO task_deps (blocker, blocked) VALUES ('a', 'b');",
+        )
+        .unwrap();
+
+        init(&conn).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), SCHEMA_VERSION);
+
+        type Row = (String, Option<String>, String, Option<String>, String);
+        let rows: Vec<Row> = conn
+            .prepare("SELECT name, parent, status, assignee, created_at FROM tasks ORDER BY id")
+            .unwrap()
+            .query_map([], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(rows[0].0, "a");
+        assert_eq!(rows[0].4, "2024-01-01T00:00:00Z");
+        assert_eq!(rows[1].1.as_deref(), Some("a"));
+        assert_eq!(rows[1].2, "active");
+        assert_eq!(rows[1].3.as_deref(), Some("agent"));
+
+        let (notes, deps): (i64, i64) = conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM notes), (SELECT COUNT(*) FROM task_deps)",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((notes, deps), (1, 1));
+        // Search covers columns added along the way.
+        let found: String = conn
+            .query_row(
+                "SELECT name FROM tasks_fts WHERE tasks_fts MATCH 'agent'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(found, "b");
+    }
 }
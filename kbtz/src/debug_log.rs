@@ -1,22 +1,187 @@
+use std::fmt;
 use std::io::Write;
+use std::panic::Location;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 static START: OnceLock<Instant> = OnceLock::new();
+static CONFIG: OnceLock<Option<Config>> = OnceLock::new();
 
-/// Append a timestamped line to the file specified by `KBTZ_DEBUG`.
-/// No-op if the env var is unset.
-pub fn log(msg: &str) {
-    let Ok(path) = std::env::var("KBTZ_DEBUG") else {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Trace => "trace",
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Level> {
+        match s {
+            "trace" => Some(Level::Trace),
+            "debug" => Some(Level::Debug),
+            "info" => Some(Level::Info),
+            "warn" => Some(Level::Warn),
+            "error" => Some(Level::Error),
+            _ => None,
+        }
+    }
+}
+
+struct Config {
+    path: PathBuf,
+    min_level: Level,
+    json: bool,
+}
+
+/// Parse `KBTZ_LOG`: comma-separated tokens naming the minimum level
+/// and/or `json` (e.g. `debug`, `warn,json`). Unknown tokens are ignored;
+/// the default is `info` in plain format.
+fn parse_spec(spec: &str) -> (Level, bool) {
+    let mut level = Level::Info;
+    let mut json = false;
+    for token in spec.split(',').map(|t| t.trim().to_ascii_lowercase()) {
+        if token == "json" {
+            json = true;
+        } else if let Some(l) = Level::parse(&token) {
+            level = l;
+        }
+    }
+    (level, json)
+}
+
+/// Read the environment once; every later call only checks the cache.
+fn config() -> Option<&'static Config> {
+    CONFIG
+        .get_or_init(|| {
+            let path = std::env::var_os("KBTZ_DEBUG")?;
+            let (min_level, json) = parse_spec(&std::env::var("KBTZ_LOG").unwrap_or_default());
+            Some(Config {
+                path: path.into(),
+                min_level,
+                json,
+            })
+        })
+        .as_ref()
+}
+
+/// Whether a message at `level` would be written. Use this to skip
+/// expensive work that only feeds a log line.
+pub fn enabled(level: Level) -> bool {
+    config().is_some_and(|c| level >= c.min_level)
+}
+
+/// The caller's source file stem, e.g. `app` for `src/app.rs`.
+fn module_of(location: &Location<'static>) -> &'static str {
+    Path::new(location.file())
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("?")
+}
+
+fn format_line(
+    json: bool,
+    level: Level,
+    module: &str,
+    elapsed_secs: f64,
+    unix_secs: f64,
+    msg: &str,
+) -> String {
+    if json {
+        serde_json::json!({
+            "ts": unix_secs,
+            "elapsed": elapsed_secs,
+            "level": level.as_str(),
+            "module": module,
+            "msg": msg,
+        })
+        .to_string()
+    } else {
+        let level = level.as_str().to_ascii_uppercase();
+        format!("[{elapsed_secs:>8.3}s] {level:<5} {msg}")
+    }
+}
+
+/// Append a line at `level` to the file specified by `KBTZ_DEBUG`, in the
+/// format selected by `KBTZ_LOG`. No-op if the env var is unset or the
+/// level is filtered out; `msg` is only formatted when the line is
+/// written, so pass `format_args!(...)` on hot paths.
+#[track_caller]
+pub fn log_at(level: Level, msg: impl fmt::Display) {
+    let Some(config) = config() else {
         return;
     };
+    if level < config.min_level {
+        return;
+    }
     let elapsed = START.get_or_init(Instant::now).elapsed();
+    let unix = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let line = format_line(
+        config.json,
+        level,
+        module_of(Location::caller()),
+        elapsed.as_secs_f64(),
+        unix.as_secs_f64(),
+        &msg.to_string(),
+    );
     let Ok(mut f) = std::fs::OpenOptions::new()
         .create(true)
         .append(true)
-        .open(&path)
+        .open(&config.path)
     else {
         return;
     };
-    let _ = writeln!(f, "[{:>8.3}s] {}", elapsed.as_secs_f64(), msg);
+    let _ = writeln!(f, "{line}");
+}
+
+/// Log `msg` at `Level::Info`.
+#[track_caller]
+pub fn log(msg: &str) {
+    log_at(Level::Info, msg);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_spec_reads_level_and_format() {
+        assert_eq!(parse_spec(""), (Level::Info, false));
+        assert_eq!(parse_spec("debug"), (Level::Debug, false));
+        assert_eq!(parse_spec("WARN, json"), (Level::Warn, true));
+        assert_eq!(parse_spec("json,bogus"), (Level::Info, true));
+    }
+
+    #[test]
+    fn format_line_plain_and_json() {
+        assert_eq!(
+            format_line(false, Level::Info, "app", 1.5, 0.0, "hello"),
+            "[   1.500s] INFO  hello"
+        );
+        let line = format_line(true, Level::Debug, "app", 1.5, 1000.25, "say \"hi\"");
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["level"], "debug");
+        assert_eq!(value["module"], "app");
+        assert_eq!(value["ts"], 1000.25);
+        assert_eq!(value["msg"], "say \"hi\"");
+    }
+
+    #[test]
+    fn module_of_uses_file_stem() {
+        assert_eq!(module_of(Location::caller()), "debug_log");
+    }
 }
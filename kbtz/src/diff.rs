@@ -0,0 +1,564 @@
+//! Plan the commands that turn the current database into a target
+//! snapshot (`kbtz diff`). Only tasks, parents, statuses and dependency
+//! edges are compared; notes and other task fields are left alone.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use anyhow::{bail, Result};
+use rusqlite::Connection;
+
+use crate::model::{ExportDoc, Task, EXPORT_VERSION};
+use crate::ops;
+use crate::validate::validate_name;
+
+/// One step of a plan. Each maps to a single `kbtz exec` line and to the
+/// `ops` call that line would make.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffOp {
+    Add {
+        name: String,
+        parent: Option<String>,
+        description: String,
+    },
+    Reparent {
+        name: String,
+        parent: Option<String>,
+    },
+    Unblock {
+        blocker: String,
+        blocked: String,
+    },
+    Remove {
+        name: String,
+    },
+    Block {
+        blocker: String,
+        blocked: String,
+    },
+    Done {
+        name: String,
+    },
+    Reopen {
+        name: String,
+    },
+    Pause {
+        name: String,
+    },
+    Unpause {
+        name: String,
+    },
+    Claim {
+        name: String,
+        assignee: String,
+    },
+    Steal {
+        name: String,
+        assignee: String,
+    },
+    ForceUnassign {
+        name: String,
+    },
+}
+
+/// Quote an exec argument if the tokenizer would otherwise split or drop it.
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && !arg.starts_with('#')
+        && !arg
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '<');
+    if plain {
+        return arg.to_string();
+    }
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A multi-line description as an exec heredoc: the `<<DELIM` token for
+/// the command line, and the body plus closing delimiter to follow it.
+/// The delimiter is chosen so no line of the body closes it early.
+fn heredoc(text: &str) -> (String, String) {
+    let mut delimiter = "EOF".to_string();
+    let mut n = 0;
+    while text.lines().any(|line| line == delimiter) {
+        n += 1;
+        delimiter = format!("EOF{n}");
+    }
+    (format!("<<{delimiter}"), format!("\n{text}\n{delimiter}"))
+}
+
+impl DiffOp {
+    /// The equivalent `kbtz exec` line. A multi-line description is given
+    /// as a heredoc, so the result then spans several lines.
+    pub fn to_exec_line(&self) -> String {
+        match self {
+            DiffOp::Add {
+                name,
+                parent,
+                description,
+            } => {
+                let (arg, body) = if description.contains('\n') {
+                    heredoc(description)
+                } else {
+                    (quote(description), String::new())
+                };
+                match parent {
+                    Some(p) => format!("add {name} {arg} -p {p}{body}"),
+                    None => format!("add {name} {arg}{body}"),
+                }
+            }
+            DiffOp::Reparent { name, parent } => match parent {
+                Some(p) => format!("reparent {name} -p {p}"),
+                None => format!("reparent {name}"),
+            },
+            DiffOp::Unblock { blocker, blocked } => format!("unblock {blocker} {blocked}"),
+            DiffOp::Remove { name } => format!("rm {name}"),
+            DiffOp::Block { blocker, blocked } => format!("block {blocker} {blocked}"),
+            DiffOp::Done { name } => format!("done {name}"),
+            DiffOp::Reopen { name } => format!("reopen {name}"),
+            DiffOp::Pause { name } => format!("pause {name}"),
+            DiffOp::Unpause { name } => format!("unpause {name}"),
+            DiffOp::Claim { name, assignee } => format!("claim {name} {}", quote(assignee)),
+            DiffOp::Steal { name, assignee } => format!("steal {name} {}", quote(assignee)),
+            DiffOp::ForceUnassign { name } => format!("force-unassign {name}"),
+        }
+    }
+
+    pub fn apply(&self, conn: &Connection) -> Result<()> {
+        match self {
+            DiffOp::Add {
+                name,
+                parent,
+                description,
+            } => ops::add_task(
+                conn,
+                ops::AddTaskParams {
+                    name,
+                    parent: parent.as_deref(),
+                    description,
+                    ..Default::default()
+                },
+            ),
+            DiffOp::Reparent { name, parent } => ops::reparent_task(conn, name, parent.as_deref()),
+            DiffOp::Unblock { blocker, blocked } => ops::remove_block(conn, blocker, blocked),
+            DiffOp::Remove { name } => ops::remove_task(conn, name, false),
+            DiffOp::Block { blocker, blocked } => ops::add_block(conn, blocker, blocked),
+            DiffOp::Done { name } => ops::mark_done(conn, name),
+            DiffOp::Reopen { name } => ops::reopen_task(conn, name),
+            DiffOp::Pause { name } => ops::pause_task(conn, name),
+            DiffOp::Unpause { name } => ops::unpause_task(conn, name),
+            DiffOp::Claim { name, assignee } => ops::claim_task(conn, name, assignee),
+            DiffOp::Steal { name, assignee } => ops::steal_task(conn, name, assignee).map(|_| ()),
+            DiffOp::ForceUnassign { name } => ops::force_unassign_task(conn, name),
+        }
+    }
+}
+
+/// Reject targets that no plan could reach: unknown versions, bad or
+/// duplicate names, dangling parents or deps, and unknown statuses.
+fn validate_target(target: &ExportDoc) -> Result<()> {
+    if target.version > EXPORT_VERSION {
+        bail!(
+            "snapshot format v{} is newer than supported v{EXPORT_VERSION}",
+            target.version
+        );
+    }
+    let mut names = HashSet::new();
+    for task in &target.tasks {
+        validate_name(&task.name)?;
+        if !names.insert(task.name.as_str()) {
+            bail!("task '{}' appears more than once in the target", task.name);
+        }
+    }
+    for task in &target.tasks {
+        if let Some(p) = &task.parent {
+            if !names.contains(p.as_str()) {
+                bail!(
+                    "task '{}' has parent '{p}', which is not in the target",
+                    task.name
+                );
+            }
+        }
+        match (task.status.as_str(), &task.assignee) {
+            ("open" | "paused" | "done", _) | ("active", Some(_)) => {}
+            ("active", None) => bail!("task '{}' is active but has no assignee", task.name),
+            (s, _) => bail!("task '{}' has unknown status '{s}'", task.name),
+        }
+    }
+    for dep in &target.deps {
+        for end in [&dep.blocker, &dep.blocked] {
+            if !names.contains(end.as_str()) {
+                bail!(
+                    "dependency '{}' -> '{}' names '{end}', which is not in the target",
+                    dep.blocker,
+                    dep.blocked
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Order `tasks` so every parent comes before its children. Tasks whose
+/// parent isn't in the list count as roots.
+fn parents_first(tasks: &[Task]) -> Vec<&Task> {
+    let by_name: HashMap<&str, &Task> = tasks.iter().map(|t| (t.name.as_str(), t)).collect();
+    let mut placed = HashSet::new();
+    let mut order = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        // Walk up to the highest unplaced ancestor, then place downwards.
+        let mut chain = vec![task];
+        while let Some(parent) = chain
+            .last()
+            .and_then(|t| t.parent.as_deref())
+            .and_then(|p| by_name.get(p))
+        {
+            if placed.contains(parent.name.as_str()) || chain.len() > tasks.len() {
+                break;
+            }
+            chain.push(parent);
+        }
+        for t in chain.into_iter().rev() {
+            if placed.insert(t.name.as_str()) {
+                order.push(t);
+            }
+        }
+    }
+    order
+}
+
+/// Commands that move a task from one status/assignee to another.
+fn status_ops(name: &str, from: (&str, Option<&str>), to: (&str, Option<&str>)) -> Vec<DiffOp> {
+    let name = name.to_string();
+    let (from_status, from_assignee) = from;
+    let (to_status, to_assignee) = to;
+    if from_status == to_status && (to_status != "active" || from_assignee == to_assignee) {
+        return Vec::new();
+    }
+    let mut steps = Vec::new();
+    // Get to open first, unless the target can be reached directly.
+    match (from_status, to_status) {
+        (_, "done") => {
+            steps.push(DiffOp::Done { name });
+            return steps;
+        }
+        ("active", "active") => {
+            steps.push(DiffOp::Steal {
+                name,
+                assignee: to_assignee.unwrap_or_default().to_string(),
+            });
+            return steps;
+        }
+        ("done", _) => steps.push(DiffOp::Reopen { name: name.clone() }),
+        ("paused", _) => steps.push(DiffOp::Unpause { name: name.clone() }),
+        ("active", _) => steps.push(DiffOp::ForceUnassign { name: name.clone() }),
+        _ => {}
+    }
+    match to_status {
+        "paused" => steps.push(DiffOp::Pause { name }),
+        "active" => steps.push(DiffOp::Claim {
+            name,
+            assignee: to_assignee.unwrap_or_default().to_string(),
+        }),
+        _ => {}
+    }
+    steps
+}
+
+/// Compute the commands that turn `current` into `target`, in an order
+/// that applies cleanly: adds and reparents (parents first), dependency
+/// removals, task removals (children first), new dependencies, and
+/// finally status changes. Tasks missing from the target are removed.
+pub fn plan(current: &ExportDoc, target: &ExportDoc) -> Result<Vec<DiffOp>> {
+    validate_target(target)?;
+    let cur: HashMap<&str, &Task> = current.tasks.iter().map(|t| (t.name.as_str(), t)).collect();
+    let want: HashSet<&str> = target.tasks.iter().map(|t| t.name.as_str()).collect();
+    let mut plan = Vec::new();
+
+    for task in parents_first(&target.tasks) {
+        match cur.get(task.name.as_str()) {
+            None => plan.push(DiffOp::Add {
+                name: task.name.clone(),
+                parent: task.parent.clone(),
+                description: task.description.clone(),
+            }),
+            Some(existing) if existing.parent != task.parent => plan.push(DiffOp::Reparent {
+                name: task.name.clone(),
+                parent: task.parent.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    let edges = |doc: &ExportDoc| -> BTreeSet<(String, String)> {
+        doc.deps
+            .iter()
+            .map(|d| (d.blocker.clone(), d.blocked.clone()))
+            .collect()
+    };
+    let (cur_edges, want_edges) = (edges(current), edges(target));
+    for (blocker, blocked) in cur_edges.difference(&want_edges) {
+        // Removing either end drops the edge with it.
+        if want.contains(blocker.as_str()) && want.contains(blocked.as_str()) {
+            plan.push(DiffOp::Unblock {
+                blocker: blocker.clone(),
+                blocked: blocked.clone(),
+            });
+        }
+    }
+
+    let parent_of = |name: &str| cur.get(name).and_then(|t| t.parent.as_deref());
+    let depth = |name: &str| {
+        let mut d = 0;
+        let mut up = parent_of(name);
+        while let Some(p) = up {
+            d += 1;
+            if d > current.tasks.len() {
+                break;
+            }
+            up = parent_of(p);
+        }
+        d
+    };
+    let mut removed: Vec<&Task> = current
+        .tasks
+        .iter()
+        .filter(|t| !want.contains(t.name.as_str()))
+        .collect();
+    removed.sort_by_key(|t| std::cmp::Reverse(depth(&t.name)));
+    for task in removed {
+        plan.push(DiffOp::Remove {
+            name: task.name.clone(),
+        });
+    }
+
+    for (blocker, blocked) in want_edges.difference(&cur_edges) {
+        plan.push(DiffOp::Block {
+            blocker: blocker.clone(),
+            blocked: blocked.clone(),
+        });
+    }
+
+    for task in &target.tasks {
+        // New tasks start open and unassigned.
+        let from = cur.get(task.name.as_str()).map_or(("open", None), |t| {
+            (t.status.as_str(), t.assignee.as_deref())
+        });
+        plan.extend(status_ops(
+            &task.name,
+            from,
+            (task.status.as_str(), task.assignee.as_deref()),
+        ));
+    }
+    Ok(plan)
+}
+
+/// Apply every step of `plan` in one transaction: either the database
+/// reaches the target or nothing changes.
+pub fn apply(conn: &Connection, plan: &[DiffOp]) -> Result<()> {
+    conn.execute_batch("SAVEPOINT diff_apply")?;
+    let result = plan.iter().try_for_each(|op| {
+        op.apply(conn)
+            .map_err(|e| e.context(format!("failed at: {}", op.to_exec_line())))
+    });
+    match result {
+        Ok(()) => {
+            conn.execute_batch("RELEASE diff_apply")?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO diff_apply");
+            let _ = conn.execute_batch("RELEASE diff_apply");
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::model::Dep;
+
+    fn setup() -> Connection {
+        let conn = db::open_memory().unwrap();
+        for (name, parent) in [("root", None), ("a", Some("root")), ("b", Some("root"))] {
+            ops::add_task(
+                &conn,
+                ops::AddTaskParams {
+                    name,
+                    parent,
+                    description: "desc",
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    fn lines(plan: &[DiffOp]) -> Vec<String> {
+        plan.iter().map(DiffOp::to_exec_line).collect()
+    }
+
+    fn task<'a>(doc: &'a mut ExportDoc, name: &str) -> &'a mut Task {
+        doc.tasks.iter_mut().find(|t| t.name == name).unwrap()
+    }
+
+    /// Apply the plan and check the database now diffs clean.
+    fn apply_and_recheck(conn: &Connection, steps: &[DiffOp], target: &ExportDoc) {
+        apply(conn, steps).unwrap();
+        let after = ops::export_all(conn).unwrap();
+        assert_eq!(plan(&after, target).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn identical_snapshot_is_a_no_op() {
+        let conn = setup();
+        let doc = ops::export_all(&conn).unwrap();
+        assert!(plan(&doc, &doc).unwrap().is_empty());
+    }
+
+    #[test]
+    fn add_only_diff_adds_parents_first() {
+        let conn = setup();
+        let current = ops::export_all(&conn).unwrap();
+        let mut target = current.clone();
+        let mut child = current.tasks[1].clone();
+        child.name = "grandchild".into();
+        child.parent = Some("new-parent".into());
+        child.description = "say \"hi\"".into();
+        let mut parent = current.tasks[1].clone();
+        parent.name = "new-parent".into();
+        parent.parent = None;
+        parent.description = "top".into();
+        // Listed child-first; the plan still creates the parent first.
+        target.tasks.push(child);
+        target.tasks.push(parent);
+        target.deps.push(Dep {
+            blocker: "a".into(),
+            blocked: "grandchild".into(),
+        });
+
+        let steps = plan(&current, &target).unwrap();
+        assert_eq!(
+            lines(&steps),
+            vec![
+                "add new-parent top",
+                r#"add grandchild "say \"hi\"" -p new-parent"#,
+                "block a grandchild",
+            ]
+        );
+        apply_and_recheck(&conn, &steps, &target);
+        let added = ops::get_task(&conn, "grandchild").unwrap();
+        assert_eq!(added.description, "say \"hi\"");
+    }
+
+    #[test]
+    fn status_change_diff() {
+        let conn = setup();
+        ops::pause_task(&conn, "b").unwrap();
+        let current = ops::export_all(&conn).unwrap();
+        let mut target = current.clone();
+        task(&mut target, "root").status = "done".into();
+        let a = task(&mut target, "a");
+        a.status = "active".into();
+        a.assignee = Some("agent 1".into());
+        task(&mut target, "b").status = "open".into();
+
+        let steps = plan(&current, &target).unwrap();
+        assert_eq!(
+            lines(&steps),
+            vec!["done root", r#"claim a "agent 1""#, "unpause b"]
+        );
+        apply_and_recheck(&conn, &steps, &target);
+
+        // Back again: done -> paused reopens first; active -> open unassigns.
+        let current = ops::export_all(&conn).unwrap();
+        let mut target = current.clone();
+        task(&mut target, "root").status = "paused".into();
+        let a = task(&mut target, "a");
+        a.status = "open".into();
+        a.assignee = None;
+        let steps = plan(&current, &target).unwrap();
+        assert_eq!(
+            lines(&steps),
+            vec!["reopen root", "pause root", "force-unassign a"]
+        );
+        apply_and_recheck(&conn, &steps, &target);
+    }
+
+    #[test]
+    fn reparent_diff_orders_moves_to_avoid_cycles() {
+        let conn = setup();
+        let current = ops::export_all(&conn).unwrap();
+        // Flip the tree: "a" becomes the root with "root" under it.
+        let mut target = current.clone();
+        task(&mut target, "a").parent = None;
+        task(&mut target, "root").parent = Some("a".into());
+        task(&mut target, "b").parent = Some("a".into());
+
+        let steps = plan(&current, &target).unwrap();
+        assert_eq!(
+            lines(&steps),
+            vec!["reparent a", "reparent root -p a", "reparent b -p a"]
+        );
+        apply_and_recheck(&conn, &steps, &target);
+    }
+
+    #[test]
+    fn missing_tasks_and_deps_are_removed_children_first() {
+        let conn = setup();
+        ops::add_block(&conn, "a", "b").unwrap();
+        let current = ops::export_all(&conn).unwrap();
+        let mut target = current.clone();
+        target.tasks.retain(|t| t.name == "b");
+        target.tasks[0].parent = None;
+        target.deps.clear();
+
+        let steps = plan(&current, &target).unwrap();
+        // The a -> b edge goes away with "a"; no separate unblock.
+        assert_eq!(lines(&steps), vec!["reparent b", "rm a", "rm root"]);
+        apply_and_recheck(&conn, &steps, &target);
+    }
+
+    #[test]
+    fn invalid_targets_are_rejected() {
+        let conn = setup();
+        let current = ops::export_all(&conn).unwrap();
+
+        let mut target = current.clone();
+        task(&mut target, "a").parent = Some("ghost".into());
+        let err = plan(&current, &target).unwrap_err().to_string();
+        assert!(err.contains("parent 'ghost'"), "{err}");
+
+        let mut target = current.clone();
+        task(&mut target, "a").status = "active".into();
+        let err = plan(&current, &target).unwrap_err().to_string();
+        assert!(err.contains("no assignee"), "{err}");
+
+        let mut target = current.clone();
+        target.deps.push(Dep {
+            blocker: "a".into(),
+            blocked: "ghost".into(),
+        });
+        assert!(plan(&current, &target).is_err());
+    }
+
+    #[test]
+    fn failed_apply_changes_nothing() {
+        let conn = setup();
+        let current = ops::export_all(&conn).unwrap();
+        let steps = vec![
+            DiffOp::Done { name: "a".into() },
+            DiffOp::Done {
+                name: "ghost".into(),
+            },
+        ];
+        let err = apply(&conn, &steps).unwrap_err();
+        assert!(format!("{err:#}").contains("failed at: done ghost"));
+        assert_eq!(ops::get_task(&conn, "a").unwrap().status, "open");
+        assert!(plan(&ops::export_all(&conn).unwrap(), &current)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::{IsTerminal, Write as _};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+
+/// Opens `$EDITOR` on a temp file holding `initial_content`, waits for it to
+/// exit, and returns the saved content. The caller owns the terminal: a TUI
+/// must leave raw mode and the alternate screen first.
+pub fn edit_text(initial_content: &str) -> Result<String> {
+    let editor = std::env::var("EDITOR").context("$EDITOR is not set")?;
+    edit_with(&editor, initial_content)
+}
+
+fn edit_with(editor: &str, initial_content: &str) -> Result<String> {
+    let mut tmp = tempfile::Builder::new()
+        .prefix("kbtz-")
+        .suffix(".md")
+        .tempfile()
+        .context("failed to create temp file")?;
+    tmp.write_all(initial_content.as_bytes())
+        .context("failed to write to temp file")?;
+    tmp.flush()?;
+
+    let path = tmp.path().to_path_buf();
+    let status = Command::new(editor)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("failed to run editor '{editor}'"))?;
+    if !status.success() {
+        bail!("editor exited with status {status}");
+    }
+
+    std::fs::read_to_string(&path).context("failed to read temp file after editor closed")
+}
+
+/// `edit_text` for plain CLI commands, which need stdin and stdout to be a
+/// terminal for the editor to take over.
+pub fn edit_text_interactive(initial_content: &str) -> Result<String> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        if std::env::var_os("EDITOR").is_none() {
+            bail!("$EDITOR is not set and stdin is not a terminal; pass the text inline instead");
+        }
+        bail!("stdin and stdout must be a terminal to open $EDITOR");
+    }
+    edit_text(initial_content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_text_returns_saved_content() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A fake editor that appends to the file it is given.
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("editor.sh");
+        std::fs::write(&script, "#!/bin/sh\nprintf ' edited' >> \"$1\"\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let edited = edit_with(script.to_str().unwrap(), "original").unwrap();
+        assert_eq!(edited, "original edited");
+    }
+
+    #[test]
+    fn edit_with_reports_editor_failure() {
+        let err = edit_with("false", "original").unwrap_err();
+        assert!(err.to_string().contains("editor exited with status"));
+    }
+}
@@ -1,6 +1,8 @@
 pub mod config;
 pub mod db;
 pub mod debug_log;
+pub mod diff;
+pub mod editor;
 pub mod model;
 pub mod ops;
 pub mod output;
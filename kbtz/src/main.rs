@@ -1,13 +1,16 @@
 mod cli;
 
+use std::collections::HashMap;
 use std::io::{IsTerminal, Read as _};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 use anyhow::{bail, Context, Result};
 use clap::Parser;
 use rusqlite::Connection;
 
 use cli::{Cli, Command};
+use kbtz::model::{ExportDoc, Task};
 use kbtz::{config, db, ops, output, tui, watch};
 use ops::StatusFilter;
 
@@ -45,6 +48,11 @@ fn open_db(db_path: &str) -> Result<Connection> {
     Ok(conn)
 }
 
+const UNBLOCK_USAGE: &str = concat!(
+    "usage: unblock <blocker> <blocked> | unblock <blocker> --all | ",
+    "unblock --blocked <name> --all"
+);
+
 /// Dispatch a single parsed command against an open database connection.
 /// Used both for direct invocations and within `exec` batches.
 fn dispatch(conn: &Connection, command: Command) -> Result<()> {
@@ -58,8 +66,12 @@ fn dispatch(conn: &Connection, command: Command) -> Result<()> {
             paused,
             agent,
             directory,
+            priority,
+            estimate,
             json,
+            time_format,
         } => {
+            let time_format = output::TimeFormat::parse(&time_format)?;
             ops::add_task(
                 conn,
                 ops::AddTaskParams {
@@ -71,20 +83,24 @@ fn dispatch(conn: &Connection, command: Command) -> Result<()> {
                     paused,
                     agent: agent.as_deref(),
                     directory: directory.as_deref(),
+                    priority,
+                    estimate: estimate.map(i64::from),
                 },
             )?;
             if json {
                 let task = ops::get_task(conn, &name)?;
+                let ancestors = ops::get_ancestors(conn, &name)?;
                 let notes = ops::list_notes(conn, &name)?;
                 let blockers = ops::get_blockers(conn, &name)?;
                 let dependents = ops::get_dependents(conn, &name)?;
                 let detail = output::TaskDetail {
                     task: &task,
+                    path: &ancestors,
                     notes: &notes,
                     blocked_by: &blockers,
                     blocks: &dependents,
                 };
-                println!("{}", serde_json::to_string_pretty(&detail)?);
+                output::print_json(&detail, time_format)?;
             }
             eprintln!("Added task '{name}'");
             if paused {
@@ -102,33 +118,36 @@ fn dispatch(conn: &Connection, command: Command) -> Result<()> {
         Command::ClaimNext {
             assignee,
             prefer,
+            root,
             json,
-        } => match ops::claim_next_task(conn, &assignee, prefer.as_deref(), None)? {
-            Some(name) => {
-                let task = ops::get_task(conn, &name)?;
-                let notes = ops::list_notes(conn, &name)?;
-                let blockers = ops::get_blockers(conn, &name)?;
-                let dependents = ops::get_dependents(conn, &name)?;
-                if json {
-                    let detail = output::TaskDetail {
-                        task: &task,
-                        notes: &notes,
-                        blocked_by: &blockers,
-                        blocks: &dependents,
-                    };
-                    println!("{}", serde_json::to_string_pretty(&detail)?);
-                } else {
-                    print!(
-                        "{}",
-                        output::format_task_detail(&task, &notes, &blockers, &dependents)
-                    );
+            time_format,
+        } => {
+            let time_format = output::TimeFormat::parse(&time_format)?;
+            let claimed =
+                ops::claim_next_task(conn, &assignee, prefer.as_deref(), root.as_deref(), None)?;
+            match claimed {
+                Some(name) => {
+                    print_task_detail(conn, &name, json, time_format)?;
+                    eprintln!("Claimed '{name}' for '{assignee}'");
+                }
+                None => {
+                    bail!("no tasks available");
                 }
-                eprintln!("Claimed '{name}' for '{assignee}'");
             }
-            None => {
-                bail!("no tasks available");
+        }
+
+        Command::Next {
+            prefer,
+            root,
+            json,
+            time_format,
+        } => {
+            let time_format = output::TimeFormat::parse(&time_format)?;
+            match ops::peek_next_task(conn, prefer.as_deref(), root.as_deref(), None)? {
+                Some(name) => print_task_detail(conn, &name, json, time_format)?,
+                None => bail!("no tasks available"),
             }
-        },
+        }
 
         Command::Steal { name, assignee } => {
             let prev = ops::steal_task(conn, &name, &assignee)?;
@@ -145,14 +164,39 @@ fn dispatch(conn: &Connection, command: Command) -> Result<()> {
             eprintln!("Force-unassigned '{name}'");
         }
 
-        Command::Done { name } => {
-            ops::mark_done(conn, &name)?;
-            eprintln!("Marked '{name}' as done");
+        Command::Reclaim { prefix, to, .. } => {
+            let names = ops::reassign_prefix(conn, &prefix, to.as_deref())?;
+            for name in &names {
+                println!("{name}");
+            }
+            match to {
+                Some(to) => eprintln!("Reassigned {} task(s) to '{to}'", names.len()),
+                None => eprintln!("Released {} task(s)", names.len()),
+            }
+        }
+
+        Command::Done {
+            name,
+            recursive,
+            force,
+        } => {
+            if recursive {
+                let count = ops::mark_done_recursive(conn, &name, force)?;
+                eprintln!("Marked {count} task(s) in '{name}' as done");
+            } else {
+                ops::mark_done(conn, &name)?;
+                eprintln!("Marked '{name}' as done");
+            }
         }
 
-        Command::Reopen { name } => {
-            ops::reopen_task(conn, &name)?;
-            eprintln!("Reopened '{name}'");
+        Command::Reopen { name, recursive } => {
+            if recursive {
+                let count = ops::reopen_task_recursive(conn, &name)?;
+                eprintln!("Reopened {count} task(s) in '{name}'");
+            } else {
+                ops::reopen_task(conn, &name)?;
+                eprintln!("Reopened '{name}'");
+            }
         }
 
         Command::Pause { name } => {
@@ -165,6 +209,23 @@ fn dispatch(conn: &Connection, command: Command) -> Result<()> {
             eprintln!("Unpaused '{name}'");
         }
 
+        Command::Bulk {
+            op,
+            names,
+            keep_going,
+        } => {
+            let bulk_op = ops::BulkOp::parse(&op)?;
+            let failures = ops::set_status_bulk(conn, bulk_op, &names, keep_going)?;
+            let applied = names.len() - failures.len();
+            eprintln!("Applied {op} to {applied} task(s)");
+            if !failures.is_empty() {
+                for (name, error) in &failures {
+                    eprintln!("{name}: {error}");
+                }
+                bail!("{} of {} task(s) failed", failures.len(), names.len());
+            }
+        }
+
         Command::Reparent { name, parent } => {
             ops::reparent_task(conn, &name, parent.as_deref())?;
             match parent.as_deref() {
@@ -173,39 +234,92 @@ fn dispatch(conn: &Connection, command: Command) -> Result<()> {
             }
         }
 
-        Command::Describe { name, desc } => {
+        Command::MoveChildren { from, to } => {
+            let moved = ops::move_children(conn, &from, to.as_deref())?;
+            match to.as_deref() {
+                Some(to) => eprintln!("Moved {} child(ren) of '{from}' under '{to}'", moved.len()),
+                None => eprintln!("Moved {} child(ren) of '{from}' to root level", moved.len()),
+            }
+        }
+
+        Command::Dup { src, prefix, notes } => {
+            let pairs = ops::duplicate_subtree(conn, &src, |n| format!("{prefix}{n}"), notes)?;
+            for (_, copy) in &pairs {
+                println!("{copy}");
+            }
+            eprintln!("Copied {} task(s) from '{src}'", pairs.len());
+        }
+
+        Command::Describe {
+            name,
+            desc: Some(desc),
+            ..
+        } => {
             ops::update_description(conn, &name, &desc)?;
             eprintln!("Updated description for '{name}'");
         }
 
-        Command::Rm { name, recursive } => {
-            ops::remove_task(conn, &name, recursive)?;
-            eprintln!("Removed task '{name}'");
+        Command::Describe { desc: None, .. } => bail!("describe --edit cannot be used inside exec"),
+
+        Command::Rename { old, new } => {
+            ops::rename_task(conn, &old, &new)?;
+            eprintln!("Renamed '{old}' to '{new}'");
         }
 
-        Command::Show { name, json } => {
-            let task = ops::get_task(conn, &name)?;
-            let notes = ops::list_notes(conn, &name)?;
-            let blockers = ops::get_blockers(conn, &name)?;
-            let dependents = ops::get_dependents(conn, &name)?;
-            if json {
-                let detail = output::TaskDetail {
-                    task: &task,
-                    notes: &notes,
-                    blocked_by: &blockers,
-                    blocks: &dependents,
-                };
-                println!("{}", serde_json::to_string_pretty(&detail)?);
+        Command::Priority { name, priority } => {
+            ops::set_priority(conn, &name, priority)?;
+            eprintln!("Set priority of '{name}' to {priority}");
+        }
+
+        Command::Touch { name } => {
+            ops::touch_task(conn, &name)?;
+            eprintln!("Touched '{name}'");
+        }
+
+        Command::Estimate { name, estimate, .. } => {
+            ops::set_estimate(conn, &name, estimate.map(i64::from))?;
+            match estimate {
+                Some(e) => eprintln!("Set estimate of '{name}' to {e}"),
+                None => eprintln!("Cleared estimate of '{name}'"),
+            }
+        }
+
+        Command::Flag { name } => {
+            ops::set_needs_human(conn, &name, true)?;
+            eprintln!("Flagged '{name}' as needing a human");
+        }
+
+        Command::Unflag { name } => {
+            ops::set_needs_human(conn, &name, false)?;
+            eprintln!("Cleared needs-human flag on '{name}'");
+        }
+
+        Command::Rm {
+            name,
+            recursive,
+            dry_run,
+        } => {
+            if dry_run {
+                let entries = ops::preview_removal(conn, &name, recursive)?;
+                print!("{}", output::format_removal_preview(&entries));
             } else {
-                print!(
-                    "{}",
-                    output::format_task_detail(&task, &notes, &blockers, &dependents)
-                );
+                ops::remove_task(conn, &name, recursive)?;
+                eprintln!("Removed task '{name}'");
             }
         }
 
+        Command::Show {
+            name,
+            json,
+            time_format,
+        } => {
+            let time_format = output::TimeFormat::parse(&time_format)?;
+            print_task_detail(conn, &name, json, time_format)?;
+        }
+
         Command::List {
             tree,
+            depth,
             status,
             all,
             root,
@@ -213,102 +327,352 @@ fn dispatch(conn: &Connection, command: Command) -> Result<()> {
             assignee,
             blocked,
             unblocked,
+            roots,
+            leaves,
+            since,
+            long,
+            sort,
+            reverse,
+            limit,
+            offset,
+            count,
             json,
+            ndjson,
+            time_format,
         } => {
+            let time_format = output::TimeFormat::parse(&time_format)?;
             let status = status.map(|s| StatusFilter::parse(&s)).transpose()?;
+            let sort = ops::TaskSort::parse(&sort)?;
             let blocked_filter = match (blocked, unblocked) {
                 (true, _) => Some(true),
                 (_, true) => Some(false),
                 _ => None,
             };
-            let tasks = if let Some(ref parent) = children {
-                ops::list_children(
-                    conn,
-                    parent,
-                    status,
-                    all,
-                    assignee.as_deref(),
-                    blocked_filter,
-                )?
+            let filter = ops::ListFilter {
+                status,
+                all,
+                assignee: assignee.as_deref(),
+                blocked: blocked_filter,
+                roots,
+                leaves,
+                since: since.as_deref(),
+            };
+            if count {
+                // A subtree is collected by walking parents, so --root
+                // still loads the tasks; everything else counts in SQL.
+                let n = match root {
+                    Some(ref root) => ops::list_tasks_filtered(conn, Some(root), filter)?.len(),
+                    None => ops::count_tasks(conn, children.as_deref(), filter)?,
+                };
+                println!("{n}");
+                return Ok(());
+            }
+            // The default keeps the fetch order: by id, or depth-first
+            // under --root. Only then can the page be cut while fetching.
+            let page = ops::Page { limit, offset };
+            let fetch_order = sort == ops::TaskSort::Id && !reverse;
+            let fetch_page = if fetch_order {
+                page
             } else {
-                ops::list_tasks(
-                    conn,
-                    status,
-                    all,
-                    root.as_deref(),
-                    assignee.as_deref(),
-                    blocked_filter,
-                )?
+                ops::Page::default()
             };
-            if json {
+            let (mut tasks, total) = if let Some(ref parent) = children {
+                ops::list_children_page(conn, parent, filter, fetch_page)?
+            } else {
+                ops::list_tasks_page(conn, root.as_deref(), filter, fetch_page)?
+            };
+            if !fetch_order {
+                ops::sort_tasks(&mut tasks, sort, reverse);
+                tasks = page.slice(tasks);
+            }
+            report_page(page, tasks.len(), total);
+            if json || ndjson {
                 let mut deps = ops::get_all_deps(conn)?;
-                let items: Vec<output::TaskListItem> = tasks
-                    .iter()
-                    .map(|t| {
-                        let (blocked_by, blocks) = deps.remove(&t.name).unwrap_or_default();
-                        output::TaskListItem {
-                            task: t,
-                            blocked_by,
-                            blocks,
-                        }
-                    })
-                    .collect();
-                println!("{}", serde_json::to_string_pretty(&items)?);
+                let items = tasks.iter().map(|t| {
+                    let (blocked_by, blocks) = deps.remove(&t.name).unwrap_or_default();
+                    output::TaskListItem {
+                        task: t,
+                        blocked_by,
+                        blocks,
+                    }
+                });
+                if ndjson {
+                    output::write_ndjson(&mut std::io::stdout().lock(), items, time_format)?;
+                } else {
+                    let items: Vec<_> = items.collect();
+                    output::print_json(&items, time_format)?;
+                }
             } else if tree {
-                print!("{}", output::format_task_tree(&tasks));
+                print!(
+                    "{}",
+                    output::format_task_tree(&tasks, depth, &list_style(conn)?)
+                );
+            } else if long {
+                print!(
+                    "{}",
+                    output::format_task_list_long(&tasks, output::unix_now(), &list_style(conn)?)
+                );
             } else {
-                print!("{}", output::format_task_list(&tasks));
+                print!("{}", output::format_task_list(&tasks, &list_style(conn)?));
             }
         }
 
-        Command::Note { name, content } => {
+        Command::Note {
+            name,
+            content,
+            label,
+            replace,
+        } => {
             let content = match content {
                 Some(c) => c,
                 None => bail!(
                     "note content must be provided explicitly (stdin is not available inside exec)"
                 ),
             };
-            ops::add_note(conn, &name, &content)?;
-            eprintln!("Added note to '{name}'");
+            write_note(conn, &name, &content, label.as_deref(), replace)?;
         }
 
-        Command::Notes { name, json } => {
-            let notes = ops::list_notes(conn, &name)?;
-            if json {
-                println!("{}", serde_json::to_string_pretty(&notes)?);
+        Command::Notes {
+            name,
+            label,
+            json,
+            ndjson,
+            time_format,
+        } => {
+            let time_format = output::TimeFormat::parse(&time_format)?;
+            let mut notes = ops::list_notes(conn, &name)?;
+            if let Some(label) = label {
+                notes.retain(|n| n.label.as_deref() == Some(label.as_str()));
+            }
+            if ndjson {
+                output::write_ndjson(&mut std::io::stdout().lock(), &notes, time_format)?;
+            } else if json {
+                output::print_json(&notes, time_format)?;
             } else {
                 print!("{}", output::format_notes(&notes));
             }
         }
 
+        Command::NoteEdit { id, content } => {
+            let content = match content {
+                Some(c) => c,
+                None => bail!(
+                    "note content must be provided explicitly (stdin is not available inside exec)"
+                ),
+            };
+            ops::edit_note(conn, id, &content)?;
+            eprintln!("Updated note {id}");
+        }
+
+        Command::NoteRm { id } => {
+            ops::delete_note(conn, id)?;
+            eprintln!("Deleted note {id}");
+        }
+
         Command::Block { blocker, blocked } => {
             ops::add_block(conn, &blocker, &blocked)?;
             eprintln!("'{blocker}' now blocks '{blocked}'");
         }
 
-        Command::Unblock { blocker, blocked } => {
-            ops::remove_block(conn, &blocker, &blocked)?;
-            eprintln!("'{blocker}' no longer blocks '{blocked}'");
+        Command::Unblock {
+            blocker,
+            blocked,
+            all,
+            blocked_task,
+        } => match (blocker, blocked, blocked_task) {
+            (None, None, Some(name)) => {
+                let count = ops::remove_all_blocks_on(conn, &name)?;
+                eprintln!("Removed {count} blocker(s) of '{name}'");
+            }
+            (Some(blocker), None, None) if all => {
+                let count = ops::remove_all_blocks_as_blocker(conn, &blocker)?;
+                eprintln!("'{blocker}' no longer blocks {count} task(s)");
+            }
+            (Some(blocker), Some(blocked), None) if !all => {
+                ops::remove_block(conn, &blocker, &blocked)?;
+                eprintln!("'{blocker}' no longer blocks '{blocked}'");
+            }
+            _ => bail!(UNBLOCK_USAGE),
+        },
+
+        Command::DepsList { json } => {
+            let deps = ops::list_all_deps(conn)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&deps)?);
+            } else {
+                print!("{}", output::format_dep_list(&deps));
+            }
         }
 
-        Command::Search { query, json } => {
-            let results = ops::search_tasks(conn, &query)?;
+        Command::Deps { name, tree, json } => {
+            let target = ops::get_task(conn, &name)?;
+            let tasks = ops::list_tasks(conn, None, true, None, None, None)?;
+            let by_name: HashMap<&str, &Task> =
+                tasks.iter().map(|t| (t.name.as_str(), t)).collect();
+            let blockers: HashMap<String, Vec<String>> = ops::get_all_deps(conn)?
+                .into_iter()
+                .map(|(name, (blocked_by, _))| (name, blocked_by))
+                .collect();
+            let max_depth = if tree { None } else { Some(1) };
+            let root = output::build_blocker_tree(&target.name, &by_name, &blockers, max_depth);
             if json {
-                println!("{}", serde_json::to_string_pretty(&results)?);
+                println!("{}", serde_json::to_string_pretty(&root)?);
+            } else {
+                print!("{}", output::format_blocker_tree(&root));
+            }
+        }
+
+        Command::Search {
+            query,
+            all,
+            prefix,
+            status,
+            assignee,
+            limit,
+            offset,
+            json,
+            ndjson,
+            time_format,
+        } => {
+            let time_format = output::TimeFormat::parse(&time_format)?;
+            let status = status.map(|s| StatusFilter::parse(&s)).transpose()?;
+            let mode = ops::FtsMode {
+                join: if all {
+                    ops::FtsJoin::All
+                } else {
+                    ops::FtsJoin::Any
+                },
+                prefix,
+            };
+            let page = ops::Page { limit, offset };
+            let (results, total) =
+                ops::search_tasks_page(conn, &query, mode, status, assignee.as_deref(), page)?;
+            report_page(page, results.len(), total);
+            if ndjson {
+                output::write_ndjson(&mut std::io::stdout().lock(), &results, time_format)?;
+            } else if json {
+                output::print_json(&results, time_format)?;
+            } else {
+                let text = output::format_search_results(&results, std::io::stdout().is_terminal());
+                print!("{text}");
+            }
+        }
+
+        Command::Assignees { json, time_format } => {
+            let time_format = output::TimeFormat::parse(&time_format)?;
+            let groups = ops::list_assignees(conn)?;
+            if json {
+                let items: Vec<output::AssigneeItem> = groups
+                    .iter()
+                    .map(|(assignee, tasks)| output::AssigneeItem { assignee, tasks })
+                    .collect();
+                output::print_json(&items, time_format)?;
+            } else {
+                print!("{}", output::format_assignees(&groups, output::unix_now()));
+            }
+        }
+
+        Command::Blocked { json, time_format } => {
+            let time_format = output::TimeFormat::parse(&time_format)?;
+            let blocked = ops::list_blocked(conn)?;
+            if json {
+                let items: Vec<output::BlockedItem> = blocked
+                    .iter()
+                    .map(|(task, blocked_by)| output::BlockedItem { task, blocked_by })
+                    .collect();
+                output::print_json(&items, time_format)?;
             } else {
-                print!("{}", output::format_search_results(&results));
+                print!("{}", output::format_blocked(&blocked));
+            }
+        }
+
+        Command::Rollup { name, json } => {
+            let rollup = ops::rollup(conn, &name)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&rollup)?);
+            } else {
+                print!("{}", output::format_rollup(&rollup));
+            }
+        }
+
+        Command::Stale {
+            minutes,
+            json,
+            time_format,
+        } => {
+            let time_format = output::TimeFormat::parse(&time_format)?;
+            let tasks = ops::stale_tasks(conn, std::time::Duration::from_secs(minutes * 60))?;
+            if json {
+                output::print_json(&tasks, time_format)?;
+            } else {
+                print!("{}", output::format_task_list(&tasks, &list_style(conn)?));
+            }
+        }
+
+        Command::Check { fix } => {
+            let issues = ops::validate_integrity(conn)?;
+            for issue in &issues {
+                println!("{issue}");
+            }
+            if issues.is_empty() {
+                eprintln!("No integrity issues found");
+            } else if fix {
+                ops::fix_integrity(conn, &issues)?;
+                let remaining = ops::validate_integrity(conn)?;
+                if !remaining.is_empty() {
+                    bail!("{} integrity issue(s) could not be fixed", remaining.len());
+                }
+                eprintln!("Fixed {} integrity issue(s)", issues.len());
+            } else {
+                bail!(
+                    "{} integrity issue(s) found; run with --fix to repair",
+                    issues.len()
+                );
+            }
+        }
+
+        Command::Export { json } => {
+            let doc = ops::export_all(conn)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&doc)?);
+            } else {
+                print!("{}", output::format_export_summary(&doc));
             }
         }
 
         Command::Agents => bail!("agents cannot be used inside exec"),
+        Command::Paths { .. } => bail!("paths cannot be used inside exec"),
+        Command::AgentStatus { .. } => bail!("agent-status cannot be used inside exec"),
+        Command::Import { .. } => bail!("import cannot be used inside exec"),
+        Command::Diff { .. } => bail!("diff cannot be used inside exec"),
+        Command::Log { .. } => bail!("log cannot be used inside exec"),
         Command::Watch { .. } => bail!("watch cannot be used inside exec"),
         Command::Wait => bail!("wait cannot be used inside exec"),
-        Command::Exec => bail!("exec cannot be nested"),
+        Command::Exec { .. } => bail!("exec cannot be nested"),
     }
 
     Ok(())
 }
 
+/// Stream task status changes as NDJSON until stdout closes. Each change
+/// to the database is diffed against the previous snapshot; the poll
+/// interval is a fallback for missed filesystem events.
+fn watch_events(db_path: &str, conn: &Connection, root: Option<&str>, poll_ms: u64) -> Result<()> {
+    let (_watcher, rx) = watch::watch_db(db_path)?;
+    let mut prev = ops::status_snapshot(conn, root)?;
+    let mut stdout = std::io::stdout().lock();
+    loop {
+        watch::wait_for_change(&rx, std::time::Duration::from_millis(poll_ms));
+        watch::drain_events(&rx);
+        let next = ops::status_snapshot(conn, root)?;
+        let events = ops::diff_status_snapshots(&prev, &next);
+        if !events.is_empty() {
+            output::write_ndjson(&mut stdout, &events, output::TimeFormat::Iso)?;
+        }
+        prev = next;
+    }
+}
+
 fn parse_exec_tokens(tokens: &[String], display_line: &str) -> Result<Command> {
     let mut args = vec!["kbtz".to_string()];
     args.extend(tokens.iter().cloned());
@@ -324,6 +688,10 @@ fn parse_exec_tokens(tokens: &[String], display_line: &str) -> Result<Command> {
 /// `Here's` do not start quoted strings.  Only double quotes delimit strings,
 /// with `\"` and `\\` as escape sequences inside them.  Backslashes outside
 /// double quotes are also literal.
+///
+/// A `#` that starts a token outside double quotes begins a comment that
+/// runs to the end of the line; a `#` inside quotes or mid-token (`task#1`)
+/// is ordinary text.
 fn tokenize_exec_line(line: &str) -> Result<Vec<String>> {
     let mut tokens = Vec::new();
     let mut current = String::new();
@@ -355,6 +723,7 @@ fn tokenize_exec_line(line: &str) -> Result<Vec<String>> {
                         in_token = false;
                     }
                 }
+                '#' if !in_token => break,
                 _ => {
                     current.push(c);
                     in_token = true;
@@ -376,9 +745,11 @@ fn tokenize_exec_line(line: &str) -> Result<Vec<String>> {
 
 /// Check whether double quotes are balanced in a string, respecting escape sequences.
 ///
-/// Returns `true` when every opening `"` has a matching closing `"`.
+/// Returns `true` when every opening `"` has a matching closing `"`. Quotes
+/// inside a trailing comment (see `tokenize_exec_line`) don't count.
 fn has_balanced_quotes(s: &str) -> bool {
     let mut in_quotes = false;
+    let mut prev = None;
     let mut chars = s.chars().peekable();
     while let Some(c) = chars.next() {
         if in_quotes {
@@ -393,7 +764,10 @@ fn has_balanced_quotes(s: &str) -> bool {
             }
         } else if c == '"' {
             in_quotes = true;
+        } else if c == '#' && prev.is_none_or(|p: char| p.is_ascii_whitespace()) {
+            break;
         }
+        prev = Some(c);
     }
     !in_quotes
 }
@@ -408,9 +782,13 @@ fn has_balanced_quotes(s: &str) -> bool {
 /// Double-quoted strings may span multiple lines. When a line has unbalanced
 /// quotes, subsequent lines are joined (with embedded newlines) until the
 /// quotes are balanced.
+///
+/// `set KEY value` lines define variables instead of producing a command;
+/// `${KEY}` in later lines is expanded before tokenizing.
 fn resolve_heredocs(input: &str) -> Result<Vec<(usize, String, Vec<String>)>> {
     let lines: Vec<&str> = input.lines().collect();
     let mut result = Vec::new();
+    let mut vars = HashMap::new();
     let mut i = 0;
 
     while i < lines.len() {
@@ -433,6 +811,8 @@ fn resolve_heredocs(input: &str) -> Result<Vec<(usize, String, Vec<String>)>> {
             i += 1;
         }
 
+        let accumulated =
+            expand_vars(&accumulated, &vars).with_context(|| format!("line {lineno}: {line}"))?;
         let mut tokens = tokenize_exec_line(&accumulated)
             .with_context(|| format!("line {lineno}: invalid quoting: {line}"))?;
 
@@ -470,30 +850,48 @@ fn resolve_heredocs(input: &str) -> Result<Vec<(usize, String, Vec<String>)>> {
             tokens[pos] = body_lines.join("\n");
         }
 
+        if tokens.first().is_some_and(|t| t == "set") {
+            let [_, key, value] = <[String; 3]>::try_from(tokens)
+                .map_err(|_| anyhow::anyhow!("line {lineno}: usage: set KEY value"))?;
+            let valid = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                bail!("line {lineno}: invalid variable name '{key}'");
+            }
+            vars.insert(key, value);
+            continue;
+        }
+
         result.push((lineno, line.to_string(), tokens));
     }
 
     Ok(result)
 }
 
-fn run_exec(conn: &Connection, input: &str) -> Result<()> {
-    let resolved = resolve_heredocs(input)?;
-
-    // Parse all commands first, before starting the transaction
-    let mut commands = Vec::new();
-    for (lineno, line, tokens) in &resolved {
-        let command = parse_exec_tokens(tokens, line).with_context(|| format!("line {lineno}"))?;
-        // Reject commands that don't belong in a batch
-        match &command {
-            Command::Agents => bail!("line {lineno}: agents cannot be used inside exec"),
-            Command::Exec => bail!("line {lineno}: exec cannot be nested"),
-            Command::Watch { .. } => bail!("line {lineno}: watch cannot be used inside exec"),
-            Command::Wait => bail!("line {lineno}: wait cannot be used inside exec"),
-            _ => {}
+/// Replace each `${NAME}` in `text` with its value from `vars`. A `$` not
+/// followed by `{` is literal.
+fn expand_vars(text: &str, vars: &HashMap<String, String>) -> Result<String> {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            bail!("unterminated '${{' in variable reference");
+        };
+        let name = &after[..end];
+        match vars.get(name) {
+            Some(value) => out.push_str(value),
+            None => bail!("undefined variable '{name}'"),
         }
-        commands.push((*lineno, line.clone(), command));
+        rest = &after[end + 1..];
     }
+    out.push_str(rest);
+    Ok(out)
+}
 
+fn run_exec(conn: &Connection, input: &str) -> Result<()> {
+    let commands = parse_exec(input)?;
     if commands.is_empty() {
         return Ok(());
     }
@@ -519,6 +917,265 @@ fn run_exec(conn: &Connection, input: &str) -> Result<()> {
     }
 }
 
+/// `exec --keep-going`: run each command in its own savepoint so a failure
+/// only undoes that command. Every failure is printed, then the batch fails
+/// as a whole if any command did. Parse errors still abort before anything
+/// runs.
+fn run_exec_keep_going(conn: &Connection, input: &str) -> Result<()> {
+    let commands = parse_exec(input)?;
+    let total = commands.len();
+    let mut failures = Vec::new();
+    for (lineno, line, command) in commands {
+        conn.execute_batch("SAVEPOINT exec_line")?;
+        match dispatch(conn, command) {
+            Ok(()) => conn.execute_batch("RELEASE exec_line")?,
+            Err(e) => {
+                let _ = conn.execute_batch("ROLLBACK TO exec_line");
+                let _ = conn.execute_batch("RELEASE exec_line");
+                failures.push(format!("line {lineno}: {line}: {e:#}"));
+            }
+        }
+    }
+    if !failures.is_empty() {
+        for failure in &failures {
+            eprintln!("{failure}");
+        }
+        bail!("{} of {total} command(s) failed", failures.len());
+    }
+    Ok(())
+}
+
+/// Parse an exec batch into `(line number, line, command)` triples,
+/// rejecting commands that don't belong in a batch. Used by both the real
+/// run and `--dry-run`, so both report the same errors.
+fn parse_exec(input: &str) -> Result<Vec<(usize, String, Command)>> {
+    let resolved = resolve_heredocs(input)?;
+
+    // Parse all commands first, before starting the transaction
+    let mut commands = Vec::new();
+    for (lineno, line, tokens) in &resolved {
+        let command = parse_exec_tokens(tokens, line).with_context(|| format!("line {lineno}"))?;
+        // Reject commands that don't belong in a batch
+        match &command {
+            Command::Agents => bail!("line {lineno}: agents cannot be used inside exec"),
+            Command::Paths { .. } => bail!("line {lineno}: paths cannot be used inside exec"),
+            Command::AgentStatus { .. } => {
+                bail!("line {lineno}: agent-status cannot be used inside exec")
+            }
+            Command::Import { .. } => bail!("line {lineno}: import cannot be used inside exec"),
+            Command::Diff { .. } => bail!("line {lineno}: diff cannot be used inside exec"),
+            Command::Log { .. } => bail!("line {lineno}: log cannot be used inside exec"),
+            Command::Describe { edit: true, .. } => {
+                bail!("line {lineno}: describe --edit cannot be used inside exec")
+            }
+            Command::Exec { .. } => bail!("line {lineno}: exec cannot be nested"),
+            Command::Watch { .. } => bail!("line {lineno}: watch cannot be used inside exec"),
+            Command::Wait => bail!("line {lineno}: wait cannot be used inside exec"),
+            _ => {}
+        }
+        commands.push((*lineno, line.clone(), command));
+    }
+    Ok(commands)
+}
+
+/// Add a note, or with `replace` update the task's note labeled `label`.
+fn write_note(
+    conn: &Connection,
+    name: &str,
+    content: &str,
+    label: Option<&str>,
+    replace: bool,
+) -> Result<()> {
+    match label {
+        Some(label) if replace => {
+            if ops::replace_labeled_note(conn, name, label, content)? {
+                eprintln!("Replaced '{label}' note on '{name}'");
+            } else {
+                eprintln!("Added '{label}' note to '{name}'");
+            }
+        }
+        _ => {
+            ops::add_labeled_note(conn, name, label, content)?;
+            eprintln!("Added note to '{name}'");
+        }
+    }
+    Ok(())
+}
+
+/// Print a task with its path, notes and dependencies, as text or JSON.
+fn print_task_detail(
+    conn: &Connection,
+    name: &str,
+    json: bool,
+    time_format: output::TimeFormat,
+) -> Result<()> {
+    let task = ops::get_task(conn, name)?;
+    let ancestors = ops::get_ancestors(conn, name)?;
+    let notes = ops::list_notes(conn, name)?;
+    let blockers = ops::get_blockers(conn, name)?;
+    let dependents = ops::get_dependents(conn, name)?;
+    if json {
+        let detail = output::TaskDetail {
+            task: &task,
+            path: &ancestors,
+            notes: &notes,
+            blocked_by: &blockers,
+            blocks: &dependents,
+        };
+        output::print_json(&detail, time_format)?;
+    } else {
+        print!(
+            "{}",
+            output::format_task_detail(
+                &task,
+                &ancestors,
+                &notes,
+                &blockers,
+                &dependents,
+                &output::Style::new(color_enabled())
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Whether `--color` resolved to color; unset (plain) until `run` decides.
+static COLOR: OnceLock<bool> = OnceLock::new();
+
+fn color_enabled() -> bool {
+    COLOR.get().copied().unwrap_or(false)
+}
+
+/// Style for task lists and trees. Blocked tasks are only looked up when
+/// they'll actually be marked.
+fn list_style(conn: &Connection) -> Result<output::Style> {
+    if !color_enabled() {
+        return Ok(output::Style::default());
+    }
+    let blocked = ops::get_all_deps(conn)?
+        .into_iter()
+        .filter(|(_, (blocked_by, _))| !blocked_by.is_empty())
+        .map(|(name, _)| name);
+    Ok(output::Style::new(true).with_blocked(blocked))
+}
+
+/// Tell paginating scripts how many results exist beyond this page.
+fn report_page(page: ops::Page, shown: usize, total: usize) {
+    if !page.is_all() {
+        eprintln!("Showing {shown} of {total} (offset {})", page.offset);
+    }
+}
+
+/// Locate the log file for `kbtz log --which`.
+fn resolve_log_path(which: &str) -> Result<PathBuf> {
+    match which {
+        "orchestrator" => {
+            Ok(PathBuf::from(kbtz::paths::workspace_dir()).join(kbtz::paths::ORCHESTRATOR_LOG))
+        }
+        "workspace" | "shepherd" => match std::env::var_os("KBTZ_DEBUG") {
+            Some(path) => Ok(PathBuf::from(path)),
+            None => bail!(
+                "{which} logging is off; start kbtz-workspace with KBTZ_DEBUG=<path> \
+                 and run this with the same KBTZ_DEBUG"
+            ),
+        },
+        other => {
+            bail!("unknown log '{other}' (expected orchestrator, workspace or shepherd)")
+        }
+    }
+}
+
+/// Shepherd messages start with `shepherd` (after the level, if any) in
+/// plain logs and carry the shepherd binary's module in JSON logs.
+fn is_shepherd_line(line: &str) -> bool {
+    line.contains("\"module\":\"kbtz-shepherd\"")
+        || line.split_once("] ").is_some_and(|(_, rest)| {
+            rest.split_whitespace()
+                .take(2)
+                .any(|word| word.starts_with("shepherd"))
+        })
+}
+
+/// Append `chunk` to `pending` and write out every complete line that
+/// passes the filter, keeping any trailing partial line for the next call.
+fn emit_log_lines(
+    pending: &mut String,
+    chunk: &str,
+    shepherd_only: bool,
+    out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    pending.push_str(chunk);
+    let Some(end) = pending.rfind('\n') else {
+        return Ok(());
+    };
+    for line in pending[..end].lines() {
+        if !shepherd_only || is_shepherd_line(line) {
+            writeln!(out, "{line}")?;
+        }
+    }
+    out.flush()?;
+    pending.drain(..=end);
+    Ok(())
+}
+
+/// Print the log at `path`. With `follow`, keep streaming appends (waiting
+/// for the file to appear, and starting over if it is truncated) until
+/// stdout closes.
+fn print_log(path: &std::path::Path, shepherd_only: bool, follow: bool) -> Result<()> {
+    use std::io::{Seek, SeekFrom};
+
+    if !follow && !path.exists() {
+        bail!("no log at {}", path.display());
+    }
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let watcher = if follow {
+        Some(watch::watch_dir(&dir)?)
+    } else {
+        None
+    };
+    let mut stdout = std::io::stdout().lock();
+    let mut pending = String::new();
+    let mut pos = 0u64;
+    loop {
+        if let Ok(mut file) = std::fs::File::open(path) {
+            let len = file.metadata()?.len();
+            if len < pos {
+                pos = 0;
+                pending.clear();
+            }
+            file.seek(SeekFrom::Start(pos))?;
+            let mut buf = Vec::new();
+            pos += file.read_to_end(&mut buf)? as u64;
+            let chunk = String::from_utf8_lossy(&buf);
+            match emit_log_lines(&mut pending, &chunk, shepherd_only, &mut stdout) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let Some((_, rx)) = &watcher else {
+            if !pending.is_empty() {
+                let _ = emit_log_lines(&mut pending, "\n", shepherd_only, &mut stdout);
+            }
+            return Ok(());
+        };
+        watch::wait_for_change(rx, std::time::Duration::from_secs(1));
+        watch::drain_events(rx);
+    }
+}
+
+fn parse_import_mode(mode: &str, force: bool) -> Result<ops::ImportMode> {
+    match mode {
+        "merge" if force => bail!("--force only applies to --mode replace"),
+        "merge" => Ok(ops::ImportMode::Merge),
+        "replace" => Ok(ops::ImportMode::Replace { force }),
+        other => bail!("unknown import mode '{other}' (expected merge or replace)"),
+    }
+}
+
 /// Check whether note content is available without blocking.
 ///
 /// Returns `Ok(Some(content))` when the content argument was provided,
@@ -534,6 +1191,21 @@ fn check_note_content(content: Option<String>, stdin_is_terminal: bool) -> Resul
     }
 }
 
+/// Resolve note content from the argument, falling back to piped stdin.
+fn read_note_content(content: Option<String>) -> Result<String> {
+    match check_note_content(content, std::io::stdin().is_terminal())? {
+        Some(c) => Ok(c),
+        None => {
+            let mut buf = String::new();
+            std::io::stdin().read_to_string(&mut buf)?;
+            if buf.is_empty() {
+                bail!("no content provided");
+            }
+            Ok(buf)
+        }
+    }
+}
+
 fn main() {
     if let Err(e) = run() {
         eprintln!("error: {e:#}");
@@ -543,7 +1215,37 @@ fn main() {
 
 fn run() -> Result<()> {
     let cli = Cli::parse();
+    let style = output::Style::resolve(
+        &cli.color,
+        std::io::stdout().is_terminal(),
+        std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+    )?;
+    let _ = COLOR.set(style.is_color());
+    if let Command::AgentStatus { state } = &cli.command {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| {
+                anyhow::anyhow!(
+                    "{name} is not set; agent-status must run inside a workspace session"
+                )
+            })
+        };
+        let session_id = var("KBTZ_SESSION_ID")?;
+        let workspace_dir = var("KBTZ_WORKSPACE_DIR")?;
+        return kbtz::paths::write_session_status(Path::new(&workspace_dir), &session_id, state);
+    }
     let db_path = resolve_db_path(cli.db)?;
+    if let Command::Paths { json } = cli.command {
+        let resolved = kbtz::paths::resolved_paths(
+            Path::new(&db_path),
+            Path::new(&kbtz::paths::workspace_dir()),
+        );
+        if json {
+            println!("{}", serde_json::to_string_pretty(&resolved)?);
+        } else {
+            print!("{}", output::format_paths(&resolved));
+        }
+        return Ok(());
+    }
     ensure_db_dir(&db_path)?;
 
     match cli.command {
@@ -567,28 +1269,87 @@ fn run() -> Result<()> {
             return Ok(());
         }
 
-        Command::Exec => {
-            let conn = open_db(&db_path)?;
+        Command::Exec {
+            dry_run,
+            keep_going,
+        } => {
             let mut input = String::new();
             std::io::stdin().read_to_string(&mut input)?;
-            run_exec(&conn, &input)?;
+            if dry_run {
+                let count = parse_exec(&input)?.len();
+                println!("{count} command(s) would run");
+            } else if keep_going {
+                let conn = open_db(&db_path)?;
+                run_exec_keep_going(&conn, &input)?;
+            } else {
+                let conn = open_db(&db_path)?;
+                run_exec(&conn, &input)?;
+            }
         }
 
-        Command::Note { name, content } => {
+        Command::Diff { file, apply } => {
+            let input = if file == "-" {
+                let mut input = String::new();
+                std::io::stdin().read_to_string(&mut input)?;
+                input
+            } else {
+                std::fs::read_to_string(&file).with_context(|| format!("failed to read {file}"))?
+            };
+            let target: ExportDoc =
+                serde_json::from_str(&input).context("failed to parse snapshot")?;
             let conn = open_db(&db_path)?;
-            let content = match check_note_content(content, std::io::stdin().is_terminal())? {
-                Some(c) => c,
-                None => {
-                    let mut buf = String::new();
-                    std::io::stdin().read_to_string(&mut buf)?;
-                    if buf.is_empty() {
-                        bail!("no content provided");
-                    }
-                    buf
+            let plan = kbtz::diff::plan(&ops::export_all(&conn)?, &target)?;
+            if apply {
+                kbtz::diff::apply(&conn, &plan)?;
+                eprintln!("Applied {} change(s)", plan.len());
+            } else {
+                for op in &plan {
+                    println!("{}", op.to_exec_line());
                 }
-            };
-            ops::add_note(&conn, &name, &content)?;
-            eprintln!("Added note to '{name}'");
+                eprintln!("{} change(s)", plan.len());
+            }
+        }
+
+        Command::Import { mode, force } => {
+            let mode = parse_import_mode(&mode, force)?;
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input)?;
+            let doc: ExportDoc =
+                serde_json::from_str(&input).context("failed to parse snapshot")?;
+            let conn = open_db(&db_path)?;
+            let stats = ops::import_all(&conn, &doc, mode)?;
+            eprintln!(
+                "Imported {} tasks, {} notes, {} deps ({} existing tasks skipped)",
+                stats.tasks, stats.notes, stats.deps, stats.skipped
+            );
+        }
+
+        Command::Note {
+            name,
+            content,
+            label,
+            replace,
+        } => {
+            let conn = open_db(&db_path)?;
+            let content = read_note_content(content)?;
+            write_note(&conn, &name, &content, label.as_deref(), replace)?;
+        }
+
+        Command::NoteEdit { id, content } => {
+            let conn = open_db(&db_path)?;
+            let content = read_note_content(content)?;
+            ops::edit_note(&conn, id, &content)?;
+            eprintln!("Updated note {id}");
+        }
+
+        Command::Watch {
+            root,
+            poll_interval,
+            json: true,
+            ..
+        } => {
+            let conn = open_db(&db_path)?;
+            watch_events(&db_path, &conn, root.as_deref(), poll_interval)?;
         }
 
         Command::Watch {
@@ -596,6 +1357,8 @@ fn run() -> Result<()> {
             poll_interval,
             action,
             workspace_dir,
+            json: false,
+            preview,
         } => {
             let conn = open_db(&db_path)?;
             tui::run(
@@ -605,9 +1368,32 @@ fn run() -> Result<()> {
                 poll_interval,
                 action.as_deref(),
                 workspace_dir.as_deref(),
+                preview,
             )?;
         }
 
+        Command::Describe {
+            name,
+            desc: None,
+            edit: true,
+        } => {
+            let conn = open_db(&db_path)?;
+            let current = ops::get_task(&conn, &name)?.description;
+            let edited = kbtz::editor::edit_text_interactive(&current)?;
+            let edited = edited.trim_end();
+            if edited == current {
+                eprintln!("Description for '{name}' unchanged");
+            } else {
+                ops::update_description(&conn, &name, edited)?;
+                eprintln!("Updated description for '{name}'");
+            }
+        }
+
+        Command::Log { which, follow } => {
+            let path = resolve_log_path(&which)?;
+            print_log(&path, which == "shepherd", follow)?;
+        }
+
         Command::Wait => {
             // Ensure DB exists before watching
             let _conn = open_db(&db_path)?;
@@ -616,6 +1402,21 @@ fn run() -> Result<()> {
             watch::wait_for_change(&rx, std::time::Duration::MAX);
         }
 
+        Command::Rm {
+            ref name,
+            recursive: true,
+            dry_run: false,
+        } if std::io::stdin().is_terminal() => {
+            // Interactive recursive removals announce how much is going away;
+            // scripted callers (and exec batches) stay quiet.
+            let conn = open_db(&db_path)?;
+            let count = ops::count_descendants(&conn, name)?;
+            if count > 0 {
+                eprintln!("Removing '{name}' and {count} descendant task(s)");
+            }
+            dispatch(&conn, cli.command)?;
+        }
+
         other => {
             let conn = open_db(&db_path)?;
             dispatch(&conn, other)?;
@@ -656,6 +1457,30 @@ note task-a "a note on task-a"
         assert_eq!(notes[0].content, "a note on task-a");
     }
 
+    #[test]
+    fn exec_unblock_all_forms() {
+        let conn = test_conn();
+        let input = r#"
+add a "A"
+add b "B"
+add c "C"
+block a b
+block a c
+block b c
+unblock --blocked c --all
+"#;
+        run_exec(&conn, input).unwrap();
+        assert!(ops::get_blockers(&conn, "c").unwrap().is_empty());
+        assert_eq!(ops::get_blockers(&conn, "b").unwrap(), vec!["a"]);
+
+        run_exec(&conn, "unblock a --all").unwrap();
+        assert!(ops::list_all_deps(&conn).unwrap().is_empty());
+
+        // --all with both endpoints is ambiguous.
+        assert!(run_exec(&conn, "unblock a b --all").is_err());
+        assert!(run_exec(&conn, "unblock a").is_err());
+    }
+
     #[test]
     fn exec_batch_rolls_back_on_failure() {
         let conn = test_conn();
@@ -694,6 +1519,58 @@ done task-x
         run_exec(&conn, "  \n  \n# only comments\n").unwrap();
     }
 
+    #[test]
+    fn exec_dry_run_parses_without_writing() {
+        let conn = test_conn();
+        let input = "add task-a \"First\"\n# comment\nnote task-a <<EOF\nbody\nEOF\n";
+        let commands = parse_exec(input).unwrap();
+        assert_eq!(commands.len(), 2);
+        assert!(ops::list_tasks(&conn, None, true, None, None, None)
+            .unwrap()
+            .is_empty());
+
+        // Errors carry the same line numbers as a real run.
+        let input = "add task-a \"First\"\nbogus-command\n";
+        let Err(dry) = parse_exec(input) else {
+            panic!("expected a parse error");
+        };
+        let real = run_exec(&conn, input).unwrap_err();
+        assert_eq!(format!("{dry:#}"), format!("{real:#}"));
+        assert!(format!("{dry:#}").starts_with("line 2"), "{dry:#}");
+        let input = "add task-a \"First\"\nwait\n";
+        let Err(dry) = parse_exec(input) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(dry.to_string(), "line 2: wait cannot be used inside exec");
+        assert!(ops::list_tasks(&conn, None, true, None, None, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn exec_keep_going_commits_good_lines() {
+        let conn = test_conn();
+        let input = r#"
+add task-a "First"
+add task-a "Duplicate"
+add task-b "Second"
+block missing task-b
+note task-b "kept"
+"#;
+        let err = run_exec_keep_going(&conn, input).unwrap_err();
+        assert_eq!(err.to_string(), "2 of 5 command(s) failed");
+
+        assert_eq!(ops::get_task(&conn, "task-a").unwrap().description, "First");
+        assert!(ops::get_blockers(&conn, "task-b").unwrap().is_empty());
+        let notes = ops::list_notes(&conn, "task-b").unwrap();
+        assert_eq!(notes[0].content, "kept");
+
+        // A clean batch succeeds, and parse errors still stop everything.
+        run_exec_keep_going(&conn, "add task-c \"Third\"\n").unwrap();
+        assert!(run_exec_keep_going(&conn, "add task-d \"x\"\nbogus\n").is_err());
+        assert!(ops::get_task(&conn, "task-d").is_err());
+    }
+
     #[test]
     fn exec_rejects_nested_exec() {
         let conn = test_conn();
@@ -794,6 +1671,25 @@ END
         assert_eq!(notes[0].content, "Line one\nLine two");
     }
 
+    #[test]
+    fn exec_heredoc_note_edit_and_note_rm() {
+        let conn = test_conn();
+        let input = "\
+add my-task \"A task\"
+note my-task \"first\"
+note my-task \"second\"
+note-edit 1 <<END
+Edited one
+Edited two
+END
+note-rm 2
+";
+        run_exec(&conn, input).unwrap();
+        let notes = ops::list_notes(&conn, "my-task").unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].content, "Edited one\nEdited two");
+    }
+
     #[test]
     fn exec_heredoc_description() {
         let conn = test_conn();
@@ -808,6 +1704,44 @@ EOF
         assert_eq!(task.description, "A multiline\ndescription here");
     }
 
+    #[test]
+    fn diff_plan_runs_through_exec() {
+        let source = test_conn();
+        let descriptions = [
+            ("plain", "one line"),
+            ("quoted", "say \"hi\" <now>"),
+            (
+                "multi",
+                "first \"line\"\n  indented ${HOME}\nEOF\n\n# not a comment\n",
+            ),
+        ];
+        for (name, description) in descriptions {
+            ops::add_task(
+                &source,
+                ops::AddTaskParams {
+                    name,
+                    parent: (name != "plain").then_some("plain"),
+                    description,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        let target = ops::export_all(&source).unwrap();
+
+        let conn = test_conn();
+        let plan = kbtz::diff::plan(&ops::export_all(&conn).unwrap(), &target).unwrap();
+        let printed: String = plan.iter().map(|op| op.to_exec_line() + "\n").collect();
+        assert_eq!(parse_exec(&printed).unwrap().len(), plan.len());
+        run_exec(&conn, &printed).unwrap();
+
+        for (name, description) in descriptions {
+            assert_eq!(ops::get_task(&conn, name).unwrap().description, description);
+        }
+        let after = ops::export_all(&conn).unwrap();
+        assert!(kbtz::diff::plan(&after, &target).unwrap().is_empty());
+    }
+
     #[test]
     fn exec_heredoc_empty_body() {
         let conn = test_conn();
@@ -916,7 +1850,10 @@ NOTE
                 paused: false,
                 agent: None,
                 directory: None,
+                priority: 0,
+                estimate: None,
                 json: true,
+                time_format: "iso".into(),
             },
         )
         .unwrap();
@@ -940,6 +1877,115 @@ NOTE
         assert_eq!(task.description, "A task");
     }
 
+    #[test]
+    fn exec_add_with_priority_and_set_priority() {
+        let conn = test_conn();
+        let input = "\
+add urgent \"A task\" --priority 3
+add later \"Another task\"
+priority later -1
+";
+        run_exec(&conn, input).unwrap();
+        assert_eq!(ops::get_task(&conn, "urgent").unwrap().priority, 3);
+        assert_eq!(ops::get_task(&conn, "later").unwrap().priority, -1);
+    }
+
+    #[test]
+    fn exec_touch_bumps_updated_at() {
+        let conn = test_conn();
+        run_exec(&conn, "add t \"A task\"\n").unwrap();
+        conn.execute(
+            "UPDATE tasks SET updated_at = '2020-01-01T00:00:00Z' WHERE name = 't'",
+            [],
+        )
+        .unwrap();
+        run_exec(&conn, "touch t\n").unwrap();
+        assert!(ops::get_task(&conn, "t").unwrap().updated_at.as_str() > "2020");
+        assert!(run_exec(&conn, "touch missing\n").is_err());
+    }
+
+    #[test]
+    fn emit_log_lines_holds_partial_lines() {
+        let mut pending = String::new();
+        let mut out = Vec::new();
+        emit_log_lines(
+            &mut pending,
+            "[   0.100s] INFO  one\n[   0.2",
+            false,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "[   0.100s] INFO  one\n");
+        emit_log_lines(&mut pending, "00s] INFO  two\n", false, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&out),
+            "[   0.100s] INFO  one\n[   0.200s] INFO  two\n"
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn emit_log_lines_filters_shepherd() {
+        let log = "\
+[   0.100s] INFO  action: remove ws/1 (task=a)
+[   0.200s] INFO  shepherd: child spawned, child_pid=Some(7)
+{\"elapsed\":0.3,\"level\":\"debug\",\"module\":\"kbtz-shepherd\",\"msg\":\"x\",\"ts\":1.0}
+{\"elapsed\":0.4,\"level\":\"info\",\"module\":\"app\",\"msg\":\"shepherd\",\"ts\":1.0}
+";
+        let mut pending = String::new();
+        let mut out = Vec::new();
+        emit_log_lines(&mut pending, log, true, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("child spawned"));
+        assert!(lines[1].contains("kbtz-shepherd"));
+    }
+
+    #[test]
+    fn log_rejects_unknown_source_and_exec() {
+        assert!(resolve_log_path("bogus").is_err());
+        let conn = test_conn();
+        let err = run_exec(&conn, "log\n").unwrap_err();
+        assert!(format!("{err:#}").contains("log cannot be used inside exec"));
+    }
+
+    #[test]
+    fn exec_rejects_describe_edit() {
+        let conn = test_conn();
+        let err = run_exec(&conn, "add a \"desc\"\ndescribe a --edit\n").unwrap_err();
+        assert!(format!("{err:#}").contains("describe --edit cannot be used inside exec"));
+        assert!(ops::get_task(&conn, "a").is_err(), "batch should not run");
+    }
+
+    #[test]
+    fn exec_flag_and_unflag() {
+        let conn = test_conn();
+        let input = "\
+add stuck \"A task\"
+add waiting \"Another task\"
+flag stuck
+flag waiting
+unflag waiting
+";
+        run_exec(&conn, input).unwrap();
+        assert!(ops::get_task(&conn, "stuck").unwrap().needs_human);
+        assert!(!ops::get_task(&conn, "waiting").unwrap().needs_human);
+    }
+
+    #[test]
+    fn exec_rename_then_use_new_name() {
+        let conn = test_conn();
+        let input = "\
+add old \"A task\"
+rename old new
+note new \"Still reachable\"
+";
+        run_exec(&conn, input).unwrap();
+        assert!(ops::get_task(&conn, "old").is_err());
+        assert_eq!(ops::list_notes(&conn, "new").unwrap().len(), 1);
+    }
+
     #[test]
     fn exec_add_with_agent_flag() {
         let conn = test_conn();
@@ -968,6 +2014,7 @@ NOTE
         let dependents = ops::get_dependents(&conn, "show-agent").unwrap();
         let detail = output::TaskDetail {
             task: &task,
+            path: &[],
             notes: &notes,
             blocked_by: &blockers,
             blocks: &dependents,
@@ -976,6 +2023,23 @@ NOTE
         assert!(json_str.contains("\"agent\": \"claude-opus-4-6\""));
     }
 
+    #[test]
+    fn show_json_includes_ancestor_path() {
+        let conn = test_conn();
+        run_exec(&conn, "add epic \"Epic\"\nadd story \"Story\" -p epic\n").unwrap();
+        let task = ops::get_task(&conn, "story").unwrap();
+        let ancestors = ops::get_ancestors(&conn, "story").unwrap();
+        let detail = output::TaskDetail {
+            task: &task,
+            path: &ancestors,
+            notes: &[],
+            blocked_by: &[],
+            blocks: &[],
+        };
+        let json: serde_json::Value = serde_json::to_value(&detail).unwrap();
+        assert_eq!(json["path"], serde_json::json!(["epic"]));
+    }
+
     #[test]
     fn show_json_agent_null_when_not_set() {
         let conn = test_conn();
@@ -994,6 +2058,7 @@ NOTE
         let dependents = ops::get_dependents(&conn, "no-agent").unwrap();
         let detail = output::TaskDetail {
             task: &task,
+            path: &[],
             notes: &notes,
             blocked_by: &blockers,
             blocks: &dependents,
@@ -1142,14 +2207,83 @@ DESC
         assert!(tokenize_exec_line("   \t  ").unwrap().is_empty());
     }
 
+    #[test]
+    fn tokenize_trailing_comment_is_dropped() {
+        let tokens = tokenize_exec_line("done task-x  # finished \"early\"").unwrap();
+        assert_eq!(tokens, vec!["done", "task-x"]);
+        assert!(has_balanced_quotes("done task-x  # finished \"early"));
+    }
+
+    #[test]
+    fn tokenize_hash_inside_quotes_is_literal() {
+        let tokens = tokenize_exec_line(r#"add task "fix # of retries" # note"#).unwrap();
+        assert_eq!(tokens, vec!["add", "task", "fix # of retries"]);
+    }
+
+    #[test]
+    fn tokenize_hash_mid_token_is_literal() {
+        let tokens = tokenize_exec_line(r##"done task#1 "a"#b"##).unwrap();
+        assert_eq!(tokens, vec!["done", "task#1", "a#b"]);
+        assert!(!has_balanced_quotes("note task#1 \"open"));
+    }
+
     #[test]
     fn tokenize_heredoc_marker_preserved() {
         let tokens = tokenize_exec_line("note task <<EOF").unwrap();
         assert_eq!(tokens, vec!["note", "task", "<<EOF"]);
     }
 
+    #[test]
+    fn expand_vars_replaces_references() {
+        let vars = HashMap::from([("P".to_string(), "epic".to_string())]);
+        assert_eq!(
+            expand_vars("add ${P}-a \"for ${P}\" -p ${P}", &vars).unwrap(),
+            "add epic-a \"for epic\" -p epic"
+        );
+        assert_eq!(expand_vars("costs $5", &vars).unwrap(), "costs $5");
+        assert!(expand_vars("add ${P", &vars).is_err());
+    }
+
     // --- exec integration tests for quoting ---
 
+    #[test]
+    fn exec_set_defines_variables_for_later_lines() {
+        let conn = test_conn();
+        let input = r#"
+set EPIC auth
+set CHILD "${EPIC}-tokens"
+add ${EPIC} "Auth rewrite"
+add ${CHILD} "Token storage for ${EPIC}" -p ${EPIC}
+note ${CHILD} <<EOF
+literal ${EPIC}
+EOF
+"#;
+        run_exec(&conn, input).unwrap();
+        let child = ops::get_task(&conn, "auth-tokens").unwrap();
+        assert_eq!(child.description, "Token storage for auth");
+        assert_eq!(child.parent.as_deref(), Some("auth"));
+        // Heredoc bodies are not expanded.
+        let notes = ops::list_notes(&conn, "auth-tokens").unwrap();
+        assert_eq!(notes[0].content, "literal ${EPIC}");
+    }
+
+    #[test]
+    fn exec_undefined_variable_reports_line() {
+        let conn = test_conn();
+        let input = "set A one\nadd ${A} \"x\"\nadd ${B} \"y\"\n";
+        let err = run_exec(&conn, input).unwrap_err();
+        let msg = format!("{err:#}");
+        assert!(msg.starts_with("line 3: "), "{msg}");
+        assert!(msg.contains("undefined variable 'B'"), "{msg}");
+        // Nothing from the batch was applied.
+        assert!(ops::get_task(&conn, "one").is_err());
+
+        let err = run_exec(&conn, "set 1X y\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 1: invalid variable name '1X'");
+        let err = run_exec(&conn, "set A\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 1: usage: set KEY value");
+    }
+
     #[test]
     fn exec_heredoc_delimiter_requires_exact_line_match() {
         let conn = test_conn();
@@ -1,6 +1,6 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub id: i64,
     pub name: String,
@@ -10,20 +10,35 @@ pub struct Task {
     pub assignee: Option<String>,
     pub agent: Option<String>,
     pub directory: Option<String>,
+    pub priority: i64,
+    /// Set by `kbtz flag`: the task is waiting on a human decision and
+    /// `claim-next` skips it. Unlike a session's NeedsInput status, this
+    /// persists across sessions.
+    #[serde(default)]
+    pub needs_human: bool,
+    /// Optional effort estimate (minutes or points; the unit is up to the
+    /// user). Summed over a subtree by `kbtz rollup`.
+    #[serde(default)]
+    pub estimate: Option<i64>,
     pub status_changed_at: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Returns display icon for a status: x=done, *=active, ~=paused, .=open
+pub fn status_icon(status: &str) -> &'static str {
+    match status {
+        "done" => "x",
+        "active" => "*",
+        "paused" => "~",
+        _ => ".",
+    }
+}
+
 impl Task {
     /// Returns display icon: x=done, *=active, ~=paused, .=open
     pub fn icon(&self) -> &'static str {
-        match self.status.as_str() {
-            "done" => "x",
-            "active" => "*",
-            "paused" => "~",
-            _ => ".",
-        }
+        status_icon(&self.status)
     }
 
     /// Returns status string for display
@@ -32,12 +47,30 @@ impl Task {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Note {
     pub id: i64,
     pub task: String,
     pub content: String,
     pub created_at: String,
+    /// Set for notes written with `kbtz note --label`; a labeled note can
+    /// be replaced in place instead of accumulating.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Estimate totals over a task and its descendants, from `kbtz rollup`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Rollup {
+    pub name: String,
+    /// Sum of every estimate in the subtree.
+    pub total: i64,
+    /// Sum of the estimates of done tasks in the subtree.
+    pub done: i64,
+    /// Number of tasks in the subtree, including the root.
+    pub tasks: usize,
+    /// Tasks in the subtree without an estimate.
+    pub unestimated: usize,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -45,4 +78,51 @@ pub struct SearchResult {
     #[serde(flatten)]
     pub task: Task,
     pub matched_in: Vec<String>,
+    /// Excerpt around the match with matched terms wrapped in `**`: from
+    /// the description when it matched, otherwise from the best-ranked
+    /// note. Absent when only the name matched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+/// Version of the `kbtz export` document format. Bump when the shape of
+/// `ExportDoc` changes incompatibly.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// A `blocker` blocks `blocked` edge from `task_deps`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dep {
+    pub blocker: String,
+    pub blocked: String,
+}
+
+/// Portable snapshot of the whole database produced by `kbtz export`.
+/// Includes every task regardless of status, so it can be imported
+/// elsewhere without loss.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportDoc {
+    pub version: u32,
+    pub tasks: Vec<Task>,
+    pub notes: Vec<Note>,
+    pub deps: Vec<Dep>,
+}
+
+/// A task status change reported by `kbtz watch --json`, serialized as
+/// e.g. `{"event":"status_changed","task":"x","from":"open","to":"active"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TaskEvent {
+    Added {
+        task: String,
+        status: String,
+    },
+    Removed {
+        task: String,
+        status: String,
+    },
+    StatusChanged {
+        task: String,
+        from: String,
+        to: String,
+    },
 }
@@ -1,10 +1,10 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use anyhow::{bail, Result};
 use rusqlite::Connection;
 
-use crate::model::{Note, SearchResult, Task};
-use crate::validate::{detect_dep_cycle, detect_parent_cycle, validate_name};
+use crate::model::{Dep, ExportDoc, Note, Rollup, SearchResult, Task, TaskEvent, EXPORT_VERSION};
+use crate::validate::{detect_dep_cycle, detect_parent_cycle, validate_name, validate_timestamp};
 
 fn task_exists(conn: &Connection, name: &str) -> Result<bool> {
     let count: i64 = conn.query_row(
@@ -32,18 +32,33 @@ fn read_task_row(row: &rusqlite::Row) -> rusqlite::Result<Task> {
         assignee: row.get(5)?,
         agent: row.get(6)?,
         directory: row.get(7)?,
-        status_changed_at: row.get(8)?,
-        created_at: row.get(9)?,
-        updated_at: row.get(10)?,
+        priority: row.get(8)?,
+        needs_human: row.get(9)?,
+        status_changed_at: row.get(10)?,
+        created_at: row.get(11)?,
+        updated_at: row.get(12)?,
+        estimate: row.get(13)?,
     })
 }
 
 const TASK_COLUMNS: &str =
-    "id, name, parent, description, status, assignee, agent, directory, status_changed_at, created_at, updated_at";
+    "id, name, parent, description, status, assignee, agent, directory, priority, needs_human, status_changed_at, created_at, updated_at, estimate";
+
+fn read_note_row(row: &rusqlite::Row) -> rusqlite::Result<Note> {
+    Ok(Note {
+        id: row.get(0)?,
+        task: row.get(1)?,
+        content: row.get(2)?,
+        created_at: row.get(3)?,
+        label: row.get(4)?,
+    })
+}
+
+const NOTE_COLUMNS: &str = "id, task, content, created_at, label";
 
 const INSERT_TASK: &str = "
-INSERT INTO tasks (name, parent, description, status, assignee, agent, directory, status_changed_at)
-VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7,
+INSERT INTO tasks (name, parent, description, status, assignee, agent, directory, priority, estimate, status_changed_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9,
     CASE WHEN ?4 != 'open' THEN strftime('%Y-%m-%dT%H:%M:%SZ', 'now') END)
 ";
 
@@ -109,6 +124,40 @@ SET description = ?1,
 WHERE name = ?2
 ";
 
+const SET_PRIORITY: &str = "
+UPDATE tasks
+SET priority = ?1,
+    updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+WHERE name = ?2
+";
+
+const SET_ESTIMATE: &str = "
+UPDATE tasks
+SET estimate = ?1,
+    updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+WHERE name = ?2
+";
+
+const TOUCH: &str = "
+UPDATE tasks
+SET updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+WHERE name = ?1
+";
+
+const SET_NEEDS_HUMAN: &str = "
+UPDATE tasks
+SET needs_human = ?1,
+    updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+WHERE name = ?2
+";
+
+const SET_NAME: &str = "
+UPDATE tasks
+SET name = ?1,
+    updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+WHERE name = ?2
+";
+
 const SET_PARENT: &str = "
 UPDATE tasks
 SET parent = ?1,
@@ -126,6 +175,8 @@ pub struct AddTaskParams<'a> {
     pub paused: bool,
     pub agent: Option<&'a str>,
     pub directory: Option<&'a str>,
+    pub priority: i64,
+    pub estimate: Option<i64>,
 }
 
 pub fn add_task(conn: &Connection, params: AddTaskParams) -> Result<()> {
@@ -133,6 +184,9 @@ pub fn add_task(conn: &Connection, params: AddTaskParams) -> Result<()> {
     if params.paused && params.claim.is_some() {
         bail!("--paused and --claim are mutually exclusive");
     }
+    if let Some(e) = params.estimate {
+        validate_estimate(e)?;
+    }
     if task_exists(conn, params.name)? {
         bail!("task '{}' already exists", params.name);
     }
@@ -155,7 +209,9 @@ pub fn add_task(conn: &Connection, params: AddTaskParams) -> Result<()> {
             status,
             params.claim,
             params.agent,
-            params.directory
+            params.directory,
+            params.priority,
+            params.estimate
         ],
     )?;
     if let Some(content) = params.note {
@@ -195,23 +251,52 @@ pub fn claim_task(conn: &Connection, name: &str, assignee: &str) -> Result<()> {
     Ok(())
 }
 
+/// How the words of a free-form query are combined into an FTS5 query.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FtsJoin {
+    /// Match rows containing any of the words.
+    #[default]
+    Any,
+    /// Match only rows containing every word.
+    All,
+}
+
+/// Options for turning free-form text into an FTS5 query.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FtsMode {
+    pub join: FtsJoin,
+    /// Match each word as a prefix (`auth` matches `authentication`).
+    pub prefix: bool,
+}
+
 /// Sanitize free-form text into an FTS5 query: split on whitespace, quote each
-/// word, join with OR. Returns None if no words remain after filtering.
-fn sanitize_fts_query(text: &str) -> Option<String> {
+/// word, and join per `mode`. Quotes and `*` are stripped from words so the
+/// only operators in the result are the ones added here. Returns None if no
+/// words remain after filtering.
+fn sanitize_fts_query(text: &str, mode: FtsMode) -> Option<String> {
     let words: Vec<String> = text
         .split_whitespace()
-        .map(|w| {
-            let cleaned: String = w.chars().filter(|c| *c != '"').collect();
-            format!("\"{}\"", cleaned)
+        .filter_map(|w| {
+            let cleaned: String = w.chars().filter(|c| *c != '"' && *c != '*').collect();
+            if cleaned.is_empty() {
+                return None;
+            }
+            let star = if mode.prefix { "*" } else { "" };
+            Some(format!("\"{cleaned}\"{star}"))
         })
         .collect();
     if words.is_empty() {
         return None;
     }
-    Some(words.join(" OR "))
+    let sep = match mode.join {
+        FtsJoin::Any => " OR ",
+        FtsJoin::All => " AND ",
+    };
+    Some(words.join(sep))
 }
 
-/// `{AGENT_FILTER}` is replaced at runtime with the agent type filter clause.
+/// `{AGENT_FILTER}` and `{ROOT_FILTER}` are replaced at runtime with the agent
+/// type and subtree filter clauses.
 const CLAIM_NEXT_WITH_PREFER: &str = "
 SELECT t.name FROM tasks t
 LEFT JOIN (
@@ -233,7 +318,9 @@ LEFT JOIN (
     GROUP BY td.blocker
 ) uc ON uc.blocker = t.name
 WHERE t.status = 'open'
+  AND t.needs_human = 0
   {AGENT_FILTER}
+  {ROOT_FILTER}
   AND NOT EXISTS (
       SELECT 1 FROM task_deps td2
       INNER JOIN tasks bt2 ON bt2.name = td2.blocker AND bt2.status NOT IN ('done')
@@ -242,12 +329,14 @@ WHERE t.status = 'open'
 ORDER BY
     CASE WHEN tfts.rank IS NOT NULL OR nfts.best_rank IS NOT NULL THEN 0 ELSE 1 END,
     MIN(COALESCE(tfts.rank, 0), COALESCE(nfts.best_rank, 0)),
+    t.priority DESC,
     COALESCE(uc.cnt, 0) DESC,
     t.id ASC
 LIMIT 1
 ";
 
-/// `{AGENT_FILTER}` is replaced at runtime with the agent type filter clause.
+/// `{AGENT_FILTER}` and `{ROOT_FILTER}` are replaced at runtime with the agent
+/// type and subtree filter clauses.
 const CLAIM_NEXT_NO_PREFER: &str = "
 SELECT t.name FROM tasks t
 LEFT JOIN (
@@ -256,18 +345,94 @@ LEFT JOIN (
     GROUP BY td.blocker
 ) uc ON uc.blocker = t.name
 WHERE t.status = 'open'
+  AND t.needs_human = 0
   {AGENT_FILTER}
+  {ROOT_FILTER}
   AND NOT EXISTS (
       SELECT 1 FROM task_deps td2
       INNER JOIN tasks bt2 ON bt2.name = td2.blocker AND bt2.status NOT IN ('done')
       WHERE td2.blocked = t.name
   )
 ORDER BY
+    t.priority DESC,
     COALESCE(uc.cnt, 0) DESC,
     t.id ASC
 LIMIT 1
 ";
 
+/// The task `claim_next_task` would claim right now, without claiming it.
+///
+/// `agent_types`: when `Some`, only consider tasks whose `agent` field is
+/// NULL or matches one of the given types. When `None`, all tasks are
+/// eligible.
+pub fn peek_next_task(
+    conn: &Connection,
+    prefer: Option<&str>,
+    root: Option<&str>,
+    agent_types: Option<&[&str]>,
+) -> Result<Option<String>> {
+    let fts_query = prefer.and_then(|p| sanitize_fts_query(p, FtsMode::default()));
+
+    // Restrict candidates to the root task and its descendants. The
+    // names are bound as a JSON array so the subtree size is unbounded.
+    let subtree = match root {
+        Some(r) => {
+            require_task(conn, r)?;
+            let mut names = collect_descendants(conn, r)?;
+            names.push(r.to_string());
+            Some(serde_json::to_string(&names)?)
+        }
+        None => None,
+    };
+    let root_filter = if subtree.is_some() {
+        "AND t.name IN (SELECT value FROM json_each(?9))"
+    } else {
+        ""
+    };
+
+    // Build the agent type filter clause if agent_types is provided.
+    let agent_filter = agent_types.map(|types| {
+        if types.is_empty() {
+            // No configured backends — only match NULL agent.
+            "AND t.agent IS NULL".to_string()
+        } else {
+            let placeholders: Vec<String> = types
+                .iter()
+                .enumerate()
+                .map(|(i, _)| format!("?{}", i + 10))
+                .collect();
+            format!(
+                "AND (t.agent IS NULL OR t.agent IN ({}))",
+                placeholders.join(", ")
+            )
+        }
+    });
+    let filter = agent_filter.as_deref().unwrap_or("");
+
+    let template = if fts_query.is_some() {
+        CLAIM_NEXT_WITH_PREFER
+    } else {
+        CLAIM_NEXT_NO_PREFER
+    };
+    let sql = template
+        .replace("{AGENT_FILTER}", filter)
+        .replace("{ROOT_FILTER}", root_filter);
+    let mut stmt = conn.prepare(&sql)?;
+    if let Some(ref q) = fts_query {
+        stmt.raw_bind_parameter(1, q)?;
+    }
+    if let Some(ref names) = subtree {
+        stmt.raw_bind_parameter(9, names)?;
+    }
+    if let Some(types) = agent_types {
+        for (i, t) in types.iter().enumerate() {
+            stmt.raw_bind_parameter(i + 10, *t)?;
+        }
+    }
+    let result = stmt.raw_query().next()?.map(|row| row.get(0)).transpose()?;
+    Ok(result)
+}
+
 /// Atomically claim the next available open task.
 ///
 /// `agent_types`: when `Some`, only claim tasks whose `agent` field is NULL
@@ -276,6 +441,7 @@ pub fn claim_next_task(
     conn: &Connection,
     assignee: &str,
     prefer: Option<&str>,
+    root: Option<&str>,
     agent_types: Option<&[&str]>,
 ) -> Result<Option<String>> {
     // Use SAVEPOINT instead of BEGIN IMMEDIATE so this works both standalone
@@ -283,54 +449,7 @@ pub fn claim_next_task(
     conn.execute_batch("SAVEPOINT claim_next")?;
 
     let result = (|| -> Result<Option<String>> {
-        let fts_query = prefer.and_then(sanitize_fts_query);
-
-        // Build the agent type filter clause if agent_types is provided.
-        let agent_filter = agent_types.map(|types| {
-            if types.is_empty() {
-                // No configured backends — only match NULL agent.
-                "AND t.agent IS NULL".to_string()
-            } else {
-                let placeholders: Vec<String> = types
-                    .iter()
-                    .enumerate()
-                    .map(|(i, _)| format!("?{}", i + 10))
-                    .collect();
-                format!(
-                    "AND (t.agent IS NULL OR t.agent IN ({}))",
-                    placeholders.join(", ")
-                )
-            }
-        });
-        let filter = agent_filter.as_deref().unwrap_or("");
-
-        let task_name: Option<String> = match fts_query {
-            Some(ref q) => {
-                let sql = CLAIM_NEXT_WITH_PREFER.replace("{AGENT_FILTER}", filter);
-                let mut stmt = conn.prepare(&sql)?;
-                stmt.raw_bind_parameter(1, q)?;
-                if let Some(types) = agent_types {
-                    for (i, t) in types.iter().enumerate() {
-                        stmt.raw_bind_parameter(i + 10, *t)?;
-                    }
-                }
-                let result = stmt.raw_query().next()?.map(|row| row.get(0)).transpose()?;
-                result
-            }
-            None => {
-                let sql = CLAIM_NEXT_NO_PREFER.replace("{AGENT_FILTER}", filter);
-                let mut stmt = conn.prepare(&sql)?;
-                if let Some(types) = agent_types {
-                    for (i, t) in types.iter().enumerate() {
-                        stmt.raw_bind_parameter(i + 10, *t)?;
-                    }
-                }
-                let result = stmt.raw_query().next()?.map(|row| row.get(0)).transpose()?;
-                result
-            }
-        };
-
-        let Some(name) = task_name else {
+        let Some(name) = peek_next_task(conn, prefer, root, agent_types)? else {
             return Ok(None);
         };
 
@@ -357,6 +476,67 @@ pub fn claim_next_task(
     }
 }
 
+/// Claim up to `count` tasks in one transaction, in the order successive
+/// `claim_next_task` calls would pick them. The tasks are assigned to
+/// `{assignee_prefix}{first_id}`, `{assignee_prefix}{first_id + 1}`, ...;
+/// returns the `(assignee, task)` pairs, which may be fewer than `count`.
+///
+/// Standalone, the batch holds the write lock from the first pick
+/// (`BEGIN IMMEDIATE`), so a concurrent batch waits on `busy_timeout`
+/// instead of racing for the same tasks. Nested inside a transaction it
+/// uses a SAVEPOINT like `claim_next_task`.
+pub fn claim_next_batch(
+    conn: &Connection,
+    assignee_prefix: &str,
+    first_id: u64,
+    count: usize,
+    prefer: Option<&str>,
+    root: Option<&str>,
+    agent_types: Option<&[&str]>,
+) -> Result<Vec<(String, String)>> {
+    let standalone = conn.is_autocommit();
+    if standalone {
+        conn.execute_batch("BEGIN IMMEDIATE")?;
+    } else {
+        conn.execute_batch("SAVEPOINT claim_batch")?;
+    }
+
+    let result = (|| -> Result<Vec<(String, String)>> {
+        let mut claimed = Vec::new();
+        for id in (first_id..).take(count) {
+            let Some(name) = peek_next_task(conn, prefer, root, agent_types)? else {
+                break;
+            };
+            let assignee = format!("{assignee_prefix}{id}");
+            if conn.execute(CLAIM_OPEN, rusqlite::params![assignee, name])? == 0 {
+                break;
+            }
+            claimed.push((assignee, name));
+        }
+        Ok(claimed)
+    })();
+
+    match (result, standalone) {
+        (Ok(v), true) => {
+            conn.execute_batch("COMMIT")?;
+            Ok(v)
+        }
+        (Ok(v), false) => {
+            conn.execute_batch("RELEASE claim_batch")?;
+            Ok(v)
+        }
+        (Err(e), true) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+        (Err(e), false) => {
+            let _ = conn.execute_batch("ROLLBACK TO claim_batch");
+            let _ = conn.execute_batch("RELEASE claim_batch");
+            Err(e)
+        }
+    }
+}
+
 pub fn steal_task(conn: &Connection, name: &str, new_assignee: &str) -> Result<String> {
     require_task(conn, name)?;
     let (status, current_assignee): (String, Option<String>) = conn.query_row(
@@ -389,6 +569,53 @@ pub fn release_task(conn: &Connection, name: &str, assignee: &str) -> Result<()>
     }
 }
 
+/// Release every active task whose assignee starts with `from_prefix`, or
+/// hand them all to `to` when given. Recovers claims left by a dead agent
+/// or workspace (e.g. prefix `ws/`). Returns the affected task names.
+pub fn reassign_prefix(
+    conn: &Connection,
+    from_prefix: &str,
+    to: Option<&str>,
+) -> Result<Vec<String>> {
+    if from_prefix.is_empty() {
+        bail!("assignee prefix must not be empty");
+    }
+
+    conn.execute_batch("SAVEPOINT reassign_prefix")?;
+
+    let result = (|| -> Result<Vec<String>> {
+        let names = conn
+            .prepare(
+                "SELECT name FROM tasks
+                 WHERE status = 'active' AND substr(assignee, 1, length(?1)) = ?1
+                 ORDER BY id",
+            )?
+            .query_map([from_prefix], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        for name in &names {
+            match to {
+                Some(assignee) => {
+                    conn.execute(REASSIGN_ACTIVE, rusqlite::params![assignee, name])?
+                }
+                None => conn.execute(RELEASE_TO_OPEN, [name])?,
+            };
+        }
+        Ok(names)
+    })();
+
+    match result {
+        Ok(names) => {
+            conn.execute_batch("RELEASE reassign_prefix")?;
+            Ok(names)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO reassign_prefix");
+            let _ = conn.execute_batch("RELEASE reassign_prefix");
+            Err(e)
+        }
+    }
+}
+
 pub fn mark_done(conn: &Connection, name: &str) -> Result<()> {
     require_task(conn, name)?;
     let status: String =
@@ -402,6 +629,62 @@ pub fn mark_done(conn: &Connection, name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Mark `name` and all of its non-done descendants done. Active descendants
+/// are refused unless `force` is set, so a working agent's task isn't
+/// completed out from under it. Returns the number of tasks marked.
+pub fn mark_done_recursive(conn: &Connection, name: &str, force: bool) -> Result<usize> {
+    require_task(conn, name)?;
+
+    conn.execute_batch("SAVEPOINT mark_done_recursive")?;
+
+    let result = (|| -> Result<usize> {
+        let mut names = vec![name.to_string()];
+        names.extend(collect_descendants(conn, name)?);
+
+        let mut pending = Vec::new();
+        let mut active = Vec::new();
+        for n in &names {
+            let status: String =
+                conn.query_row("SELECT status FROM tasks WHERE name = ?1", [n], |row| {
+                    row.get(0)
+                })?;
+            match status.as_str() {
+                "done" => continue,
+                "active" if n != name => active.push(n.as_str()),
+                _ => {}
+            }
+            pending.push(n.as_str());
+        }
+
+        if !active.is_empty() && !force {
+            bail!(
+                "subtask(s) still active: {}; use --force to mark them done anyway",
+                active.join(", ")
+            );
+        }
+        if pending.is_empty() {
+            bail!("task '{name}' and its subtasks are already done");
+        }
+
+        for n in &pending {
+            conn.execute(SET_DONE, [n])?;
+        }
+        Ok(pending.len())
+    })();
+
+    match result {
+        Ok(count) => {
+            conn.execute_batch("RELEASE mark_done_recursive")?;
+            Ok(count)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO mark_done_recursive");
+            let _ = conn.execute_batch("RELEASE mark_done_recursive");
+            Err(e)
+        }
+    }
+}
+
 pub fn force_unassign_task(conn: &Connection, name: &str) -> Result<()> {
     require_task(conn, name)?;
     let status: String =
@@ -428,6 +711,47 @@ pub fn reopen_task(conn: &Connection, name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Reopen every done task in the subtree rooted at `name` (including `name`
+/// itself). Returns the number of tasks reopened.
+pub fn reopen_task_recursive(conn: &Connection, name: &str) -> Result<usize> {
+    require_task(conn, name)?;
+
+    conn.execute_batch("SAVEPOINT reopen_recursive")?;
+
+    let result = (|| -> Result<usize> {
+        let mut names = vec![name.to_string()];
+        names.extend(collect_descendants(conn, name)?);
+
+        let mut count = 0;
+        for n in &names {
+            let status: String =
+                conn.query_row("SELECT status FROM tasks WHERE name = ?1", [n], |row| {
+                    row.get(0)
+                })?;
+            if status == "done" {
+                conn.execute(RELEASE_TO_OPEN, [n])?;
+                count += 1;
+            }
+        }
+        if count == 0 {
+            bail!("no done tasks in '{name}' or its subtasks");
+        }
+        Ok(count)
+    })();
+
+    match result {
+        Ok(count) => {
+            conn.execute_batch("RELEASE reopen_recursive")?;
+            Ok(count)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO reopen_recursive");
+            let _ = conn.execute_batch("RELEASE reopen_recursive");
+            Err(e)
+        }
+    }
+}
+
 pub fn pause_task(conn: &Connection, name: &str) -> Result<()> {
     require_task(conn, name)?;
     let status: String =
@@ -457,12 +781,152 @@ pub fn unpause_task(conn: &Connection, name: &str) -> Result<()> {
     Ok(())
 }
 
+/// A status change `kbtz bulk` applies to each named task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkOp {
+    Pause,
+    Unpause,
+    Done,
+    Reopen,
+}
+
+impl BulkOp {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "pause" => Ok(Self::Pause),
+            "unpause" => Ok(Self::Unpause),
+            "done" => Ok(Self::Done),
+            "reopen" => Ok(Self::Reopen),
+            _ => bail!("invalid bulk op '{s}': must be pause, unpause, done, or reopen"),
+        }
+    }
+
+    fn apply(self, conn: &Connection, name: &str) -> Result<()> {
+        match self {
+            Self::Pause => pause_task(conn, name),
+            Self::Unpause => unpause_task(conn, name),
+            Self::Done => mark_done(conn, name),
+            Self::Reopen => reopen_task(conn, name),
+        }
+    }
+}
+
+/// Apply `op` to every task in `names`. By default the batch is
+/// all-or-nothing: the first failure rolls back the rest and is returned.
+/// With `keep_going`, each task gets its own savepoint, so failures only
+/// skip that task; they are returned as `(name, error)` pairs.
+pub fn set_status_bulk(
+    conn: &Connection,
+    op: BulkOp,
+    names: &[String],
+    keep_going: bool,
+) -> Result<Vec<(String, String)>> {
+    if keep_going {
+        let mut failures = Vec::new();
+        for name in names {
+            conn.execute_batch("SAVEPOINT bulk_task")?;
+            match op.apply(conn, name) {
+                Ok(()) => conn.execute_batch("RELEASE bulk_task")?,
+                Err(e) => {
+                    let _ = conn.execute_batch("ROLLBACK TO bulk_task");
+                    let _ = conn.execute_batch("RELEASE bulk_task");
+                    failures.push((name.clone(), format!("{e:#}")));
+                }
+            }
+        }
+        return Ok(failures);
+    }
+
+    conn.execute_batch("SAVEPOINT bulk_status")?;
+
+    let result = names.iter().try_for_each(|name| op.apply(conn, name));
+
+    match result {
+        Ok(()) => {
+            conn.execute_batch("RELEASE bulk_status")?;
+            Ok(Vec::new())
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO bulk_status");
+            let _ = conn.execute_batch("RELEASE bulk_status");
+            Err(e)
+        }
+    }
+}
+
 pub fn update_description(conn: &Connection, name: &str, description: &str) -> Result<()> {
     require_task(conn, name)?;
     conn.execute(SET_DESCRIPTION, rusqlite::params![description, name])?;
     Ok(())
 }
 
+pub fn set_priority(conn: &Connection, name: &str, priority: i64) -> Result<()> {
+    require_task(conn, name)?;
+    conn.execute(SET_PRIORITY, rusqlite::params![priority, name])?;
+    Ok(())
+}
+
+/// Bump `updated_at` to now without changing anything else, to mark a task
+/// as still relevant.
+pub fn touch_task(conn: &Connection, name: &str) -> Result<()> {
+    require_task(conn, name)?;
+    conn.execute(TOUCH, [name])?;
+    Ok(())
+}
+
+fn validate_estimate(estimate: i64) -> Result<()> {
+    if estimate < 0 {
+        bail!("estimate must not be negative (got {estimate})");
+    }
+    Ok(())
+}
+
+/// Set a task's estimate, or clear it with `None`.
+pub fn set_estimate(conn: &Connection, name: &str, estimate: Option<i64>) -> Result<()> {
+    require_task(conn, name)?;
+    if let Some(e) = estimate {
+        validate_estimate(e)?;
+    }
+    conn.execute(SET_ESTIMATE, rusqlite::params![estimate, name])?;
+    Ok(())
+}
+
+const ROLLUP: &str = "
+SELECT COUNT(*),
+       COALESCE(SUM(estimate), 0),
+       COALESCE(SUM(CASE WHEN status = 'done' THEN estimate END), 0),
+       COUNT(*) - COUNT(estimate)
+FROM tasks
+WHERE name IN (SELECT value FROM json_each(?1))
+";
+
+/// Sum the estimates of `name` and all of its descendants, in total and
+/// over the done tasks.
+pub fn rollup(conn: &Connection, name: &str) -> Result<Rollup> {
+    require_task(conn, name)?;
+    let mut names = collect_descendants(conn, name)?;
+    names.push(name.to_string());
+    let names = serde_json::to_string(&names)?;
+    let (tasks, total, done, unestimated) = conn.query_row(ROLLUP, [names], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    })?;
+    Ok(Rollup {
+        name: name.to_string(),
+        total,
+        done,
+        tasks,
+        unestimated,
+    })
+}
+
+/// Set or clear the needs-human flag. Flagged tasks stay open but are
+/// skipped by `claim_next_task`; an explicit `claim_task` still works.
+pub fn set_needs_human(conn: &Connection, name: &str, needs_human: bool) -> Result<()> {
+    require_task(conn, name)?;
+    conn.execute(SET_NEEDS_HUMAN, rusqlite::params![needs_human, name])?;
+    Ok(())
+}
+
 pub fn reparent_task(conn: &Connection, name: &str, parent: Option<&str>) -> Result<()> {
     require_task(conn, name)?;
     if let Some(new_parent) = parent {
@@ -475,6 +939,164 @@ pub fn reparent_task(conn: &Connection, name: &str, parent: Option<&str>) -> Res
     Ok(())
 }
 
+/// Reparent every direct child of `from` under `to` (root level when
+/// `None`). All-or-nothing: if any move would create a cycle, nothing
+/// moves. Returns the moved task names.
+pub fn move_children(conn: &Connection, from: &str, to: Option<&str>) -> Result<Vec<String>> {
+    require_task(conn, from)?;
+    if let Some(to) = to {
+        require_task(conn, to)?;
+    }
+
+    conn.execute_batch("SAVEPOINT move_children")?;
+
+    let result = (|| -> Result<Vec<String>> {
+        let children = conn
+            .prepare("SELECT name FROM tasks WHERE parent = ?1 ORDER BY id")?
+            .query_map([from], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        for child in &children {
+            if let Some(to) = to {
+                if detect_parent_cycle(conn, child, to)? {
+                    bail!("moving '{child}' under '{to}' would create a cycle");
+                }
+            }
+            conn.execute(SET_PARENT, rusqlite::params![to, child])?;
+        }
+        Ok(children)
+    })();
+
+    match result {
+        Ok(children) => {
+            conn.execute_batch("RELEASE move_children")?;
+            Ok(children)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO move_children");
+            let _ = conn.execute_batch("RELEASE move_children");
+            Err(e)
+        }
+    }
+}
+
+const DUPLICATE_TASK: &str = "
+INSERT INTO tasks (name, parent, description, agent, directory, priority, estimate)
+SELECT ?1, ?2, description, agent, directory, priority, estimate FROM tasks WHERE name = ?3
+";
+
+/// Copy `src` and all of its descendants as a fresh subtree, naming each
+/// copy `rename(original)`. The copy of `src` gets `src`'s parent. Copies
+/// are open and unassigned; dependencies between tasks in the subtree are
+/// recreated between their copies, while those on outside tasks are
+/// dropped. Notes are copied when `with_notes` is set. Every new name is
+/// checked before anything is inserted. Returns `(original, copy)` pairs,
+/// parents first.
+pub fn duplicate_subtree(
+    conn: &Connection,
+    src: &str,
+    rename: impl Fn(&str) -> String,
+    with_notes: bool,
+) -> Result<Vec<(String, String)>> {
+    require_task(conn, src)?;
+    let mut originals = vec![src.to_string()];
+    originals.extend(collect_descendants(conn, src)?);
+
+    let mut copies: HashMap<String, String> = HashMap::new();
+    let mut taken = HashSet::new();
+    for original in &originals {
+        let copy = rename(original);
+        validate_name(&copy)?;
+        if task_exists(conn, &copy)? || !taken.insert(copy.clone()) {
+            bail!("task '{copy}' already exists");
+        }
+        copies.insert(original.clone(), copy);
+    }
+
+    conn.execute_batch("SAVEPOINT duplicate_subtree")?;
+
+    let result = (|| -> Result<Vec<(String, String)>> {
+        let mut pairs = Vec::new();
+        for original in &originals {
+            let parent: Option<String> = conn.query_row(
+                "SELECT parent FROM tasks WHERE name = ?1",
+                [original],
+                |row| row.get(0),
+            )?;
+            let parent = if original == src {
+                parent
+            } else {
+                parent.map(|p| copies[&p].clone())
+            };
+            let copy = &copies[original];
+            conn.execute(DUPLICATE_TASK, rusqlite::params![copy, parent, original])?;
+            if with_notes {
+                conn.execute(
+                    "INSERT INTO notes (task, content, label)
+                     SELECT ?1, content, label FROM notes WHERE task = ?2 ORDER BY id",
+                    rusqlite::params![copy, original],
+                )?;
+            }
+            pairs.push((original.clone(), copy.clone()));
+        }
+
+        let deps = conn
+            .prepare("SELECT blocker, blocked FROM task_deps")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<(String, String)>>>()?;
+        for (blocker, blocked) in deps {
+            if let (Some(blocker), Some(blocked)) = (copies.get(&blocker), copies.get(&blocked)) {
+                conn.execute(
+                    "INSERT INTO task_deps (blocker, blocked) VALUES (?1, ?2)",
+                    rusqlite::params![blocker, blocked],
+                )?;
+            }
+        }
+        Ok(pairs)
+    })();
+
+    match result {
+        Ok(pairs) => {
+            conn.execute_batch("RELEASE duplicate_subtree")?;
+            Ok(pairs)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO duplicate_subtree");
+            let _ = conn.execute_batch("RELEASE duplicate_subtree");
+            Err(e)
+        }
+    }
+}
+
+pub fn rename_task(conn: &Connection, old: &str, new: &str) -> Result<()> {
+    validate_name(new)?;
+    require_task(conn, old)?;
+    if task_exists(conn, new)? {
+        bail!("task '{new}' already exists");
+    }
+
+    conn.execute_batch("SAVEPOINT rename_task")?;
+
+    let result = (|| -> Result<()> {
+        // Parent, note, and dependency references follow via ON UPDATE
+        // CASCADE, and the tasks_fts_au trigger reindexes the row under the
+        // same rowid. Note rowids are untouched, so notes_fts stays valid.
+        conn.execute(SET_NAME, rusqlite::params![new, old])?;
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => {
+            conn.execute_batch("RELEASE rename_task")?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO rename_task");
+            let _ = conn.execute_batch("RELEASE rename_task");
+            Err(e)
+        }
+    }
+}
+
 pub fn remove_task(conn: &Connection, name: &str, recursive: bool) -> Result<()> {
     require_task(conn, name)?;
 
@@ -498,14 +1120,66 @@ pub fn remove_task(conn: &Connection, name: &str, recursive: bool) -> Result<()>
     Ok(())
 }
 
-fn collect_descendants(conn: &Connection, name: &str) -> Result<Vec<String>> {
-    let mut result = Vec::new();
-    let mut queue = std::collections::VecDeque::new();
-    queue.push_back(name.to_string());
-    while let Some(current) = queue.pop_front() {
-        let mut stmt = conn.prepare_cached("SELECT name FROM tasks WHERE parent = ?1")?;
-        let children: Vec<String> = stmt
-            .query_map([&current], |row| row.get(0))?
+/// A task that `remove_task` would delete, with the notes and dependency
+/// edges that go with it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RemovalEntry {
+    pub name: String,
+    pub notes: i64,
+    pub deps: i64,
+}
+
+/// List what `remove_task` would delete without touching the database:
+/// the task itself followed by its descendants when `recursive`. Fails the
+/// same way `remove_task` would for a non-recursive removal of a parent.
+pub fn preview_removal(
+    conn: &Connection,
+    name: &str,
+    recursive: bool,
+) -> Result<Vec<RemovalEntry>> {
+    require_task(conn, name)?;
+    let mut names = vec![name.to_string()];
+    let descendants = collect_descendants(conn, name)?;
+    if !recursive && !descendants.is_empty() {
+        bail!("task '{name}' has children; use --recursive to remove");
+    }
+    names.extend(descendants);
+
+    names
+        .into_iter()
+        .map(|n| {
+            let notes =
+                conn.query_row("SELECT COUNT(*) FROM notes WHERE task = ?1", [&n], |row| {
+                    row.get(0)
+                })?;
+            let deps = conn.query_row(
+                "SELECT COUNT(*) FROM task_deps WHERE blocker = ?1 OR blocked = ?1",
+                [&n],
+                |row| row.get(0),
+            )?;
+            Ok(RemovalEntry {
+                name: n,
+                notes,
+                deps,
+            })
+        })
+        .collect()
+}
+
+/// Number of tasks beneath `name` in the parent/child tree.
+pub fn count_descendants(conn: &Connection, name: &str) -> Result<usize> {
+    require_task(conn, name)?;
+    Ok(collect_descendants(conn, name)?.len())
+}
+
+fn collect_descendants(conn: &Connection, name: &str) -> Result<Vec<String>> {
+    let mut result = Vec::new();
+    let mut queue = std::collections::VecDeque::new();
+    queue.push_back(name.to_string());
+    while let Some(current) = queue.pop_front() {
+        let mut stmt = conn.prepare_cached("SELECT name FROM tasks WHERE parent = ?1")?;
+        let children: Vec<String> = stmt
+            .query_map([&current], |row| row.get(0))?
             .collect::<rusqlite::Result<_>>()?;
         for child in children {
             result.push(child.clone());
@@ -515,6 +1189,30 @@ fn collect_descendants(conn: &Connection, name: &str) -> Result<Vec<String>> {
     Ok(result)
 }
 
+/// Upper bound on parent-chain length walked by `get_ancestors`. Parent
+/// cycles are rejected on write, so this only guards against a corrupt DB.
+const MAX_ANCESTOR_DEPTH: usize = 1000;
+
+/// Names of `name`'s ancestors, ordered from the root down to its parent.
+pub fn get_ancestors(conn: &Connection, name: &str) -> Result<Vec<String>> {
+    require_task(conn, name)?;
+    let mut stmt = conn.prepare_cached("SELECT parent FROM tasks WHERE name = ?1")?;
+    let mut ancestors = Vec::new();
+    let mut current = name.to_string();
+    while ancestors.len() < MAX_ANCESTOR_DEPTH {
+        let parent: Option<String> = stmt.query_row([&current], |row| row.get(0))?;
+        match parent {
+            Some(p) => {
+                ancestors.push(p.clone());
+                current = p;
+            }
+            None => break,
+        }
+    }
+    ancestors.reverse();
+    Ok(ancestors)
+}
+
 pub fn get_task(conn: &Connection, name: &str) -> Result<Task> {
     require_task(conn, name)?;
     let query = format!("SELECT {TASK_COLUMNS} FROM tasks WHERE name = ?1");
@@ -542,26 +1240,133 @@ impl StatusFilter {
         }
     }
 
-    fn matches(&self, task: &Task) -> bool {
+    pub fn as_str(&self) -> &'static str {
         match self {
-            Self::Open => task.status == "open",
-            Self::Active => task.status == "active",
-            Self::Paused => task.status == "paused",
-            Self::Done => task.status == "done",
+            Self::Open => "open",
+            Self::Active => "active",
+            Self::Paused => "paused",
+            Self::Done => "done",
         }
     }
 }
 
-fn get_blocked_task_names(conn: &Connection) -> Result<HashSet<String>> {
-    let mut stmt = conn.prepare(
-        "SELECT DISTINCT td.blocked FROM task_deps td \
-         INNER JOIN tasks t ON t.name = td.blocker AND t.status != 'done'",
-    )?;
-    let rows = stmt.query_map([], |row| row.get(0))?;
-    rows.collect::<rusqlite::Result<HashSet<_>>>()
+impl rusqlite::ToSql for StatusFilter {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(self.as_str().into())
+    }
+}
+
+/// `kbtz list` filters. Done and paused tasks are left out unless `status`
+/// picks one or `all` is set. `roots` and `leaves` may both be set: a task
+/// with no parent and no children matches both.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListFilter<'a> {
+    /// Only tasks with this status.
+    pub status: Option<StatusFilter>,
+    /// Every status, overriding `status` and the default.
+    pub all: bool,
+    pub assignee: Option<&'a str>,
+    /// Only tasks with (`true`) or without (`false`) an unfinished blocker.
+    pub blocked: Option<bool>,
+    /// Only top-level tasks (no parent).
+    pub roots: bool,
+    /// Only tasks with no children, of any status.
+    pub leaves: bool,
+    /// Only tasks updated strictly after this UTC timestamp. Stored
+    /// timestamps are fixed-width, so a validated `since` compares as text.
+    pub since: Option<&'a str>,
+}
+
+impl<'a> ListFilter<'a> {
+    fn validate(&self) -> Result<()> {
+        if let Some(ts) = self.since {
+            validate_timestamp(ts)?;
+        }
+        Ok(())
+    }
+
+    /// Parameters ?1-?9 of `TASK_FILTER`.
+    fn params<'p>(
+        &'p self,
+        name: &'p Option<&'a str>,
+        parent: &'p Option<&'a str>,
+    ) -> [&'p dyn rusqlite::ToSql; 9] {
+        [
+            name,
+            parent,
+            &self.all,
+            &self.status,
+            &self.assignee,
+            &self.blocked,
+            &self.roots,
+            &self.leaves,
+            &self.since,
+        ]
+    }
+}
+
+/// WHERE clause shared by `LIST_TASKS` and `COUNT_TASKS`: an optional task
+/// name and parent, then the `ListFilter`.
+const TASK_FILTER: &str = "
+WHERE (?1 IS NULL OR t.name = ?1)
+  AND (?2 IS NULL OR t.parent = ?2)
+  AND (?3 = 1 OR CASE WHEN ?4 IS NULL THEN t.status NOT IN ('done', 'paused')
+                      ELSE t.status = ?4 END)
+  AND (?5 IS NULL OR t.assignee = ?5)
+  AND (?6 IS NULL OR EXISTS (
+        SELECT 1 FROM task_deps td
+        JOIN tasks b ON b.name = td.blocker AND b.status != 'done'
+        WHERE td.blocked = t.name
+      ) = ?6)
+  AND (?7 = 0 OR t.parent IS NULL)
+  AND (?8 = 0 OR NOT EXISTS (SELECT 1 FROM tasks c WHERE c.parent = t.name))
+  AND (?9 IS NULL OR t.updated_at > ?9)
+";
+
+const LIST_TASKS: &str = "
+SELECT {TASK_COLUMNS} FROM tasks t
+{TASK_FILTER}
+ORDER BY t.id
+LIMIT ?10 OFFSET ?11
+";
+
+const COUNT_TASKS: &str = "
+SELECT COUNT(*) FROM tasks t
+{TASK_FILTER}
+";
+
+/// The `page` of tasks matching `filter`, narrowed to the task called
+/// `name` and/or the children of `parent` when given.
+fn query_tasks(
+    conn: &Connection,
+    name: Option<&str>,
+    parent: Option<&str>,
+    filter: &ListFilter,
+    page: Page,
+) -> Result<Vec<Task>> {
+    let sql = LIST_TASKS
+        .replace("{TASK_FILTER}", TASK_FILTER)
+        .replace("{TASK_COLUMNS}", TASK_COLUMNS);
+    let mut stmt = conn.prepare_cached(&sql)?;
+    // SQLite treats a negative LIMIT as no limit.
+    let limit = page.limit.map_or(-1, |l| l as i64);
+    let offset = page.offset as i64;
+    let mut params = filter.params(&name, &parent).to_vec();
+    params.extend([&limit as &dyn rusqlite::ToSql, &offset]);
+    let rows = stmt.query_map(&*params, read_task_row)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
         .map_err(Into::into)
 }
 
+/// Number of tasks `query_tasks` would return for `parent` and `filter`
+/// without a page.
+fn query_count(conn: &Connection, parent: Option<&str>, filter: &ListFilter) -> Result<usize> {
+    let sql = COUNT_TASKS.replace("{TASK_FILTER}", TASK_FILTER);
+    let mut stmt = conn.prepare_cached(&sql)?;
+    let n: i64 = stmt.query_row(filter.params(&None, &parent), |row| row.get(0))?;
+    Ok(n as usize)
+}
+
 pub fn list_tasks(
     conn: &Connection,
     status: Option<StatusFilter>,
@@ -570,48 +1375,114 @@ pub fn list_tasks(
     assignee: Option<&str>,
     blocked: Option<bool>,
 ) -> Result<Vec<Task>> {
-    if let Some(r) = root {
-        require_task(conn, r)?;
-    }
+    let filter = ListFilter {
+        status,
+        all,
+        assignee,
+        blocked,
+        ..Default::default()
+    };
+    list_tasks_filtered(conn, root, filter)
+}
 
-    let mut tasks: Vec<Task> = Vec::new();
+/// Tasks matching `filter`, by id, or depth-first under `root` when given.
+pub fn list_tasks_filtered(
+    conn: &Connection,
+    root: Option<&str>,
+    filter: ListFilter,
+) -> Result<Vec<Task>> {
+    list_tasks_page(conn, root, filter, Page::default()).map(|(tasks, _)| tasks)
+}
 
-    if let Some(root_name) = root {
-        let root_task = get_task(conn, root_name)?;
-        tasks.push(root_task);
-        let descendants = collect_descendants(conn, root_name)?;
-        for d in &descendants {
-            tasks.push(get_task(conn, d)?);
-        }
-    } else {
-        let query = format!("SELECT {TASK_COLUMNS} FROM tasks ORDER BY id");
-        let mut stmt = conn.prepare(&query)?;
-        let rows = stmt.query_map([], read_task_row)?;
-        for row in rows {
-            tasks.push(row?);
-        }
+/// One `page` of `list_tasks_filtered`, plus the total number of matches.
+/// The page is cut in SQL except under `root`, whose depth-first order is
+/// built here.
+pub fn list_tasks_page(
+    conn: &Connection,
+    root: Option<&str>,
+    filter: ListFilter,
+    page: Page,
+) -> Result<(Vec<Task>, usize)> {
+    filter.validate()?;
+    let Some(root_name) = root else {
+        return query_page(conn, None, &filter, page);
+    };
+    require_task(conn, root_name)?;
+    let mut tasks = query_tasks(conn, Some(root_name), None, &filter, Page::default())?;
+    for d in &collect_descendants(conn, root_name)? {
+        tasks.extend(query_tasks(conn, Some(d), None, &filter, Page::default())?);
     }
+    let total = tasks.len();
+    Ok((page.slice(tasks), total))
+}
 
-    // Apply status filter
-    if !all {
-        if let Some(s) = status {
-            tasks.retain(|t| s.matches(t));
-        } else {
-            // Default: exclude done and paused tasks
-            tasks.retain(|t| t.status != "done" && t.status != "paused");
-        }
-    }
+/// `page` of the tasks matching `filter` under `parent`, and their total.
+fn query_page(
+    conn: &Connection,
+    parent: Option<&str>,
+    filter: &ListFilter,
+    page: Page,
+) -> Result<(Vec<Task>, usize)> {
+    let tasks = query_tasks(conn, None, parent, filter, page)?;
+    let total = if page.is_all() {
+        tasks.len()
+    } else {
+        query_count(conn, parent, filter)?
+    };
+    Ok((tasks, total))
+}
+
+/// Sort order for `kbtz list --sort`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskSort {
+    Id,
+    Name,
+    Status,
+    Updated,
+    Created,
+}
 
-    if let Some(assignee) = assignee {
-        tasks.retain(|t| t.assignee.as_deref() == Some(assignee));
+impl TaskSort {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "id" => Ok(Self::Id),
+            "name" => Ok(Self::Name),
+            "status" => Ok(Self::Status),
+            "updated" => Ok(Self::Updated),
+            "created" => Ok(Self::Created),
+            _ => bail!("invalid sort '{s}': must be id, name, status, updated, or created"),
+        }
     }
+}
 
-    if let Some(want_blocked) = blocked {
-        let blocked_names = get_blocked_task_names(conn)?;
-        tasks.retain(|t| blocked_names.contains(&t.name) == want_blocked);
+/// Lifecycle order used by `TaskSort::Status`: work in progress first.
+fn status_rank(status: &str) -> u8 {
+    match status {
+        "active" => 0,
+        "open" => 1,
+        "paused" => 2,
+        _ => 3,
     }
+}
 
-    Ok(tasks)
+/// Sort tasks by `key` (ties broken by id), descending when `reverse`.
+/// Timestamps are fixed-width UTC strings, so they compare as text.
+pub fn sort_tasks(tasks: &mut [Task], key: TaskSort, reverse: bool) {
+    tasks.sort_by(|a, b| {
+        let ord = match key {
+            TaskSort::Id => std::cmp::Ordering::Equal,
+            TaskSort::Name => a.name.cmp(&b.name),
+            TaskSort::Status => status_rank(&a.status).cmp(&status_rank(&b.status)),
+            TaskSort::Updated => a.updated_at.cmp(&b.updated_at),
+            TaskSort::Created => a.created_at.cmp(&b.created_at),
+        }
+        .then(a.id.cmp(&b.id));
+        if reverse {
+            ord.reverse()
+        } else {
+            ord
+        }
+    });
 }
 
 pub fn list_children(
@@ -622,53 +1493,188 @@ pub fn list_children(
     assignee: Option<&str>,
     blocked: Option<bool>,
 ) -> Result<Vec<Task>> {
+    let filter = ListFilter {
+        status,
+        all,
+        assignee,
+        blocked,
+        ..Default::default()
+    };
+    list_children_filtered(conn, parent, filter)
+}
+
+/// Direct children of `parent` matching `filter`, by id.
+pub fn list_children_filtered(
+    conn: &Connection,
+    parent: &str,
+    filter: ListFilter,
+) -> Result<Vec<Task>> {
+    list_children_page(conn, parent, filter, Page::default()).map(|(tasks, _)| tasks)
+}
+
+/// One `page` of `list_children_filtered`, cut in SQL, plus the total
+/// number of matches.
+pub fn list_children_page(
+    conn: &Connection,
+    parent: &str,
+    filter: ListFilter,
+    page: Page,
+) -> Result<(Vec<Task>, usize)> {
     require_task(conn, parent)?;
-    let query = format!("SELECT {TASK_COLUMNS} FROM tasks WHERE parent = ?1 ORDER BY id");
-    let mut stmt = conn.prepare(&query)?;
-    let rows = stmt.query_map([parent], read_task_row)?;
-    let mut tasks: Vec<Task> = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+    filter.validate()?;
+    query_page(conn, Some(parent), &filter, page)
+}
 
-    if !all {
-        if let Some(s) = status {
-            tasks.retain(|t| s.matches(t));
-        } else {
-            tasks.retain(|t| t.status != "done" && t.status != "paused");
-        }
+/// Count the tasks `list_tasks` (or `list_children` when `parent` is given)
+/// would return for the same filters, without loading them.
+pub fn count_tasks(conn: &Connection, parent: Option<&str>, filter: ListFilter) -> Result<usize> {
+    if let Some(p) = parent {
+        require_task(conn, p)?;
     }
+    filter.validate()?;
+    query_count(conn, parent, &filter)
+}
 
-    if let Some(assignee) = assignee {
-        tasks.retain(|t| t.assignee.as_deref() == Some(assignee));
-    }
+const STALE_TASKS: &str = "
+SELECT {TASK_COLUMNS} FROM tasks
+WHERE status = 'active'
+  AND COALESCE(status_changed_at, updated_at)
+      < strftime('%Y-%m-%dT%H:%M:%SZ', 'now', ?1)
+ORDER BY COALESCE(status_changed_at, updated_at), id
+";
 
-    if let Some(want_blocked) = blocked {
-        let blocked_names = get_blocked_task_names(conn)?;
-        tasks.retain(|t| blocked_names.contains(&t.name) == want_blocked);
+/// Active tasks whose status last changed more than `older_than` ago.
+/// Tasks without a `status_changed_at` (claimed before it was tracked) fall
+/// back to `updated_at`.
+pub fn stale_tasks(conn: &Connection, older_than: std::time::Duration) -> Result<Vec<Task>> {
+    let modifier = format!("-{} seconds", older_than.as_secs());
+    let sql = STALE_TASKS.replace("{TASK_COLUMNS}", TASK_COLUMNS);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([modifier], read_task_row)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(Into::into)
+}
+
+/// Active tasks grouped by assignee, ordered by assignee and then by how
+/// long each task has been held (longest first).
+pub fn list_assignees(conn: &Connection) -> Result<Vec<(String, Vec<Task>)>> {
+    let query = format!(
+        "SELECT {TASK_COLUMNS} FROM tasks WHERE status = 'active' \
+         ORDER BY assignee, COALESCE(status_changed_at, updated_at), id"
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt.query_map([], read_task_row)?;
+    let mut groups: Vec<(String, Vec<Task>)> = Vec::new();
+    for row in rows {
+        let task = row?;
+        let assignee = task.assignee.clone().unwrap_or_default();
+        match groups.last_mut() {
+            Some((a, tasks)) if *a == assignee => tasks.push(task),
+            _ => groups.push((assignee, vec![task])),
+        }
     }
+    Ok(groups)
+}
 
-    Ok(tasks)
+/// Unfinished tasks with at least one unfinished blocker, by id, each with
+/// the names of those blockers.
+pub fn list_blocked(conn: &Connection) -> Result<Vec<(Task, Vec<String>)>> {
+    let mut blocked = Vec::new();
+    for task in list_tasks(conn, None, true, None, None, Some(true))? {
+        if task.status == "done" {
+            continue;
+        }
+        let blockers = get_blockers(conn, &task.name)?;
+        blocked.push((task, blockers));
+    }
+    Ok(blocked)
 }
 
 pub fn add_note(conn: &Connection, task_name: &str, content: &str) -> Result<()> {
+    add_labeled_note(conn, task_name, None, content)
+}
+
+/// Add a note, optionally tagged with `label`. Labels don't have to be
+/// unique; see `replace_labeled_note` for keeping one note per label.
+pub fn add_labeled_note(
+    conn: &Connection,
+    task_name: &str,
+    label: Option<&str>,
+    content: &str,
+) -> Result<()> {
     require_task(conn, task_name)?;
     conn.execute(
-        "INSERT INTO notes (task, content) VALUES (?1, ?2)",
-        rusqlite::params![task_name, content],
+        "INSERT INTO notes (task, content, label) VALUES (?1, ?2, ?3)",
+        rusqlite::params![task_name, content, label],
     )?;
     Ok(())
 }
 
+const REPLACE_LABELED_NOTE: &str = "
+UPDATE notes
+SET content = ?3, created_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+WHERE id = (SELECT MAX(id) FROM notes WHERE task = ?1 AND label = ?2)
+";
+
+/// Upsert the note labeled `label` on a task: the newest such note gets the
+/// new content and timestamp and any older ones are deleted, or a new note
+/// is added if there is none. Returns whether an existing note was replaced.
+pub fn replace_labeled_note(
+    conn: &Connection,
+    task_name: &str,
+    label: &str,
+    content: &str,
+) -> Result<bool> {
+    require_task(conn, task_name)?;
+    let updated = conn.execute(
+        REPLACE_LABELED_NOTE,
+        rusqlite::params![task_name, label, content],
+    )?;
+    if updated == 0 {
+        add_labeled_note(conn, task_name, Some(label), content)?;
+        return Ok(false);
+    }
+    conn.execute(
+        "DELETE FROM notes WHERE task = ?1 AND label = ?2 \
+         AND id < (SELECT MAX(id) FROM notes WHERE task = ?1 AND label = ?2)",
+        rusqlite::params![task_name, label],
+    )?;
+    Ok(true)
+}
+
+fn require_note(conn: &Connection, note_id: i64) -> Result<()> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM notes WHERE id = ?1",
+        [note_id],
+        |row| row.get(0),
+    )?;
+    if count == 0 {
+        bail!("note {note_id} not found");
+    }
+    Ok(())
+}
+
+pub fn edit_note(conn: &Connection, note_id: i64, content: &str) -> Result<()> {
+    require_note(conn, note_id)?;
+    conn.execute(
+        "UPDATE notes SET content = ?1 WHERE id = ?2",
+        rusqlite::params![content, note_id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_note(conn: &Connection, note_id: i64) -> Result<()> {
+    require_note(conn, note_id)?;
+    conn.execute("DELETE FROM notes WHERE id = ?1", [note_id])?;
+    Ok(())
+}
+
 pub fn list_notes(conn: &Connection, task_name: &str) -> Result<Vec<Note>> {
     require_task(conn, task_name)?;
-    let mut stmt = conn
-        .prepare("SELECT id, task, content, created_at FROM notes WHERE task = ?1 ORDER BY id")?;
-    let rows = stmt.query_map([task_name], |row| {
-        Ok(Note {
-            id: row.get(0)?,
-            task: row.get(1)?,
-            content: row.get(2)?,
-            created_at: row.get(3)?,
-        })
-    })?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {NOTE_COLUMNS} FROM notes WHERE task = ?1 ORDER BY id"
+    ))?;
+    let rows = stmt.query_map([task_name], read_note_row)?;
     rows.collect::<rusqlite::Result<Vec<_>>>()
         .map_err(Into::into)
 }
@@ -704,13 +1710,26 @@ pub fn remove_block(conn: &Connection, blocker: &str, blocked: &str) -> Result<(
 
 const SEARCH_TASKS: &str = "
 SELECT DISTINCT t.id, t.name, t.parent, t.description, t.status,
-       t.assignee, t.agent, t.directory, t.status_changed_at, t.created_at, t.updated_at,
-       CASE WHEN tfts.rowid IS NOT NULL THEN 1 ELSE 0 END as task_match,
+       t.assignee, t.agent, t.directory, t.priority, t.needs_human, t.status_changed_at, t.created_at,
+       t.updated_at, t.estimate,
+       COALESCE(tfts.in_task, 0) as task_match,
        CASE WHEN nfts.task IS NOT NULL THEN 1 ELSE 0 END as note_match,
-       COALESCE(MIN(COALESCE(tfts.rank, 0), COALESCE(nfts.best_rank, 0)), 0) as best_rank
+       COALESCE(MIN(COALESCE(tfts.rank, 0), COALESCE(nfts.best_rank, 0)), 0) as best_rank,
+       tfts.snip,
+       CASE WHEN nfts.task IS NOT NULL THEN (
+           SELECT snippet(notes_fts, 0, '\u{E000}', '\u{E001}', '…', 12)
+           FROM notes_fts JOIN notes n ON n.id = notes_fts.rowid
+           WHERE notes_fts MATCH ?1 AND n.task = t.name
+           ORDER BY rank LIMIT 1
+       ) END as note_snip,
+       COALESCE(tfts.in_assignee, 0) as assignee_match
 FROM tasks t
 LEFT JOIN (
-    SELECT rowid, rank FROM tasks_fts WHERE tasks_fts MATCH ?1
+    SELECT rowid, rank, snippet(tasks_fts, 1, '\u{E000}', '\u{E001}', '…', 12) as snip,
+           instr(highlight(tasks_fts, 0, '\u{E000}', ''), '\u{E000}') > 0
+               OR instr(highlight(tasks_fts, 1, '\u{E000}', ''), '\u{E000}') > 0 as in_task,
+           instr(highlight(tasks_fts, 2, '\u{E000}', ''), '\u{E000}') > 0 as in_assignee
+    FROM tasks_fts WHERE tasks_fts MATCH ?1
 ) tfts ON tfts.rowid = t.id
 LEFT JOIN (
     SELECT n.task, MIN(nfts2.rank) as best_rank
@@ -719,32 +1738,107 @@ LEFT JOIN (
     WHERE notes_fts MATCH ?1
     GROUP BY n.task
 ) nfts ON nfts.task = t.name
-WHERE tfts.rowid IS NOT NULL OR nfts.task IS NOT NULL
+WHERE (tfts.rowid IS NOT NULL OR nfts.task IS NOT NULL)
+  AND (?2 IS NULL OR t.status = ?2)
+  AND (?3 IS NULL OR t.assignee = ?3)
 ORDER BY best_rank ASC, t.id ASC
 ";
 
-pub fn search_tasks(conn: &Connection, query: &str) -> Result<Vec<SearchResult>> {
-    let fts_query = sanitize_fts_query(query);
+/// A `--limit`/`--offset` window over a result list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Page {
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+impl Page {
+    pub fn is_all(&self) -> bool {
+        self.limit.is_none() && self.offset == 0
+    }
+
+    /// Keep only the items inside the window.
+    pub fn slice<T>(&self, items: Vec<T>) -> Vec<T> {
+        items
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+/// Turn an FTS5 `snippet()` into display form: matches marked with `**`
+/// and whitespace collapsed onto one line. A description snippet with no
+/// marked terms means only the name matched, so there is nothing to show.
+fn clean_snippet(raw: Option<String>) -> Option<String> {
+    let raw = raw?;
+    if !raw.contains('\u{E000}') {
+        return None;
+    }
+    let flat = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(flat.replace(['\u{E000}', '\u{E001}'], "**"))
+}
+
+pub fn search_tasks(
+    conn: &Connection,
+    query: &str,
+    mode: FtsMode,
+    status: Option<StatusFilter>,
+    assignee: Option<&str>,
+) -> Result<Vec<SearchResult>> {
+    search_tasks_page(conn, query, mode, status, assignee, Page::default()).map(|(r, _)| r)
+}
+
+/// `search_tasks` restricted to `page`, applied in SQL. Also returns the
+/// number of matches across all pages.
+pub fn search_tasks_page(
+    conn: &Connection,
+    query: &str,
+    mode: FtsMode,
+    status: Option<StatusFilter>,
+    assignee: Option<&str>,
+    page: Page,
+) -> Result<(Vec<SearchResult>, usize)> {
+    let fts_query = sanitize_fts_query(query, mode);
     let Some(fts_query) = fts_query else {
         bail!("empty search query");
     };
 
-    let mut stmt = conn.prepare(SEARCH_TASKS)?;
-    let rows = stmt.query_map([&fts_query], |row| {
-        let task = read_task_row(row)?;
-        let task_match: bool = row.get(11)?;
-        let note_match: bool = row.get(12)?;
-        let mut matched_in = Vec::new();
-        if task_match {
-            matched_in.push("task".to_string());
-        }
-        if note_match {
-            matched_in.push("notes".to_string());
-        }
-        Ok(SearchResult { task, matched_in })
-    })?;
-    rows.collect::<rusqlite::Result<Vec<_>>>()
-        .map_err(Into::into)
+    let status = status.map(|s| s.as_str());
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM ({SEARCH_TASKS})"),
+        rusqlite::params![fts_query, status, assignee],
+        |row| row.get(0),
+    )?;
+    // SQLite treats a negative LIMIT as no limit.
+    let limit = page.limit.map_or(-1, |l| l as i64);
+    let mut stmt = conn.prepare(&format!("{SEARCH_TASKS}LIMIT ?4 OFFSET ?5"))?;
+    let rows = stmt.query_map(
+        rusqlite::params![fts_query, status, assignee, limit, page.offset as i64],
+        |row| {
+            let task = read_task_row(row)?;
+            let task_match: bool = row.get(14)?;
+            let note_match: bool = row.get(15)?;
+            let assignee_match: bool = row.get(19)?;
+            let mut matched_in = Vec::new();
+            if task_match {
+                matched_in.push("task".to_string());
+            }
+            if assignee_match {
+                matched_in.push("assignee".to_string());
+            }
+            if note_match {
+                matched_in.push("notes".to_string());
+            }
+            let snippet = clean_snippet(row.get(17)?).or(clean_snippet(row.get(18)?));
+            Ok(SearchResult {
+                task,
+                matched_in,
+                snippet,
+            })
+        },
+    )?;
+    let results = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok((results, total as usize))
 }
 
 pub fn get_blockers(conn: &Connection, task_name: &str) -> Result<Vec<String>> {
@@ -766,6 +1860,86 @@ pub fn get_dependents(conn: &Connection, task_name: &str) -> Result<Vec<String>>
         .map_err(Into::into)
 }
 
+/// Status of every task (or every task under `root`), keyed by name.
+pub fn status_snapshot(conn: &Connection, root: Option<&str>) -> Result<BTreeMap<String, String>> {
+    Ok(list_tasks(conn, None, true, root, None, None)?
+        .into_iter()
+        .map(|t| (t.name, t.status))
+        .collect())
+}
+
+/// Events describing how `next` differs from `prev`, ordered by task name.
+/// A renamed task shows up as a removal plus an addition.
+pub fn diff_status_snapshots(
+    prev: &BTreeMap<String, String>,
+    next: &BTreeMap<String, String>,
+) -> Vec<TaskEvent> {
+    let mut events: Vec<TaskEvent> = Vec::new();
+    for (task, status) in next {
+        match prev.get(task) {
+            None => events.push(TaskEvent::Added {
+                task: task.clone(),
+                status: status.clone(),
+            }),
+            Some(from) if from != status => events.push(TaskEvent::StatusChanged {
+                task: task.clone(),
+                from: from.clone(),
+                to: status.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (task, status) in prev {
+        if !next.contains_key(task) {
+            events.push(TaskEvent::Removed {
+                task: task.clone(),
+                status: status.clone(),
+            });
+        }
+    }
+    events.sort_by(|a, b| event_task(a).cmp(event_task(b)));
+    events
+}
+
+fn event_task(event: &TaskEvent) -> &str {
+    match event {
+        TaskEvent::Added { task, .. }
+        | TaskEvent::Removed { task, .. }
+        | TaskEvent::StatusChanged { task, .. } => task,
+    }
+}
+
+/// Every dependency edge, including those whose blocker is done, ordered
+/// by blocker then blocked.
+pub fn list_all_deps(conn: &Connection) -> Result<Vec<Dep>> {
+    let mut deps: Vec<Dep> = get_all_deps(conn)?
+        .into_iter()
+        .flat_map(|(blocker, (_, blocks))| {
+            blocks.into_iter().map(move |blocked| Dep {
+                blocker: blocker.clone(),
+                blocked,
+            })
+        })
+        .collect();
+    deps.sort_by(|a, b| (&a.blocker, &a.blocked).cmp(&(&b.blocker, &b.blocked)));
+    Ok(deps)
+}
+
+/// Remove every edge where `blocker` blocks another task. Returns the
+/// number of edges removed.
+pub fn remove_all_blocks_as_blocker(conn: &Connection, blocker: &str) -> Result<usize> {
+    require_task(conn, blocker)?;
+    let changed = conn.execute("DELETE FROM task_deps WHERE blocker = ?1", [blocker])?;
+    Ok(changed)
+}
+
+/// Remove every edge blocking `blocked`. Returns the number of edges removed.
+pub fn remove_all_blocks_on(conn: &Connection, blocked: &str) -> Result<usize> {
+    require_task(conn, blocked)?;
+    let changed = conn.execute("DELETE FROM task_deps WHERE blocked = ?1", [blocked])?;
+    Ok(changed)
+}
+
 /// (blocked_by, blocks) for a single task.
 pub type TaskDeps = (Vec<String>, Vec<String>);
 
@@ -802,6 +1976,327 @@ pub fn get_all_deps(conn: &Connection) -> Result<HashMap<String, TaskDeps>> {
     Ok(map)
 }
 
+const IMPORT_TASK: &str = "
+INSERT INTO tasks (name, parent, description, status, assignee, agent, directory, priority,
+    needs_human, status_changed_at, created_at, updated_at, estimate)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+";
+
+/// How `import_all` treats tasks that already exist in the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Skip tasks (and their notes) whose names already exist.
+    Merge,
+    /// Refuse to import if any name collides; with `force`, wipe the
+    /// database first and load the snapshot in its place.
+    Replace { force: bool },
+}
+
+/// Counts reported by `import_all`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    pub tasks: usize,
+    pub skipped: usize,
+    pub notes: usize,
+    pub deps: usize,
+}
+
+/// Load an `export_all` snapshot. Everything happens in one savepoint, so
+/// a malformed document leaves the database untouched. Tasks are inserted
+/// parents-first and every dependency edge is cycle-checked before it is
+/// added.
+pub fn import_all(conn: &Connection, doc: &ExportDoc, mode: ImportMode) -> Result<ImportStats> {
+    if doc.version > EXPORT_VERSION {
+        bail!(
+            "snapshot format v{} is newer than supported v{EXPORT_VERSION}",
+            doc.version
+        );
+    }
+
+    conn.execute_batch("SAVEPOINT import_all")?;
+
+    let result = (|| -> Result<ImportStats> {
+        let mut seen = HashSet::new();
+        for task in &doc.tasks {
+            validate_name(&task.name)?;
+            if !seen.insert(task.name.as_str()) {
+                bail!(
+                    "task '{}' appears more than once in the snapshot",
+                    task.name
+                );
+            }
+        }
+
+        if mode == (ImportMode::Replace { force: true }) {
+            // Detach parents first: the parent reference is ON DELETE RESTRICT.
+            conn.execute_batch(
+                "DELETE FROM task_deps;
+                 DELETE FROM notes;
+                 UPDATE tasks SET parent = NULL;
+                 DELETE FROM tasks;",
+            )?;
+        }
+
+        let mut stats = ImportStats::default();
+        let mut pending = Vec::new();
+        let mut collisions = Vec::new();
+        for task in &doc.tasks {
+            if task_exists(conn, &task.name)? {
+                collisions.push(task.name.as_str());
+            } else {
+                pending.push(task);
+            }
+        }
+        if !collisions.is_empty() {
+            if mode == ImportMode::Merge {
+                stats.skipped = collisions.len();
+            } else {
+                bail!(
+                    "task(s) already exist: {}; use --mode merge or --force",
+                    collisions.join(", ")
+                );
+            }
+        }
+
+        // Parent-before-child: repeatedly insert tasks whose parent is
+        // already present until nothing is left or no progress is made.
+        let imported: HashSet<&str> = pending.iter().map(|t| t.name.as_str()).collect();
+        while !pending.is_empty() {
+            let before = pending.len();
+            let mut deferred = Vec::new();
+            for task in pending {
+                let ready = match task.parent.as_deref() {
+                    None => true,
+                    Some(p) => task_exists(conn, p)?,
+                };
+                if !ready {
+                    deferred.push(task);
+                    continue;
+                }
+                conn.execute(
+                    IMPORT_TASK,
+                    rusqlite::params![
+                        task.name,
+                        task.parent,
+                        task.description,
+                        task.status,
+                        task.assignee,
+                        task.agent,
+                        task.directory,
+                        task.priority,
+                        task.needs_human,
+                        task.status_changed_at,
+                        task.created_at,
+                        task.updated_at,
+                        task.estimate,
+                    ],
+                )?;
+                stats.tasks += 1;
+            }
+            if deferred.len() == before {
+                let names: Vec<&str> = deferred.iter().map(|t| t.name.as_str()).collect();
+                bail!("missing or cyclic parent for task(s): {}", names.join(", "));
+            }
+            pending = deferred;
+        }
+
+        for note in &doc.notes {
+            if !imported.contains(note.task.as_str()) {
+                if task_exists(conn, &note.task)? {
+                    // Belongs to a task skipped by merge.
+                    continue;
+                }
+                bail!("note {} refers to unknown task '{}'", note.id, note.task);
+            }
+            conn.execute(
+                "INSERT INTO notes (task, content, created_at, label) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![note.task, note.content, note.created_at, note.label],
+            )?;
+            stats.notes += 1;
+        }
+
+        for dep in &doc.deps {
+            require_task(conn, &dep.blocker)?;
+            require_task(conn, &dep.blocked)?;
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM task_deps WHERE blocker = ?1 AND blocked = ?2)",
+                rusqlite::params![dep.blocker, dep.blocked],
+                |row| row.get(0),
+            )?;
+            if exists {
+                continue;
+            }
+            if detect_dep_cycle(conn, &dep.blocker, &dep.blocked)? {
+                bail!(
+                    "dependency {} -> {} would create a cycle",
+                    dep.blocker,
+                    dep.blocked
+                );
+            }
+            conn.execute(
+                "INSERT INTO task_deps (blocker, blocked) VALUES (?1, ?2)",
+                rusqlite::params![dep.blocker, dep.blocked],
+            )?;
+            stats.deps += 1;
+        }
+
+        Ok(stats)
+    })();
+
+    match result {
+        Ok(stats) => {
+            conn.execute_batch("RELEASE import_all")?;
+            Ok(stats)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO import_all");
+            let _ = conn.execute_batch("RELEASE import_all");
+            Err(e)
+        }
+    }
+}
+
+/// A reference that points at a task which no longer exists. Foreign keys
+/// prevent these in normal use, but external edits can leave them behind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityIssue {
+    DanglingParent { task: String, parent: String },
+    DanglingDep { blocker: String, blocked: String },
+    OrphanNote { id: i64, task: String },
+}
+
+impl std::fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntegrityIssue::DanglingParent { task, parent } => {
+                write!(f, "task '{task}' has missing parent '{parent}'")
+            }
+            IntegrityIssue::DanglingDep { blocker, blocked } => {
+                write!(
+                    f,
+                    "dependency {blocker} -> {blocked} refers to a missing task"
+                )
+            }
+            IntegrityIssue::OrphanNote { id, task } => {
+                write!(f, "note {id} refers to missing task '{task}'")
+            }
+        }
+    }
+}
+
+const DANGLING_PARENTS: &str = "
+SELECT t.name, t.parent FROM tasks t
+WHERE t.parent IS NOT NULL
+  AND NOT EXISTS (SELECT 1 FROM tasks p WHERE p.name = t.parent)
+ORDER BY t.name
+";
+
+const DANGLING_DEPS: &str = "
+SELECT d.blocker, d.blocked FROM task_deps d
+WHERE NOT EXISTS (SELECT 1 FROM tasks t WHERE t.name = d.blocker)
+   OR NOT EXISTS (SELECT 1 FROM tasks t WHERE t.name = d.blocked)
+ORDER BY d.blocker, d.blocked
+";
+
+const ORPHAN_NOTES: &str = "
+SELECT n.id, n.task FROM notes n
+WHERE NOT EXISTS (SELECT 1 FROM tasks t WHERE t.name = n.task)
+ORDER BY n.id
+";
+
+/// Find dangling parent references, dependency edges with a missing
+/// endpoint, and notes attached to missing tasks.
+pub fn validate_integrity(conn: &Connection) -> Result<Vec<IntegrityIssue>> {
+    let mut issues = Vec::new();
+
+    let mut stmt = conn.prepare(DANGLING_PARENTS)?;
+    for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+        let (task, parent) = row?;
+        issues.push(IntegrityIssue::DanglingParent { task, parent });
+    }
+
+    let mut stmt = conn.prepare(DANGLING_DEPS)?;
+    for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+        let (blocker, blocked) = row?;
+        issues.push(IntegrityIssue::DanglingDep { blocker, blocked });
+    }
+
+    let mut stmt = conn.prepare(ORPHAN_NOTES)?;
+    for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+        let (id, task) = row?;
+        issues.push(IntegrityIssue::OrphanNote { id, task });
+    }
+
+    Ok(issues)
+}
+
+/// Repair the given issues: dangling-parent tasks move to the root, and
+/// dangling dependency edges and orphaned notes are deleted.
+pub fn fix_integrity(conn: &Connection, issues: &[IntegrityIssue]) -> Result<()> {
+    for issue in issues {
+        match issue {
+            IntegrityIssue::DanglingParent { task, .. } => {
+                conn.execute(
+                    "UPDATE tasks SET parent = NULL, \
+                     updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now') WHERE name = ?1",
+                    [task],
+                )?;
+            }
+            IntegrityIssue::DanglingDep { blocker, blocked } => {
+                conn.execute(
+                    "DELETE FROM task_deps WHERE blocker = ?1 AND blocked = ?2",
+                    rusqlite::params![blocker, blocked],
+                )?;
+            }
+            IntegrityIssue::OrphanNote { id, .. } => {
+                conn.execute("DELETE FROM notes WHERE id = ?1", [id])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Snapshot every task, note and dependency edge, ignoring the default
+/// listing filters. Reads happen inside a savepoint so the three tables
+/// are consistent with each other.
+pub fn export_all(conn: &Connection) -> Result<ExportDoc> {
+    conn.execute_batch("SAVEPOINT export_all")?;
+
+    let result = (|| -> Result<ExportDoc> {
+        let mut stmt = conn.prepare(&format!("SELECT {TASK_COLUMNS} FROM tasks ORDER BY id"))?;
+        let tasks = stmt
+            .query_map([], read_task_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut stmt = conn.prepare(&format!("SELECT {NOTE_COLUMNS} FROM notes ORDER BY id"))?;
+        let notes = stmt
+            .query_map([], read_note_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut stmt =
+            conn.prepare("SELECT blocker, blocked FROM task_deps ORDER BY blocker, blocked")?;
+        let deps = stmt
+            .query_map([], |row| {
+                Ok(Dep {
+                    blocker: row.get(0)?,
+                    blocked: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(ExportDoc {
+            version: EXPORT_VERSION,
+            tasks,
+            notes,
+            deps,
+        })
+    })();
+
+    // Read-only, so releasing is fine whether or not the reads succeeded.
+    conn.execute_batch("RELEASE export_all")?;
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1036,6 +2531,107 @@ mod tests {
         assert_eq!(task_before.updated_at, task_after.updated_at);
     }
 
+    fn add_mixed_subtree(conn: &Connection) {
+        for (name, parent) in [
+            ("epic", None),
+            ("open-child", Some("epic")),
+            ("active-child", Some("epic")),
+            ("paused-child", Some("epic")),
+            ("done-child", Some("epic")),
+            ("grandchild", Some("open-child")),
+        ] {
+            add_task(
+                conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        claim_task(conn, "active-child", "agent").unwrap();
+        pause_task(conn, "paused-child").unwrap();
+        mark_done(conn, "done-child").unwrap();
+    }
+
+    #[test]
+    fn mark_done_recursive_refuses_active_subtasks() {
+        let conn = db::open_memory().unwrap();
+        add_mixed_subtree(&conn);
+
+        let err = mark_done_recursive(&conn, "epic", false).unwrap_err();
+        assert!(err.to_string().contains("active-child"));
+        // Nothing was changed.
+        assert_eq!(get_task(&conn, "epic").unwrap().status, "open");
+        assert_eq!(get_task(&conn, "grandchild").unwrap().status, "open");
+    }
+
+    #[test]
+    fn mark_done_recursive_force_marks_whole_subtree() {
+        let conn = db::open_memory().unwrap();
+        add_mixed_subtree(&conn);
+
+        let count = mark_done_recursive(&conn, "epic", true).unwrap();
+        assert_eq!(count, 5);
+        for name in [
+            "epic",
+            "open-child",
+            "active-child",
+            "paused-child",
+            "grandchild",
+        ] {
+            let task = get_task(&conn, name).unwrap();
+            assert_eq!(task.status, "done", "{name}");
+            assert!(task.assignee.is_none());
+        }
+    }
+
+    #[test]
+    fn mark_done_recursive_without_active_subtasks() {
+        let conn = db::open_memory().unwrap();
+        add_mixed_subtree(&conn);
+
+        // The open-child subtree has no active tasks.
+        assert_eq!(mark_done_recursive(&conn, "open-child", false).unwrap(), 2);
+        assert!(mark_done_recursive(&conn, "open-child", false).is_err());
+    }
+
+    #[test]
+    fn reopen_recursive_makes_subtree_claimable_again() {
+        let conn = db::open_memory().unwrap();
+        add_mixed_subtree(&conn);
+        mark_done_recursive(&conn, "epic", true).unwrap();
+        assert_eq!(claim_next_task(&conn, "w", None, None, None).unwrap(), None);
+
+        conn.execute(
+            "UPDATE tasks SET status_changed_at = '2000-01-01T00:00:00Z'",
+            [],
+        )
+        .unwrap();
+        let count = reopen_task_recursive(&conn, "epic").unwrap();
+        assert_eq!(count, 6);
+
+        for name in ["epic", "open-child", "paused-child", "grandchild"] {
+            let task = get_task(&conn, name).unwrap();
+            assert_eq!(task.status, "open", "{name}");
+            assert_ne!(
+                task.status_changed_at.as_deref(),
+                Some("2000-01-01T00:00:00Z")
+            );
+        }
+        assert!(claim_next_task(&conn, "w", None, Some("epic"), None)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn reopen_recursive_without_done_tasks_fails() {
+        let conn = db::open_memory().unwrap();
+        add_mixed_subtree(&conn);
+        assert!(reopen_task_recursive(&conn, "open-child").is_err());
+    }
+
     #[test]
     fn reopen_open_task_fails() {
         let conn = db::open_memory().unwrap();
@@ -1055,62 +2651,390 @@ mod tests {
     }
 
     #[test]
-    fn reopen_active_task_fails() {
+    fn reopen_active_task_fails() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "t",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        claim_task(&conn, "t", "agent").unwrap();
+        let err = reopen_task(&conn, "t").unwrap_err();
+        assert!(
+            err.to_string().contains("not done"),
+            "expected 'not done' error: {err}"
+        );
+    }
+
+    #[test]
+    fn reopen_paused_task_fails() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "t",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        pause_task(&conn, "t").unwrap();
+        let err = reopen_task(&conn, "t").unwrap_err();
+        assert!(
+            err.to_string().contains("not done"),
+            "expected 'not done' error: {err}"
+        );
+    }
+
+    #[test]
+    fn update_description_works() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "t",
+                description: "old",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        update_description(&conn, "t", "new").unwrap();
+        let task = get_task(&conn, "t").unwrap();
+        assert_eq!(task.description, "new");
+    }
+
+    #[test]
+    fn reparent_works() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "a",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "b",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        reparent_task(&conn, "b", Some("a")).unwrap();
+        let task = get_task(&conn, "b").unwrap();
+        assert_eq!(task.parent.as_deref(), Some("a"));
+
+        // Clear parent
+        reparent_task(&conn, "b", None).unwrap();
+        let task = get_task(&conn, "b").unwrap();
+        assert!(task.parent.is_none());
+    }
+
+    #[test]
+    fn move_children_reparents_all_or_nothing() {
+        let conn = db::open_memory().unwrap();
+        for (name, parent) in [
+            ("old", None),
+            ("new", None),
+            ("a", Some("old")),
+            ("b", Some("old")),
+            ("b1", Some("b")),
+        ] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+
+        let moved = move_children(&conn, "old", Some("new")).unwrap();
+        assert_eq!(moved, ["a", "b"]);
+        assert_eq!(get_task(&conn, "a").unwrap().parent.as_deref(), Some("new"));
+        assert_eq!(get_task(&conn, "b1").unwrap().parent.as_deref(), Some("b"));
+        assert!(move_children(&conn, "old", Some("new")).unwrap().is_empty());
+
+        // "b1" sits under "b", so moving "new"'s children under "b1" would
+        // put "b" inside itself; "a" must stay where it is.
+        let err = move_children(&conn, "new", Some("b1")).unwrap_err();
+        assert!(err.to_string().contains("cycle"), "{err}");
+        assert_eq!(get_task(&conn, "a").unwrap().parent.as_deref(), Some("new"));
+        assert_eq!(get_task(&conn, "b").unwrap().parent.as_deref(), Some("new"));
+
+        let moved = move_children(&conn, "new", None).unwrap();
+        assert_eq!(moved, ["a", "b"]);
+        assert!(get_task(&conn, "a").unwrap().parent.is_none());
+        assert!(get_task(&conn, "b").unwrap().parent.is_none());
+
+        assert!(move_children(&conn, "missing", None).is_err());
+        assert!(move_children(&conn, "old", Some("missing")).is_err());
+    }
+
+    fn bulk_fixture() -> Connection {
+        let conn = db::open_memory().unwrap();
+        for name in ["a", "b", "c"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        pause_task(&conn, "b").unwrap();
+        conn
+    }
+
+    fn statuses(conn: &Connection) -> Vec<String> {
+        ["a", "b", "c"]
+            .iter()
+            .map(|n| get_task(conn, n).unwrap().status)
+            .collect()
+    }
+
+    #[test]
+    fn set_status_bulk_is_all_or_nothing() {
+        let conn = bulk_fixture();
+        let names: Vec<String> = ["a", "b", "c"].map(String::from).to_vec();
+
+        // "b" is already paused, so pausing all three changes nothing.
+        let err = set_status_bulk(&conn, BulkOp::Pause, &names, false).unwrap_err();
+        assert!(err.to_string().contains("'b'"), "{err}");
+        assert_eq!(statuses(&conn), ["open", "paused", "open"]);
+
+        let names: Vec<String> = ["a", "c"].map(String::from).to_vec();
+        assert!(set_status_bulk(&conn, BulkOp::Done, &names, false)
+            .unwrap()
+            .is_empty());
+        assert_eq!(statuses(&conn), ["done", "paused", "done"]);
+        set_status_bulk(&conn, BulkOp::Reopen, &names, false).unwrap();
+        assert_eq!(statuses(&conn), ["open", "paused", "open"]);
+    }
+
+    #[test]
+    fn set_status_bulk_keep_going_skips_failures() {
+        let conn = bulk_fixture();
+        let names: Vec<String> = ["a", "b", "missing", "c"].map(String::from).to_vec();
+        let failures = set_status_bulk(&conn, BulkOp::Pause, &names, true).unwrap();
+        let failed: Vec<&str> = failures.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(failed, ["b", "missing"]);
+        assert!(failures[0].1.contains("already paused"), "{:?}", failures);
+        assert_eq!(statuses(&conn), ["paused", "paused", "paused"]);
+
+        let failures = set_status_bulk(&conn, BulkOp::Unpause, &names, true).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(statuses(&conn), ["open", "open", "open"]);
+        assert!(BulkOp::parse("archive").is_err());
+    }
+
+    #[test]
+    fn duplicate_subtree_copies_template_with_fresh_statuses() {
+        let conn = db::open_memory().unwrap();
+        for (name, parent, desc) in [
+            ("outside", None, "not copied"),
+            ("tmpl", Some("outside"), "Release checklist"),
+            ("build", Some("tmpl"), "Build artifacts"),
+            ("build-linux", Some("build"), "Linux build"),
+            ("build-mac", Some("build"), "Mac build"),
+            ("announce", Some("tmpl"), "Announce"),
+        ] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    description: desc,
+                    priority: 2,
+                    estimate: Some(1),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        add_note(&conn, "build", "use the release profile").unwrap();
+        add_block(&conn, "build", "announce").unwrap();
+        add_block(&conn, "outside", "build-mac").unwrap();
+        claim_task(&conn, "build-linux", "agent").unwrap();
+        mark_done(&conn, "build-mac").unwrap();
+        set_needs_human(&conn, "announce", true).unwrap();
+
+        let pairs = duplicate_subtree(&conn, "tmpl", |n| format!("v2-{n}"), true).unwrap();
+        assert_eq!(pairs.len(), 5);
+        assert_eq!(pairs[0], ("tmpl".to_string(), "v2-tmpl".to_string()));
+
+        let root = get_task(&conn, "v2-tmpl").unwrap();
+        assert_eq!(root.parent.as_deref(), Some("outside"));
+        assert_eq!(root.description, "Release checklist");
+        for (name, parent) in [
+            ("v2-build", "v2-tmpl"),
+            ("v2-build-linux", "v2-build"),
+            ("v2-build-mac", "v2-build"),
+            ("v2-announce", "v2-tmpl"),
+        ] {
+            let task = get_task(&conn, name).unwrap();
+            assert_eq!(task.parent.as_deref(), Some(parent), "{name}");
+            assert_eq!(task.status, "open", "{name}");
+            assert_eq!(task.assignee, None, "{name}");
+            assert!(!task.needs_human, "{name}");
+            assert_eq!((task.priority, task.estimate), (2, Some(1)), "{name}");
+        }
+        assert_eq!(get_blockers(&conn, "v2-announce").unwrap(), ["v2-build"]);
+        assert!(get_blockers(&conn, "v2-build-mac").unwrap().is_empty());
+        let notes = list_notes(&conn, "v2-build").unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].content, "use the release profile");
+        // The originals are untouched.
+        assert_eq!(get_task(&conn, "build-linux").unwrap().status, "active");
+
+        let pairs = duplicate_subtree(&conn, "build", |n| format!("{n}-copy"), false).unwrap();
+        assert_eq!(pairs.len(), 3);
+        assert!(list_notes(&conn, "build-copy").unwrap().is_empty());
+    }
+
+    #[test]
+    fn duplicate_subtree_checks_names_before_inserting() {
+        let conn = db::open_memory().unwrap();
+        for (name, parent) in [("t", None), ("t-a", Some("t")), ("x-t-a", None)] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        let before = list_tasks(&conn, None, true, None, None, None)
+            .unwrap()
+            .len();
+
+        // "x-t" is free but "x-t-a" is taken.
+        let err = duplicate_subtree(&conn, "t", |n| format!("x-{n}"), false).unwrap_err();
+        assert!(err.to_string().contains("'x-t-a' already exists"), "{err}");
+        let err = duplicate_subtree(&conn, "t", |_| "same".to_string(), false).unwrap_err();
+        assert!(err.to_string().contains("'same' already exists"), "{err}");
+        assert!(duplicate_subtree(&conn, "t", |n| format!("bad name {n}"), false).is_err());
+        assert_eq!(
+            list_tasks(&conn, None, true, None, None, None)
+                .unwrap()
+                .len(),
+            before
+        );
+    }
+
+    #[test]
+    fn get_ancestors_returns_root_first() {
+        let conn = db::open_memory().unwrap();
+        for (name, parent) in [
+            ("root", None),
+            ("epic", Some("root")),
+            ("story", Some("epic")),
+            ("task", Some("story")),
+        ] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            get_ancestors(&conn, "task").unwrap(),
+            vec!["root", "epic", "story"]
+        );
+        assert!(get_ancestors(&conn, "root").unwrap().is_empty());
+        assert!(get_ancestors(&conn, "missing").is_err());
+    }
+
+    #[test]
+    fn rename_updates_all_references() {
         let conn = db::open_memory().unwrap();
         add_task(
             &conn,
             AddTaskParams {
-                name: "t",
+                name: "old",
+                description: "renamed task",
+                note: Some("a note"),
                 ..Default::default()
             },
         )
         .unwrap();
-        claim_task(&conn, "t", "agent").unwrap();
-        let err = reopen_task(&conn, "t").unwrap_err();
-        assert!(
-            err.to_string().contains("not done"),
-            "expected 'not done' error: {err}"
-        );
-    }
-
-    #[test]
-    fn reopen_paused_task_fails() {
-        let conn = db::open_memory().unwrap();
         add_task(
             &conn,
             AddTaskParams {
-                name: "t",
+                name: "child",
+                parent: Some("old"),
                 ..Default::default()
             },
         )
         .unwrap();
-        pause_task(&conn, "t").unwrap();
-        let err = reopen_task(&conn, "t").unwrap_err();
-        assert!(
-            err.to_string().contains("not done"),
-            "expected 'not done' error: {err}"
-        );
-    }
-
-    #[test]
-    fn update_description_works() {
-        let conn = db::open_memory().unwrap();
         add_task(
             &conn,
             AddTaskParams {
-                name: "t",
-                description: "old",
+                name: "other",
                 ..Default::default()
             },
         )
         .unwrap();
-        update_description(&conn, "t", "new").unwrap();
-        let task = get_task(&conn, "t").unwrap();
-        assert_eq!(task.description, "new");
+        add_block(&conn, "old", "other").unwrap();
+        add_block(&conn, "other", "child").unwrap();
+
+        rename_task(&conn, "old", "new").unwrap();
+
+        assert!(get_task(&conn, "old").is_err());
+        assert_eq!(get_task(&conn, "new").unwrap().description, "renamed task");
+        assert_eq!(
+            get_task(&conn, "child").unwrap().parent.as_deref(),
+            Some("new")
+        );
+        assert_eq!(list_notes(&conn, "new").unwrap().len(), 1);
+        assert_eq!(get_dependents(&conn, "new").unwrap(), vec!["other"]);
+        assert_eq!(get_blockers(&conn, "child").unwrap(), vec!["other"]);
+
+        // FTS indexes the new name and still finds the note.
+        let results = search_tasks(&conn, "new", FtsMode::default(), None, None).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].task.name, "new");
+        let results = search_tasks(&conn, "note", FtsMode::default(), None, None).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].task.name, "new");
+        assert!(search_tasks(&conn, "old", FtsMode::default(), None, None)
+            .unwrap()
+            .is_empty());
     }
 
     #[test]
-    fn reparent_works() {
+    fn rename_to_existing_name_fails() {
+        let conn = db::open_memory().unwrap();
+        for name in ["a", "b"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        let err = rename_task(&conn, "a", "b").unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+        assert!(get_task(&conn, "a").is_ok());
+    }
+
+    #[test]
+    fn rename_validates_new_name() {
         let conn = db::open_memory().unwrap();
         add_task(
             &conn,
@@ -1120,22 +3044,9 @@ mod tests {
             },
         )
         .unwrap();
-        add_task(
-            &conn,
-            AddTaskParams {
-                name: "b",
-                ..Default::default()
-            },
-        )
-        .unwrap();
-        reparent_task(&conn, "b", Some("a")).unwrap();
-        let task = get_task(&conn, "b").unwrap();
-        assert_eq!(task.parent.as_deref(), Some("a"));
-
-        // Clear parent
-        reparent_task(&conn, "b", None).unwrap();
-        let task = get_task(&conn, "b").unwrap();
-        assert!(task.parent.is_none());
+        assert!(rename_task(&conn, "a", "bad name").is_err());
+        assert!(rename_task(&conn, "missing", "b").is_err());
+        assert!(get_task(&conn, "a").is_ok());
     }
 
     #[test]
@@ -1176,6 +3087,55 @@ mod tests {
         assert!(remove_task(&conn, "parent", false).is_err());
     }
 
+    #[test]
+    fn preview_removal_lists_subtree_without_deleting() {
+        let conn = db::open_memory().unwrap();
+        for (name, parent) in [
+            ("parent", None),
+            ("child", Some("parent")),
+            ("grandchild", Some("child")),
+            ("other", None),
+        ] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        add_note(&conn, "child", "n1").unwrap();
+        add_note(&conn, "child", "n2").unwrap();
+        add_block(&conn, "other", "grandchild").unwrap();
+
+        assert!(preview_removal(&conn, "parent", false).is_err());
+        let entries = preview_removal(&conn, "parent", true).unwrap();
+        assert_eq!(
+            entries,
+            [
+                RemovalEntry {
+                    name: "parent".into(),
+                    notes: 0,
+                    deps: 0,
+                },
+                RemovalEntry {
+                    name: "child".into(),
+                    notes: 2,
+                    deps: 0,
+                },
+                RemovalEntry {
+                    name: "grandchild".into(),
+                    notes: 0,
+                    deps: 1,
+                },
+            ]
+        );
+        assert_eq!(count_descendants(&conn, "parent").unwrap(), 2);
+        assert!(get_task(&conn, "grandchild").is_ok());
+    }
+
     #[test]
     fn remove_parent_recursive() {
         let conn = db::open_memory().unwrap();
@@ -1324,6 +3284,75 @@ mod tests {
         assert_eq!(tasks.len(), 2);
     }
 
+    #[test]
+    fn sort_tasks_by_each_key() {
+        let conn = db::open_memory().unwrap();
+        for name in ["charlie", "alpha", "bravo", "delta"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        claim_task(&conn, "bravo", "agent").unwrap();
+        pause_task(&conn, "charlie").unwrap();
+        // Distinct timestamps: created in reverse name order, updated in
+        // an unrelated order.
+        for (name, created, updated) in [
+            ("charlie", "2024-01-04T00:00:00Z", "2024-02-02T00:00:00Z"),
+            ("alpha", "2024-01-03T00:00:00Z", "2024-02-04T00:00:00Z"),
+            ("bravo", "2024-01-02T00:00:00Z", "2024-02-01T00:00:00Z"),
+            ("delta", "2024-01-01T00:00:00Z", "2024-02-03T00:00:00Z"),
+        ] {
+            conn.execute(
+                "UPDATE tasks SET created_at = ?1, updated_at = ?2 WHERE name = ?3",
+                rusqlite::params![created, updated, name],
+            )
+            .unwrap();
+        }
+        let mut tasks = list_tasks(&conn, None, true, None, None, None).unwrap();
+        let names = |tasks: &[Task]| tasks.iter().map(|t| t.name.clone()).collect::<Vec<_>>();
+
+        for (key, expected) in [
+            (TaskSort::Name, ["alpha", "bravo", "charlie", "delta"]),
+            (TaskSort::Status, ["bravo", "alpha", "delta", "charlie"]),
+            (TaskSort::Updated, ["bravo", "charlie", "delta", "alpha"]),
+            (TaskSort::Created, ["delta", "bravo", "alpha", "charlie"]),
+            (TaskSort::Id, ["charlie", "alpha", "bravo", "delta"]),
+        ] {
+            sort_tasks(&mut tasks, key, false);
+            assert_eq!(names(&tasks), expected, "{key:?}");
+        }
+
+        sort_tasks(&mut tasks, TaskSort::Name, true);
+        assert_eq!(names(&tasks), ["delta", "charlie", "bravo", "alpha"]);
+        sort_tasks(&mut tasks, TaskSort::Id, true);
+        assert_eq!(names(&tasks), ["delta", "bravo", "alpha", "charlie"]);
+    }
+
+    #[test]
+    fn sort_ties_break_by_id() {
+        let conn = db::open_memory().unwrap();
+        for name in ["b", "a", "c"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        let mut tasks = list_tasks(&conn, None, false, None, None, None).unwrap();
+        sort_tasks(&mut tasks, TaskSort::Status, false);
+        let names: Vec<_> = tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["b", "a", "c"]);
+        assert!(TaskSort::parse("bogus").is_err());
+    }
+
     #[test]
     fn notes_crud() {
         let conn = db::open_memory().unwrap();
@@ -1343,6 +3372,83 @@ mod tests {
         assert_eq!(notes[1].content, "note 2");
     }
 
+    #[test]
+    fn edit_and_delete_note() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "t",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_note(&conn, "t", "note 1").unwrap();
+        add_note(&conn, "t", "note 2").unwrap();
+        let notes = list_notes(&conn, "t").unwrap();
+
+        edit_note(&conn, notes[0].id, "fixed typo").unwrap();
+        delete_note(&conn, notes[1].id).unwrap();
+
+        let notes = list_notes(&conn, "t").unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].content, "fixed typo");
+    }
+
+    #[test]
+    fn replace_labeled_note_upserts() {
+        let conn = db::open_memory().unwrap();
+        for name in ["t", "other"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        add_note(&conn, "t", "plain").unwrap();
+        add_labeled_note(&conn, "other", Some("status"), "elsewhere").unwrap();
+
+        // No labeled note yet: one is added.
+        assert!(!replace_labeled_note(&conn, "t", "status", "starting").unwrap());
+        // Then it is updated in place rather than accumulating.
+        let id = list_notes(&conn, "t").unwrap()[1].id;
+        assert!(replace_labeled_note(&conn, "t", "status", "halfway").unwrap());
+        let notes = list_notes(&conn, "t").unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].content, "plain");
+        assert_eq!(notes[0].label, None);
+        assert_eq!(notes[1].id, id);
+        assert_eq!(notes[1].content, "halfway");
+        assert_eq!(notes[1].label.as_deref(), Some("status"));
+
+        // Duplicates from plain --label notes collapse to the newest.
+        add_labeled_note(&conn, "t", Some("status"), "dup").unwrap();
+        assert!(replace_labeled_note(&conn, "t", "status", "done").unwrap());
+        let labeled: Vec<_> = list_notes(&conn, "t")
+            .unwrap()
+            .into_iter()
+            .filter(|n| n.label.is_some())
+            .map(|n| n.content)
+            .collect();
+        assert_eq!(labeled, vec!["done"]);
+
+        // Other tasks' notes with the same label are untouched.
+        assert_eq!(list_notes(&conn, "other").unwrap()[0].content, "elsewhere");
+        assert!(replace_labeled_note(&conn, "missing", "status", "x").is_err());
+    }
+
+    #[test]
+    fn edit_or_delete_missing_note_fails() {
+        let conn = db::open_memory().unwrap();
+        let err = edit_note(&conn, 42, "x").unwrap_err();
+        assert!(err.to_string().contains("note 42 not found"));
+        let err = delete_note(&conn, 42).unwrap_err();
+        assert!(err.to_string().contains("note 42 not found"));
+    }
+
     #[test]
     fn blocking_relationships() {
         let conn = db::open_memory().unwrap();
@@ -1369,6 +3475,93 @@ mod tests {
         assert!(get_blockers(&conn, "b").unwrap().is_empty());
     }
 
+    #[test]
+    fn remove_all_blocks_by_blocker_or_blocked() {
+        let conn = db::open_memory().unwrap();
+        for name in ["a", "b", "c", "d"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        add_block(&conn, "a", "b").unwrap();
+        add_block(&conn, "a", "c").unwrap();
+        add_block(&conn, "d", "c").unwrap();
+        mark_done(&conn, "d").unwrap();
+
+        // Done blockers still show up in the full edge list.
+        let all: Vec<(String, String)> = list_all_deps(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.blocker, d.blocked))
+            .collect();
+        assert_eq!(
+            all,
+            [("a", "b"), ("a", "c"), ("d", "c")].map(|(x, y)| (x.to_string(), y.to_string()))
+        );
+
+        assert_eq!(remove_all_blocks_on(&conn, "c").unwrap(), 2);
+        assert_eq!(get_blockers(&conn, "b").unwrap(), vec!["a"]);
+        assert_eq!(remove_all_blocks_as_blocker(&conn, "a").unwrap(), 1);
+        assert!(list_all_deps(&conn).unwrap().is_empty());
+        assert!(remove_all_blocks_on(&conn, "missing").is_err());
+    }
+
+    #[test]
+    fn diff_status_snapshots_reports_changes() {
+        let conn = db::open_memory().unwrap();
+        for name in ["a", "b", "c"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        let before = status_snapshot(&conn, None).unwrap();
+        assert!(diff_status_snapshots(&before, &before).is_empty());
+
+        claim_task(&conn, "a", "agent").unwrap();
+        remove_task(&conn, "b", false).unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "d",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let after = status_snapshot(&conn, None).unwrap();
+        assert_eq!(
+            diff_status_snapshots(&before, &after),
+            [
+                TaskEvent::StatusChanged {
+                    task: "a".into(),
+                    from: "open".into(),
+                    to: "active".into(),
+                },
+                TaskEvent::Removed {
+                    task: "b".into(),
+                    status: "open".into(),
+                },
+                TaskEvent::Added {
+                    task: "d".into(),
+                    status: "open".into(),
+                },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&diff_status_snapshots(&before, &after)[0]).unwrap(),
+            r#"{"event":"status_changed","task":"a","from":"open","to":"active"}"#
+        );
+    }
+
     #[test]
     fn self_block_fails() {
         let conn = db::open_memory().unwrap();
@@ -1533,16 +3726,138 @@ mod tests {
     #[test]
     fn claim_next_no_tasks() {
         let conn = db::open_memory().unwrap();
-        assert_eq!(claim_next_task(&conn, "agent", None, None).unwrap(), None);
+        assert_eq!(
+            claim_next_task(&conn, "agent", None, None, None).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn claim_next_picks_oldest() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "second",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "third",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        // "second" has lower id, should be picked first
+        let picked = claim_next_task(&conn, "agent", None, None, None).unwrap();
+        assert_eq!(picked.as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn claim_next_skips_done_and_assigned() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "done-task",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        mark_done(&conn, "done-task").unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "claimed-task",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        claim_task(&conn, "claimed-task", "other-agent").unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "available",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let picked = claim_next_task(&conn, "agent", None, None, None).unwrap();
+        assert_eq!(picked.as_deref(), Some("available"));
+    }
+
+    #[test]
+    fn claim_next_skips_blocked_tasks() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "blocker",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "blocked",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_block(&conn, "blocker", "blocked").unwrap();
+
+        // "blocked" has undone blocker, so only "blocker" is available
+        let picked = claim_next_task(&conn, "agent", None, None, None).unwrap();
+        assert_eq!(picked.as_deref(), Some("blocker"));
+    }
+
+    #[test]
+    fn claim_next_prefers_unblockers() {
+        let conn = db::open_memory().unwrap();
+        // "plain" is older (lower id), but "unblocker" unblocks "downstream"
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "plain",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "unblocker",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "downstream",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_block(&conn, "unblocker", "downstream").unwrap();
+
+        let picked = claim_next_task(&conn, "agent", None, None, None).unwrap();
+        assert_eq!(picked.as_deref(), Some("unblocker"));
     }
 
     #[test]
-    fn claim_next_picks_oldest() {
+    fn claim_next_prefers_higher_priority() {
         let conn = db::open_memory().unwrap();
+        // "unblocker" unblocks another task, but "urgent" has higher priority
         add_task(
             &conn,
             AddTaskParams {
-                name: "second",
+                name: "unblocker",
                 ..Default::default()
             },
         )
@@ -1550,57 +3865,364 @@ mod tests {
         add_task(
             &conn,
             AddTaskParams {
-                name: "third",
+                name: "downstream",
                 ..Default::default()
             },
         )
         .unwrap();
-        // "second" has lower id, should be picked first
-        let picked = claim_next_task(&conn, "agent", None, None).unwrap();
-        assert_eq!(picked.as_deref(), Some("second"));
-    }
-
-    #[test]
-    fn claim_next_skips_done_and_assigned() {
-        let conn = db::open_memory().unwrap();
+        add_block(&conn, "unblocker", "downstream").unwrap();
         add_task(
             &conn,
             AddTaskParams {
-                name: "done-task",
+                name: "urgent",
+                priority: 5,
                 ..Default::default()
             },
         )
         .unwrap();
-        mark_done(&conn, "done-task").unwrap();
+
+        let picked = claim_next_task(&conn, "agent", None, None, None).unwrap();
+        assert_eq!(picked.as_deref(), Some("urgent"));
+    }
+
+    #[test]
+    fn claim_next_skips_needs_human_tasks() {
+        let conn = db::open_memory().unwrap();
+        for (name, priority) in [("stuck", 5), ("ready", 0)] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    priority,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        set_needs_human(&conn, "stuck", true).unwrap();
+        assert!(get_task(&conn, "stuck").unwrap().needs_human);
+
+        let picked = claim_next_task(&conn, "agent", None, None, None).unwrap();
+        assert_eq!(picked.as_deref(), Some("ready"));
+        // Nothing else is claimable while the flag is set.
+        let picked = claim_next_task(&conn, "agent-2", None, None, None).unwrap();
+        assert_eq!(picked, None);
+
+        set_needs_human(&conn, "stuck", false).unwrap();
+        let picked = claim_next_task(&conn, "agent-2", None, None, None).unwrap();
+        assert_eq!(picked.as_deref(), Some("stuck"));
+    }
+
+    #[test]
+    fn claim_next_with_preference_skips_needs_human_tasks() {
+        let conn = db::open_memory().unwrap();
+        for (name, description) in [
+            ("stuck", "dashboard redesign"),
+            ("other", "database cleanup"),
+        ] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    description,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        set_needs_human(&conn, "stuck", true).unwrap();
+
+        let picked = claim_next_task(&conn, "agent", Some("dashboard"), None, None).unwrap();
+        assert_eq!(picked.as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn set_needs_human_requires_task() {
+        let conn = db::open_memory().unwrap();
+        assert!(set_needs_human(&conn, "missing", true).is_err());
+    }
+
+    #[test]
+    fn claim_next_restricted_to_root_subtree() {
+        let conn = db::open_memory().unwrap();
+        for (name, parent) in [
+            ("outside", None),
+            ("epic", None),
+            ("done-child", Some("epic")),
+            ("blocked-child", Some("epic")),
+            ("grandchild", Some("done-child")),
+        ] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        claim_task(&conn, "epic", "lead").unwrap();
+        mark_done(&conn, "done-child").unwrap();
+        add_block(&conn, "outside", "blocked-child").unwrap();
+
+        // Blocked and done filtering still apply within the subtree.
+        let picked = claim_next_task(&conn, "agent", None, Some("epic"), None).unwrap();
+        assert_eq!(picked.as_deref(), Some("grandchild"));
+        let picked = claim_next_task(&conn, "agent", Some("outside"), Some("epic"), None).unwrap();
+        assert_eq!(picked, None);
+    }
+
+    #[test]
+    fn peek_next_matches_claim_next_without_claiming() {
+        let conn = db::open_memory().unwrap();
+        for (name, priority, parent) in [
+            ("epic", 0, None),
+            ("low", 0, Some("epic")),
+            ("urgent", 5, Some("epic")),
+            ("stuck", 9, Some("epic")),
+            ("outside", 7, None),
+        ] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    priority,
+                    parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        claim_task(&conn, "epic", "lead").unwrap();
+        set_needs_human(&conn, "stuck", true).unwrap();
+
+        let peeked = peek_next_task(&conn, None, Some("epic"), None).unwrap();
+        assert_eq!(peeked.as_deref(), Some("urgent"));
+        // Peeking twice gives the same answer and leaves the task unclaimed.
+        let again = peek_next_task(&conn, None, Some("epic"), None).unwrap();
+        assert_eq!(again, peeked);
+        let task = get_task(&conn, "urgent").unwrap();
+        assert_eq!(task.status, "open");
+        assert_eq!(task.assignee, None);
+
+        let claimed = claim_next_task(&conn, "agent", None, Some("epic"), None).unwrap();
+        assert_eq!(claimed, peeked);
+        let peeked = peek_next_task(&conn, Some("low"), Some("epic"), None).unwrap();
+        assert_eq!(peeked.as_deref(), Some("low"));
+    }
+
+    #[test]
+    fn claim_next_batch_matches_sequential_claims() {
+        let setup = || {
+            let conn = db::open_memory().unwrap();
+            for (name, priority) in [("a", 0), ("b", 2), ("c", 1), ("d", 0), ("e", 0)] {
+                add_task(
+                    &conn,
+                    AddTaskParams {
+                        name,
+                        priority,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            }
+            add_block(&conn, "a", "d").unwrap();
+            pause_task(&conn, "e").unwrap();
+            conn
+        };
+
+        let conn = setup();
+        let mut expected = Vec::new();
+        for id in 7.. {
+            let assignee = format!("ws/{id}");
+            match claim_next_task(&conn, &assignee, None, None, None).unwrap() {
+                Some(name) => expected.push((assignee, name)),
+                None => break,
+            }
+        }
+        // Priority order; "d" waits on "a" and "e" is paused.
+        let names: Vec<&str> = expected.iter().map(|(_, n)| n.as_str()).collect();
+        assert_eq!(names, ["b", "c", "a"]);
+
+        let conn = setup();
+        let batch = claim_next_batch(&conn, "ws/", 7, 10, None, None, None).unwrap();
+        assert_eq!(batch, expected);
+        assert!(conn.is_autocommit());
+        let task = get_task(&conn, "b").unwrap();
+        assert_eq!(task.status, "active");
+        assert_eq!(task.assignee.as_deref(), Some("ws/7"));
+
+        let conn = setup();
+        let batch = claim_next_batch(&conn, "ws/", 1, 2, None, None, None).unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(get_task(&conn, "a").unwrap().status, "open");
+        assert!(claim_next_batch(&conn, "ws/", 3, 0, None, None, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn claim_next_batch_concurrent_never_double_claims() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kbtz.db");
+        let path = path.to_str().unwrap().to_string();
+        {
+            let conn = db::open(&path).unwrap();
+            db::init(&conn).unwrap();
+            for i in 0..40 {
+                add_task(
+                    &conn,
+                    AddTaskParams {
+                        name: &format!("t{i}"),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            }
+        }
+
+        let workers: Vec<_> = ["a/", "b/", "c/", "d/"]
+            .into_iter()
+            .map(|prefix| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let conn = db::open(&path).unwrap();
+                    let mut claimed = Vec::new();
+                    let mut next_id = 1;
+                    loop {
+                        let batch =
+                            claim_next_batch(&conn, prefix, next_id, 3, None, None, None).unwrap();
+                        if batch.is_empty() {
+                            return claimed;
+                        }
+                        next_id += batch.len() as u64;
+                        claimed.extend(batch);
+                    }
+                })
+            })
+            .collect();
+        let mut claimed: Vec<(String, String)> = workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect();
+
+        assert_eq!(claimed.len(), 40);
+        claimed.sort_by(|a, b| a.1.cmp(&b.1));
+        claimed.dedup_by(|a, b| a.1 == b.1);
+        assert_eq!(claimed.len(), 40, "a task was claimed twice");
+
+        let conn = db::open(&path).unwrap();
+        for (assignee, name) in &claimed {
+            assert_eq!(
+                get_task(&conn, name).unwrap().assignee.as_deref(),
+                Some(assignee.as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn reassign_prefix_releases_or_reassigns_matching_claims() {
+        let setup = || {
+            let conn = db::open_memory().unwrap();
+            for (name, assignee) in [
+                ("a", "ws/3"),
+                ("b", "ws/7"),
+                ("c", "agent-ws/1"),
+                ("d", "other"),
+            ] {
+                add_task(
+                    &conn,
+                    AddTaskParams {
+                        name,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+                claim_task(&conn, name, assignee).unwrap();
+            }
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name: "e",
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            conn
+        };
+
+        let conn = setup();
+        let released = reassign_prefix(&conn, "ws/", None).unwrap();
+        assert_eq!(released, ["a", "b"]);
+        for name in ["a", "b"] {
+            let task = get_task(&conn, name).unwrap();
+            assert_eq!(task.status, "open");
+            assert_eq!(task.assignee, None);
+        }
+        assert_eq!(
+            get_task(&conn, "c").unwrap().assignee.as_deref(),
+            Some("agent-ws/1")
+        );
+        assert_eq!(
+            get_task(&conn, "d").unwrap().assignee.as_deref(),
+            Some("other")
+        );
+        assert!(reassign_prefix(&conn, "ws/", None).unwrap().is_empty());
+
+        let conn = setup();
+        let moved = reassign_prefix(&conn, "ws/", Some("rescuer")).unwrap();
+        assert_eq!(moved, ["a", "b"]);
+        for name in ["a", "b"] {
+            let task = get_task(&conn, name).unwrap();
+            assert_eq!(task.status, "active");
+            assert_eq!(task.assignee.as_deref(), Some("rescuer"));
+        }
+        assert_eq!(get_task(&conn, "e").unwrap().status, "open");
+
+        assert!(reassign_prefix(&conn, "", None).is_err());
+    }
+
+    #[test]
+    fn peek_next_empty_returns_none() {
+        let conn = db::open_memory().unwrap();
+        assert_eq!(peek_next_task(&conn, None, None, None).unwrap(), None);
+    }
+
+    #[test]
+    fn claim_next_empty_subtree_returns_none() {
+        let conn = db::open_memory().unwrap();
         add_task(
             &conn,
             AddTaskParams {
-                name: "claimed-task",
+                name: "leaf",
+                paused: true,
                 ..Default::default()
             },
         )
         .unwrap();
-        claim_task(&conn, "claimed-task", "other-agent").unwrap();
         add_task(
             &conn,
             AddTaskParams {
-                name: "available",
+                name: "other",
                 ..Default::default()
             },
         )
         .unwrap();
-
-        let picked = claim_next_task(&conn, "agent", None, None).unwrap();
-        assert_eq!(picked.as_deref(), Some("available"));
+        let picked = claim_next_task(&conn, "agent", None, Some("leaf"), None).unwrap();
+        assert_eq!(picked, None);
+        assert!(claim_next_task(&conn, "agent", None, Some("missing"), None).is_err());
     }
 
     #[test]
-    fn claim_next_skips_blocked_tasks() {
+    fn claim_next_preference_beats_priority() {
         let conn = db::open_memory().unwrap();
         add_task(
             &conn,
             AddTaskParams {
-                name: "blocker",
+                name: "urgent",
+                description: "server-side API work",
+                priority: 10,
                 ..Default::default()
             },
         )
@@ -1608,50 +4230,166 @@ mod tests {
         add_task(
             &conn,
             AddTaskParams {
-                name: "blocked",
+                name: "frontend",
+                description: "UI components for dashboard",
                 ..Default::default()
             },
         )
         .unwrap();
-        add_block(&conn, "blocker", "blocked").unwrap();
 
-        // "blocked" has undone blocker, so only "blocker" is available
-        let picked = claim_next_task(&conn, "agent", None, None).unwrap();
-        assert_eq!(picked.as_deref(), Some("blocker"));
+        let picked = claim_next_task(&conn, "agent", Some("UI components"), None, None).unwrap();
+        assert_eq!(picked.as_deref(), Some("frontend"));
     }
 
     #[test]
-    fn claim_next_prefers_unblockers() {
+    fn set_priority_works() {
         let conn = db::open_memory().unwrap();
-        // "plain" is older (lower id), but "unblocker" unblocks "downstream"
         add_task(
             &conn,
             AddTaskParams {
-                name: "plain",
+                name: "t",
                 ..Default::default()
             },
         )
         .unwrap();
-        add_task(
-            &conn,
-            AddTaskParams {
-                name: "unblocker",
-                ..Default::default()
-            },
+        assert_eq!(get_task(&conn, "t").unwrap().priority, 0);
+        set_priority(&conn, "t", 3).unwrap();
+        assert_eq!(get_task(&conn, "t").unwrap().priority, 3);
+        set_priority(&conn, "t", -1).unwrap();
+        assert_eq!(get_task(&conn, "t").unwrap().priority, -1);
+    }
+
+    #[test]
+    fn rollup_sums_estimates_over_mixed_status_subtree() {
+        let conn = db::open_memory().unwrap();
+        for (name, parent, estimate) in [
+            ("epic", None, None),
+            ("design", Some("epic"), Some(3)),
+            ("build", Some("epic"), Some(8)),
+            ("build-api", Some("build"), Some(5)),
+            ("build-ui", Some("build"), None),
+            ("docs", Some("epic"), Some(2)),
+            ("unrelated", None, Some(100)),
+        ] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    estimate,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        mark_done(&conn, "design").unwrap();
+        mark_done(&conn, "build-api").unwrap();
+        claim_task(&conn, "build", "agent").unwrap();
+        pause_task(&conn, "docs").unwrap();
+
+        assert_eq!(
+            rollup(&conn, "epic").unwrap(),
+            Rollup {
+                name: "epic".into(),
+                total: 18,
+                done: 8,
+                tasks: 6,
+                unestimated: 2,
+            }
+        );
+        let build = rollup(&conn, "build").unwrap();
+        assert_eq!((build.total, build.done, build.tasks), (13, 5, 3));
+
+        set_estimate(&conn, "build-ui", Some(4)).unwrap();
+        set_estimate(&conn, "build", None).unwrap();
+        let build = rollup(&conn, "build").unwrap();
+        assert_eq!((build.total, build.done, build.unestimated), (9, 5, 1));
+        assert_eq!(get_task(&conn, "build-ui").unwrap().estimate, Some(4));
+
+        assert!(set_estimate(&conn, "build-ui", Some(-1)).is_err());
+        assert!(rollup(&conn, "missing").is_err());
+    }
+
+    #[test]
+    fn list_since_returns_only_newer_tasks() {
+        let conn = db::open_memory().unwrap();
+        for name in ["old", "new"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "UPDATE tasks SET updated_at = '2020-01-01T00:00:00Z' WHERE name = 'old'",
+            [],
         )
         .unwrap();
+
+        let since = |ts| ListFilter {
+            since: Some(ts),
+            ..Default::default()
+        };
+        let filter = since("2021-01-01T00:00:00Z");
+        let names: Vec<String> = list_tasks_filtered(&conn, None, filter)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, vec!["new"]);
+        assert_eq!(count_tasks(&conn, None, filter).unwrap(), 1);
+        // Strictly after: a task updated exactly at `since` is left out.
+        let at = since("2020-01-01T00:00:00Z");
+        assert_eq!(list_tasks_filtered(&conn, None, at).unwrap().len(), 1);
+
+        let err = list_tasks_filtered(&conn, None, since("2021-01-01")).unwrap_err();
+        assert!(err.to_string().contains("invalid timestamp '2021-01-01'"));
+        assert!(count_tasks(&conn, None, since("yesterday")).is_err());
+    }
+
+    #[test]
+    fn touch_bumps_only_updated_at() {
+        let conn = db::open_memory().unwrap();
         add_task(
             &conn,
             AddTaskParams {
-                name: "downstream",
+                name: "t",
+                claim: Some("agent"),
                 ..Default::default()
             },
         )
         .unwrap();
-        add_block(&conn, "unblocker", "downstream").unwrap();
+        conn.execute(
+            "UPDATE tasks SET updated_at = '2020-01-01T00:00:00Z', \
+             status_changed_at = '2020-01-01T00:00:00Z' WHERE name = 't'",
+            [],
+        )
+        .unwrap();
 
-        let picked = claim_next_task(&conn, "agent", None, None).unwrap();
-        assert_eq!(picked.as_deref(), Some("unblocker"));
+        touch_task(&conn, "t").unwrap();
+        let task = get_task(&conn, "t").unwrap();
+        assert!(task.updated_at.as_str() > "2020-01-01T00:00:00Z");
+        assert_eq!(
+            task.status_changed_at.as_deref(),
+            Some("2020-01-01T00:00:00Z")
+        );
+        assert_eq!(task.status, "active");
+        assert_eq!(task.assignee.as_deref(), Some("agent"));
+
+        assert!(touch_task(&conn, "missing").is_err());
+    }
+
+    #[test]
+    fn set_priority_nonexistent_fails() {
+        let conn = db::open_memory().unwrap();
+        let err = set_priority(&conn, "nope", 1).unwrap_err();
+        assert!(
+            err.to_string().contains("not found"),
+            "unexpected error: {err}"
+        );
     }
 
     #[test]
@@ -1677,7 +4415,7 @@ mod tests {
         )
         .unwrap();
 
-        let picked = claim_next_task(&conn, "agent", Some("UI components"), None).unwrap();
+        let picked = claim_next_task(&conn, "agent", Some("UI components"), None, None).unwrap();
         assert_eq!(picked.as_deref(), Some("frontend"));
     }
 
@@ -1704,7 +4442,8 @@ mod tests {
         .unwrap();
         add_note(&conn, "task-b", "needs database migration work").unwrap();
 
-        let picked = claim_next_task(&conn, "agent", Some("database migration"), None).unwrap();
+        let picked =
+            claim_next_task(&conn, "agent", Some("database migration"), None, None).unwrap();
         assert_eq!(picked.as_deref(), Some("task-b"));
     }
 
@@ -1722,7 +4461,7 @@ mod tests {
         )
         .unwrap();
 
-        let picked = claim_next_task(&conn, "agent", Some("nonexistent-xyz"), None).unwrap();
+        let picked = claim_next_task(&conn, "agent", Some("nonexistent-xyz"), None, None).unwrap();
         assert_eq!(picked.as_deref(), Some("only-task"));
     }
 
@@ -1738,7 +4477,7 @@ mod tests {
         )
         .unwrap();
 
-        let picked = claim_next_task(&conn, "my-agent", None, None).unwrap();
+        let picked = claim_next_task(&conn, "my-agent", None, None, None).unwrap();
         assert_eq!(picked.as_deref(), Some("t"));
 
         let task = get_task(&conn, "t").unwrap();
@@ -1931,60 +4670,348 @@ mod tests {
         )
         .unwrap();
 
-        let picked = claim_next_task(&conn, "agent", None, None).unwrap();
+        let picked = claim_next_task(&conn, "agent", None, None, None).unwrap();
         assert_eq!(picked.as_deref(), Some("available"));
     }
 
     #[test]
-    fn list_excludes_paused_by_default() {
+    fn list_excludes_paused_by_default() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "open-task",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "paused-task",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        pause_task(&conn, "paused-task").unwrap();
+        let tasks = list_tasks(&conn, None, false, None, None, None).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].name, "open-task");
+    }
+
+    #[test]
+    fn list_filter_paused() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "open-task",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "paused-task",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        pause_task(&conn, "paused-task").unwrap();
+
+        let paused =
+            list_tasks(&conn, Some(StatusFilter::Paused), false, None, None, None).unwrap();
+        assert_eq!(paused.len(), 1);
+        assert_eq!(paused[0].name, "paused-task");
+    }
+
+    #[test]
+    fn stale_tasks_finds_long_idle_active_tasks() {
+        let conn = db::open_memory().unwrap();
+        for name in ["idle", "fresh", "open"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        claim_task(&conn, "idle", "agent-1").unwrap();
+        claim_task(&conn, "fresh", "agent-2").unwrap();
+        conn.execute(
+            "UPDATE tasks SET status_changed_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now', '-2 hours') \
+             WHERE name IN ('idle', 'open')",
+            [],
+        )
+        .unwrap();
+
+        let stale = stale_tasks(&conn, std::time::Duration::from_secs(3600)).unwrap();
+        let names: Vec<&str> = stale.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["idle"]);
+
+        let stale = stale_tasks(&conn, std::time::Duration::from_secs(3 * 3600)).unwrap();
+        assert!(stale.is_empty());
+    }
+
+    #[test]
+    fn list_blocked_follows_a_chain() {
+        let conn = db::open_memory().unwrap();
+        for name in ["first", "middle", "last", "free"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        add_block(&conn, "first", "middle").unwrap();
+        add_block(&conn, "middle", "last").unwrap();
+
+        let summary = |conn: &Connection| -> Vec<(String, Vec<String>)> {
+            list_blocked(conn)
+                .unwrap()
+                .into_iter()
+                .map(|(t, b)| (t.name, b))
+                .collect()
+        };
+        assert_eq!(
+            summary(&conn),
+            vec![
+                ("middle".to_string(), vec!["first".to_string()]),
+                ("last".to_string(), vec!["middle".to_string()]),
+            ]
+        );
+
+        // Finishing the head unblocks the middle task but not the tail.
+        mark_done(&conn, "first").unwrap();
+        assert_eq!(
+            summary(&conn),
+            vec![("last".to_string(), vec!["middle".to_string()])]
+        );
+    }
+
+    #[test]
+    fn list_assignees_groups_active_tasks() {
+        let conn = db::open_memory().unwrap();
+        for name in ["a", "b", "c", "d"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        claim_task(&conn, "a", "ws/2").unwrap();
+        claim_task(&conn, "b", "alice").unwrap();
+        claim_task(&conn, "c", "ws/2").unwrap();
+        claim_task(&conn, "d", "alice").unwrap();
+        mark_done(&conn, "d").unwrap();
+
+        let groups = list_assignees(&conn).unwrap();
+        let summary: Vec<(&str, Vec<&str>)> = groups
+            .iter()
+            .map(|(a, tasks)| (a.as_str(), tasks.iter().map(|t| t.name.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("alice", vec!["b"]), ("ws/2", vec!["a", "c"])]
+        );
+    }
+
+    #[test]
+    fn list_roots_and_leaves_compose_with_filters() {
+        let conn = db::open_memory().unwrap();
+        for (name, parent) in [
+            ("epic", None),
+            ("a", Some("epic")),
+            ("a1", Some("a")),
+            ("a2", Some("a")),
+            ("b", Some("epic")),
+            ("solo", None),
+        ] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        mark_done(&conn, "a2").unwrap();
+        claim_task(&conn, "b", "alice").unwrap();
+
+        let names = |status, all, assignee, roots, leaves| -> Vec<String> {
+            let filter = ListFilter {
+                status,
+                all,
+                assignee,
+                roots,
+                leaves,
+                ..Default::default()
+            };
+            list_tasks_filtered(&conn, None, filter)
+                .unwrap()
+                .into_iter()
+                .map(|t| t.name)
+                .collect()
+        };
+        assert_eq!(names(None, false, None, true, false), ["epic", "solo"]);
+        // a2 is a done leaf: hidden by default, shown with --all.
+        assert_eq!(names(None, false, None, false, true), ["a1", "b", "solo"]);
+        assert_eq!(
+            names(None, true, None, false, true),
+            ["a1", "a2", "b", "solo"]
+        );
+        assert_eq!(
+            names(Some(StatusFilter::Done), false, None, false, true),
+            ["a2"]
+        );
+        assert_eq!(names(None, false, Some("alice"), false, true), ["b"]);
+        assert_eq!(names(None, false, None, true, true), ["solo"]);
+        // The subtree under --root is filtered the same way.
+        let leaves = ListFilter {
+            leaves: true,
+            ..Default::default()
+        };
+        let subtree: Vec<String> = list_tasks_filtered(&conn, Some("epic"), leaves)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(subtree, ["b", "a1"]);
+        // A task whose children are all done is still not a leaf.
+        mark_done(&conn, "a1").unwrap();
+        assert_eq!(names(None, false, None, false, true), ["b", "solo"]);
+    }
+
+    #[test]
+    fn list_pages_report_total_matches() {
         let conn = db::open_memory().unwrap();
-        add_task(
-            &conn,
-            AddTaskParams {
-                name: "open-task",
-                ..Default::default()
-            },
-        )
-        .unwrap();
-        add_task(
-            &conn,
-            AddTaskParams {
-                name: "paused-task",
-                ..Default::default()
-            },
-        )
-        .unwrap();
-        pause_task(&conn, "paused-task").unwrap();
-        let tasks = list_tasks(&conn, None, false, None, None, None).unwrap();
-        assert_eq!(tasks.len(), 1);
-        assert_eq!(tasks[0].name, "open-task");
+        for (name, parent) in [
+            ("root", None),
+            ("a", Some("root")),
+            ("b", Some("root")),
+            ("c", Some("root")),
+            ("a1", Some("a")),
+            ("d", None),
+        ] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        mark_done(&conn, "b").unwrap();
+
+        let names = |(tasks, total): (Vec<Task>, usize)| {
+            (tasks.into_iter().map(|t| t.name).collect::<Vec<_>>(), total)
+        };
+        let page = |limit, offset| Page { limit, offset };
+        let open = ListFilter::default();
+
+        assert_eq!(
+            names(list_tasks_page(&conn, None, open, page(Some(2), 1)).unwrap()),
+            (vec!["a".to_string(), "c".to_string()], 5)
+        );
+        assert_eq!(
+            names(list_tasks_page(&conn, None, open, page(None, 4)).unwrap()),
+            (vec!["d".to_string()], 5)
+        );
+        // Done tasks are filtered before the page is cut.
+        assert_eq!(
+            names(list_children_page(&conn, "root", open, page(Some(1), 1)).unwrap()),
+            (vec!["c".to_string()], 2)
+        );
+        // Under --root the page is cut from the depth-first order.
+        assert_eq!(
+            names(list_tasks_page(&conn, Some("root"), open, page(Some(2), 1)).unwrap()),
+            (vec!["a".to_string(), "c".to_string()], 4)
+        );
+        assert_eq!(
+            names(list_tasks_page(&conn, None, open, page(Some(2), 10)).unwrap()),
+            (vec![], 5)
+        );
     }
 
     #[test]
-    fn list_filter_paused() {
+    fn count_tasks_matches_list_lengths() {
         let conn = db::open_memory().unwrap();
-        add_task(
-            &conn,
-            AddTaskParams {
-                name: "open-task",
-                ..Default::default()
-            },
-        )
-        .unwrap();
-        add_task(
-            &conn,
-            AddTaskParams {
-                name: "paused-task",
-                ..Default::default()
-            },
-        )
-        .unwrap();
-        pause_task(&conn, "paused-task").unwrap();
-
-        let paused =
-            list_tasks(&conn, Some(StatusFilter::Paused), false, None, None, None).unwrap();
-        assert_eq!(paused.len(), 1);
-        assert_eq!(paused[0].name, "paused-task");
+        for (name, parent) in [
+            ("root", None),
+            ("a", Some("root")),
+            ("b", Some("root")),
+            ("c", Some("root")),
+            ("d", Some("a")),
+            ("e", None),
+        ] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        claim_task(&conn, "a", "alice").unwrap();
+        claim_task(&conn, "d", "alice").unwrap();
+        mark_done(&conn, "c").unwrap();
+        pause_task(&conn, "e").unwrap();
+        add_block(&conn, "a", "b").unwrap();
+        add_block(&conn, "c", "root").unwrap();
+
+        let statuses = [
+            None,
+            Some(StatusFilter::Open),
+            Some(StatusFilter::Active),
+            Some(StatusFilter::Paused),
+            Some(StatusFilter::Done),
+        ];
+        for status in statuses {
+            for all in [false, true] {
+                for assignee in [None, Some("alice")] {
+                    for (blocked, (roots, leaves)) in
+                        [None, Some(true), Some(false)].into_iter().flat_map(|b| {
+                            [(false, false), (true, false), (false, true), (true, true)]
+                                .map(|shape| (b, shape))
+                        })
+                    {
+                        let filter = ListFilter {
+                            status,
+                            all,
+                            assignee,
+                            blocked,
+                            roots,
+                            leaves,
+                            ..Default::default()
+                        };
+                        let what = format!("{filter:?}");
+                        let listed = list_tasks_filtered(&conn, None, filter).unwrap().len();
+                        let counted = count_tasks(&conn, None, filter).unwrap();
+                        assert_eq!(counted, listed, "{what}");
+
+                        let listed = list_children_filtered(&conn, "root", filter).unwrap().len();
+                        let counted = count_tasks(&conn, Some("root"), filter).unwrap();
+                        assert_eq!(counted, listed, "{what}");
+                    }
+                }
+            }
+        }
+        assert!(count_tasks(&conn, Some("missing"), ListFilter::default()).is_err());
     }
 
     #[test]
@@ -2367,7 +5394,7 @@ mod tests {
             },
         )
         .unwrap();
-        let picked = claim_next_task(&conn, "agent", None, None).unwrap();
+        let picked = claim_next_task(&conn, "agent", None, None, None).unwrap();
         assert_eq!(picked.as_deref(), Some("open-task"));
     }
 
@@ -2487,6 +5514,67 @@ mod tests {
         assert!(deps.is_empty());
     }
 
+    #[test]
+    fn search_page_limits_in_sql_and_reports_total() {
+        let conn = db::open_memory().unwrap();
+        for name in ["cache-a", "cache-b", "cache-c", "cache-d", "other"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    description: if name == "other" {
+                        "unrelated"
+                    } else {
+                        "cache work"
+                    },
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        let search = |page| {
+            let (results, total) =
+                search_tasks_page(&conn, "cache", FtsMode::default(), None, None, page).unwrap();
+            let names: Vec<String> = results.into_iter().map(|r| r.task.name).collect();
+            (names, total)
+        };
+
+        let all = search(Page::default());
+        assert_eq!(all.0.len(), 4);
+        assert_eq!(all.1, 4);
+        let (names, total) = search(Page {
+            limit: Some(2),
+            offset: 1,
+        });
+        assert_eq!(names, all.0[1..3]);
+        assert_eq!(total, 4);
+        // Past the end: empty page, total still reported.
+        let (names, total) = search(Page {
+            limit: None,
+            offset: 10,
+        });
+        assert!(names.is_empty());
+        assert_eq!(total, 4);
+    }
+
+    #[test]
+    fn page_slice_applies_offset_then_limit() {
+        let items: Vec<u32> = (0..10).collect();
+        assert_eq!(Page::default().slice(items.clone()), items);
+        let page = Page {
+            limit: Some(3),
+            offset: 8,
+        };
+        assert_eq!(page.slice(items.clone()), [8, 9]);
+        let page = Page {
+            limit: Some(0),
+            offset: 0,
+        };
+        assert!(page.slice(items).is_empty());
+        assert!(Page::default().is_all());
+        assert!(!page.is_all());
+    }
+
     #[test]
     fn search_matches_task_name() {
         let conn = db::open_memory().unwrap();
@@ -2509,10 +5597,12 @@ mod tests {
         )
         .unwrap();
 
-        let results = search_tasks(&conn, "auth").unwrap();
+        let results = search_tasks(&conn, "auth", FtsMode::default(), None, None).unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].task.name, "auth-login");
         assert!(results[0].matched_in.contains(&"task".to_string()));
+        // Only the name matched, so there is no excerpt to show.
+        assert_eq!(results[0].snippet, None);
     }
 
     #[test]
@@ -2537,10 +5627,15 @@ mod tests {
         )
         .unwrap();
 
-        let results = search_tasks(&conn, "authentication").unwrap();
+        let results =
+            search_tasks(&conn, "authentication", FtsMode::default(), None, None).unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].task.name, "task-a");
         assert!(results[0].matched_in.contains(&"task".to_string()));
+        assert_eq!(
+            results[0].snippet.as_deref(),
+            Some("implement **authentication**")
+        );
     }
 
     #[test]
@@ -2566,10 +5661,271 @@ mod tests {
         .unwrap();
         add_note(&conn, "task-b", "needs database migration").unwrap();
 
-        let results = search_tasks(&conn, "migration").unwrap();
+        let results = search_tasks(&conn, "migration", FtsMode::default(), None, None).unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].task.name, "task-b");
         assert!(results[0].matched_in.contains(&"notes".to_string()));
+        assert_eq!(
+            results[0].snippet.as_deref(),
+            Some("needs database **migration**")
+        );
+    }
+
+    #[test]
+    fn search_snippet_is_trimmed_and_flattened() {
+        let conn = db::open_memory().unwrap();
+        let description = "step one\nstep two\nthen we finally rotate the signing keys \
+                           used by every service in the fleet and update the docs";
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "keys",
+                description,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_note(&conn, "keys", "rotation also needs a keys audit").unwrap();
+
+        let results = search_tasks(&conn, "keys", FtsMode::default(), None, None).unwrap();
+        // The description wins over the note when both match.
+        let snippet = results[0].snippet.as_deref().unwrap();
+        assert!(snippet.contains("**keys**"), "{snippet}");
+        assert!(!snippet.contains('\n'), "{snippet}");
+        assert!(snippet.ends_with('…'), "{snippet}");
+    }
+
+    #[test]
+    fn search_skips_deleted_note() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "t",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_note(&conn, "t", "needs database migration").unwrap();
+        let id = list_notes(&conn, "t").unwrap()[0].id;
+
+        delete_note(&conn, id).unwrap();
+        assert!(
+            search_tasks(&conn, "migration", FtsMode::default(), None, None)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn search_reflects_edited_note() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "t",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_note(&conn, "t", "needs database migration").unwrap();
+        let id = list_notes(&conn, "t").unwrap()[0].id;
+
+        edit_note(&conn, id, "needs schema review").unwrap();
+        assert!(
+            search_tasks(&conn, "migration", FtsMode::default(), None, None)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            search_tasks(&conn, "schema", FtsMode::default(), None, None)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn search_matches_assignee_and_follows_claims() {
+        let conn = db::open_memory().unwrap();
+        for name in ["login", "billing", "docs"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        claim_task(&conn, "login", "alice").unwrap();
+        claim_task(&conn, "billing", "alice").unwrap();
+        claim_task(&conn, "docs", "bob").unwrap();
+
+        let search = |conn: &Connection, q: &str| -> Vec<(String, Vec<String>)> {
+            search_tasks(conn, q, FtsMode::default(), None, None)
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.task.name, r.matched_in))
+                .collect()
+        };
+        let assignee = vec!["assignee".to_string()];
+        assert_eq!(
+            search(&conn, "alice"),
+            vec![
+                ("login".to_string(), assignee.clone()),
+                ("billing".to_string(), assignee.clone()),
+            ]
+        );
+
+        // The index follows release and steal.
+        release_task(&conn, "login", "alice").unwrap();
+        steal_task(&conn, "docs", "alice").unwrap();
+        let names: Vec<_> = search(&conn, "alice").into_iter().map(|r| r.0).collect();
+        assert_eq!(names, vec!["billing", "docs"]);
+        assert!(search(&conn, "bob").is_empty());
+
+        // A name match is still reported as a task match.
+        assert_eq!(
+            search(&conn, "docs alice"),
+            vec![
+                ("docs".to_string(), vec!["task".into(), "assignee".into()]),
+                ("billing".to_string(), assignee),
+            ]
+        );
+    }
+
+    #[test]
+    fn search_filters_by_status_and_assignee() {
+        let conn = db::open_memory().unwrap();
+        for name in ["auth-login", "auth-logout", "auth-tokens"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    description: "auth work",
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        claim_task(&conn, "auth-logout", "me").unwrap();
+        claim_task(&conn, "auth-tokens", "me").unwrap();
+        mark_done(&conn, "auth-tokens").unwrap();
+        claim_task(&conn, "auth-login", "other").unwrap();
+
+        let names = |results: Vec<SearchResult>| -> Vec<String> {
+            results.into_iter().map(|r| r.task.name).collect()
+        };
+        assert_eq!(
+            names(
+                search_tasks(
+                    &conn,
+                    "auth",
+                    FtsMode::default(),
+                    Some(StatusFilter::Active),
+                    None
+                )
+                .unwrap()
+            ),
+            vec!["auth-login", "auth-logout"]
+        );
+        assert_eq!(
+            names(
+                search_tasks(
+                    &conn,
+                    "auth",
+                    FtsMode::default(),
+                    Some(StatusFilter::Active),
+                    Some("me")
+                )
+                .unwrap()
+            ),
+            vec!["auth-logout"]
+        );
+        assert_eq!(
+            names(
+                search_tasks(
+                    &conn,
+                    "auth",
+                    FtsMode::default(),
+                    Some(StatusFilter::Done),
+                    None
+                )
+                .unwrap()
+            ),
+            vec!["auth-tokens"]
+        );
+        assert_eq!(
+            search_tasks(&conn, "auth", FtsMode::default(), None, None)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn sanitize_fts_query_modes() {
+        let any = FtsMode::default();
+        let all_prefix = FtsMode {
+            join: FtsJoin::All,
+            prefix: true,
+        };
+        assert_eq!(
+            sanitize_fts_query("database migration", any).as_deref(),
+            Some("\"database\" OR \"migration\"")
+        );
+        assert_eq!(
+            sanitize_fts_query("auth tok", all_prefix).as_deref(),
+            Some("\"auth\"* AND \"tok\"*")
+        );
+        // Stray quotes and stars can't smuggle in FTS syntax.
+        assert_eq!(
+            sanitize_fts_query("a*b \"* *", all_prefix).as_deref(),
+            Some("\"ab\"*")
+        );
+        assert_eq!(sanitize_fts_query("** \"", any), None);
+    }
+
+    #[test]
+    fn search_all_and_prefix_modes() {
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "both",
+                description: "database migration",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "one",
+                description: "database backup",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let all = FtsMode {
+            join: FtsJoin::All,
+            prefix: false,
+        };
+        let results = search_tasks(&conn, "database migration", all, None, None).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].task.name, "both");
+
+        let prefix = FtsMode {
+            join: FtsJoin::Any,
+            prefix: true,
+        };
+        assert!(search_tasks(&conn, "migr", FtsMode::default(), None, None)
+            .unwrap()
+            .is_empty());
+        let results = search_tasks(&conn, "migr", prefix, None, None).unwrap();
+        assert_eq!(results.len(), 1);
     }
 
     #[test]
@@ -2586,7 +5942,8 @@ mod tests {
         .unwrap();
         mark_done(&conn, "done-task").unwrap();
 
-        let results = search_tasks(&conn, "authentication").unwrap();
+        let results =
+            search_tasks(&conn, "authentication", FtsMode::default(), None, None).unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].task.name, "done-task");
         assert_eq!(results[0].task.status, "done");
@@ -2605,15 +5962,15 @@ mod tests {
         )
         .unwrap();
 
-        let results = search_tasks(&conn, "nonexistent").unwrap();
+        let results = search_tasks(&conn, "nonexistent", FtsMode::default(), None, None).unwrap();
         assert!(results.is_empty());
     }
 
     #[test]
     fn search_empty_query_fails() {
         let conn = db::open_memory().unwrap();
-        assert!(search_tasks(&conn, "").is_err());
-        assert!(search_tasks(&conn, "   ").is_err());
+        assert!(search_tasks(&conn, "", FtsMode::default(), None, None).is_err());
+        assert!(search_tasks(&conn, "   ", FtsMode::default(), None, None).is_err());
     }
 
     #[test]
@@ -2630,7 +5987,8 @@ mod tests {
         .unwrap();
         add_note(&conn, "auth-task", "authentication details here").unwrap();
 
-        let results = search_tasks(&conn, "authentication").unwrap();
+        let results =
+            search_tasks(&conn, "authentication", FtsMode::default(), None, None).unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].task.name, "auth-task");
         assert!(results[0].matched_in.contains(&"task".to_string()));
@@ -2908,7 +6266,7 @@ mod tests {
         )
         .unwrap();
 
-        let picked = claim_next_task(&conn, "agent", None, Some(&["claude"])).unwrap();
+        let picked = claim_next_task(&conn, "agent", None, None, Some(&["claude"])).unwrap();
         assert_eq!(picked.as_deref(), Some("claude-task"));
 
         // gemini-task should still be open (not claimed)
@@ -2928,7 +6286,7 @@ mod tests {
         )
         .unwrap();
 
-        let picked = claim_next_task(&conn, "agent", None, Some(&["claude"])).unwrap();
+        let picked = claim_next_task(&conn, "agent", None, None, Some(&["claude"])).unwrap();
         assert_eq!(picked.as_deref(), Some("default-task"));
     }
 
@@ -2964,7 +6322,8 @@ mod tests {
         .unwrap();
 
         // With both claude and gemini allowed, should pick gemini-task (oldest)
-        let picked = claim_next_task(&conn, "agent", None, Some(&["claude", "gemini"])).unwrap();
+        let picked =
+            claim_next_task(&conn, "agent", None, None, Some(&["claude", "gemini"])).unwrap();
         assert_eq!(picked.as_deref(), Some("gemini-task"));
 
         // gpt-task should still be open
@@ -2986,7 +6345,7 @@ mod tests {
         .unwrap();
 
         // None means no filtering — backward compatible
-        let picked = claim_next_task(&conn, "agent", None, None).unwrap();
+        let picked = claim_next_task(&conn, "agent", None, None, None).unwrap();
         assert_eq!(picked.as_deref(), Some("gemini-task"));
     }
 
@@ -3003,7 +6362,7 @@ mod tests {
         )
         .unwrap();
 
-        let picked = claim_next_task(&conn, "agent", None, Some(&["claude"])).unwrap();
+        let picked = claim_next_task(&conn, "agent", None, None, Some(&["claude"])).unwrap();
         assert_eq!(picked, None);
     }
 
@@ -3029,7 +6388,7 @@ mod tests {
         .unwrap();
 
         // Empty slice = no configured backends, only NULL agent tasks
-        let picked = claim_next_task(&conn, "agent", None, Some(&[])).unwrap();
+        let picked = claim_next_task(&conn, "agent", None, None, Some(&[])).unwrap();
         assert_eq!(picked.as_deref(), Some("default-task"));
     }
 
@@ -3068,8 +6427,233 @@ mod tests {
         .unwrap();
 
         // Prefer "UI" but only claude backends
-        let picked =
-            claim_next_task(&conn, "agent", Some("UI components"), Some(&["claude"])).unwrap();
+        let picked = claim_next_task(
+            &conn,
+            "agent",
+            Some("UI components"),
+            None,
+            Some(&["claude"]),
+        )
+        .unwrap();
         assert_eq!(picked.as_deref(), Some("claude-ui"));
     }
+
+    #[test]
+    fn export_all_includes_every_status_notes_and_deps() {
+        let conn = db::open_memory().unwrap();
+        for name in ["parent", "child", "paused", "finished"] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent: (name == "child").then_some("parent"),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        claim_task(&conn, "child", "agent-1").unwrap();
+        pause_task(&conn, "paused").unwrap();
+        mark_done(&conn, "finished").unwrap();
+        add_note(&conn, "child", "progress").unwrap();
+        add_block(&conn, "finished", "parent").unwrap();
+
+        let doc = export_all(&conn).unwrap();
+        assert_eq!(doc.version, EXPORT_VERSION);
+        let names: Vec<&str> = doc.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["parent", "child", "paused", "finished"]);
+        let child = &doc.tasks[1];
+        assert_eq!(child.parent.as_deref(), Some("parent"));
+        assert_eq!(child.assignee.as_deref(), Some("agent-1"));
+        assert!(child.status_changed_at.is_some());
+        assert_eq!(doc.notes.len(), 1);
+        assert_eq!(doc.notes[0].content, "progress");
+        // Edges from done blockers are kept even though they no longer block.
+        assert_eq!(
+            doc.deps,
+            [Dep {
+                blocker: "finished".into(),
+                blocked: "parent".into(),
+            }]
+        );
+
+        let json = serde_json::to_string(&doc).unwrap();
+        let back: ExportDoc = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.tasks.len(), 4);
+        assert_eq!(back.deps, doc.deps);
+    }
+
+    fn sample_export() -> ExportDoc {
+        let conn = db::open_memory().unwrap();
+        for (name, parent) in [("root", None), ("leaf", Some("root")), ("other", None)] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    description: "desc",
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        claim_task(&conn, "leaf", "agent-1").unwrap();
+        mark_done(&conn, "other").unwrap();
+        add_labeled_note(&conn, "leaf", Some("status"), "a note").unwrap();
+        add_block(&conn, "other", "leaf").unwrap();
+        export_all(&conn).unwrap()
+    }
+
+    #[test]
+    fn import_round_trips_export() {
+        let mut doc = sample_export();
+        // Children listed before their parents still import.
+        doc.tasks.reverse();
+
+        let conn = db::open_memory().unwrap();
+        let stats = import_all(&conn, &doc, ImportMode::Merge).unwrap();
+        assert_eq!(
+            stats,
+            ImportStats {
+                tasks: 3,
+                skipped: 0,
+                notes: 1,
+                deps: 1,
+            }
+        );
+
+        let back = export_all(&conn).unwrap();
+        let leaf = back.tasks.iter().find(|t| t.name == "leaf").unwrap();
+        let orig = doc.tasks.iter().find(|t| t.name == "leaf").unwrap();
+        assert_eq!(leaf.parent.as_deref(), Some("root"));
+        assert_eq!(leaf.status, "active");
+        assert_eq!(leaf.assignee.as_deref(), Some("agent-1"));
+        assert_eq!(leaf.status_changed_at, orig.status_changed_at);
+        assert_eq!(leaf.created_at, orig.created_at);
+        assert_eq!(back.notes[0].created_at, doc.notes[0].created_at);
+        assert_eq!(back.notes[0].label.as_deref(), Some("status"));
+        assert_eq!(back.deps, doc.deps);
+    }
+
+    #[test]
+    fn import_merge_skips_existing_tasks_and_their_notes() {
+        let doc = sample_export();
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "leaf",
+                description: "local",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let stats = import_all(&conn, &doc, ImportMode::Merge).unwrap();
+        assert_eq!(stats.tasks, 2);
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(stats.notes, 0);
+        assert_eq!(get_task(&conn, "leaf").unwrap().description, "local");
+        assert!(list_notes(&conn, "leaf").unwrap().is_empty());
+    }
+
+    #[test]
+    fn import_replace_rejects_collisions_unless_forced() {
+        let doc = sample_export();
+        let conn = db::open_memory().unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "root",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        add_task(
+            &conn,
+            AddTaskParams {
+                name: "local-only",
+                parent: Some("root"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let err = import_all(&conn, &doc, ImportMode::Replace { force: false }).unwrap_err();
+        assert!(err.to_string().contains("root"), "{err}");
+        assert!(task_exists(&conn, "local-only").unwrap());
+
+        import_all(&conn, &doc, ImportMode::Replace { force: true }).unwrap();
+        assert!(!task_exists(&conn, "local-only").unwrap());
+        assert_eq!(export_all(&conn).unwrap().tasks.len(), 3);
+    }
+
+    #[test]
+    fn import_rejects_malformed_graph_without_changes() {
+        let mut doc = sample_export();
+        doc.deps.push(Dep {
+            blocker: "leaf".into(),
+            blocked: "other".into(),
+        });
+        let conn = db::open_memory().unwrap();
+        let err = import_all(&conn, &doc, ImportMode::Merge).unwrap_err();
+        assert!(err.to_string().contains("cycle"), "{err}");
+        assert!(export_all(&conn).unwrap().tasks.is_empty());
+
+        let mut doc = sample_export();
+        doc.tasks.retain(|t| t.name != "root");
+        let err = import_all(&conn, &doc, ImportMode::Merge).unwrap_err();
+        assert!(err.to_string().contains("leaf"), "{err}");
+        assert!(export_all(&conn).unwrap().tasks.is_empty());
+    }
+
+    #[test]
+    fn integrity_issues_are_reported_and_fixed() {
+        let conn = db::open_memory().unwrap();
+        for (name, parent) in [("parent", None), ("child", Some("parent")), ("other", None)] {
+            add_task(
+                &conn,
+                AddTaskParams {
+                    name,
+                    parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        add_block(&conn, "other", "child").unwrap();
+        add_note(&conn, "other", "keep").unwrap();
+        assert!(validate_integrity(&conn).unwrap().is_empty());
+
+        // Simulate an external edit that bypasses foreign keys.
+        conn.execute_batch(
+            "PRAGMA foreign_keys = OFF;
+             DELETE FROM tasks WHERE name = 'parent';
+             INSERT INTO task_deps (blocker, blocked) VALUES ('ghost', 'other');
+             INSERT INTO notes (task, content) VALUES ('ghost', 'lost');
+             PRAGMA foreign_keys = ON;",
+        )
+        .unwrap();
+
+        let issues = validate_integrity(&conn).unwrap();
+        assert_eq!(issues.len(), 3);
+        assert_eq!(
+            issues[0],
+            IntegrityIssue::DanglingParent {
+                task: "child".into(),
+                parent: "parent".into(),
+            }
+        );
+        assert_eq!(
+            issues[1].to_string(),
+            "dependency ghost -> other refers to a missing task"
+        );
+        assert!(matches!(issues[2], IntegrityIssue::OrphanNote { .. }));
+
+        fix_integrity(&conn, &issues).unwrap();
+        assert!(validate_integrity(&conn).unwrap().is_empty());
+        assert!(get_task(&conn, "child").unwrap().parent.is_none());
+        assert_eq!(get_blockers(&conn, "child").unwrap(), vec!["other"]);
+        assert_eq!(list_notes(&conn, "other").unwrap().len(), 1);
+    }
 }
@@ -1,13 +1,91 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
 
 use serde::Serialize;
 
-use crate::model::{Note, SearchResult, Task};
+use crate::model::{status_icon, Dep, ExportDoc, Note, Rollup, SearchResult, Task};
+use crate::ops::RemovalEntry;
+use crate::paths::ResolvedPath;
+
+/// Terminal styling for the plain-text formatters. JSON output never goes
+/// through here. The default is plain: no escape codes at all.
+#[derive(Debug, Clone, Default)]
+pub struct Style {
+    color: bool,
+    /// Tasks to mark as blocked in list and tree output.
+    blocked: HashSet<String>,
+}
+
+impl Style {
+    pub fn new(color: bool) -> Self {
+        Style {
+            color,
+            blocked: HashSet::new(),
+        }
+    }
+
+    /// Decide whether to color from `--color`: `auto` colors only a
+    /// terminal, and only when `NO_COLOR` is unset.
+    pub fn resolve(choice: &str, is_terminal: bool, no_color: bool) -> anyhow::Result<Self> {
+        let color = match choice {
+            "always" => true,
+            "never" => false,
+            "auto" => is_terminal && !no_color,
+            _ => anyhow::bail!("invalid color choice '{choice}': must be auto, always or never"),
+        };
+        Ok(Style::new(color))
+    }
+
+    pub fn is_color(&self) -> bool {
+        self.color
+    }
+
+    pub fn with_blocked(mut self, names: impl IntoIterator<Item = String>) -> Self {
+        self.blocked.extend(names);
+        self
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// `text` in the color for `status`: green active, yellow paused, dim
+    /// done; open tasks stay in the default color.
+    fn status(&self, status: &str, text: &str) -> String {
+        match status {
+            "active" => self.paint("32", text),
+            "paused" => self.paint("33", text),
+            "done" => self.paint("2", text),
+            _ => text.to_string(),
+        }
+    }
+
+    /// The icon and name that start a task's line.
+    fn task_head(&self, task: &Task) -> String {
+        self.status(&task.status, &format!("{} {}", task.icon(), task.name))
+    }
+
+    /// A red marker after blocked tasks; only drawn in color, so plain
+    /// output is unchanged.
+    fn blocked_marker(&self, task: &Task) -> String {
+        if self.color && self.blocked.contains(&task.name) {
+            self.paint("31", " [blocked]")
+        } else {
+            String::new()
+        }
+    }
+}
 
 #[derive(Serialize)]
 pub struct TaskDetail<'a> {
     #[serde(flatten)]
     pub task: &'a Task,
+    /// Ancestor names from the root down to the parent.
+    pub path: &'a [String],
     pub notes: &'a [Note],
     pub blocked_by: &'a [String],
     pub blocks: &'a [String],
@@ -21,19 +99,49 @@ pub struct TaskListItem<'a> {
     pub blocks: Vec<String>,
 }
 
+/// The first line of a description, with an ellipsis when more follows,
+/// so row-per-task layouts stay one row per task.
+pub fn description_summary(description: &str) -> std::borrow::Cow<'_, str> {
+    match description.split_once('\n') {
+        Some((first, _)) => format!("{}…", first.trim_end()).into(),
+        None => description.into(),
+    }
+}
+
 pub fn format_task_detail(
     task: &Task,
+    ancestors: &[String],
     notes: &[Note],
     blockers: &[String],
     dependents: &[String],
+    style: &Style,
 ) -> String {
     let mut out = String::new();
+    if !ancestors.is_empty() {
+        out.push_str(&format!(
+            "Path:        {} > {}\n",
+            ancestors.join(" > "),
+            task.name
+        ));
+    }
     out.push_str(&format!("Name:        {}\n", task.name));
-    out.push_str(&format!("Status:      {}\n", task.status_str()));
+    out.push_str(&format!(
+        "Status:      {}\n",
+        style.status(&task.status, task.status_str())
+    ));
     if let Some(ref p) = task.parent {
         out.push_str(&format!("Parent:      {}\n", p));
     }
-    if !task.description.is_empty() {
+    if task.description.contains('\n') {
+        out.push_str("Description:\n");
+        for line in task.description.lines() {
+            if !line.is_empty() {
+                out.push_str("  ");
+            }
+            out.push_str(line);
+            out.push('\n');
+        }
+    } else if !task.description.is_empty() {
         out.push_str(&format!("Description: {}\n", task.description));
     }
     if let Some(ref assignee) = task.assignee {
@@ -45,14 +153,39 @@ pub fn format_task_detail(
     if let Some(ref directory) = task.directory {
         out.push_str(&format!("Directory:   {}\n", directory));
     }
+    if task.priority != 0 {
+        out.push_str(&format!("Priority:    {}\n", task.priority));
+    }
+    if let Some(estimate) = task.estimate {
+        out.push_str(&format!("Estimate:    {}\n", estimate));
+    }
+    if task.needs_human {
+        out.push_str("Needs human: yes\n");
+    }
+    let now = unix_now();
     if let Some(ref status_changed_at) = task.status_changed_at {
-        out.push_str(&format!("Status changed: {}\n", status_changed_at));
+        out.push_str(&format!(
+            "Status changed: {} ({})\n",
+            status_changed_at,
+            relative_time(status_changed_at, now)
+        ));
     }
-    out.push_str(&format!("Created:     {}\n", task.created_at));
-    out.push_str(&format!("Updated:     {}\n", task.updated_at));
+    out.push_str(&format!(
+        "Created:     {} ({})\n",
+        task.created_at,
+        relative_time(&task.created_at, now)
+    ));
+    out.push_str(&format!(
+        "Updated:     {} ({})\n",
+        task.updated_at,
+        relative_time(&task.updated_at, now)
+    ));
 
     if !blockers.is_empty() {
-        out.push_str(&format!("Blocked by:  {}\n", blockers.join(", ")));
+        out.push_str(&format!(
+            "Blocked by:  {}\n",
+            style.paint("31", &blockers.join(", "))
+        ));
     }
     if !dependents.is_empty() {
         out.push_str(&format!("Blocks:      {}\n", dependents.join(", ")));
@@ -62,38 +195,130 @@ pub fn format_task_detail(
         out.push('\n');
         out.push_str("Notes:\n");
         for note in notes {
-            out.push_str(&format!("  [{}] {}\n", note.created_at, note.content));
+            out.push_str(&format!(
+                "  [{}] {}{}\n",
+                note.created_at,
+                note_label_prefix(note),
+                note.content
+            ));
         }
     }
 
     out
 }
 
-pub fn format_task_list(tasks: &[Task]) -> String {
+/// Current time as seconds since the Unix epoch.
+pub fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Parse a `YYYY-MM-DDTHH:MM:SSZ` timestamp (the format SQLite writes for
+/// us) into seconds since the Unix epoch.
+pub fn parse_timestamp(ts: &str) -> Option<i64> {
+    let ts = ts.strip_suffix('Z').unwrap_or(ts);
+    let (date, time) = ts.split_once('T')?;
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (y, m, d) = (
+        date_parts.next()??,
+        date_parts.next()??,
+        date_parts.next()??,
+    );
+    let mut time_parts = time.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (hh, mm, ss) = (
+        time_parts.next()??,
+        time_parts.next()??,
+        time_parts.next()??,
+    );
+
+    // Days from civil date (proleptic Gregorian), per Howard Hinnant.
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    Some(days * 86400 + hh * 3600 + mm * 60 + ss)
+}
+
+/// Render `timestamp` relative to `now` (e.g. "3h ago"). Timestamps in the
+/// future (clock skew between writers) render as "just now"; unparseable
+/// timestamps are returned unchanged.
+pub fn relative_time(timestamp: &str, now: i64) -> String {
+    let Some(then) = parse_timestamp(timestamp) else {
+        return timestamp.to_string();
+    };
+    let secs = now - then;
+    if secs < 60 {
+        "just now".to_string()
+    } else if secs < 3600 {
+        format!("{}m ago", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h ago", secs / 3600)
+    } else {
+        format!("{}d ago", secs / 86400)
+    }
+}
+
+pub fn format_task_list(tasks: &[Task], style: &Style) -> String {
+    format_task_list_inner(tasks, None, style)
+}
+
+/// Like `format_task_list`, with a column showing how long ago each task
+/// was last updated.
+pub fn format_task_list_long(tasks: &[Task], now: i64, style: &Style) -> String {
+    format_task_list_inner(tasks, Some(now), style)
+}
+
+fn format_task_list_inner(tasks: &[Task], now: Option<i64>, style: &Style) -> String {
     let mut out = String::new();
     for task in tasks {
+        let age = now
+            .map(|now| format!("{:>9} ", relative_time(&task.updated_at, now)))
+            .unwrap_or_default();
         let parent_info = task
             .parent
             .as_ref()
             .map(|p| format!(" (parent: {p})"))
             .unwrap_or_default();
+        let priority_info = if task.priority != 0 {
+            format!(" (priority: {})", task.priority)
+        } else {
+            String::new()
+        };
+        let needs_human_info = if task.needs_human {
+            " (needs human)"
+        } else {
+            ""
+        };
         let desc = if task.description.is_empty() {
             String::new()
         } else {
-            format!("  {}", task.description)
+            format!("  {}", description_summary(&task.description))
         };
         out.push_str(&format!(
-            "{} {}{}{}\n",
-            task.icon(),
-            task.name,
+            "{} {}{}{}{}{}{}{}\n",
+            style.status(&task.status, task.icon()),
+            age,
+            style.status(&task.status, &task.name),
+            style.blocked_marker(task),
             parent_info,
+            priority_info,
+            needs_human_info,
             desc
         ));
     }
     out
 }
 
-pub fn format_task_tree(tasks: &[Task]) -> String {
+/// Render `tasks` as a tree. With `max_depth`, tasks nested deeper than
+/// that many levels below a root are hidden and their parent is annotated
+/// with `(+N more)`; `Some(0)` shows roots only.
+pub fn format_task_tree(tasks: &[Task], max_depth: Option<usize>, style: &Style) -> String {
     if tasks.is_empty() {
         return String::new();
     }
@@ -114,7 +339,7 @@ pub fn format_task_tree(tasks: &[Task]) -> String {
     let mut out = String::new();
     let roots = children_map.get(&None).cloned().unwrap_or_default();
     for root in &roots {
-        write_tree(&mut out, root, &children_map, "", "");
+        write_tree(&mut out, root, &children_map, "", "", max_depth, style);
     }
     out
 }
@@ -122,31 +347,57 @@ pub fn format_task_tree(tasks: &[Task]) -> String {
 /// Write a task line and recurse into children.
 /// `line_prefix` is what goes before the status icon on this task's line.
 /// `child_prefix` is the base prefix for this task's children's tree connectors.
+/// `levels_left` is how many more levels may be shown below this task.
 fn write_tree(
     out: &mut String,
     task: &Task,
     children_map: &HashMap<Option<&str>, Vec<&Task>>,
     line_prefix: &str,
     child_prefix: &str,
+    levels_left: Option<usize>,
+    style: &Style,
 ) {
     let desc = if task.description.is_empty() {
         String::new()
     } else {
-        format!("  {}", task.description)
+        format!("  {}", description_summary(&task.description))
     };
 
-    out.push_str(&format!(
-        "{}{} {}{}\n",
-        line_prefix,
-        task.icon(),
-        task.name,
-        desc
-    ));
+    let needs_human_info = if task.needs_human {
+        " (needs human)"
+    } else {
+        ""
+    };
+
+    let estimate_info = subtree_estimate(task, children_map)
+        .map(|e| format!(" (est: {e})"))
+        .unwrap_or_default();
 
     let children = children_map
         .get(&Some(task.name.as_str()))
         .cloned()
         .unwrap_or_default();
+    let truncated = levels_left == Some(0) && !children.is_empty();
+    let more_info = if truncated {
+        format!(" (+{} more)", children.len())
+    } else {
+        String::new()
+    };
+
+    out.push_str(&format!(
+        "{}{}{}{}{}{}{}\n",
+        line_prefix,
+        style.task_head(task),
+        style.blocked_marker(task),
+        needs_human_info,
+        estimate_info,
+        more_info,
+        desc
+    ));
+
+    if truncated {
+        return;
+    }
 
     for (i, child) in children.iter().enumerate() {
         let is_last = i == children.len() - 1;
@@ -161,11 +412,203 @@ fn write_tree(
             children_map,
             &format!("{child_prefix}{connector}"),
             &format!("{child_prefix}{extension}"),
+            levels_left.map(|n| n - 1),
+            style,
+        );
+    }
+}
+
+/// Sum of the estimates of `task` and its listed descendants, or None when
+/// none of them has one.
+fn subtree_estimate(task: &Task, children_map: &HashMap<Option<&str>, Vec<&Task>>) -> Option<i64> {
+    let children = children_map.get(&Some(task.name.as_str()));
+    children
+        .into_iter()
+        .flatten()
+        .filter_map(|child| subtree_estimate(child, children_map))
+        .chain(task.estimate)
+        .reduce(|a, b| a + b)
+}
+
+/// `kbtz rollup` summary: done vs. total estimate, and how much of the
+/// subtree is unestimated.
+pub fn format_rollup(rollup: &Rollup) -> String {
+    let mut out = format!("{}: {} of {} done", rollup.name, rollup.done, rollup.total);
+    if rollup.total > 0 {
+        out.push_str(&format!(" ({}%)", rollup.done * 100 / rollup.total));
+    }
+    out.push('\n');
+    if rollup.unestimated > 0 {
+        out.push_str(&format!(
+            "{} of {} task(s) have no estimate\n",
+            rollup.unestimated, rollup.tasks
+        ));
+    }
+    out
+}
+
+/// One line per path for `kbtz paths`, flagging the ones that don't exist.
+pub fn format_paths(paths: &[ResolvedPath]) -> String {
+    let width = paths.iter().map(|p| p.name.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for p in paths {
+        let missing = if p.exists { "" } else { " (missing)" };
+        out.push_str(&format!(
+            "{:<width$}  {}{missing}\n",
+            p.name,
+            p.path.display()
+        ));
+    }
+    out
+}
+
+/// A task and the non-done tasks that block it, for `kbtz deps`.
+#[derive(Serialize)]
+pub struct BlockerNode {
+    pub name: String,
+    pub status: String,
+    pub assignee: Option<String>,
+    pub blocked_by: Vec<BlockerNode>,
+    /// Set when this task already appears higher up the same chain. Cycles
+    /// are rejected on write, so this only guards against a corrupt DB.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cycle: bool,
+}
+
+/// Build the tree of tasks blocking `name`, following `blockers` edges up to
+/// `max_depth` levels (`None` for the full transitive closure).
+pub fn build_blocker_tree(
+    name: &str,
+    tasks: &HashMap<&str, &Task>,
+    blockers: &HashMap<String, Vec<String>>,
+    max_depth: Option<usize>,
+) -> BlockerNode {
+    let mut path = Vec::new();
+    build_blocker_node(name, tasks, blockers, max_depth, &mut path)
+}
+
+fn build_blocker_node<'a>(
+    name: &'a str,
+    tasks: &HashMap<&str, &Task>,
+    blockers: &'a HashMap<String, Vec<String>>,
+    max_depth: Option<usize>,
+    path: &mut Vec<&'a str>,
+) -> BlockerNode {
+    let task = tasks.get(name);
+    let mut node = BlockerNode {
+        name: name.to_string(),
+        status: task.map(|t| t.status.clone()).unwrap_or_default(),
+        assignee: task.and_then(|t| t.assignee.clone()),
+        blocked_by: Vec::new(),
+        cycle: path.contains(&name),
+    };
+    if node.cycle || max_depth.is_some_and(|d| path.len() >= d) {
+        return node;
+    }
+    path.push(name);
+    for blocker in blockers.get(name).into_iter().flatten() {
+        node.blocked_by.push(build_blocker_node(
+            blocker, tasks, blockers, max_depth, path,
+        ));
+    }
+    path.pop();
+    node
+}
+
+pub fn format_blocker_tree(root: &BlockerNode) -> String {
+    let mut out = String::new();
+    write_blocker_node(&mut out, root, "", "");
+    out
+}
+
+fn write_blocker_node(out: &mut String, node: &BlockerNode, line_prefix: &str, child_prefix: &str) {
+    let icon = status_icon(&node.status);
+    let assignee = node
+        .assignee
+        .as_ref()
+        .map(|a| format!(" [{a}]"))
+        .unwrap_or_default();
+    let cycle = if node.cycle { " (cycle)" } else { "" };
+    out.push_str(&format!(
+        "{line_prefix}{icon} {}{assignee}{cycle}\n",
+        node.name
+    ));
+
+    for (i, child) in node.blocked_by.iter().enumerate() {
+        let (connector, extension) = if i == node.blocked_by.len() - 1 {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        write_blocker_node(
+            out,
+            child,
+            &format!("{child_prefix}{connector}"),
+            &format!("{child_prefix}{extension}"),
         );
     }
 }
 
-pub fn format_search_results(results: &[SearchResult]) -> String {
+#[derive(Serialize)]
+pub struct AssigneeItem<'a> {
+    pub assignee: &'a str,
+    pub tasks: &'a [Task],
+}
+
+#[derive(Serialize)]
+pub struct BlockedItem<'a> {
+    #[serde(flatten)]
+    pub task: &'a Task,
+    pub blocked_by: &'a [String],
+}
+
+/// Render each blocked task followed by the tasks blocking it.
+pub fn format_blocked(blocked: &[(Task, Vec<String>)]) -> String {
+    let mut out = String::new();
+    for (task, blockers) in blocked {
+        let desc = if task.description.is_empty() {
+            String::new()
+        } else {
+            format!("  {}", description_summary(&task.description))
+        };
+        out.push_str(&format!("{} {}{}\n", task.icon(), task.name, desc));
+        out.push_str(&format!("  blocked by {}\n", blockers.join(", ")));
+    }
+    out
+}
+
+/// Render each assignee followed by its active tasks and how long ago each
+/// was claimed.
+pub fn format_assignees(groups: &[(String, Vec<Task>)], now: i64) -> String {
+    let mut out = String::new();
+    for (assignee, tasks) in groups {
+        out.push_str(&format!("{assignee}\n"));
+        for task in tasks {
+            let since = task
+                .status_changed_at
+                .as_deref()
+                .unwrap_or(&task.updated_at);
+            let desc = if task.description.is_empty() {
+                String::new()
+            } else {
+                format!("  {}", description_summary(&task.description))
+            };
+            out.push_str(&format!(
+                "  {} {} (claimed {}){}\n",
+                task.icon(),
+                task.name,
+                relative_time(since, now),
+                desc
+            ));
+        }
+    }
+    out
+}
+
+/// Search results, one per line, each followed by its snippet (if any)
+/// indented below. With `highlight`, the `**` match markers become bold
+/// terminal escapes.
+pub fn format_search_results(results: &[SearchResult], highlight: bool) -> String {
     let mut out = String::new();
     for result in results {
         let task = &result.task;
@@ -173,7 +616,7 @@ pub fn format_search_results(results: &[SearchResult]) -> String {
         let desc = if task.description.is_empty() {
             String::new()
         } else {
-            format!("  {}", task.description)
+            format!("  {}", description_summary(&task.description))
         };
         out.push_str(&format!(
             "{} {}{} [{}]\n",
@@ -182,18 +625,174 @@ pub fn format_search_results(results: &[SearchResult]) -> String {
             desc,
             matched
         ));
+        if let Some(snippet) = &result.snippet {
+            let snippet = if highlight {
+                highlight_snippet(snippet)
+            } else {
+                snippet.clone()
+            };
+            out.push_str(&format!("    {snippet}\n"));
+        }
     }
     out
 }
 
+/// Replace alternating `**` markers with bold on/off escapes.
+fn highlight_snippet(snippet: &str) -> String {
+    let mut out = String::new();
+    for (i, part) in snippet.split("**").enumerate() {
+        if i > 0 {
+            out.push_str(if i % 2 == 1 { "\x1b[1m" } else { "\x1b[0m" });
+        }
+        out.push_str(part);
+    }
+    // An unpaired marker from the text itself must not leave bold on.
+    if snippet.matches("**").count() % 2 == 1 {
+        out.push_str("\x1b[0m");
+    }
+    out
+}
+
+/// `(label) ` for a labeled note, empty otherwise.
+fn note_label_prefix(note: &Note) -> String {
+    match &note.label {
+        Some(label) => format!("({label}) "),
+        None => String::new(),
+    }
+}
+
 pub fn format_notes(notes: &[Note]) -> String {
     let mut out = String::new();
     for note in notes {
-        out.push_str(&format!("[{}] {}\n", note.created_at, note.content));
+        out.push_str(&format!(
+            "#{} [{}] {}{}\n",
+            note.id,
+            note.created_at,
+            note_label_prefix(note),
+            note.content
+        ));
     }
     out
 }
 
+pub fn format_dep_list(deps: &[Dep]) -> String {
+    let mut out = String::new();
+    for dep in deps {
+        out.push_str(&format!("{} -> {}\n", dep.blocker, dep.blocked));
+    }
+    out
+}
+
+/// Dry-run listing for `rm --dry-run`: one line per task that would be
+/// removed, with the notes and dependency edges removed alongside it.
+pub fn format_removal_preview(entries: &[RemovalEntry]) -> String {
+    let mut out = format!("Would remove {} task(s):\n", entries.len());
+    for entry in entries {
+        out.push_str(&format!(
+            "  {} ({} notes, {} deps)\n",
+            entry.name, entry.notes, entry.deps
+        ));
+    }
+    out
+}
+
+/// Write each item as one compact JSON object per line, so consumers can
+/// process results as they arrive without parsing a whole array.
+pub fn write_ndjson<W: Write, T: Serialize>(
+    out: &mut W,
+    items: impl IntoIterator<Item = T>,
+    time_format: TimeFormat,
+) -> anyhow::Result<()> {
+    for item in items {
+        serde_json::to_writer(&mut *out, &to_json_value(&item, time_format)?)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// How JSON output renders the stored timestamps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// As stored: `YYYY-MM-DDTHH:MM:SSZ`.
+    #[default]
+    Iso,
+    /// Seconds since the Unix epoch.
+    Epoch,
+}
+
+impl TimeFormat {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "iso" => Ok(Self::Iso),
+            "epoch" => Ok(Self::Epoch),
+            _ => anyhow::bail!("invalid time format '{s}': must be iso or epoch"),
+        }
+    }
+}
+
+/// Fields holding `strftime('%Y-%m-%dT%H:%M:%SZ')` strings, wherever they
+/// appear in serialized output (tasks, notes, nested lists).
+const TIMESTAMP_FIELDS: &[&str] = &["created_at", "updated_at", "status_changed_at"];
+
+/// Serialize `item`, converting timestamp fields to `time_format`. In epoch
+/// mode a timestamp that fails to parse becomes null.
+pub fn to_json_value<T: Serialize>(
+    item: &T,
+    time_format: TimeFormat,
+) -> serde_json::Result<serde_json::Value> {
+    let mut value = serde_json::to_value(item)?;
+    if time_format == TimeFormat::Epoch {
+        timestamps_to_epoch(&mut value);
+    }
+    Ok(value)
+}
+
+fn timestamps_to_epoch(value: &mut serde_json::Value) {
+    use serde_json::Value;
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                match v {
+                    Value::String(ts) if TIMESTAMP_FIELDS.contains(&key.as_str()) => {
+                        *v = parse_timestamp(ts).map_or(Value::Null, Value::from);
+                    }
+                    _ => timestamps_to_epoch(v),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(timestamps_to_epoch),
+        _ => {}
+    }
+}
+
+/// Pretty-print `item` as JSON with timestamps in `time_format`.
+pub fn print_json<T: Serialize>(item: &T, time_format: TimeFormat) -> anyhow::Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&to_json_value(item, time_format)?)?
+    );
+    Ok(())
+}
+
+/// One-line summary of an export document, shown when `--json` is not
+/// given.
+pub fn format_export_summary(doc: &ExportDoc) -> String {
+    let count = |status: &str| doc.tasks.iter().filter(|t| t.status == status).count();
+    format!(
+        "{} tasks ({} open, {} active, {} paused, {} done), {} notes, {} deps (format v{})\n\
+         Use --json to write the full snapshot.\n",
+        doc.tasks.len(),
+        count("open"),
+        count("active"),
+        count("paused"),
+        count("done"),
+        doc.notes.len(),
+        doc.deps.len(),
+        doc.version,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -214,6 +813,9 @@ mod tests {
             assignee: assignee.map(|s| s.to_string()),
             agent: None,
             directory: None,
+            priority: 0,
+            needs_human: false,
+            estimate: None,
             status_changed_at: assignee.map(|_| "2025-01-01T00:00:00Z".to_string()),
             created_at: "2025-01-01T00:00:00Z".to_string(),
             updated_at: "2025-01-01T00:00:00Z".to_string(),
@@ -229,7 +831,7 @@ mod tests {
             Some("agent"),
             "Root task",
         )];
-        let out = format_task_tree(&tasks);
+        let out = format_task_tree(&tasks, None, &Style::default());
         assert_eq!(out, "* root  Root task\n");
     }
 
@@ -240,7 +842,7 @@ mod tests {
             make_task("child1", Some("root"), "active", Some("agent"), ""),
             make_task("child2", Some("root"), "open", None, ""),
         ];
-        let out = format_task_tree(&tasks);
+        let out = format_task_tree(&tasks, None, &Style::default());
         assert!(out.contains("root"));
         assert!(out.contains("child1"));
         assert!(out.contains("child2"));
@@ -248,22 +850,506 @@ mod tests {
         assert!(out.contains("└──"));
     }
 
+    #[test]
+    fn tree_shows_subtree_estimates() {
+        let mut root = make_task("root", None, "open", None, "");
+        root.estimate = Some(1);
+        let mut a = make_task("a", Some("root"), "open", None, "");
+        a.estimate = Some(3);
+        let b = make_task("b", Some("root"), "open", None, "");
+        let mut b1 = make_task("b1", Some("b"), "open", None, "");
+        b1.estimate = Some(5);
+        let other = make_task("other", None, "open", None, "");
+        let out = format_task_tree(&[root, a, b, b1, other], None, &Style::default());
+        assert!(out.contains("root (est: 9)\n"), "{out}");
+        assert!(out.contains("a (est: 3)\n"), "{out}");
+        assert!(out.contains("b (est: 5)\n"), "{out}");
+        assert!(out.contains(". other\n"), "{out}");
+    }
+
+    fn deep_tree() -> Vec<Task> {
+        vec![
+            make_task("root", None, "open", None, ""),
+            make_task("a", Some("root"), "open", None, ""),
+            make_task("a1", Some("a"), "open", None, ""),
+            make_task("a1x", Some("a1"), "open", None, ""),
+            make_task("b", Some("root"), "open", None, ""),
+            make_task("other", None, "open", None, ""),
+        ]
+    }
+
+    #[test]
+    fn tree_depth_limits_nesting() {
+        let tasks = deep_tree();
+        assert_eq!(
+            format_task_tree(&tasks, Some(1), &Style::default()),
+            ". root\n├── . a (+1 more)\n└── . b\n. other\n"
+        );
+        assert_eq!(
+            format_task_tree(&tasks, Some(2), &Style::default()),
+            ". root\n├── . a\n│   └── . a1 (+1 more)\n└── . b\n. other\n"
+        );
+        assert_eq!(
+            format_task_tree(&tasks, Some(3), &Style::default()),
+            format_task_tree(&tasks, None, &Style::default())
+        );
+    }
+
+    #[test]
+    fn tree_depth_zero_shows_roots_with_child_counts() {
+        let out = format_task_tree(&deep_tree(), Some(0), &Style::default());
+        assert_eq!(out, ". root (+2 more)\n. other\n");
+    }
+
+    #[test]
+    fn rollup_summary() {
+        let rollup = Rollup {
+            name: "epic".into(),
+            total: 18,
+            done: 8,
+            tasks: 6,
+            unestimated: 2,
+        };
+        assert_eq!(
+            format_rollup(&rollup),
+            "epic: 8 of 18 done (44%)\n2 of 6 task(s) have no estimate\n"
+        );
+        let empty = Rollup {
+            name: "leaf".into(),
+            tasks: 1,
+            ..Default::default()
+        };
+        assert_eq!(format_rollup(&empty), "leaf: 0 of 0 done\n");
+    }
+
+    #[test]
+    fn paths_listing_marks_missing() {
+        let paths = [
+            ResolvedPath {
+                name: "db",
+                path: "/k/kbtz.db".into(),
+                exists: true,
+            },
+            ResolvedPath {
+                name: "workspace_lock",
+                path: "/k/ws/workspace.lock".into(),
+                exists: false,
+            },
+        ];
+        assert_eq!(
+            format_paths(&paths),
+            "db              /k/kbtz.db\nworkspace_lock  /k/ws/workspace.lock (missing)\n"
+        );
+    }
+
     #[test]
     fn flat_list() {
         let tasks = vec![
             make_task("a", None, "active", Some("agent"), "desc A"),
             make_task("b", None, "open", None, ""),
         ];
-        let out = format_task_list(&tasks);
+        let out = format_task_list(&tasks, &Style::default());
         assert!(out.contains("* a  desc A")); // active = *
         assert!(out.contains(". b")); // open = .
     }
 
+    #[test]
+    fn color_resolves_from_terminal_and_no_color() {
+        assert!(Style::resolve("auto", true, false).unwrap().is_color());
+        assert!(!Style::resolve("auto", false, false).unwrap().is_color());
+        assert!(!Style::resolve("auto", true, true).unwrap().is_color());
+        assert!(Style::resolve("always", false, true).unwrap().is_color());
+        assert!(!Style::resolve("never", true, false).unwrap().is_color());
+        assert!(Style::resolve("sometimes", true, false).is_err());
+    }
+
+    #[test]
+    fn piped_output_has_no_escape_codes() {
+        let mut parent = make_task("p", None, "active", Some("agent"), "");
+        parent.estimate = Some(2);
+        let tasks = vec![parent, make_task("c", Some("p"), "done", None, "")];
+        let style = Style::resolve("auto", false, false)
+            .unwrap()
+            .with_blocked(["p".to_string()]);
+        let task = &tasks[0];
+        for out in [
+            format_task_list(&tasks, &style),
+            format_task_list_long(&tasks, 0, &style),
+            format_task_tree(&tasks, None, &style),
+            format_task_detail(task, &[], &[], &["c".into()], &[], &style),
+        ] {
+            assert!(!out.contains('\x1b'), "{out:?}");
+            assert!(!out.contains("[blocked]"), "{out:?}");
+        }
+    }
+
+    #[test]
+    fn color_marks_status_and_blocked_tasks() {
+        let tasks = vec![
+            make_task("a", None, "active", Some("agent"), ""),
+            make_task("b", None, "open", None, ""),
+        ];
+        let style = Style::new(true).with_blocked(["b".to_string()]);
+        let list = format_task_list(&tasks, &style);
+        assert!(
+            list.contains("\x1b[32m*\x1b[0m \x1b[32ma\x1b[0m\n"),
+            "{list:?}"
+        );
+        assert!(list.contains(". b\x1b[31m [blocked]\x1b[0m\n"), "{list:?}");
+        let tree = format_task_tree(&tasks, None, &style);
+        assert!(tree.contains("\x1b[32m* a\x1b[0m\n"), "{tree:?}");
+        let detail = format_task_detail(&tasks[1], &[], &[], &["a".into()], &[], &style);
+        assert!(
+            detail.contains("Blocked by:  \x1b[31ma\x1b[0m\n"),
+            "{detail:?}"
+        );
+    }
+
+    #[test]
+    fn json_time_format_iso_keeps_stored_strings() {
+        let task = make_task("a", None, "open", None, "");
+        let value = to_json_value(&task, TimeFormat::Iso).unwrap();
+        assert_eq!(value["created_at"], "2025-01-01T00:00:00Z");
+        assert_eq!(value["status_changed_at"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn json_time_format_epoch_converts_nested_timestamps() {
+        let mut task = make_task("a", None, "open", None, "");
+        task.updated_at = "not a timestamp".to_string();
+        let notes = vec![Note {
+            id: 1,
+            task: "a".to_string(),
+            content: "hi".to_string(),
+            created_at: "1970-01-02T00:00:01Z".to_string(),
+            label: None,
+        }];
+        let detail = TaskDetail {
+            task: &task,
+            path: &[],
+            notes: &notes,
+            blocked_by: &[],
+            blocks: &[],
+        };
+        let value = to_json_value(&detail, TimeFormat::Epoch).unwrap();
+        assert_eq!(value["created_at"], 1735689600);
+        assert_eq!(value["notes"][0]["created_at"], 86401);
+        // Malformed timestamps become null rather than failing.
+        assert_eq!(value["updated_at"], serde_json::Value::Null);
+        assert_eq!(value["name"], "a");
+
+        let mut out = Vec::new();
+        write_ndjson(&mut out, [&task], TimeFormat::Epoch).unwrap();
+        let line: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(line["created_at"], 1735689600);
+        assert!(TimeFormat::parse("rfc2822").is_err());
+    }
+
+    #[test]
+    fn multiline_description_stays_one_row_in_list_and_tree() {
+        let tasks = vec![
+            make_task(
+                "multi",
+                None,
+                "open",
+                None,
+                "Summary line\n\nBody text\nmore",
+            ),
+            make_task("single", None, "open", None, "Just one line"),
+        ];
+        let list = format_task_list(&tasks, &Style::default());
+        assert_eq!(list.lines().count(), 2);
+        assert!(list.contains(". multi  Summary line…\n"));
+        assert!(list.contains(". single  Just one line\n"));
+        assert!(!list.contains("Body text"));
+
+        let tree = format_task_tree(&tasks, None, &Style::default());
+        assert_eq!(tree.lines().count(), 2);
+        assert!(tree.contains(". multi  Summary line…\n"));
+    }
+
+    #[test]
+    fn detail_renders_multiline_description_as_block() {
+        let task = make_task("multi", None, "open", None, "Summary line\n\nBody text");
+        let out = format_task_detail(&task, &[], &[], &[], &[], &Style::default());
+        assert!(out.contains("Description:\n  Summary line\n\n  Body text\nCreated:"));
+
+        let task = make_task("single", None, "open", None, "Just one line");
+        let out = format_task_detail(&task, &[], &[], &[], &[], &Style::default());
+        assert!(out.contains("Description: Just one line\n"));
+    }
+
+    #[test]
+    fn list_and_tree_mark_needs_human() {
+        let mut stuck = make_task("stuck", None, "open", None, "");
+        stuck.needs_human = true;
+        let tasks = vec![stuck, make_task("fine", None, "open", None, "")];
+        let list = format_task_list(&tasks, &Style::default());
+        assert!(list.contains(". stuck (needs human)"));
+        assert!(!list.contains("fine (needs human)"));
+        let tree = format_task_tree(&tasks, None, &Style::default());
+        assert!(tree.contains(". stuck (needs human)"));
+    }
+
+    #[test]
+    fn flat_list_shows_nonzero_priority() {
+        let mut urgent = make_task("urgent", None, "open", None, "");
+        urgent.priority = 2;
+        let tasks = vec![urgent, make_task("normal", None, "open", None, "")];
+        let out = format_task_list(&tasks, &Style::default());
+        assert!(out.contains(". urgent (priority: 2)"));
+        assert!(!out.contains("normal (priority"));
+    }
+
     #[test]
     fn show_includes_directory() {
         let mut task = make_task("t", None, "open", None, "desc");
         task.directory = Some("/work/dir".to_string());
-        let out = format_task_detail(&task, &[], &[], &[]);
+        let out = format_task_detail(&task, &[], &[], &[], &[], &Style::default());
         assert!(out.contains("Directory:   /work/dir"));
     }
+
+    #[test]
+    fn parse_timestamp_epoch_and_known_date() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_timestamp("2025-01-01T00:00:00Z"), Some(1_735_689_600));
+        assert_eq!(parse_timestamp("2024-02-29T12:30:15Z"), Some(1_709_209_815));
+        assert_eq!(parse_timestamp("garbage"), None);
+    }
+
+    #[test]
+    fn relative_time_buckets() {
+        let base = parse_timestamp("2025-01-01T00:00:00Z").unwrap();
+        let ts = "2025-01-01T00:00:00Z";
+        assert_eq!(relative_time(ts, base + 30), "just now");
+        assert_eq!(relative_time(ts, base + 5 * 60), "5m ago");
+        assert_eq!(relative_time(ts, base + 3 * 3600), "3h ago");
+        assert_eq!(relative_time(ts, base + 2 * 86400), "2d ago");
+    }
+
+    #[test]
+    fn relative_time_future_is_just_now() {
+        let base = parse_timestamp("2025-01-01T00:00:00Z").unwrap();
+        assert_eq!(
+            relative_time("2025-01-01T00:00:00Z", base - 600),
+            "just now"
+        );
+    }
+
+    #[test]
+    fn long_list_shows_age_column() {
+        let now = parse_timestamp("2025-01-01T03:00:00Z").unwrap();
+        let tasks = vec![make_task("a", None, "open", None, "desc A")];
+        let out = format_task_list_long(&tasks, now, &Style::default());
+        assert_eq!(out, ".    3h ago a  desc A\n");
+    }
+
+    #[test]
+    fn show_without_status_changed_omits_line() {
+        let task = make_task("t", None, "open", None, "");
+        let out = format_task_detail(&task, &[], &[], &[], &[], &Style::default());
+        assert!(!out.contains("Status changed"));
+        assert!(out.contains("Updated:     2025-01-01T00:00:00Z ("));
+    }
+
+    #[test]
+    fn show_includes_breadcrumb_path() {
+        let task = make_task("task", Some("story"), "open", None, "");
+        let ancestors = vec!["root".to_string(), "story".to_string()];
+        let out = format_task_detail(&task, &ancestors, &[], &[], &[], &Style::default());
+        assert!(out.starts_with("Path:        root > story > task\n"));
+
+        let root = make_task("root", None, "open", None, "");
+        let out = format_task_detail(&root, &[], &[], &[], &[], &Style::default());
+        assert!(!out.contains("Path:"));
+    }
+
+    #[test]
+    fn blocker_tree_follows_transitive_blockers() {
+        let tasks = [
+            make_task("target", None, "open", None, ""),
+            make_task("a", None, "active", Some("agent"), ""),
+            make_task("b", None, "open", None, ""),
+            make_task("c", None, "open", None, ""),
+        ];
+        let by_name: HashMap<&str, &Task> = tasks.iter().map(|t| (t.name.as_str(), t)).collect();
+        let blockers: HashMap<String, Vec<String>> = [
+            ("target".to_string(), vec!["a".to_string(), "b".to_string()]),
+            ("b".to_string(), vec!["c".to_string()]),
+        ]
+        .into_iter()
+        .collect();
+
+        let tree = build_blocker_tree("target", &by_name, &blockers, None);
+        assert_eq!(
+            format_blocker_tree(&tree),
+            ". target\n├── * a [agent]\n└── . b\n    └── . c\n"
+        );
+
+        let shallow = build_blocker_tree("target", &by_name, &blockers, Some(1));
+        assert_eq!(shallow.blocked_by.len(), 2);
+        assert!(shallow.blocked_by[1].blocked_by.is_empty());
+    }
+
+    #[test]
+    fn blocker_tree_stops_at_cycles() {
+        let tasks = [
+            make_task("a", None, "open", None, ""),
+            make_task("b", None, "open", None, ""),
+        ];
+        let by_name: HashMap<&str, &Task> = tasks.iter().map(|t| (t.name.as_str(), t)).collect();
+        let blockers: HashMap<String, Vec<String>> = [
+            ("a".to_string(), vec!["b".to_string()]),
+            ("b".to_string(), vec!["a".to_string()]),
+        ]
+        .into_iter()
+        .collect();
+
+        let tree = build_blocker_tree("a", &by_name, &blockers, None);
+        assert_eq!(
+            format_blocker_tree(&tree),
+            ". a\n└── . b\n    └── . a (cycle)\n"
+        );
+        let json = serde_json::to_value(&tree).unwrap();
+        assert_eq!(json["blocked_by"][0]["blocked_by"][0]["cycle"], true);
+        assert!(json.get("cycle").is_none());
+    }
+
+    #[test]
+    fn assignees_shows_claim_age() {
+        let now = parse_timestamp("2025-01-01T02:00:00Z").unwrap();
+        let mut legacy = make_task("legacy", None, "active", Some("ws/1"), "");
+        legacy.status_changed_at = None;
+        let groups = vec![(
+            "ws/1".to_string(),
+            vec![
+                make_task("a", None, "active", Some("ws/1"), "desc A"),
+                legacy,
+            ],
+        )];
+        assert_eq!(
+            format_assignees(&groups, now),
+            "ws/1\n  * a (claimed 2h ago)  desc A\n  * legacy (claimed 2h ago)\n"
+        );
+    }
+
+    #[test]
+    fn export_summary_counts_statuses() {
+        let doc = ExportDoc {
+            version: 1,
+            tasks: vec![
+                make_task("a", None, "open", None, ""),
+                make_task("b", None, "active", Some("ws/1"), ""),
+                make_task("c", None, "done", None, ""),
+            ],
+            notes: vec![],
+            deps: vec![],
+        };
+        assert_eq!(
+            format_export_summary(&doc),
+            "3 tasks (1 open, 1 active, 0 paused, 1 done), 0 notes, 0 deps (format v1)\n\
+             Use --json to write the full snapshot.\n"
+        );
+    }
+
+    #[test]
+    fn ndjson_writes_one_compact_object_per_line() {
+        let tasks = vec![
+            make_task("a", None, "open", None, "first"),
+            make_task("b", Some("a"), "open", None, "second\nline"),
+        ];
+        let mut buf = Vec::new();
+        write_ndjson(&mut buf, &tasks, TimeFormat::Iso).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["name"], "a");
+        let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second["description"], "second\nline");
+        assert!(text.ends_with('\n'));
+    }
+
+    #[test]
+    fn removal_preview_lists_counts() {
+        let entries = vec![
+            RemovalEntry {
+                name: "a".into(),
+                notes: 1,
+                deps: 0,
+            },
+            RemovalEntry {
+                name: "b".into(),
+                notes: 0,
+                deps: 2,
+            },
+        ];
+        assert_eq!(
+            format_removal_preview(&entries),
+            "Would remove 2 task(s):\n  a (1 notes, 0 deps)\n  b (0 notes, 2 deps)\n"
+        );
+    }
+
+    #[test]
+    fn search_results_show_snippet_below_match() {
+        let results = vec![
+            SearchResult {
+                task: make_task("keys", None, "open", None, "rotate keys"),
+                matched_in: vec!["task".to_string()],
+                snippet: Some("rotate **keys**".to_string()),
+            },
+            SearchResult {
+                task: make_task("keys-doc", None, "open", None, ""),
+                matched_in: vec!["task".to_string()],
+                snippet: None,
+            },
+        ];
+        assert_eq!(
+            format_search_results(&results, false),
+            ". keys  rotate keys [task]\n    rotate **keys**\n. keys-doc [task]\n"
+        );
+        assert_eq!(
+            format_search_results(&results[..1], true),
+            ". keys  rotate keys [task]\n    rotate \x1b[1mkeys\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn highlight_snippet_closes_unpaired_marker() {
+        assert_eq!(highlight_snippet("a **b"), "a \x1b[1mb\x1b[0m");
+    }
+
+    #[test]
+    fn blocked_lists_blockers_under_each_task() {
+        let blocked = vec![
+            (
+                make_task("deploy", None, "open", None, "ship it"),
+                vec!["build".to_string(), "review".to_string()],
+            ),
+            (
+                make_task("announce", None, "paused", None, ""),
+                vec!["deploy".to_string()],
+            ),
+        ];
+        assert_eq!(
+            format_blocked(&blocked),
+            ". deploy  ship it\n  blocked by build, review\n\
+             ~ announce\n  blocked by deploy\n"
+        );
+    }
+
+    #[test]
+    fn notes_show_labels() {
+        let note = |id, label: Option<&str>| Note {
+            id,
+            task: "t".to_string(),
+            content: "text".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            label: label.map(str::to_string),
+        };
+        assert_eq!(
+            format_notes(&[note(1, None), note(2, Some("status"))]),
+            "#1 [2025-01-01T00:00:00Z] text\n#2 [2025-01-01T00:00:00Z] (status) text\n"
+        );
+    }
 }
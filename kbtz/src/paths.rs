@@ -4,6 +4,11 @@
 //! Status files on disk use `-` in place of `/` (e.g. `ws-3`). The encoding
 //! functions here are the single source of truth for this convention.
 
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
 /// Resolve the kbtz database path.
 /// Checks `KBTZ_DB` env var, falls back to `$HOME/.kbtz/kbtz.db`.
 pub fn db_path() -> String {
@@ -22,6 +27,42 @@ pub fn workspace_dir() -> String {
     })
 }
 
+/// kbtz-tmux orchestrator log, in the workspace directory.
+pub const ORCHESTRATOR_LOG: &str = "orchestrator.log";
+
+/// kbtz-tmux single-instance lock, in the workspace directory.
+pub const ORCHESTRATOR_LOCK: &str = "orchestrator.lock";
+
+/// kbtz-workspace single-instance lock, in the workspace directory.
+pub const WORKSPACE_LOCK: &str = "workspace.lock";
+
+/// A named location as resolved for `kbtz paths`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResolvedPath {
+    pub name: &'static str,
+    pub path: PathBuf,
+    pub exists: bool,
+}
+
+/// Every file and directory the kbtz binaries agree on, given the
+/// database path and workspace directory in effect.
+pub fn resolved_paths(db: &Path, workspace_dir: &Path) -> Vec<ResolvedPath> {
+    [
+        ("db", db.to_path_buf()),
+        ("workspace_dir", workspace_dir.to_path_buf()),
+        ("orchestrator_log", workspace_dir.join(ORCHESTRATOR_LOG)),
+        ("orchestrator_lock", workspace_dir.join(ORCHESTRATOR_LOCK)),
+        ("workspace_lock", workspace_dir.join(WORKSPACE_LOCK)),
+    ]
+    .into_iter()
+    .map(|(name, path)| ResolvedPath {
+        name,
+        exists: path.exists(),
+        path,
+    })
+    .collect()
+}
+
 /// Convert a session ID to a status filename.
 /// `ws/3` → `ws-3`
 pub fn session_id_to_filename(session_id: &str) -> String {
@@ -38,6 +79,34 @@ pub fn is_session_filename(filename: &str) -> bool {
     filename_to_session_id(stem).starts_with(SESSION_ID_PREFIX)
 }
 
+/// Session states an agent may write to its status file. The workspace
+/// treats anything else as `starting`.
+pub const SESSION_STATES: &[&str] = &["starting", "active", "idle", "needs_input", "error"];
+
+/// Path of the status file for `session_id` in `workspace_dir`.
+pub fn session_status_path(workspace_dir: &Path, session_id: &str) -> PathBuf {
+    workspace_dir.join(session_id_to_filename(session_id))
+}
+
+/// Atomically write `state` to the status file for `session_id`, so the
+/// workspace never reads a partial write. `state` must be one of
+/// [`SESSION_STATES`].
+pub fn write_session_status(workspace_dir: &Path, session_id: &str, state: &str) -> Result<()> {
+    if !SESSION_STATES.contains(&state) {
+        bail!(
+            "unknown session state '{state}' (expected one of: {})",
+            SESSION_STATES.join(", ")
+        );
+    }
+    let path = session_status_path(workspace_dir, session_id);
+    // Dot-prefixed so the workspace's stale-file cleanup never mistakes
+    // it for a session file.
+    let tmp = workspace_dir.join(format!(".{}.tmp", session_id_to_filename(session_id)));
+    std::fs::write(&tmp, state).with_context(|| format!("writing {}", tmp.display()))?;
+    std::fs::rename(&tmp, &path).with_context(|| format!("writing {}", path.display()))?;
+    Ok(())
+}
+
 /// Convert a status filename back to a session ID.
 /// `ws-3` → `ws/3`
 ///
@@ -106,6 +175,45 @@ mod tests {
         assert!(is_session_filename("ws-2.child-pid"));
     }
 
+    #[test]
+    fn write_session_status_roundtrips() {
+        let dir = tempfile::tempdir().unwrap();
+        write_session_status(dir.path(), "ws/3", "needs_input").unwrap();
+        let path = session_status_path(dir.path(), "ws/3");
+        assert_eq!(path, dir.path().join("ws-3"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "needs_input");
+        write_session_status(dir.path(), "ws/3", "idle").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "idle");
+        assert!(write_session_status(dir.path(), "ws/3", "busy").is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "idle");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn resolved_paths_reports_existence() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(WORKSPACE_LOCK), "").unwrap();
+        let db = dir.path().join("missing.db");
+        let paths = resolved_paths(&db, dir.path());
+        let names: Vec<&str> = paths.iter().map(|p| p.name).collect();
+        assert_eq!(
+            names,
+            [
+                "db",
+                "workspace_dir",
+                "orchestrator_log",
+                "orchestrator_lock",
+                "workspace_lock"
+            ]
+        );
+        assert_eq!(paths[0].path, db);
+        assert!(!paths[0].exists);
+        assert!(paths[1].exists);
+        assert_eq!(paths[2].path, dir.path().join("orchestrator.log"));
+        assert!(!paths[2].exists);
+        assert!(paths[4].exists);
+    }
+
     #[test]
     fn is_session_filename_rejects_non_session_files() {
         assert!(!is_session_filename("kbtz.db"));
@@ -1,15 +1,198 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use rusqlite::Connection;
 
-use crate::ops;
+use crate::model::Task;
 use crate::ui::{
     self, ActiveTaskPolicy, DefaultDecorator, FileStatusDecorator, NotesPanel, TreeDecorator,
     TreeView,
 };
 use crate::validate::validate_name;
+use crate::{ops, output};
+
+/// How many of the newest notes the preview pane shows.
+const PREVIEW_NOTES: usize = 5;
+
+/// How many mutating actions `u` can step back through.
+const UNDO_LIMIT: usize = 20;
+
+/// How long a toast stays in the footer if no key is pressed.
+const TOAST_TTL: Duration = Duration::from_secs(3);
+
+/// Summarize how task statuses moved between two snapshots, e.g.
+/// "2 tasks claimed, 1 done". `None` when nothing changed.
+fn summarize_changes(
+    before: &HashMap<String, String>,
+    after: &HashMap<String, String>,
+) -> Option<String> {
+    const VERBS: [&str; 9] = [
+        "added", "claimed", "released", "done", "reopened", "paused", "unpaused", "changed",
+        "removed",
+    ];
+    let mut counts = [0usize; VERBS.len()];
+    let mut bump = |verb: &str| {
+        let i = VERBS.iter().position(|v| *v == verb).unwrap();
+        counts[i] += 1;
+    };
+    for (name, status) in after {
+        let Some(old) = before.get(name) else {
+            bump("added");
+            continue;
+        };
+        if old == status {
+            continue;
+        }
+        bump(match (old.as_str(), status.as_str()) {
+            (_, "active") => "claimed",
+            (_, "done") => "done",
+            (_, "paused") => "paused",
+            ("active", "open") => "released",
+            ("done", "open") => "reopened",
+            ("paused", "open") => "unpaused",
+            _ => "changed",
+        });
+    }
+    for name in before.keys() {
+        if !after.contains_key(name) {
+            bump("removed");
+        }
+    }
+
+    let parts: Vec<String> = VERBS
+        .iter()
+        .zip(counts)
+        .filter(|(_, n)| *n > 0)
+        .enumerate()
+        .map(|(i, (verb, n))| match (i, n) {
+            (0, 1) => format!("1 task {verb}"),
+            (0, n) => format!("{n} tasks {verb}"),
+            (_, n) => format!("{n} {verb}"),
+        })
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+/// A task's status before a change, and who held it if it was active.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriorStatus {
+    pub name: String,
+    pub status: String,
+    pub assignee: Option<String>,
+}
+
+impl From<Task> for PriorStatus {
+    fn from(task: Task) -> Self {
+        Self {
+            name: task.name,
+            status: task.status,
+            assignee: task.assignee,
+        }
+    }
+}
+
+/// The inverse of a change made from the TUI, replayed by `u`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Undo {
+    /// Put each task back to the status it had before.
+    Status(Vec<PriorStatus>),
+    /// Remove a task added with the form.
+    Remove(String),
+    /// Rename `from` back to `to`.
+    Rename { from: String, to: String },
+    /// Restore a task's previous description.
+    Description { name: String, description: String },
+    /// Add (`added` false) or remove (`added` true) a dependency again.
+    Block {
+        blocker: String,
+        blocked: String,
+        added: bool,
+    },
+}
+
+impl Undo {
+    /// Replay the inverse. Multi-task restores are all-or-nothing.
+    fn apply(&self, conn: &Connection) -> Result<()> {
+        match self {
+            Undo::Status(priors) => {
+                conn.execute_batch("SAVEPOINT undo")?;
+                let result = priors
+                    .iter()
+                    .try_for_each(|prior| restore_status(conn, prior));
+                match result {
+                    Ok(()) => conn.execute_batch("RELEASE undo")?,
+                    Err(e) => {
+                        let _ = conn.execute_batch("ROLLBACK TO undo");
+                        let _ = conn.execute_batch("RELEASE undo");
+                        return Err(e);
+                    }
+                }
+                Ok(())
+            }
+            Undo::Remove(name) => ops::remove_task(conn, name, false),
+            Undo::Rename { from, to } => ops::rename_task(conn, from, to),
+            Undo::Description { name, description } => {
+                ops::update_description(conn, name, description)
+            }
+            Undo::Block {
+                blocker,
+                blocked,
+                added: true,
+            } => ops::remove_block(conn, blocker, blocked),
+            Undo::Block {
+                blocker,
+                blocked,
+                added: false,
+            } => ops::add_block(conn, blocker, blocked),
+        }
+    }
+
+    /// What replaying this did, for the toast.
+    fn describe(&self) -> String {
+        match self {
+            Undo::Status(priors) => match priors.as_slice() {
+                [prior] => format!("restored '{}' to {}", prior.name, prior.status),
+                _ => format!("restored {} tasks", priors.len()),
+            },
+            Undo::Remove(name) => format!("removed '{name}'"),
+            Undo::Rename { from, to } => format!("renamed '{from}' back to '{to}'"),
+            Undo::Description { name, .. } => format!("restored description of '{name}'"),
+            Undo::Block {
+                blocker,
+                blocked,
+                added: true,
+            } => format!("'{blocker}' no longer blocks '{blocked}'"),
+            Undo::Block {
+                blocker,
+                blocked,
+                added: false,
+            } => format!("'{blocker}' blocks '{blocked}' again"),
+        }
+    }
+}
+
+/// Move a task back to its prior status (open, paused, or active under its
+/// prior assignee), reopening it first if it has been closed since.
+fn restore_status(conn: &Connection, prior: &PriorStatus) -> Result<()> {
+    let name = prior.name.as_str();
+    let mut current = ops::get_task(conn, name)?.status;
+    if current == prior.status {
+        return Ok(());
+    }
+    if current == "done" {
+        ops::reopen_task(conn, name)?;
+        current = "open".into();
+    }
+    match (current.as_str(), prior.status.as_str(), &prior.assignee) {
+        (c, p, _) if c == p => Ok(()),
+        ("open", "paused", _) => ops::pause_task(conn, name),
+        ("paused", "open", _) => ops::unpause_task(conn, name),
+        ("open", "active", Some(assignee)) => ops::claim_task(conn, name, assignee),
+        (c, p, _) => anyhow::bail!("cannot restore '{name}' from {c} to {p}"),
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AddField {
@@ -18,6 +201,17 @@ pub enum AddField {
     Note,
 }
 
+/// Where a task added from the tree goes, relative to the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddPlacement {
+    /// Under the selected task (`a`).
+    Child,
+    /// Next to the selected task, under the same parent (`s`).
+    Sibling,
+    /// At the top level (`A`).
+    Root,
+}
+
 pub struct AddForm {
     pub name: String,
     pub description: String,
@@ -77,16 +271,52 @@ impl AddForm {
     }
 }
 
+/// Inline rename of the task under the cursor (`r`), shown in the footer.
+pub struct RenameInput {
+    pub old: String,
+    pub input: String,
+    pub error: Option<String>,
+}
+
+/// Picking the other end of a dependency (`b` / `B`): the cursor moves
+/// to the blocker and Enter confirms.
+pub struct BlockPick {
+    /// The task being blocked or unblocked.
+    pub target: String,
+    /// Remove the picked blocker instead of adding it.
+    pub unblock: bool,
+    pub error: Option<String>,
+}
+
 pub struct App {
     pub tree: TreeView,
     pub notes_panel: Option<NotesPanel>,
     pub add_form: Option<AddForm>,
+    pub rename: Option<RenameInput>,
+    pub block_pick: Option<BlockPick>,
     pub workspace_dir: Option<PathBuf>,
     pub decorator: Box<dyn TreeDecorator>,
+    /// Visual multi-select mode (`v`): space toggles rows into `selected`
+    /// and `d`/`p` act on all of them.
+    pub select_mode: bool,
+    pub selected: HashSet<String>,
+    /// Detail text for the task under the cursor while the preview pane
+    /// is open; `None` when it's closed.
+    pub preview: Option<String>,
+    /// Inverses of this session's changes, newest last.
+    pub undo_stack: Vec<Undo>,
+    /// One-shot footer message, cleared by the next key or after
+    /// `TOAST_TTL`.
+    pub toast: Option<String>,
+    toast_shown: Option<Instant>,
+    /// Status of every task (before view filters) as of the last refresh,
+    /// for summarizing changes made outside the TUI.
+    statuses: HashMap<String, String>,
 }
 
 impl App {
     pub fn new(
+        db_path: &str,
         conn: &Connection,
         root: Option<&str>,
         workspace_dir: Option<&Path>,
@@ -95,16 +325,36 @@ impl App {
             tree: TreeView::new(ActiveTaskPolicy::Refuse),
             notes_panel: None,
             add_form: None,
+            rename: None,
+            block_pick: None,
             workspace_dir: workspace_dir.map(PathBuf::from),
             decorator: Box::new(DefaultDecorator),
+            select_mode: false,
+            selected: HashSet::new(),
+            preview: None,
+            undo_stack: Vec::new(),
+            toast: None,
+            toast_shown: None,
+            statuses: HashMap::new(),
         };
+        app.tree
+            .load_collapsed(ui::tree_state_path(db_path), conn)?;
         app.refresh(conn, root)?;
         Ok(app)
     }
 
     pub fn refresh(&mut self, conn: &Connection, root: Option<&str>) -> Result<()> {
         let mut tasks = ops::list_tasks(conn, None, true, root, None, None)?;
+        self.statuses = tasks
+            .iter()
+            .map(|t| (t.name.clone(), t.status.clone()))
+            .collect();
         self.tree.filter_tasks(&mut tasks, &HashSet::new());
+        // Forget selected tasks that were deleted, renamed, or are now
+        // hidden (e.g. marked done elsewhere), so a bulk action never
+        // touches a task the user can't see.
+        self.selected
+            .retain(|name| tasks.iter().any(|t| &t.name == name));
         let rows = ui::flatten_tree(&tasks, &self.tree.collapsed, conn)?;
         self.tree.rows = match &self.tree.filter {
             Some(query) => ui::filter_rows(&rows, query),
@@ -117,6 +367,71 @@ impl App {
                 panel.load(conn, name)?;
             }
         }
+        self.load_preview(conn)
+    }
+
+    /// Refresh after the DB watcher fired, toasting a summary of what
+    /// changed. The TUI's own edits were already picked up by the refresh
+    /// that followed them, so only outside changes are reported.
+    pub fn refresh_from_watch(&mut self, conn: &Connection, root: Option<&str>) -> Result<()> {
+        let before = std::mem::take(&mut self.statuses);
+        self.refresh(conn, root)?;
+        if let Some(summary) = summarize_changes(&before, &self.statuses) {
+            self.show_toast(summary);
+        }
+        Ok(())
+    }
+
+    pub fn show_toast(&mut self, message: String) {
+        self.toast = Some(message);
+        self.toast_shown = Some(Instant::now());
+    }
+
+    /// Drop the toast once it has been up for `TOAST_TTL`.
+    pub fn expire_toast(&mut self, now: Instant) {
+        if self
+            .toast_shown
+            .is_some_and(|shown| now.duration_since(shown) >= TOAST_TTL)
+        {
+            self.toast = None;
+            self.toast_shown = None;
+        }
+    }
+
+    pub fn toggle_preview(&mut self, conn: &Connection) -> Result<()> {
+        if self.preview.is_some() {
+            self.preview = None;
+            Ok(())
+        } else {
+            self.preview = Some(String::new());
+            self.load_preview(conn)
+        }
+    }
+
+    /// Rebuild the preview for the task under the cursor, if the pane is
+    /// open. Only the most recent notes are shown; `n` has the rest.
+    pub fn load_preview(&mut self, conn: &Connection) -> Result<()> {
+        if self.preview.is_none() {
+            return Ok(());
+        }
+        let Some(name) = self.tree.selected_name() else {
+            self.preview = Some(String::new());
+            return Ok(());
+        };
+        let task = ops::get_task(conn, name)?;
+        let ancestors = ops::get_ancestors(conn, name)?;
+        let mut notes = ops::list_notes(conn, name)?;
+        notes.drain(..notes.len().saturating_sub(PREVIEW_NOTES));
+        let blockers = ops::get_blockers(conn, name)?;
+        let dependents = ops::get_dependents(conn, name)?;
+        self.preview = Some(output::format_task_detail(
+            &task,
+            &ancestors,
+            &notes,
+            &blockers,
+            &dependents,
+            &output::Style::default(),
+        ));
         Ok(())
     }
 
@@ -144,15 +459,95 @@ impl App {
         self.tree.selected_name()
     }
 
-    pub fn enter_add_mode(&mut self, with_parent: bool) {
-        let parent = if with_parent {
-            self.tree.selected_name().map(|s| s.to_string())
+    /// Add or remove the task under the cursor from the selection.
+    pub fn toggle_selected(&mut self) {
+        let Some(name) = self.tree.selected_name() else {
+            return;
+        };
+        let name = name.to_string();
+        if !self.selected.remove(&name) {
+            self.selected.insert(name);
+        }
+    }
+
+    /// Leave select mode and drop the selection.
+    pub fn clear_selection(&mut self) {
+        self.select_mode = false;
+        self.selected.clear();
+    }
+
+    /// Selected task names in tree order. Tasks hidden under a collapsed
+    /// parent come last, by name.
+    pub fn selected_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .tree
+            .rows
+            .iter()
+            .filter(|row| self.selected.contains(&row.name))
+            .map(|row| row.name.clone())
+            .collect();
+        let mut hidden: Vec<String> = self
+            .selected
+            .iter()
+            .filter(|name| !names.contains(name))
+            .cloned()
+            .collect();
+        hidden.sort();
+        names.extend(hidden);
+        names
+    }
+
+    /// Apply `d` (`pause` false) or `p` to every selected task in one
+    /// transaction, then clear the selection. `p` unpauses when every
+    /// selected task is paused and pauses otherwise. Like the single-task
+    /// keys, active tasks are refused; failures are shown in the footer.
+    pub fn apply_bulk(&mut self, conn: &Connection, pause: bool, root: Option<&str>) -> Result<()> {
+        let names = self.selected_names();
+        let mut priors: Vec<PriorStatus> = Vec::with_capacity(names.len());
+        for name in &names {
+            priors.push(ops::get_task(conn, name)?.into());
+        }
+        if let Some(i) = priors.iter().position(|p| p.status == "active") {
+            let verb = if pause { "pause" } else { "close" };
+            self.tree.error = Some(format!("cannot {verb} active task '{}'", names[i]));
+            return Ok(());
+        }
+        let op = if !pause {
+            ops::BulkOp::Done
+        } else if priors.iter().all(|p| p.status == "paused") {
+            ops::BulkOp::Unpause
         } else {
-            None
+            ops::BulkOp::Pause
+        };
+        match ops::set_status_bulk(conn, op, &names, false) {
+            Ok(_) => self.push_undo(Undo::Status(priors)),
+            Err(e) => self.tree.error = Some(e.to_string()),
+        }
+        self.clear_selection();
+        self.refresh(conn, root)
+    }
+
+    pub fn enter_add_mode(&mut self, placement: AddPlacement) {
+        let parent = match placement {
+            AddPlacement::Child => self.tree.selected_name().map(|s| s.to_string()),
+            AddPlacement::Sibling => self.displayed_parent(),
+            AddPlacement::Root => None,
         };
         self.add_form = Some(AddForm::new(parent));
     }
 
+    /// Parent of the row under the cursor as the tree shows it: the
+    /// nearest row above it at a shallower depth.
+    fn displayed_parent(&self) -> Option<String> {
+        let rows = &self.tree.rows;
+        let depth = rows.get(self.tree.cursor)?.depth;
+        rows[..self.tree.cursor]
+            .iter()
+            .rev()
+            .find(|row| row.depth < depth)
+            .map(|row| row.name.clone())
+    }
+
     pub fn cancel_add_mode(&mut self) {
         self.add_form = None;
     }
@@ -170,6 +565,14 @@ impl App {
             Some(form.note.clone())
         };
         let parent = form.parent.clone();
+        // The parent was picked when the form opened; it may have been
+        // removed or renamed since.
+        if let Some(p) = &parent {
+            if ops::get_task(conn, p).is_err() {
+                form.error = Some(format!("parent '{p}' no longer exists"));
+                return Ok(());
+            }
+        }
         match ops::add_task(
             conn,
             ops::AddTaskParams {
@@ -181,6 +584,7 @@ impl App {
             },
         ) {
             Ok(()) => {
+                self.push_undo(Undo::Remove(name));
                 self.add_form = None;
                 self.refresh(conn, root)?;
             }
@@ -190,4 +594,248 @@ impl App {
         }
         Ok(())
     }
+
+    /// Open the rename line preloaded with the selected task's name.
+    pub fn enter_rename(&mut self) {
+        if let Some(name) = self.tree.selected_name() {
+            self.rename = Some(RenameInput {
+                old: name.to_string(),
+                input: name.to_string(),
+                error: None,
+            });
+        }
+    }
+
+    /// Rename the task and keep the cursor on it. Errors (e.g. the new
+    /// name is taken) leave the line open so the input can be fixed.
+    pub fn submit_rename(&mut self, conn: &Connection, root: Option<&str>) -> Result<()> {
+        let Some(rename) = self.rename.as_mut() else {
+            return Ok(());
+        };
+        if rename.input == rename.old {
+            self.rename = None;
+            return Ok(());
+        }
+        if let Err(e) = ops::rename_task(conn, &rename.old, &rename.input) {
+            rename.error = Some(e.to_string());
+            return Ok(());
+        }
+        let undo = Undo::Rename {
+            from: rename.input.clone(),
+            to: rename.old.clone(),
+        };
+        if self.selected.remove(&rename.old) {
+            self.selected.insert(rename.input.clone());
+        }
+        self.tree.pending_select = Some(rename.input.clone());
+        self.rename = None;
+        self.push_undo(undo);
+        self.refresh(conn, root)
+    }
+
+    /// Start picking a blocker for the selected task.
+    pub fn enter_block_pick(&mut self, unblock: bool) {
+        if let Some(name) = self.tree.selected_name() {
+            self.block_pick = Some(BlockPick {
+                target: name.to_string(),
+                unblock,
+                error: None,
+            });
+        }
+    }
+
+    /// Add or remove the dependency between the task under the cursor and
+    /// the pick's target. Errors (cycles, missing deps) keep the pick open
+    /// so another task can be chosen.
+    pub fn confirm_block(&mut self, conn: &Connection, root: Option<&str>) -> Result<()> {
+        let Some(pick) = self.block_pick.as_mut() else {
+            return Ok(());
+        };
+        let Some(blocker) = self.tree.selected_name().map(str::to_string) else {
+            return Ok(());
+        };
+        let result = if pick.unblock {
+            ops::remove_block(conn, &blocker, &pick.target)
+        } else {
+            ops::add_block(conn, &blocker, &pick.target)
+        };
+        if let Err(e) = result {
+            pick.error = Some(e.to_string());
+            return Ok(());
+        }
+        let message = if pick.unblock {
+            format!("'{blocker}' no longer blocks '{}'", pick.target)
+        } else {
+            format!("'{blocker}' now blocks '{}'", pick.target)
+        };
+        let undo = Undo::Block {
+            blocker,
+            blocked: pick.target.clone(),
+            added: !pick.unblock,
+        };
+        self.tree.pending_select = Some(pick.target.clone());
+        self.block_pick = None;
+        self.push_undo(undo);
+        self.show_toast(message);
+        self.refresh(conn, root)
+    }
+
+    /// Store a description edited in `$EDITOR`.
+    pub fn update_description(
+        &mut self,
+        conn: &Connection,
+        name: &str,
+        description: &str,
+        root: Option<&str>,
+    ) -> Result<()> {
+        let previous = match ops::get_task(conn, name) {
+            Ok(task) => task.description,
+            Err(e) => {
+                self.tree.error = Some(e.to_string());
+                return Ok(());
+            }
+        };
+        if let Err(e) = ops::update_description(conn, name, description) {
+            self.tree.error = Some(e.to_string());
+            return Ok(());
+        }
+        self.push_undo(Undo::Description {
+            name: name.to_string(),
+            description: previous,
+        });
+        self.refresh(conn, root)
+    }
+
+    /// Apply a single-task status change from a tree key, remembering the
+    /// prior status (and assignee) so `u` can put it back.
+    pub fn set_status(
+        &mut self,
+        conn: &Connection,
+        name: &str,
+        op: ops::BulkOp,
+        root: Option<&str>,
+    ) -> Result<()> {
+        let names = [name.to_string()];
+        let prior = ops::get_task(conn, name).map(PriorStatus::from);
+        match prior.and_then(|prior| {
+            ops::set_status_bulk(conn, op, &names, false)?;
+            Ok(prior)
+        }) {
+            Ok(prior) => self.push_undo(Undo::Status(vec![prior])),
+            Err(e) => {
+                self.tree.error = Some(e.to_string());
+                return Ok(());
+            }
+        }
+        self.refresh(conn, root)
+    }
+
+    fn push_undo(&mut self, undo: Undo) {
+        if self.undo_stack.len() == UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(undo);
+    }
+
+    /// Replay the inverse of the most recent change (`u`). A failed undo
+    /// (e.g. the task changed again outside the TUI) is dropped, not retried.
+    pub fn undo(&mut self, conn: &Connection, root: Option<&str>) -> Result<()> {
+        let Some(undo) = self.undo_stack.pop() else {
+            self.tree.error = Some("nothing to undo".into());
+            return Ok(());
+        };
+        match undo.apply(conn) {
+            Ok(()) => {
+                if let Undo::Rename { to, .. } = &undo {
+                    self.tree.pending_select = Some(to.clone());
+                }
+                self.show_toast(format!("undo: {}", undo.describe()));
+            }
+            Err(e) => self.tree.error = Some(format!("undo failed: {e}")),
+        }
+        self.refresh(conn, root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(n, s)| (n.to_string(), s.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn summarize_changes_counts_transitions() {
+        let before = snapshot(&[
+            ("a", "open"),
+            ("b", "open"),
+            ("c", "active"),
+            ("d", "paused"),
+            ("gone", "done"),
+        ]);
+        let after = snapshot(&[
+            ("a", "active"),
+            ("b", "active"),
+            ("c", "done"),
+            ("d", "paused"),
+            ("new", "open"),
+        ]);
+        assert_eq!(
+            summarize_changes(&before, &after).as_deref(),
+            Some("1 task added, 2 claimed, 1 done, 1 removed")
+        );
+
+        let after = snapshot(&[("a", "open"), ("b", "active")]);
+        let before = snapshot(&[("a", "open"), ("b", "open")]);
+        assert_eq!(
+            summarize_changes(&before, &after).as_deref(),
+            Some("1 task claimed")
+        );
+        assert_eq!(summarize_changes(&after, &after), None);
+    }
+
+    #[test]
+    fn summarize_changes_names_reverse_transitions() {
+        let before = snapshot(&[("a", "active"), ("b", "done"), ("c", "paused")]);
+        let after = snapshot(&[("a", "open"), ("b", "open"), ("c", "open")]);
+        assert_eq!(
+            summarize_changes(&before, &after).as_deref(),
+            Some("1 task released, 1 reopened, 1 unpaused")
+        );
+    }
+
+    #[test]
+    fn watch_refresh_toasts_outside_changes_until_expired() {
+        let conn = crate::db::open_memory().unwrap();
+        for name in ["a", "b"] {
+            ops::add_task(
+                &conn,
+                ops::AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("kbtz.db");
+        let mut app = App::new(db_path.to_str().unwrap(), &conn, None, None).unwrap();
+
+        app.refresh_from_watch(&conn, None).unwrap();
+        assert!(app.toast.is_none());
+
+        ops::claim_task(&conn, "a", "agent").unwrap();
+        ops::mark_done(&conn, "b").unwrap();
+        app.refresh_from_watch(&conn, None).unwrap();
+        assert_eq!(app.toast.as_deref(), Some("1 task claimed, 1 done"));
+
+        app.expire_toast(Instant::now());
+        assert!(app.toast.is_some());
+        app.expire_toast(Instant::now() + TOAST_TTL);
+        assert!(app.toast.is_none());
+    }
 }
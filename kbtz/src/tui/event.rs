@@ -1,7 +1,7 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-use super::app::{AddField, App};
-use crate::ui::{NotesKeyAction, TreeKeyAction};
+use super::app::{AddField, AddPlacement, App};
+use crate::ui::{NotesKeyAction, TreeKeyAction, TreeMode};
 
 /// Result of handling a key press.
 pub enum KeyAction {
@@ -11,30 +11,63 @@ pub enum KeyAction {
     OpenEditor,
     AddNote,
     ToggleNotes,
+    TogglePreview,
+    DeleteNote(i64),
     Pause(String),
     Unpause(String),
     MarkDone(String),
     ForceUnassign(String),
+    /// Rename the task as typed in the rename line.
+    SubmitRename,
+    /// Edit this task's description in `$EDITOR`.
+    EditDescription(String),
+    /// Revert the most recent change made in this session.
+    Undo,
+    /// Use the task under the cursor as the blocker being picked.
+    ConfirmBlock,
+    /// Mark every selected task done.
+    BulkDone,
+    /// Pause (or, if all are paused, unpause) every selected task.
+    BulkPause,
     RunAction,
     Continue,
 }
 
 /// Handle a key press. Returns an action indicating what the event loop should do.
-pub fn handle_key(app: &mut App, key: KeyEvent) -> KeyAction {
+/// Enter runs `--action` when one is configured (`has_action`) and toggles
+/// the detail pane otherwise.
+pub fn handle_key(app: &mut App, key: KeyEvent, has_action: bool) -> KeyAction {
+    app.toast = None;
     if app.add_form.is_some() {
         return handle_add(app, key);
     }
 
+    if app.rename.is_some() {
+        return handle_rename(app, key);
+    }
+
+    if app.block_pick.is_some() {
+        return handle_block_pick(app, key);
+    }
+
     if let Some(panel) = &mut app.notes_panel {
         return match panel.handle_key(key) {
             NotesKeyAction::Close => {
                 app.notes_panel = None;
                 KeyAction::Continue
             }
+            NotesKeyAction::Delete(id) => KeyAction::DeleteNote(id),
             NotesKeyAction::Continue => KeyAction::Continue,
         };
     }
 
+    let was_searching = matches!(app.tree.mode, TreeMode::Search(_));
+    if matches!(app.tree.mode, TreeMode::Normal) {
+        if let Some(action) = handle_select(app, key) {
+            return action;
+        }
+    }
+
     match app.tree.handle_key(key) {
         TreeKeyAction::Quit => KeyAction::Quit,
         TreeKeyAction::Refresh => KeyAction::Refresh,
@@ -46,22 +79,77 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> KeyAction {
         TreeKeyAction::Continue => KeyAction::Continue,
         TreeKeyAction::Unhandled => match key.code {
             KeyCode::Esc => KeyAction::Quit,
-            KeyCode::Enter => KeyAction::RunAction,
+            KeyCode::Enter if has_action => KeyAction::RunAction,
+            // Enter that confirms a search shouldn't also open the pane.
+            KeyCode::Enter if was_searching => KeyAction::Continue,
+            KeyCode::Enter => KeyAction::TogglePreview,
             KeyCode::Char('n') => KeyAction::ToggleNotes,
             KeyCode::Char('a') => {
-                app.enter_add_mode(true);
+                app.enter_add_mode(AddPlacement::Child);
+                KeyAction::Continue
+            }
+            KeyCode::Char('s') => {
+                app.enter_add_mode(AddPlacement::Sibling);
                 KeyAction::Continue
             }
             KeyCode::Char('A') => {
-                app.enter_add_mode(false);
+                app.enter_add_mode(AddPlacement::Root);
+                KeyAction::Continue
+            }
+            KeyCode::Char('r') => {
+                app.enter_rename();
+                KeyAction::Continue
+            }
+            KeyCode::Char('u') => KeyAction::Undo,
+            KeyCode::Char('b') => {
+                app.enter_block_pick(false);
+                KeyAction::Continue
+            }
+            KeyCode::Char('B') => {
+                app.enter_block_pick(true);
                 KeyAction::Continue
             }
+            KeyCode::Char('e') => match app.selected_name() {
+                Some(name) => KeyAction::EditDescription(name.to_string()),
+                None => KeyAction::Continue,
+            },
             KeyCode::Char('N') => KeyAction::AddNote,
             _ => KeyAction::Continue,
         },
     }
 }
 
+/// Multi-select keys, checked before the tree's own bindings. Returns
+/// `None` for keys that select mode doesn't change (e.g. movement).
+fn handle_select(app: &mut App, key: KeyEvent) -> Option<KeyAction> {
+    if !app.select_mode {
+        if key.code == KeyCode::Char('v') {
+            app.tree.error = None;
+            app.select_mode = true;
+            return Some(KeyAction::Continue);
+        }
+        return None;
+    }
+    let action = match key.code {
+        KeyCode::Char('v') | KeyCode::Esc => {
+            app.clear_selection();
+            KeyAction::Continue
+        }
+        KeyCode::Char(' ') => {
+            app.toggle_selected();
+            KeyAction::Continue
+        }
+        KeyCode::Char('d') | KeyCode::Char('p') if app.selected.is_empty() => {
+            app.tree.error = Some("no tasks selected (space selects)".into());
+            KeyAction::Continue
+        }
+        KeyCode::Char('d') => KeyAction::BulkDone,
+        KeyCode::Char('p') => KeyAction::BulkPause,
+        _ => return None,
+    };
+    Some(action)
+}
+
 fn handle_add(app: &mut App, key: KeyEvent) -> KeyAction {
     match key.code {
         KeyCode::Esc => {
@@ -109,3 +197,485 @@ fn handle_add(app: &mut App, key: KeyEvent) -> KeyAction {
         _ => KeyAction::Continue,
     }
 }
+
+/// While picking a blocker only navigation, Enter and Esc do anything.
+fn handle_block_pick(app: &mut App, key: KeyEvent) -> KeyAction {
+    match key.code {
+        KeyCode::Esc => {
+            app.block_pick = None;
+            KeyAction::Continue
+        }
+        KeyCode::Enter => KeyAction::ConfirmBlock,
+        KeyCode::Char('j') | KeyCode::Down => {
+            app.tree.move_down();
+            KeyAction::Continue
+        }
+        KeyCode::Char('k') | KeyCode::Up => {
+            app.tree.move_up();
+            KeyAction::Continue
+        }
+        _ => KeyAction::Continue,
+    }
+}
+
+fn handle_rename(app: &mut App, key: KeyEvent) -> KeyAction {
+    let Some(rename) = &mut app.rename else {
+        return KeyAction::Continue;
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app.rename = None;
+            KeyAction::Continue
+        }
+        KeyCode::Enter => KeyAction::SubmitRename,
+        KeyCode::Backspace => {
+            rename.input.pop();
+            rename.error = None;
+            KeyAction::Continue
+        }
+        KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            rename.input.clear();
+            rename.error = None;
+            KeyAction::Continue
+        }
+        KeyCode::Char(c) => {
+            rename.input.push(c);
+            rename.error = None;
+            KeyAction::Continue
+        }
+        _ => KeyAction::Continue,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::ActiveTaskPolicy;
+    use crate::{db, ops};
+    use rusqlite::Connection;
+
+    fn press(app: &mut App, c: char) -> KeyAction {
+        handle_key(
+            app,
+            KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            false,
+        )
+    }
+
+    fn setup() -> (tempfile::TempDir, Connection, App) {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = db::open_memory().unwrap();
+        for name in ["a", "b", "c"] {
+            ops::add_task(
+                &conn,
+                ops::AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        let db_path = dir.path().join("kbtz.db");
+        let app = App::new(db_path.to_str().unwrap(), &conn, None, None).unwrap();
+        (dir, conn, app)
+    }
+
+    #[test]
+    fn select_mode_toggles_rows_and_escape_clears() {
+        let (_dir, _conn, mut app) = setup();
+        // Outside select mode, space still collapses and nothing is selected.
+        press(&mut app, ' ');
+        assert!(app.selected.is_empty());
+
+        press(&mut app, 'v');
+        assert!(app.select_mode);
+        press(&mut app, ' ');
+        press(&mut app, 'j');
+        press(&mut app, 'j');
+        press(&mut app, ' ');
+        assert_eq!(app.selected_names(), ["a", "c"]);
+        press(&mut app, ' ');
+        assert_eq!(app.selected_names(), ["a"]);
+
+        let esc = handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
+            false,
+        );
+        assert!(matches!(esc, KeyAction::Continue), "Esc must not quit");
+        assert!(!app.select_mode);
+        assert!(app.selected.is_empty());
+    }
+
+    #[test]
+    fn bulk_keys_need_a_selection() {
+        let (_dir, _conn, mut app) = setup();
+        press(&mut app, 'v');
+        assert!(matches!(press(&mut app, 'd'), KeyAction::Continue));
+        assert!(app.tree.error.is_some());
+        press(&mut app, ' ');
+        assert!(matches!(press(&mut app, 'd'), KeyAction::BulkDone));
+        assert!(matches!(press(&mut app, 'p'), KeyAction::BulkPause));
+    }
+
+    #[test]
+    fn bulk_actions_apply_to_selection_then_clear_it() {
+        let (_dir, conn, mut app) = setup();
+        press(&mut app, 'v');
+        press(&mut app, ' ');
+        press(&mut app, 'j');
+        press(&mut app, ' ');
+        app.apply_bulk(&conn, true, None).unwrap();
+        assert_eq!(ops::get_task(&conn, "a").unwrap().status, "paused");
+        assert_eq!(ops::get_task(&conn, "b").unwrap().status, "paused");
+        assert!(!app.select_mode && app.selected.is_empty());
+
+        // Paused tasks are hidden by default; show them to select again.
+        press(&mut app, 'P');
+        app.refresh(&conn, None).unwrap();
+        app.select_mode = true;
+        app.selected.extend(["a".to_string(), "b".to_string()]);
+        app.apply_bulk(&conn, true, None).unwrap();
+        assert_eq!(ops::get_task(&conn, "a").unwrap().status, "open");
+        assert_eq!(ops::get_task(&conn, "b").unwrap().status, "open");
+
+        ops::claim_task(&conn, "c", "agent").unwrap();
+        app.refresh(&conn, None).unwrap();
+        app.selected.extend(["a".to_string(), "c".to_string()]);
+        app.apply_bulk(&conn, false, None).unwrap();
+        assert!(app.tree.error.as_deref().unwrap().contains("'c'"));
+        assert_eq!(ops::get_task(&conn, "a").unwrap().status, "open");
+    }
+
+    fn type_keys(app: &mut App, conn: &Connection, keys: &str) {
+        for c in keys.chars() {
+            if let KeyAction::Refresh = press(app, c) {
+                app.refresh(conn, None).unwrap();
+            }
+        }
+    }
+
+    fn row_names(app: &App) -> Vec<&str> {
+        app.tree.rows.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    #[test]
+    fn slash_filter_narrows_rows_and_keeps_ancestors() {
+        let (_dir, conn, mut app) = setup();
+        ops::add_task(
+            &conn,
+            ops::AddTaskParams {
+                name: "login-form",
+                parent: Some("b"),
+                description: "Build the auth page",
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        app.refresh(&conn, None).unwrap();
+
+        type_keys(&mut app, &conn, "/auth");
+        assert_eq!(row_names(&app), ["b", "login-form"]);
+
+        // Enter keeps the filter; Esc then clears it.
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+            false,
+        );
+        assert_eq!(app.tree.filter.as_deref(), Some("auth"));
+        let esc = handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
+            false,
+        );
+        assert!(
+            matches!(esc, KeyAction::Refresh),
+            "Esc clears before quitting"
+        );
+        app.refresh(&conn, None).unwrap();
+        assert_eq!(row_names(&app), ["a", "b", "login-form", "c"]);
+    }
+
+    #[test]
+    fn enter_runs_action_or_toggles_preview() {
+        let (_dir, _conn, mut app) = setup();
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            handle_key(&mut app, enter, true),
+            KeyAction::RunAction
+        ));
+        assert!(matches!(
+            handle_key(&mut app, enter, false),
+            KeyAction::TogglePreview
+        ));
+    }
+
+    fn submit(app: &mut App, conn: &Connection, name: &str) {
+        for c in name.chars() {
+            press(app, c);
+        }
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(handle_key(app, enter, false), KeyAction::Submit));
+        app.submit_add(conn, None).unwrap();
+    }
+
+    #[test]
+    fn add_child_and_sibling_prefill_parent() {
+        let (_dir, conn, mut app) = setup();
+        press(&mut app, 'j'); // cursor on "b"
+        press(&mut app, 'a');
+        assert_eq!(app.add_form.as_ref().unwrap().parent.as_deref(), Some("b"));
+        submit(&mut app, &conn, "b1");
+        assert!(app.add_form.is_none());
+        assert_eq!(
+            ops::get_task(&conn, "b1").unwrap().parent.as_deref(),
+            Some("b")
+        );
+
+        // Rows are now a, b, b1, c; a sibling of "b1" also goes under "b".
+        press(&mut app, 'j');
+        assert_eq!(app.selected_name(), Some("b1"));
+        press(&mut app, 's');
+        submit(&mut app, &conn, "b2");
+        assert_eq!(
+            ops::get_task(&conn, "b2").unwrap().parent.as_deref(),
+            Some("b")
+        );
+
+        // A sibling of a top-level task is top-level.
+        press(&mut app, 'k');
+        assert_eq!(app.selected_name(), Some("b"));
+        press(&mut app, 's');
+        assert_eq!(app.add_form.as_ref().unwrap().parent, None);
+    }
+
+    #[test]
+    fn add_child_reports_parent_removed_before_submit() {
+        let (_dir, conn, mut app) = setup();
+        press(&mut app, 'a');
+        assert_eq!(app.add_form.as_ref().unwrap().parent.as_deref(), Some("a"));
+        ops::remove_task(&conn, "a", false).unwrap();
+        submit(&mut app, &conn, "a1");
+        let form = app.add_form.as_ref().expect("form stays open");
+        assert_eq!(form.error.as_deref(), Some("parent 'a' no longer exists"));
+        assert!(ops::get_task(&conn, "a1").is_err());
+    }
+
+    #[test]
+    fn rename_updates_row_and_keeps_cursor_on_it() {
+        let (_dir, conn, mut app) = setup();
+        press(&mut app, 'j');
+        press(&mut app, 'r');
+        assert_eq!(app.rename.as_ref().unwrap().input, "b");
+        let backspace = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
+        handle_key(&mut app, backspace, false);
+        for c in "zed".chars() {
+            press(&mut app, c);
+        }
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            handle_key(&mut app, enter, false),
+            KeyAction::SubmitRename
+        ));
+        app.submit_rename(&conn, None).unwrap();
+        assert!(app.rename.is_none());
+        assert!(ops::get_task(&conn, "b").is_err());
+        assert_eq!(app.selected_name(), Some("zed"));
+    }
+
+    #[test]
+    fn rename_to_taken_name_keeps_input_open() {
+        let (_dir, conn, mut app) = setup();
+        press(&mut app, 'r');
+        let backspace = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
+        handle_key(&mut app, backspace, false);
+        press(&mut app, 'c');
+        app.submit_rename(&conn, None).unwrap();
+        let rename = app.rename.as_ref().expect("line stays open");
+        assert_eq!(rename.error.as_deref(), Some("task 'c' already exists"));
+        assert_eq!(rename.input, "c");
+        assert!(ops::get_task(&conn, "a").is_ok());
+
+        // Typing clears the error; Esc abandons the rename.
+        press(&mut app, 'x');
+        assert!(app.rename.as_ref().unwrap().error.is_none());
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        handle_key(&mut app, esc, false);
+        assert!(app.rename.is_none());
+    }
+
+    #[test]
+    fn edit_key_updates_description() {
+        let (_dir, conn, mut app) = setup();
+        let KeyAction::EditDescription(name) = press(&mut app, 'e') else {
+            panic!("expected EditDescription");
+        };
+        assert_eq!(name, "a");
+        app.update_description(&conn, &name, "new words", None)
+            .unwrap();
+        assert_eq!(ops::get_task(&conn, "a").unwrap().description, "new words");
+        assert!(app.tree.error.is_none());
+    }
+
+    fn status(conn: &Connection, name: &str) -> String {
+        ops::get_task(conn, name).unwrap().status
+    }
+
+    #[test]
+    fn done_then_undo_restores_prior_status() {
+        let (_dir, conn, mut app) = setup();
+        ops::pause_task(&conn, "b").unwrap();
+        app.tree.show_done = true;
+        app.tree.show_paused = true;
+        app.refresh(&conn, None).unwrap();
+
+        let KeyAction::MarkDone(name) = press(&mut app, 'd') else {
+            panic!("expected MarkDone");
+        };
+        app.set_status(&conn, &name, ops::BulkOp::Done, None)
+            .unwrap();
+        press(&mut app, 'j'); // "b"
+        let KeyAction::MarkDone(name) = press(&mut app, 'd') else {
+            panic!("expected MarkDone");
+        };
+        app.set_status(&conn, &name, ops::BulkOp::Done, None)
+            .unwrap();
+        assert_eq!(status(&conn, "a"), "done");
+        assert_eq!(status(&conn, "b"), "done");
+
+        assert!(matches!(press(&mut app, 'u'), KeyAction::Undo));
+        app.undo(&conn, None).unwrap();
+        assert_eq!(status(&conn, "b"), "paused");
+        assert_eq!(app.toast.as_deref(), Some("undo: restored 'b' to paused"));
+        app.undo(&conn, None).unwrap();
+        assert_eq!(status(&conn, "a"), "open");
+
+        // The toast lasts one key; an empty stack is reported.
+        press(&mut app, 'u');
+        assert!(app.toast.is_none());
+        app.undo(&conn, None).unwrap();
+        assert_eq!(app.tree.error.as_deref(), Some("nothing to undo"));
+    }
+
+    #[test]
+    fn done_then_undo_reclaims_active_task() {
+        let (_dir, conn, mut app) = setup();
+        ops::claim_task(&conn, "a", "alice").unwrap();
+        app.tree.active_policy = ActiveTaskPolicy::Allow;
+        app.refresh(&conn, None).unwrap();
+
+        let KeyAction::MarkDone(name) = press(&mut app, 'd') else {
+            panic!("expected MarkDone");
+        };
+        app.set_status(&conn, &name, ops::BulkOp::Done, None)
+            .unwrap();
+        assert_eq!(status(&conn, "a"), "done");
+
+        app.undo(&conn, None).unwrap();
+        assert!(app.tree.error.is_none(), "{:?}", app.tree.error);
+        let task = ops::get_task(&conn, "a").unwrap();
+        assert_eq!(task.status, "active");
+        assert_eq!(task.assignee.as_deref(), Some("alice"));
+        assert_eq!(app.toast.as_deref(), Some("undo: restored 'a' to active"));
+    }
+
+    #[test]
+    fn undo_reverts_bulk_add_and_rename() {
+        let (_dir, conn, mut app) = setup();
+        press(&mut app, 'v');
+        press(&mut app, ' ');
+        press(&mut app, 'j');
+        press(&mut app, ' ');
+        press(&mut app, 'p');
+        app.apply_bulk(&conn, true, None).unwrap();
+        press(&mut app, 'A');
+        submit(&mut app, &conn, "new");
+        app.tree.pending_select = Some("new".into());
+        app.refresh(&conn, None).unwrap();
+        press(&mut app, 'r');
+        press(&mut app, '2');
+        app.submit_rename(&conn, None).unwrap();
+        assert!(ops::get_task(&conn, "new2").is_ok());
+
+        app.undo(&conn, None).unwrap();
+        assert!(ops::get_task(&conn, "new2").is_err());
+        assert_eq!(app.selected_name(), Some("new"));
+        app.undo(&conn, None).unwrap();
+        assert!(ops::get_task(&conn, "new").is_err());
+        app.undo(&conn, None).unwrap();
+        assert_eq!(app.toast.as_deref(), Some("undo: restored 2 tasks"));
+        assert_eq!(status(&conn, "a"), "open");
+        assert_eq!(status(&conn, "b"), "open");
+        assert!(app.undo_stack.is_empty());
+    }
+
+    fn enter(app: &mut App) -> KeyAction {
+        handle_key(
+            app,
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+            false,
+        )
+    }
+
+    #[test]
+    fn block_pick_adds_and_removes_dependency() {
+        let (_dir, conn, mut app) = setup();
+        press(&mut app, 'b');
+        assert_eq!(app.block_pick.as_ref().unwrap().target, "a");
+        press(&mut app, 'j');
+        press(&mut app, 'j');
+        assert_eq!(app.selected_name(), Some("c"));
+        assert!(matches!(enter(&mut app), KeyAction::ConfirmBlock));
+        app.confirm_block(&conn, None).unwrap();
+        assert!(app.block_pick.is_none());
+        assert_eq!(app.toast.as_deref(), Some("'c' now blocks 'a'"));
+        assert_eq!(ops::get_blockers(&conn, "a").unwrap(), vec!["c"]);
+        assert_eq!(app.selected_name(), Some("a"));
+
+        press(&mut app, 'B');
+        press(&mut app, 'j');
+        // "b" isn't a blocker of "a": the pick stays open with the error.
+        app.confirm_block(&conn, None).unwrap();
+        let pick = app.block_pick.as_ref().unwrap();
+        assert_eq!(pick.error.as_deref(), Some("'b' is not blocking 'a'"));
+        press(&mut app, 'j');
+        app.confirm_block(&conn, None).unwrap();
+        assert!(app.block_pick.is_none());
+        assert!(ops::get_blockers(&conn, "a").unwrap().is_empty());
+    }
+
+    #[test]
+    fn block_pick_rejects_cycles_inline() {
+        let (_dir, conn, mut app) = setup();
+        ops::add_block(&conn, "a", "b").unwrap();
+        app.refresh(&conn, None).unwrap();
+
+        // Asking "b" to block "a" would close the loop.
+        press(&mut app, 'b');
+        press(&mut app, 'j');
+        assert_eq!(app.selected_name(), Some("b"));
+        app.confirm_block(&conn, None).unwrap();
+        let pick = app.block_pick.as_ref().expect("pick stays open");
+        assert_eq!(
+            pick.error.as_deref(),
+            Some("adding this dependency would create a cycle")
+        );
+        assert!(ops::get_blockers(&conn, "a").unwrap().is_empty());
+
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        handle_key(&mut app, esc, false);
+        assert!(app.block_pick.is_none());
+    }
+
+    #[test]
+    fn refresh_drops_selected_tasks_that_disappear() {
+        let (_dir, conn, mut app) = setup();
+        app.select_mode = true;
+        app.selected.extend(["a".to_string(), "b".to_string()]);
+        ops::remove_task(&conn, "a", false).unwrap();
+        ops::mark_done(&conn, "b").unwrap();
+        app.refresh(&conn, None).unwrap();
+        assert!(app.selected.is_empty());
+    }
+}
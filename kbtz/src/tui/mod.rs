@@ -5,7 +5,8 @@ mod tree;
 
 use std::io;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use crossterm::event::{self as ct_event, Event, KeyEventKind};
@@ -26,9 +27,13 @@ pub fn run(
     poll_interval: u64,
     action: Option<&str>,
     workspace_dir: Option<&str>,
+    preview: bool,
 ) -> Result<()> {
     let workspace_dir = workspace_dir.map(PathBuf::from);
-    let mut app = App::new(conn, root, workspace_dir.as_deref())?;
+    let mut app = App::new(db_path, conn, root, workspace_dir.as_deref())?;
+    if preview {
+        app.toggle_preview(conn)?;
+    }
 
     terminal::enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -74,12 +79,19 @@ fn run_loop(
         .transpose()?;
 
     loop {
+        app.expire_toast(Instant::now());
         terminal.draw(|frame| tree::render(frame, app))?;
 
-        if ct_event::poll(poll_duration)? {
+        let wake = wait_for_wake(
+            poll_duration,
+            &rx,
+            status_watcher.as_ref().map(|(_, rx)| rx),
+            |timeout| Ok(ct_event::poll(timeout)?),
+        )?;
+        if wake == Wake::Input {
             if let Event::Key(key) = ct_event::read()? {
                 if key.kind == KeyEventKind::Press {
-                    match event::handle_key(app, key) {
+                    match event::handle_key(app, key, action.is_some()) {
                         KeyAction::Quit => return Ok(()),
                         KeyAction::Submit => {
                             app.submit_add(conn, root)?;
@@ -97,9 +109,30 @@ fn run_loop(
                                 }
                             }
                         }
+                        KeyAction::SubmitRename => {
+                            app.submit_rename(conn, root)?;
+                        }
+                        KeyAction::EditDescription(name) => match ops::get_task(conn, &name) {
+                            Ok(task) => match editor::open_editor(terminal, &task.description) {
+                                Ok(content) => {
+                                    app.update_description(conn, &name, content.trim_end(), root)?;
+                                }
+                                Err(e) => app.tree.error = Some(e.to_string()),
+                            },
+                            Err(e) => app.tree.error = Some(e.to_string()),
+                        },
                         KeyAction::ToggleNotes => {
                             app.toggle_notes(conn)?;
                         }
+                        KeyAction::TogglePreview => {
+                            app.toggle_preview(conn)?;
+                        }
+                        KeyAction::DeleteNote(id) => {
+                            // The panel is reloaded for the selected task below.
+                            if let Err(e) = ops::delete_note(conn, id) {
+                                app.tree.error = Some(e.to_string());
+                            }
+                        }
                         KeyAction::AddNote => {
                             if let Some(task_name) = app.selected_name() {
                                 let task_name = task_name.to_string();
@@ -126,25 +159,13 @@ fn run_loop(
                             }
                         }
                         KeyAction::Pause(name) => {
-                            if let Err(e) = ops::pause_task(conn, &name) {
-                                app.tree.error = Some(e.to_string());
-                            } else {
-                                app.refresh(conn, root)?;
-                            }
+                            app.set_status(conn, &name, ops::BulkOp::Pause, root)?;
                         }
                         KeyAction::Unpause(name) => {
-                            if let Err(e) = ops::unpause_task(conn, &name) {
-                                app.tree.error = Some(e.to_string());
-                            } else {
-                                app.refresh(conn, root)?;
-                            }
+                            app.set_status(conn, &name, ops::BulkOp::Unpause, root)?;
                         }
                         KeyAction::MarkDone(name) => {
-                            if let Err(e) = ops::mark_done(conn, &name) {
-                                app.tree.error = Some(e.to_string());
-                            } else {
-                                app.refresh(conn, root)?;
-                            }
+                            app.set_status(conn, &name, ops::BulkOp::Done, root)?;
                         }
                         KeyAction::ForceUnassign(name) => {
                             if let Err(e) = ops::force_unassign_task(conn, &name) {
@@ -153,6 +174,18 @@ fn run_loop(
                                 app.refresh(conn, root)?;
                             }
                         }
+                        KeyAction::ConfirmBlock => {
+                            app.confirm_block(conn, root)?;
+                        }
+                        KeyAction::Undo => {
+                            app.undo(conn, root)?;
+                        }
+                        KeyAction::BulkDone => {
+                            app.apply_bulk(conn, false, root)?;
+                        }
+                        KeyAction::BulkPause => {
+                            app.apply_bulk(conn, true, root)?;
+                        }
                         KeyAction::RunAction => {
                             if let Some(cmd) = action {
                                 if let Some(row) = app.tree.rows.get(app.tree.cursor) {
@@ -199,22 +232,119 @@ fn run_loop(
                             panel.load(conn, name)?;
                         }
                     }
+                    app.load_preview(conn)?;
                 }
             }
         }
 
         // Check for DB file changes (non-blocking)
-        if watch::wait_for_change(&rx, Duration::ZERO) {
+        if wake == Wake::Db || watch::wait_for_change(&rx, Duration::ZERO) {
             watch::drain_events(&rx);
-            app.refresh(conn, root)?;
+            app.refresh_from_watch(conn, root)?;
         }
 
         // Check for workspace status file changes (non-blocking)
         if let Some((_, ref status_rx)) = status_watcher {
-            if watch::wait_for_change(status_rx, Duration::ZERO) {
+            if wake == Wake::Status || watch::wait_for_change(status_rx, Duration::ZERO) {
                 watch::drain_events(status_rx);
                 app.refresh_statuses();
             }
         }
     }
 }
+
+/// Shortest input wait between watcher checks, used right after the loop
+/// wakes so a burst of changes is picked up quickly.
+const MIN_INPUT_SLICE: Duration = Duration::from_millis(10);
+
+/// Longest input wait between watcher checks. Bounds how stale the tree
+/// can be after a DB change, independent of `--poll-interval`.
+const MAX_INPUT_SLICE: Duration = Duration::from_millis(100);
+
+/// Why `wait_for_wake` returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wake {
+    Input,
+    Db,
+    Status,
+    Timeout,
+}
+
+/// Block until a key is ready, a watcher fires, or `cap` elapses.
+///
+/// crossterm can't wait on our channels, so this waits for input in slices
+/// that start at `MIN_INPUT_SLICE` and double up to `MAX_INPUT_SLICE`,
+/// checking the watchers before each one. Keys still wake the loop
+/// immediately; watcher events are seen within one slice. The event that
+/// caused a `Db` or `Status` wake has been consumed from its channel.
+fn wait_for_wake(
+    cap: Duration,
+    db_rx: &Receiver<()>,
+    status_rx: Option<&Receiver<()>>,
+    mut input_ready: impl FnMut(Duration) -> Result<bool>,
+) -> Result<Wake> {
+    let deadline = Instant::now() + cap;
+    let mut slice = MIN_INPUT_SLICE;
+    loop {
+        if db_rx.try_recv().is_ok() {
+            return Ok(Wake::Db);
+        }
+        if status_rx.is_some_and(|rx| rx.try_recv().is_ok()) {
+            return Ok(Wake::Status);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(Wake::Timeout);
+        }
+        if input_ready(slice.min(remaining))? {
+            return Ok(Wake::Input);
+        }
+        slice = (slice * 2).min(MAX_INPUT_SLICE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn no_input(timeout: Duration) -> Result<bool> {
+        std::thread::sleep(timeout);
+        Ok(false)
+    }
+
+    #[test]
+    fn watcher_event_wakes_before_poll_interval() {
+        let (tx, rx) = mpsc::channel();
+        let sender = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            tx.send(()).unwrap();
+        });
+        let start = Instant::now();
+        let wake = wait_for_wake(Duration::from_secs(30), &rx, None, no_input).unwrap();
+        assert_eq!(wake, Wake::Db);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn status_and_input_wake_and_cap_times_out() {
+        let (_db_tx, db_rx) = mpsc::channel();
+        let (status_tx, status_rx) = mpsc::channel();
+        status_tx.send(()).unwrap();
+        let wake = wait_for_wake(Duration::from_secs(30), &db_rx, Some(&status_rx), no_input);
+        assert_eq!(wake.unwrap(), Wake::Status);
+
+        let wake = wait_for_wake(Duration::from_secs(30), &db_rx, None, |_| Ok(true));
+        assert_eq!(wake.unwrap(), Wake::Input);
+
+        let mut slices = Vec::new();
+        let wake = wait_for_wake(Duration::from_millis(400), &db_rx, None, |t| {
+            slices.push(t);
+            no_input(t)
+        });
+        assert_eq!(wake.unwrap(), Wake::Timeout);
+        assert_eq!(slices[0], MIN_INPUT_SLICE);
+        assert!(slices.iter().all(|t| *t <= MAX_INPUT_SLICE));
+    }
+}
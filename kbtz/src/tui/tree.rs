@@ -1,8 +1,29 @@
 use ratatui::prelude::*;
-use ratatui::widgets::{Block, Borders, Clear, List, Paragraph};
+use ratatui::widgets::{Block, Borders, Clear, List, Paragraph, Wrap};
 
-use super::app::{AddField, App};
-use crate::ui;
+use std::collections::HashSet;
+
+use super::app::{AddField, App, BlockPick, RenameInput};
+use crate::ui::{self, RowDecoration, TreeDecorator, TreeRow};
+
+/// Marks rows picked in select mode on top of the app's own decoration.
+struct SelectionDecorator<'a> {
+    inner: &'a dyn TreeDecorator,
+    selected: &'a HashSet<String>,
+}
+
+impl TreeDecorator for SelectionDecorator<'_> {
+    fn decorate(&self, row: &TreeRow) -> RowDecoration {
+        let mut decoration = self.inner.decorate(row);
+        if self.selected.contains(&row.name) {
+            decoration.after_name.insert(
+                0,
+                Span::styled(" \u{2713}", Style::default().fg(Color::Cyan).bold()),
+            );
+        }
+        decoration
+    }
+}
 
 pub fn render(frame: &mut Frame, app: &mut App) {
     if let Some(panel) = &app.notes_panel {
@@ -11,7 +32,18 @@ pub fn render(frame: &mut Frame, app: &mut App) {
     }
 
     let area = frame.area();
-    render_tree(frame, app, area);
+    match &app.preview {
+        // Too narrow for two useful columns: keep the tree full width.
+        Some(_) if area.width >= PREVIEW_MIN_WIDTH => {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+                .split(area);
+            render_tree(frame, app, chunks[0]);
+            render_preview(frame, app, chunks[1]);
+        }
+        _ => render_tree(frame, app, area),
+    }
 
     match &app.tree.mode {
         ui::TreeMode::ConfirmDone(name) => {
@@ -28,8 +60,22 @@ pub fn render(frame: &mut Frame, app: &mut App) {
     }
 }
 
+/// Narrowest terminal that shows the preview pane beside the tree.
+const PREVIEW_MIN_WIDTH: u16 = 80;
+
+fn render_preview(frame: &mut Frame, app: &App, area: Rect) {
+    let text = app.preview.as_deref().unwrap_or_default();
+    let paragraph = Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title(" Details "))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
+
 fn render_tree(frame: &mut Frame, app: &mut App, area: Rect) {
-    let needs_footer = app.tree.error.is_some()
+    let needs_footer = app.rename.is_some()
+        || app.block_pick.is_some()
+        || app.toast.is_some()
+        || app.tree.error.is_some()
         || matches!(app.tree.mode, ui::TreeMode::Search(_))
         || app.tree.filter.is_some();
 
@@ -44,13 +90,22 @@ fn render_tree(frame: &mut Frame, app: &mut App, area: Rect) {
     };
 
     if let Some(footer) = footer_area {
-        if let ui::TreeMode::Search(query) = &app.tree.mode {
+        if let Some(rename) = &app.rename {
+            frame.render_widget(Paragraph::new(rename_footer_line(rename)), footer);
+        } else if let Some(pick) = &app.block_pick {
+            frame.render_widget(Paragraph::new(block_pick_footer_line(pick)), footer);
+        } else if let ui::TreeMode::Search(query) = &app.tree.mode {
             frame.render_widget(Paragraph::new(ui::search_footer_line(query)), footer);
         } else if let Some(err) = &app.tree.error {
             frame.render_widget(
                 Paragraph::new(err.as_str()).style(Style::default().fg(Color::Red)),
                 footer,
             );
+        } else if let Some(toast) = &app.toast {
+            frame.render_widget(
+                Paragraph::new(toast.as_str()).style(Style::default().fg(Color::Green)),
+                footer,
+            );
         } else if let Some(filter) = &app.tree.filter {
             frame.render_widget(
                 Paragraph::new(Line::from(ui::filter_footer_spans(filter))),
@@ -59,17 +114,67 @@ fn render_tree(frame: &mut Frame, app: &mut App, area: Rect) {
         }
     }
 
-    let items = ui::build_tree_items(&app.tree.rows, &app.tree.collapsed, app.decorator.as_ref());
-    let title = match app.tree.filter_label() {
+    let decorator = SelectionDecorator {
+        inner: app.decorator.as_ref(),
+        selected: &app.selected,
+    };
+    let items = ui::build_tree_items(&app.tree.rows, &app.tree.collapsed, &decorator);
+    let mut title = match app.tree.filter_label() {
         Some(label) => format!(" Tasks ({label}) "),
         None => " Tasks ".to_string(),
     };
+    if app.select_mode {
+        title.push_str(&format!(
+            "[select: {} \u{2014} space toggles, d/p apply, Esc cancels] ",
+            app.selected.len()
+        ));
+    }
     let list = List::new(items)
         .block(Block::default().borders(Borders::ALL).title(title))
         .highlight_style(Style::default().bg(Color::DarkGray));
     frame.render_stateful_widget(list, tree_area, &mut app.tree.list_state);
 }
 
+fn rename_footer_line(rename: &RenameInput) -> Line<'_> {
+    let mut spans = vec![
+        Span::styled(
+            format!("rename {}: ", rename.old),
+            Style::default().fg(Color::Cyan),
+        ),
+        Span::raw(rename.input.as_str()),
+        Span::styled("_", Style::default().fg(Color::DarkGray)),
+    ];
+    if let Some(err) = &rename.error {
+        spans.push(Span::styled(
+            format!("  {err}"),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    Line::from(spans)
+}
+
+fn block_pick_footer_line(pick: &BlockPick) -> Line<'_> {
+    let prompt = if pick.unblock {
+        format!("pick the blocker to remove from '{}'", pick.target)
+    } else {
+        format!("pick the task that blocks '{}'", pick.target)
+    };
+    let mut spans = vec![
+        Span::styled(prompt, Style::default().fg(Color::Cyan)),
+        Span::styled(
+            "  Enter: confirm  Esc: cancel",
+            Style::default().fg(Color::DarkGray),
+        ),
+    ];
+    if let Some(err) = &pick.error {
+        spans.push(Span::styled(
+            format!("  {err}"),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    Line::from(spans)
+}
+
 fn render_field(
     frame: &mut Frame,
     label: &str,
@@ -194,7 +299,7 @@ fn render_add_dialog(frame: &mut Frame, app: &App) {
 fn render_help(frame: &mut Frame) {
     let term = frame.area();
     let width = 50.min(term.width.saturating_sub(4));
-    let height = 25.min(term.height.saturating_sub(2));
+    let height = 31.min(term.height.saturating_sub(2));
     let area = ui::centered_rect(width, height, term);
 
     frame.render_widget(Clear, area);
@@ -221,7 +326,7 @@ fn render_help(frame: &mut Frame) {
         ]),
         Line::from(vec![
             Span::styled("Enter   ", Style::default().fg(Color::Cyan)),
-            Span::raw("Run action (--action)"),
+            Span::raw("Run action (--action), else details"),
         ]),
         Line::from(vec![
             Span::styled("n       ", Style::default().fg(Color::Cyan)),
@@ -231,10 +336,30 @@ fn render_help(frame: &mut Frame) {
             Span::styled("a       ", Style::default().fg(Color::Cyan)),
             Span::raw("Add child task"),
         ]),
+        Line::from(vec![
+            Span::styled("s       ", Style::default().fg(Color::Cyan)),
+            Span::raw("Add sibling task"),
+        ]),
         Line::from(vec![
             Span::styled("A       ", Style::default().fg(Color::Cyan)),
             Span::raw("Add root task"),
         ]),
+        Line::from(vec![
+            Span::styled("r       ", Style::default().fg(Color::Cyan)),
+            Span::raw("Rename task"),
+        ]),
+        Line::from(vec![
+            Span::styled("e       ", Style::default().fg(Color::Cyan)),
+            Span::raw("Edit description in $EDITOR"),
+        ]),
+        Line::from(vec![
+            Span::styled("b / B   ", Style::default().fg(Color::Cyan)),
+            Span::raw("Pick a blocker to add / remove"),
+        ]),
+        Line::from(vec![
+            Span::styled("u       ", Style::default().fg(Color::Cyan)),
+            Span::raw("Undo last change"),
+        ]),
         Line::from(vec![
             Span::styled("N       ", Style::default().fg(Color::Cyan)),
             Span::raw("Add note to selected task"),
@@ -251,6 +376,10 @@ fn render_help(frame: &mut Frame) {
             Span::styled("U       ", Style::default().fg(Color::Cyan)),
             Span::raw("Force-unassign task"),
         ]),
+        Line::from(vec![
+            Span::styled("v       ", Style::default().fg(Color::Cyan)),
+            Span::raw("Select mode (Space picks, d/p apply)"),
+        ]),
         Line::from(vec![
             Span::styled("/       ", Style::default().fg(Color::Cyan)),
             Span::raw("Search/filter tasks"),
@@ -305,3 +434,75 @@ fn render_help(frame: &mut Frame) {
     let paragraph = Paragraph::new(help_text);
     frame.render_widget(paragraph, inner);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db, ops};
+    use ratatui::backend::TestBackend;
+    use rusqlite::Connection;
+
+    fn setup() -> (tempfile::TempDir, Connection, App) {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = db::open_memory().unwrap();
+        for (name, desc) in [("api", "Build the API"), ("docs", "Write docs")] {
+            ops::add_task(
+                &conn,
+                ops::AddTaskParams {
+                    name,
+                    description: desc,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        ops::add_block(&conn, "api", "docs").unwrap();
+        ops::add_note(&conn, "api", "schema drafted").unwrap();
+        let db_path = dir.path().join("kbtz.db");
+        let app = App::new(db_path.to_str().unwrap(), &conn, None, None).unwrap();
+        (dir, conn, app)
+    }
+
+    fn draw(app: &mut App, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| render(frame, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let mut out = String::new();
+        for y in 0..height {
+            for x in 0..width {
+                out.push_str(buffer[(x, y)].symbol());
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    #[test]
+    fn preview_shows_selected_task_details() {
+        let (_dir, conn, mut app) = setup();
+        assert!(!draw(&mut app, 100, 20).contains("Details"));
+
+        app.toggle_preview(&conn).unwrap();
+        let screen = draw(&mut app, 100, 20);
+        assert!(screen.contains("Details"), "{screen}");
+        assert!(screen.contains("Name:        api"), "{screen}");
+        assert!(screen.contains("Blocks:      docs"), "{screen}");
+        assert!(screen.contains("schema drafted"), "{screen}");
+
+        // Moving the cursor follows the selection.
+        app.tree.move_down();
+        app.load_preview(&conn).unwrap();
+        let screen = draw(&mut app, 100, 20);
+        assert!(screen.contains("Name:        docs"), "{screen}");
+        assert!(screen.contains("Blocked by:  api"), "{screen}");
+    }
+
+    #[test]
+    fn preview_hidden_on_narrow_terminals() {
+        let (_dir, conn, mut app) = setup();
+        app.toggle_preview(&conn).unwrap();
+        let screen = draw(&mut app, PREVIEW_MIN_WIDTH - 1, 20);
+        assert!(!screen.contains("Details"), "{screen}");
+        assert!(screen.contains("api"), "{screen}");
+    }
+}
@@ -3,7 +3,7 @@
 //! Used by both `kbtz watch` (the CLI TUI) and `kbtz-workspace`.
 
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use crossterm::event::{KeyCode, KeyEvent};
@@ -27,6 +27,8 @@ pub struct TreeRow {
     pub has_children: bool,
     pub is_last_at_depth: Vec<bool>,
     pub blocked_by: Vec<String>,
+    pub priority: i64,
+    pub needs_human: bool,
 }
 
 /// Flatten a list of tasks into a displayable tree.
@@ -38,6 +40,19 @@ pub fn flatten_tree(
     collapsed: &HashSet<String>,
     conn: &Connection,
 ) -> Result<Vec<TreeRow>> {
+    let deps = ops::get_all_deps(conn)?;
+    Ok(flatten_tree_with_deps(tasks, collapsed, &deps))
+}
+
+/// Like `flatten_tree`, but takes dependencies (as returned by
+/// `ops::get_all_deps`) instead of querying them, so callers that cache
+/// them can rebuild the tree without touching the database. Child flags
+/// come from `tasks` itself, so the whole tree costs no per-node queries.
+pub fn flatten_tree_with_deps(
+    tasks: &[Task],
+    collapsed: &HashSet<String>,
+    deps: &HashMap<String, ops::TaskDeps>,
+) -> Vec<TreeRow> {
     let task_names: HashSet<&str> = tasks.iter().map(|t| t.name.as_str()).collect();
 
     let mut children_map: HashMap<Option<&str>, Vec<&Task>> = HashMap::new();
@@ -59,13 +74,13 @@ pub fn flatten_tree(
             root,
             &children_map,
             collapsed,
-            conn,
+            deps,
             0,
             &mut vec![is_last],
-        )?;
+        );
     }
 
-    Ok(rows)
+    rows
 }
 
 fn flatten_node(
@@ -73,16 +88,19 @@ fn flatten_node(
     task: &Task,
     children_map: &HashMap<Option<&str>, Vec<&Task>>,
     collapsed: &HashSet<String>,
-    conn: &Connection,
+    deps: &HashMap<String, ops::TaskDeps>,
     depth: usize,
     is_last_at_depth: &mut Vec<bool>,
-) -> Result<()> {
+) {
     let children = children_map
         .get(&Some(task.name.as_str()))
         .cloned()
         .unwrap_or_default();
     let has_children = !children.is_empty();
-    let blocked_by = ops::get_blockers(conn, &task.name)?;
+    let blocked_by = deps
+        .get(&task.name)
+        .map(|(blocked_by, _)| blocked_by.clone())
+        .unwrap_or_default();
 
     rows.push(TreeRow {
         name: task.name.clone(),
@@ -93,6 +111,8 @@ fn flatten_node(
         has_children,
         is_last_at_depth: is_last_at_depth.clone(),
         blocked_by,
+        priority: task.priority,
+        needs_human: task.needs_human,
     });
 
     if has_children && !collapsed.contains(&task.name) {
@@ -104,15 +124,13 @@ fn flatten_node(
                 child,
                 children_map,
                 collapsed,
-                conn,
+                deps,
                 depth + 1,
                 is_last_at_depth,
-            )?;
+            );
             is_last_at_depth.pop();
         }
     }
-
-    Ok(())
 }
 
 // ── Rendering helpers ──────────────────────────────────────────────────
@@ -194,6 +212,12 @@ pub fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
     Rect::new(x, y, width.min(area.width), height.min(area.height))
 }
 
+/// Where `kbtz watch` persists collapsed tree nodes for the database at
+/// `db_path`.
+pub fn tree_state_path(db_path: &str) -> PathBuf {
+    PathBuf::from(format!("{db_path}.tree-state.json"))
+}
+
 /// What to do when the user tries to act on an active (claimed) task.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ActiveTaskPolicy {
@@ -201,6 +225,9 @@ pub enum ActiveTaskPolicy {
     Refuse,
     /// Show a confirmation dialog (session-managed mode).
     Confirm,
+    /// Act immediately without asking (session-managed mode with
+    /// confirmations disabled).
+    Allow,
 }
 
 /// Modal state for the tree view.
@@ -253,6 +280,9 @@ pub struct TreeView {
     /// Task name to select on the next tree refresh (e.g. after returning
     /// from a zoomed session). Consumed by `clamp_cursor`.
     pub pending_select: Option<String>,
+    /// File the `collapsed` set is persisted to, if any. Set by
+    /// `load_collapsed`.
+    pub state_path: Option<PathBuf>,
 }
 
 impl TreeView {
@@ -270,6 +300,40 @@ impl TreeView {
             show_paused: false,
             show_inactive: true,
             pending_select: None,
+            state_path: None,
+        }
+    }
+
+    /// Restore the `collapsed` set from `path` and remember the path so
+    /// later toggles are saved back to it. A missing or unreadable file
+    /// leaves the tree fully expanded; names of tasks that no longer exist
+    /// are dropped.
+    pub fn load_collapsed(&mut self, path: PathBuf, conn: &Connection) -> Result<()> {
+        let saved: Vec<String> = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        self.state_path = Some(path);
+        if saved.is_empty() {
+            return Ok(());
+        }
+        let mut stmt = conn.prepare("SELECT name FROM tasks")?;
+        let existing: HashSet<String> = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        self.collapsed = saved.into_iter().filter(|n| existing.contains(n)).collect();
+        Ok(())
+    }
+
+    /// Best-effort write of the `collapsed` set to `state_path`.
+    fn save_collapsed(&self) {
+        let Some(path) = &self.state_path else {
+            return;
+        };
+        let mut names: Vec<&String> = self.collapsed.iter().collect();
+        names.sort();
+        if let Ok(json) = serde_json::to_string(&names) {
+            let _ = std::fs::write(path, json);
         }
     }
 
@@ -287,6 +351,27 @@ impl TreeView {
         }
     }
 
+    /// Map a screen row to a tree row index, given the bordered `area` the
+    /// list was rendered into. Accounts for the list's scroll offset.
+    /// Returns `None` for the border or for space below the last row.
+    pub fn row_at(&self, area: Rect, y: u16) -> Option<usize> {
+        let inner_top = area.y + 1;
+        let inner_bottom = area.y + area.height.saturating_sub(1);
+        if y < inner_top || y >= inner_bottom {
+            return None;
+        }
+        let idx = self.list_state.offset() + (y - inner_top) as usize;
+        (idx < self.rows.len()).then_some(idx)
+    }
+
+    /// Move the cursor to `idx`. Out-of-range indices are ignored.
+    pub fn select(&mut self, idx: usize) {
+        if idx < self.rows.len() {
+            self.cursor = idx;
+            self.list_state.select(Some(idx));
+        }
+    }
+
     pub fn toggle_collapse(&mut self) {
         if let Some(row) = self.rows.get(self.cursor) {
             if row.has_children {
@@ -294,6 +379,7 @@ impl TreeView {
                 if !self.collapsed.remove(&name) {
                     self.collapsed.insert(name);
                 }
+                self.save_collapsed();
             }
         }
     }
@@ -564,6 +650,7 @@ impl TreeView {
             "paused" => TreeKeyAction::Unpause(name),
             "open" => TreeKeyAction::Pause(name),
             "active" => match self.active_policy {
+                ActiveTaskPolicy::Allow => TreeKeyAction::Pause(name),
                 ActiveTaskPolicy::Confirm => {
                     self.mode = TreeMode::ConfirmPause(name);
                     TreeKeyAction::Continue
@@ -591,6 +678,7 @@ impl TreeView {
                 TreeKeyAction::Continue
             }
             "active" => match self.active_policy {
+                ActiveTaskPolicy::Allow => TreeKeyAction::MarkDone(name),
                 ActiveTaskPolicy::Confirm => {
                     self.mode = TreeMode::ConfirmDone(name);
                     TreeKeyAction::Continue
@@ -828,6 +916,18 @@ pub fn build_tree_items(
                 (icon_for_task(row), status_style(&row.status))
             };
 
+            let priority_info = if row.priority != 0 {
+                format!(" [priority: {}]", row.priority)
+            } else {
+                String::new()
+            };
+
+            let needs_human_info = if row.needs_human {
+                " [needs human]"
+            } else {
+                ""
+            };
+
             let blocked_info = if row.blocked_by.is_empty() {
                 String::new()
             } else {
@@ -837,7 +937,7 @@ pub fn build_tree_items(
             let desc = if row.description.is_empty() {
                 String::new()
             } else {
-                format!("  {}", row.description)
+                format!("  {}", crate::output::description_summary(&row.description))
             };
 
             let mut spans = vec![
@@ -847,6 +947,14 @@ pub fn build_tree_items(
                 Span::styled(row.name.clone(), Style::default().bold()),
             ];
             spans.extend(decoration.after_name);
+            spans.push(Span::styled(
+                priority_info,
+                Style::default().fg(Color::Magenta),
+            ));
+            spans.push(Span::styled(
+                needs_human_info,
+                Style::default().fg(Color::Yellow),
+            ));
             spans.push(Span::styled(blocked_info, Style::default().fg(Color::Red)));
             spans.push(Span::raw(desc));
 
@@ -897,6 +1005,9 @@ pub enum NotesKeyAction {
     Continue,
     /// User dismissed the notes panel.
     Close,
+    /// User confirmed deletion of the note with this ID; caller should
+    /// delete it and reload the panel.
+    Delete(i64),
 }
 
 /// Shared notes-panel state used by both `kbtz watch` and `kbtz-workspace`.
@@ -904,6 +1015,10 @@ pub enum NotesKeyAction {
 pub struct NotesPanel {
     pub notes: Vec<Note>,
     pub scroll: u16,
+    /// Index into `notes` of the note targeted by `d`.
+    pub selected: usize,
+    /// True while waiting for y/n on deleting the selected note.
+    pub confirm_delete: bool,
 }
 
 impl NotesPanel {
@@ -914,11 +1029,36 @@ impl NotesPanel {
     /// Load notes for `task_name` from the database.
     pub fn load(&mut self, conn: &Connection, task_name: &str) -> Result<()> {
         self.notes = ops::list_notes(conn, task_name)?;
+        self.selected = self.selected.min(self.notes.len().saturating_sub(1));
         Ok(())
     }
 
+    /// Line offset of the header of the note at `index`.
+    fn note_offset(&self, index: usize) -> u16 {
+        self.notes
+            .iter()
+            .take(index)
+            .map(|n| n.content.lines().count() as u16 + 2)
+            .sum()
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index;
+        self.scroll = self.note_offset(index);
+    }
+
     /// Handle a key press while the notes panel is visible.
     pub fn handle_key(&mut self, key: KeyEvent) -> NotesKeyAction {
+        if self.confirm_delete {
+            self.confirm_delete = false;
+            if matches!(key.code, KeyCode::Char('y') | KeyCode::Enter) {
+                if let Some(note) = self.notes.get(self.selected) {
+                    return NotesKeyAction::Delete(note.id);
+                }
+            }
+            return NotesKeyAction::Continue;
+        }
+
         match key.code {
             KeyCode::Esc | KeyCode::Enter | KeyCode::Char('n') | KeyCode::Char('q') => {
                 NotesKeyAction::Close
@@ -931,6 +1071,20 @@ impl NotesPanel {
                 self.scroll = self.scroll.saturating_sub(1);
                 NotesKeyAction::Continue
             }
+            KeyCode::Char('J') => {
+                if self.selected + 1 < self.notes.len() {
+                    self.select(self.selected + 1);
+                }
+                NotesKeyAction::Continue
+            }
+            KeyCode::Char('K') => {
+                self.select(self.selected.saturating_sub(1));
+                NotesKeyAction::Continue
+            }
+            KeyCode::Char('d') => {
+                self.confirm_delete = !self.notes.is_empty();
+                NotesKeyAction::Continue
+            }
             KeyCode::PageDown => {
                 self.scroll = self.scroll.saturating_add(20);
                 NotesKeyAction::Continue
@@ -940,12 +1094,7 @@ impl NotesPanel {
                 NotesKeyAction::Continue
             }
             KeyCode::Char('G') => {
-                let lines: u16 = self
-                    .notes
-                    .iter()
-                    .map(|n| n.content.lines().count() as u16 + 2)
-                    .sum();
-                self.scroll = lines.saturating_sub(1);
+                self.scroll = self.note_offset(self.notes.len()).saturating_sub(1);
                 NotesKeyAction::Continue
             }
             KeyCode::Char('g') => {
@@ -969,28 +1118,48 @@ impl NotesPanel {
             .map(|n| format!(" Notes: {n} "))
             .unwrap_or_else(|| " Notes ".to_string());
 
-        let text = if self.notes.is_empty() {
-            "No notes.".to_string()
-        } else {
-            self.notes
-                .iter()
-                .map(|n| format!("[{}]\n{}\n", n.created_at, n.content))
-                .collect::<Vec<_>>()
-                .join("\n")
-        };
+        let mut lines: Vec<Line> = Vec::new();
+        if self.notes.is_empty() {
+            lines.push(Line::raw("No notes."));
+        }
+        for (i, note) in self.notes.iter().enumerate() {
+            let mut header = format!("[{}] #{}", note.created_at, note.id);
+            if let Some(label) = &note.label {
+                header.push_str(&format!(" ({label})"));
+            }
+            if i == self.selected {
+                lines.push(Line::styled(
+                    format!("> {header}"),
+                    Style::default().fg(Color::Yellow).bold(),
+                ));
+            } else {
+                lines.push(Line::raw(format!("  {header}")));
+            }
+            lines.extend(note.content.lines().map(|l| Line::raw(l.to_string())));
+            lines.push(Line::raw(""));
+        }
 
-        let paragraph = Paragraph::new(text)
+        let paragraph = Paragraph::new(lines)
             .block(Block::default().borders(Borders::ALL).title(title))
             .wrap(Wrap { trim: false })
             .scroll((self.scroll, 0));
 
         frame.render_widget(paragraph, notes_area);
 
-        frame.render_widget(
-            Paragraph::new("Esc/q/n: back  j/k: scroll  g/G: top/bottom")
-                .style(Style::default().fg(Color::DarkGray)),
-            hint_area,
-        );
+        let hint = if self.confirm_delete {
+            Paragraph::new(Line::from(vec![
+                Span::raw("Delete selected note? "),
+                Span::styled("y", Style::default().fg(Color::Green).bold()),
+                Span::raw("/"),
+                Span::styled("n", Style::default().fg(Color::Red).bold()),
+            ]))
+        } else {
+            Paragraph::new(
+                "Esc/q/n: back  j/k: scroll  J/K: select note  d: delete note  g/G: top/bottom",
+            )
+            .style(Style::default().fg(Color::DarkGray))
+        };
+        frame.render_widget(hint, hint_area);
     }
 }
 
@@ -1009,6 +1178,9 @@ mod tests {
             assignee: None,
             agent: None,
             directory: None,
+            priority: 0,
+            needs_human: false,
+            estimate: None,
             status_changed_at: None,
             created_at: String::new(),
             updated_at: String::new(),
@@ -1025,6 +1197,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }
     }
 
@@ -1201,6 +1375,53 @@ mod tests {
         assert_eq!(rows[1].blocked_by, vec!["blocker"]);
     }
 
+    #[test]
+    fn flatten_tree_matches_per_node_lookups() {
+        let conn = db::open_memory().unwrap();
+        for (name, parent) in [
+            ("root", None),
+            ("a", Some("root")),
+            ("b", Some("root")),
+            ("c", Some("a")),
+            ("lone", None),
+            ("finished", None),
+        ] {
+            ops::add_task(
+                &conn,
+                ops::AddTaskParams {
+                    name,
+                    parent,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        ops::add_block(&conn, "a", "b").unwrap();
+        ops::add_block(&conn, "lone", "b").unwrap();
+        ops::add_block(&conn, "finished", "c").unwrap();
+        ops::mark_done(&conn, "finished").unwrap();
+
+        let tasks = ops::list_tasks(&conn, None, true, None, None, None).unwrap();
+        let rows = flatten_tree(&tasks, &HashSet::new(), &conn).unwrap();
+        assert_eq!(rows.len(), tasks.len());
+        for row in &rows {
+            assert_eq!(
+                row.blocked_by,
+                ops::get_blockers(&conn, &row.name).unwrap(),
+                "blocked_by for {}",
+                row.name
+            );
+            let has_children = tasks
+                .iter()
+                .any(|t| t.parent.as_deref() == Some(row.name.as_str()));
+            assert_eq!(
+                row.has_children, has_children,
+                "has_children for {}",
+                row.name
+            );
+        }
+    }
+
     // ── centered_rect ──
 
     #[test]
@@ -1236,6 +1457,8 @@ mod tests {
                 has_children: false,
                 is_last_at_depth: vec![false],
                 blocked_by: vec![],
+                priority: 0,
+                needs_human: false,
             },
             TreeRow {
                 name: "b".into(),
@@ -1246,6 +1469,8 @@ mod tests {
                 has_children: false,
                 is_last_at_depth: vec![true],
                 blocked_by: vec![],
+                priority: 0,
+                needs_human: false,
             },
         ];
         tv.move_down();
@@ -1266,11 +1491,43 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         tv.move_up(); // already at 0
         assert_eq!(tv.cursor, 0);
     }
 
+    #[test]
+    fn row_at_maps_screen_rows_with_offset() {
+        let mut tv = TreeView::new(ActiveTaskPolicy::Refuse);
+        tv.rows = (0..10)
+            .map(|i| make_row(&format!("t{i}"), "open", None))
+            .collect();
+        let area = Rect::new(0, 0, 40, 6); // 4 inner rows
+
+        assert_eq!(tv.row_at(area, 0), None); // top border
+        assert_eq!(tv.row_at(area, 1), Some(0));
+        assert_eq!(tv.row_at(area, 4), Some(3));
+        assert_eq!(tv.row_at(area, 5), None); // bottom border
+
+        *tv.list_state.offset_mut() = 7;
+        assert_eq!(tv.row_at(area, 1), Some(7));
+        assert_eq!(tv.row_at(area, 3), Some(9));
+        assert_eq!(tv.row_at(area, 4), None); // past the last row
+    }
+
+    #[test]
+    fn select_ignores_out_of_range() {
+        let mut tv = TreeView::new(ActiveTaskPolicy::Refuse);
+        tv.rows = vec![make_row("a", "open", None), make_row("b", "open", None)];
+        tv.select(1);
+        assert_eq!(tv.cursor, 1);
+        assert_eq!(tv.list_state.selected(), Some(1));
+        tv.select(5);
+        assert_eq!(tv.cursor, 1);
+    }
+
     #[test]
     fn tree_view_toggle_collapse() {
         let mut tv = TreeView::new(ActiveTaskPolicy::Refuse);
@@ -1283,6 +1540,8 @@ mod tests {
             has_children: true,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         assert!(!tv.collapsed.contains("parent"));
         tv.toggle_collapse();
@@ -1311,6 +1570,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         tv.cursor = 5;
         tv.clamp_cursor();
@@ -1329,6 +1590,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         };
         tv.rows = vec![row("a"), row("b"), row("c")];
         tv.cursor = 0;
@@ -1350,6 +1613,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         };
         tv.rows = vec![row("a"), row("b")];
         tv.cursor = 1;
@@ -1387,6 +1652,8 @@ mod tests {
             has_children: true,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         let key = KeyEvent::from(KeyCode::Char(' '));
         assert!(matches!(tv.handle_key(key), TreeKeyAction::Refresh));
@@ -1405,6 +1672,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         let key = KeyEvent::from(KeyCode::Char('d'));
         assert!(matches!(tv.handle_key(key), TreeKeyAction::Continue));
@@ -1423,6 +1692,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         let key = KeyEvent::from(KeyCode::Char('d'));
         assert!(matches!(tv.handle_key(key), TreeKeyAction::Continue));
@@ -1434,6 +1705,18 @@ mod tests {
         assert!(matches!(tv.mode, TreeMode::Normal));
     }
 
+    #[test]
+    fn handle_key_done_and_pause_allow_active_skips_confirm() {
+        let mut tv = TreeView::new(ActiveTaskPolicy::Allow);
+        tv.rows = vec![make_row("t", "active", Some("ws/1"))];
+        let key = KeyEvent::from(KeyCode::Char('d'));
+        assert!(matches!(tv.handle_key(key), TreeKeyAction::MarkDone(ref n) if n == "t"));
+        assert!(matches!(tv.mode, TreeMode::Normal));
+        let key = KeyEvent::from(KeyCode::Char('p'));
+        assert!(matches!(tv.handle_key(key), TreeKeyAction::Pause(ref n) if n == "t"));
+        assert!(matches!(tv.mode, TreeMode::Normal));
+    }
+
     #[test]
     fn handle_key_done_open_task() {
         let mut tv = TreeView::new(ActiveTaskPolicy::Refuse);
@@ -1446,6 +1729,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         let key = KeyEvent::from(KeyCode::Char('d'));
         assert!(matches!(tv.handle_key(key), TreeKeyAction::MarkDone(_)));
@@ -1463,6 +1748,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         let key = KeyEvent::from(KeyCode::Char('p'));
         assert!(matches!(tv.handle_key(key), TreeKeyAction::Pause(_)));
@@ -1480,6 +1767,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         let key = KeyEvent::from(KeyCode::Char('p'));
         assert!(matches!(tv.handle_key(key), TreeKeyAction::Unpause(_)));
@@ -1497,6 +1786,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         let key = KeyEvent::from(KeyCode::Char('p'));
         assert!(matches!(tv.handle_key(key), TreeKeyAction::Continue));
@@ -1515,6 +1806,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         let key = KeyEvent::from(KeyCode::Char('U'));
         assert!(matches!(
@@ -1846,6 +2139,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         let items = build_tree_items(&rows, &collapsed, &DefaultDecorator);
         assert_eq!(items.len(), 1);
@@ -1863,6 +2158,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }];
         struct TestDecorator;
         impl TreeDecorator for TestDecorator {
@@ -1891,6 +2188,8 @@ mod tests {
                 has_children: true,
                 is_last_at_depth: vec![true],
                 blocked_by: vec![],
+                priority: 0,
+                needs_human: false,
             },
             TreeRow {
                 name: "leaf".into(),
@@ -1901,6 +2200,8 @@ mod tests {
                 has_children: false,
                 is_last_at_depth: vec![true],
                 blocked_by: vec![],
+                priority: 0,
+                needs_human: false,
             },
         ];
         let items = build_tree_items(&rows, &collapsed, &DefaultDecorator);
@@ -2067,6 +2368,8 @@ mod tests {
             has_children: false,
             is_last_at_depth: vec![true],
             blocked_by: vec![],
+            priority: 0,
+            needs_human: false,
         }
     }
 
@@ -2113,6 +2416,8 @@ mod tests {
                 has_children: true,
                 is_last_at_depth: vec![true],
                 blocked_by: vec![],
+                priority: 0,
+                needs_human: false,
             },
             TreeRow {
                 name: "child-match".into(),
@@ -2123,6 +2428,8 @@ mod tests {
                 has_children: false,
                 is_last_at_depth: vec![true, true],
                 blocked_by: vec![],
+                priority: 0,
+                needs_human: false,
             },
             TreeRow {
                 name: "child-no".into(),
@@ -2133,6 +2440,8 @@ mod tests {
                 has_children: false,
                 is_last_at_depth: vec![true, true],
                 blocked_by: vec![],
+                priority: 0,
+                needs_human: false,
             },
         ];
         let filtered = filter_rows(&rows, "match");
@@ -2269,4 +2578,95 @@ mod tests {
         tv.handle_key(key);
         assert!(matches!(tv.mode, TreeMode::Search(ref q) if q == "old"));
     }
+
+    // ── NotesPanel ──
+
+    fn make_note(id: i64, content: &str) -> Note {
+        Note {
+            id,
+            task: "t".into(),
+            content: content.into(),
+            created_at: String::new(),
+            label: None,
+        }
+    }
+
+    #[test]
+    fn notes_panel_delete_requires_confirmation() {
+        let mut panel = NotesPanel::new();
+        panel.notes = vec![make_note(1, "one"), make_note(2, "two\nlines")];
+
+        panel.handle_key(KeyEvent::from(KeyCode::Char('J')));
+        assert_eq!(panel.selected, 1);
+        assert_eq!(panel.scroll, 3);
+
+        panel.handle_key(KeyEvent::from(KeyCode::Char('d')));
+        assert!(panel.confirm_delete);
+        let action = panel.handle_key(KeyEvent::from(KeyCode::Char('y')));
+        assert!(matches!(action, NotesKeyAction::Delete(2)));
+        assert!(!panel.confirm_delete);
+    }
+
+    #[test]
+    fn notes_panel_delete_cancel() {
+        let mut panel = NotesPanel::new();
+        panel.notes = vec![make_note(1, "one")];
+        panel.handle_key(KeyEvent::from(KeyCode::Char('d')));
+        let action = panel.handle_key(KeyEvent::from(KeyCode::Char('n')));
+        assert!(matches!(action, NotesKeyAction::Continue));
+        assert!(!panel.confirm_delete);
+    }
+
+    #[test]
+    fn notes_panel_delete_ignored_without_notes() {
+        let mut panel = NotesPanel::new();
+        panel.handle_key(KeyEvent::from(KeyCode::Char('d')));
+        assert!(!panel.confirm_delete);
+    }
+
+    // ── Collapsed state persistence ──
+
+    #[test]
+    fn collapsed_state_round_trips_and_prunes() {
+        let conn = db::open_memory().unwrap();
+        for name in ["a", "b"] {
+            ops::add_task(
+                &conn,
+                ops::AddTaskParams {
+                    name,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree-state.json");
+        std::fs::write(&path, r#"["a", "b", "gone"]"#).unwrap();
+
+        let mut tv = TreeView::new(ActiveTaskPolicy::Refuse);
+        tv.load_collapsed(path.clone(), &conn).unwrap();
+        assert_eq!(tv.collapsed, HashSet::from(["a".into(), "b".into()]));
+
+        let mut row = make_row("a", "open", None);
+        row.has_children = true;
+        tv.rows = vec![row];
+        tv.toggle_collapse();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), r#"["b"]"#);
+    }
+
+    #[test]
+    fn collapsed_state_missing_or_corrupt_starts_expanded() {
+        let conn = db::open_memory().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut tv = TreeView::new(ActiveTaskPolicy::Refuse);
+        tv.load_collapsed(dir.path().join("missing.json"), &conn)
+            .unwrap();
+        assert!(tv.collapsed.is_empty());
+
+        let corrupt = dir.path().join("corrupt.json");
+        std::fs::write(&corrupt, "{not json").unwrap();
+        tv.load_collapsed(corrupt, &conn).unwrap();
+        assert!(tv.collapsed.is_empty());
+    }
 }
@@ -15,6 +15,31 @@ pub fn validate_name(name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Validate a timestamp in the `YYYY-MM-DDTHH:MM:SSZ` form kbtz stores,
+/// so it can be compared against stored timestamps as text.
+pub fn validate_timestamp(ts: &str) -> Result<()> {
+    let b = ts.as_bytes();
+    let shape_ok = b.len() == 20
+        && b.iter().enumerate().all(|(i, c)| match i {
+            4 | 7 => *c == b'-',
+            10 => *c == b'T',
+            13 | 16 => *c == b':',
+            19 => *c == b'Z',
+            _ => c.is_ascii_digit(),
+        });
+    let field = |range: std::ops::Range<usize>| ts[range].parse::<u32>().unwrap_or(0);
+    if !shape_ok
+        || !(1..=12).contains(&field(5..7))
+        || !(1..=31).contains(&field(8..10))
+        || field(11..13) > 23
+        || field(14..16) > 59
+        || field(17..19) > 59
+    {
+        bail!("invalid timestamp '{ts}': expected UTC time as YYYY-MM-DDTHH:MM:SSZ");
+    }
+    Ok(())
+}
+
 /// Detect if setting `task_name`'s parent to `new_parent` would create a cycle.
 /// A cycle exists if `new_parent` is a descendant of `task_name` (or is `task_name` itself).
 pub fn detect_parent_cycle(conn: &Connection, task_name: &str, new_parent: &str) -> Result<bool> {
@@ -85,4 +110,24 @@ mod tests {
         assert!(validate_name("foo.bar").is_err());
         assert!(validate_name("foo/bar").is_err());
     }
+
+    #[test]
+    fn timestamps() {
+        assert!(validate_timestamp("2024-01-01T00:00:00Z").is_ok());
+        assert!(validate_timestamp("2024-12-31T23:59:59Z").is_ok());
+        for bad in [
+            "",
+            "2024-01-01",
+            "2024-01-01 00:00:00",
+            "2024-01-01T00:00:00",
+            "2024-01-01T00:00:00+00:00",
+            "2024-13-01T00:00:00Z",
+            "2024-01-00T00:00:00Z",
+            "2024-01-01T24:00:00Z",
+            "２024-01-01T00:00:00Z",
+        ] {
+            let err = validate_timestamp(bad).unwrap_err().to_string();
+            assert!(err.contains("YYYY-MM-DDTHH:MM:SSZ"), "{bad}: {err}");
+        }
+    }
 }
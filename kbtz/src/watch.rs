@@ -1,5 +1,5 @@
 use std::path::Path;
-use std::sync::mpsc::{self, Receiver};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::time::Duration;
 
 use anyhow::{Context, Result};
@@ -8,14 +8,55 @@ use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 /// SQLite auxiliary file suffixes appended to the database filename.
 const SQLITE_SUFFIXES: &[&str] = &["-wal", "-shm", "-journal"];
 
+/// Default window within which bursts of filesystem events are collapsed
+/// into a single notification.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Forward events from `raw` to `tx`, collapsing everything that arrives
+/// within `window` of the first event into one send. Notifications are
+/// delayed by at most `window`, even under a continuous flood. Returns when
+/// either side of the channel is dropped.
+fn coalesce(raw: Receiver<()>, tx: Sender<()>, window: Duration) {
+    while raw.recv().is_ok() {
+        std::thread::sleep(window);
+        while raw.try_recv().is_ok() {}
+        if tx.send(()).is_err() {
+            return;
+        }
+    }
+}
+
+/// Channel pair whose receiver gets debounced notifications for events
+/// sent on the returned sender.
+fn debounced_channel(window: Duration) -> (Sender<()>, Receiver<()>) {
+    let (raw_tx, raw_rx) = mpsc::channel();
+    let (tx, rx) = mpsc::channel();
+    if window.is_zero() {
+        return (tx, rx);
+    }
+    std::thread::spawn(move || coalesce(raw_rx, tx, window));
+    (raw_tx, rx)
+}
+
 /// Creates a watcher for the database file and returns a receiver for change events.
 /// The watcher must be kept alive for events to be received.
 ///
 /// We watch the parent directory (since SQLite uses temp files like -wal and -shm
 /// alongside the main database file), but filter events to only those affecting
 /// the database file itself or its known SQLite auxiliaries.
+///
+/// Bursts of events are coalesced using `DEFAULT_DEBOUNCE`.
 pub fn watch_db(db_path: &str) -> Result<(RecommendedWatcher, Receiver<()>)> {
-    let (tx, rx) = mpsc::channel();
+    watch_db_debounced(db_path, DEFAULT_DEBOUNCE)
+}
+
+/// Like `watch_db`, but collapses events within `debounce` of each other
+/// into one notification. A zero window sends one notification per event.
+pub fn watch_db_debounced(
+    db_path: &str,
+    debounce: Duration,
+) -> Result<(RecommendedWatcher, Receiver<()>)> {
+    let (tx, rx) = debounced_channel(debounce);
 
     let db_filename = Path::new(db_path)
         .file_name()
@@ -44,10 +85,13 @@ pub fn watch_db(db_path: &str) -> Result<(RecommendedWatcher, Receiver<()>)> {
                     })
                     .unwrap_or(false)
             });
-            crate::debug_log::log(&format!(
-                "db watcher: kind={:?} dominated={dominated} paths={:?}",
-                event.kind, event.paths
-            ));
+            crate::debug_log::log_at(
+                crate::debug_log::Level::Debug,
+                format_args!(
+                    "db watcher: kind={:?} dominated={dominated} paths={:?}",
+                    event.kind, event.paths
+                ),
+            );
             if dominated {
                 let _ = tx.send(());
             }
@@ -65,8 +109,18 @@ pub fn watch_db(db_path: &str) -> Result<(RecommendedWatcher, Receiver<()>)> {
 }
 
 /// Creates a watcher for a directory and returns a receiver for change events.
+/// Bursts of events are coalesced using `DEFAULT_DEBOUNCE`.
 pub fn watch_dir(dir: &Path) -> Result<(RecommendedWatcher, Receiver<()>)> {
-    let (tx, rx) = mpsc::channel();
+    watch_dir_debounced(dir, DEFAULT_DEBOUNCE)
+}
+
+/// Like `watch_dir`, but collapses events within `debounce` of each other
+/// into one notification. A zero window sends one notification per event.
+pub fn watch_dir_debounced(
+    dir: &Path,
+    debounce: Duration,
+) -> Result<(RecommendedWatcher, Receiver<()>)> {
+    let (tx, rx) = debounced_channel(debounce);
 
     let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
         if let Ok(event) = res {
@@ -95,3 +149,38 @@ pub fn wait_for_change(rx: &Receiver<()>, timeout: Duration) -> bool {
 pub fn drain_events(rx: &Receiver<()>) {
     while rx.try_recv().is_ok() {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debounced_channel_coalesces_burst() {
+        let (tx, rx) = debounced_channel(Duration::from_millis(100));
+        for _ in 0..1000 {
+            tx.send(()).unwrap();
+        }
+        assert!(wait_for_change(&rx, Duration::from_secs(5)));
+        assert!(!wait_for_change(&rx, Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn zero_debounce_passes_every_event() {
+        let (tx, rx) = debounced_channel(Duration::ZERO);
+        for _ in 0..3 {
+            tx.send(()).unwrap();
+        }
+        assert_eq!(rx.try_iter().count(), 3);
+    }
+
+    #[test]
+    fn watch_dir_coalesces_flood_of_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_watcher, rx) = watch_dir_debounced(dir.path(), Duration::from_millis(200)).unwrap();
+        for i in 0..50 {
+            std::fs::write(dir.path().join(format!("f{i}")), "x").unwrap();
+        }
+        assert!(wait_for_change(&rx, Duration::from_secs(5)));
+        assert!(!wait_for_change(&rx, Duration::from_millis(500)));
+    }
+}